@@ -1,10 +1,11 @@
 use either::Either;
+use serde::{Deserialize, Serialize};
 
-use crate::lexing::token::Token;
+use crate::lexing::token::{Token, TokenPos};
 
 use super::{LetCondition, Pattern, Statement, TypeName};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LambdaParam
 {
     pub name: Token,
@@ -12,7 +13,7 @@ pub struct LambdaParam
     pub type_name: Option<TypeName>
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LambdaParams
 {
     Simple(Token),
@@ -26,7 +27,7 @@ pub enum LambdaParams
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LambdaExpr
 {
     pub params: LambdaParams,
@@ -34,7 +35,7 @@ pub struct LambdaExpr
     pub expression: Box<Expression>
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallExpr
 {
     pub expression: Box<Expression>,
@@ -43,7 +44,7 @@ pub struct CallExpr
     pub close_paren: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IndexExpr
 {
     pub expression: Box<Expression>,
@@ -52,7 +53,7 @@ pub struct IndexExpr
     pub close_bracket: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GroupingExpr
 {
     pub open_paren: Token,
@@ -60,7 +61,7 @@ pub struct GroupingExpr
     pub close_paren: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccessExpr
 {
     pub expression: Box<Expression>,
@@ -68,14 +69,14 @@ pub struct AccessExpr
     pub identifier: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnaryExpr
 {
     pub expression: Box<Expression>,
     pub operator: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BinaryExpr
 {
     pub left: Box<Expression>,
@@ -83,7 +84,7 @@ pub struct BinaryExpr
     pub right: Box<Expression>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlockExpr
 {
     pub open_brace: Token,
@@ -92,7 +93,15 @@ pub struct BlockExpr
     pub close_brace: Token,
 }
 
-#[derive(Debug, Clone)]
+impl BlockExpr
+{
+    pub fn span(&self) -> TokenPos
+    {
+        TokenPos { begin: self.open_brace.pos.begin, end: self.close_brace.pos.end }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstructionArg
 {
     pub name: Token,
@@ -100,7 +109,7 @@ pub struct ConstructionArg
     pub value: Box<Expression>
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConstructionExpr
 {
     pub type_name: TypeName,
@@ -109,7 +118,7 @@ pub struct ConstructionExpr
     pub close_brace: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnumConstructionExpr
 {
     pub type_name: TypeName,
@@ -118,7 +127,7 @@ pub struct EnumConstructionExpr
     pub close_paren: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TypeValueExpr
 {
     pub type_name: TypeName,
@@ -126,15 +135,16 @@ pub struct TypeValueExpr
     pub name: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchBranch
 {
     pub pattern: Pattern,
+    pub guard: Option<(Token, Box<Expression>)>, // Token is "if"
     pub arrow: Token,
     pub expression: Box<Expression>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MatchExpr
 {
     pub match_tok: Token,
@@ -144,7 +154,7 @@ pub struct MatchExpr
     pub close_brace: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct IfExpr
 {
     pub if_tok: Token,
@@ -153,14 +163,47 @@ pub struct IfExpr
     pub else_branch: Option<ElseBranch>
 }
 
-#[derive(Debug, Clone)]
+impl IfExpr
+{
+    /// The text the expression covers, up to the end of its last `else` branch
+    pub fn span(&self) -> TokenPos
+    {
+        let end = match &self.else_branch
+        {
+            Some(ElseBranch { else_tok: _, body: Either::Left(if_expr) }) => if_expr.span().end,
+            Some(ElseBranch { else_tok: _, body: Either::Right(block) }) => block.close_brace.pos.end,
+            None => self.block.close_brace.pos.end,
+        };
+
+        TokenPos { begin: self.if_tok.pos.begin, end }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ElseBranch
 {
     pub else_tok: Token,
     pub body: Either<Box<IfExpr>, BlockExpr>,
 }
 
-#[derive(Debug, Clone)]
+/// Hands a value to whoever is iterating the generator the expression is in, as in `yield node`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YieldExpr
+{
+    pub yield_tok: Token,
+    pub expression: Box<Expression>,
+}
+
+/// A `break`, `continue` or `return` where an expression is expected, as in `_ => continue,`. It never produces a
+/// value, so it fits wherever one of any type is expected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JumpExpr
+{
+    pub keyword: Token,
+    pub expression: Option<Box<Expression>>, // only a `return` can carry a value
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArrayLiteral
 {
     pub open_bracket: Token,
@@ -168,7 +211,7 @@ pub struct ArrayLiteral
     pub close_bracket: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CastExpr
 {
     pub expression: Box<Expression>,
@@ -176,7 +219,15 @@ pub struct CastExpr
     pub type_name: TypeName,
 }
 
-#[derive(Debug, Clone)]
+/// A string with expressions embedded in it, as in `"a ${b} c"`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterpolationExpr
+{
+    pub start: Token, // the text before the first expression, `"a ${`
+    pub parts: Vec<(Expression, Token)>, // each expression with the text after it, `} c ${` or `} c"`
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Expression
 {
     Lambda(LambdaExpr),
@@ -196,5 +247,78 @@ pub enum Expression
     Binary(BinaryExpr),
     IfExpr(IfExpr),
     MatchExpr(MatchExpr),
-    Cast(CastExpr)
-}
\ No newline at end of file
+    Cast(CastExpr),
+    Interpolation(InterpolationExpr),
+    Yield(YieldExpr),
+    Jump(JumpExpr),
+}
+impl Expression
+{
+    /// The text the expression covers, from its first token to its last
+    pub fn span(&self) -> TokenPos
+    {
+        let between = |first: &Token, last: &Token| TokenPos { begin: first.pos.begin, end: last.pos.end };
+        let after = |first: TokenPos, last: &Token| TokenPos { begin: first.begin, end: last.pos.end };
+
+        match self
+        {
+            Expression::Lambda(lambda) =>
+            {
+                let first = match &lambda.params
+                {
+                    LambdaParams::Simple(name) => name,
+                    LambdaParams::Complex { open_pipe, .. } => open_pipe,
+                };
+
+                TokenPos { begin: first.pos.begin, end: lambda.expression.span().end }
+            },
+            Expression::Literal(token) | Expression::Identifier(token) | Expression::SelfExpr(token) => token.pos,
+            Expression::ArrayLiteral(array) => between(&array.open_bracket, &array.close_bracket),
+            Expression::Grouping(grouping) => between(&grouping.open_paren, &grouping.close_paren),
+            Expression::BlockExpr(block) => block.span(),
+            Expression::TypeValue(type_value) => after(type_value.type_name.span(), &type_value.name),
+            Expression::Construction(construction) => after(construction.type_name.span(), &construction.close_brace),
+            Expression::EnumConstruction(construction) => after(construction.type_name.span(), &construction.close_paren),
+            Expression::Call(call) => after(call.expression.span(), &call.close_paren),
+            Expression::Access(access) => after(access.expression.span(), &access.identifier),
+            Expression::Index(index) => after(index.expression.span(), &index.close_bracket),
+            Expression::Unary(unary) => TokenPos { begin: unary.operator.pos.begin, end: unary.expression.span().end },
+            Expression::Binary(binary) => TokenPos { begin: binary.left.span().begin, end: binary.right.span().end },
+            Expression::IfExpr(if_expr) => if_expr.span(),
+            Expression::MatchExpr(match_expr) => between(&match_expr.match_tok, &match_expr.close_brace),
+            Expression::Cast(cast) => TokenPos { begin: cast.expression.span().begin, end: cast.type_name.span().end },
+            Expression::Interpolation(interpolation) => between(&interpolation.start, interpolation.parts.last().map_or(&interpolation.start, |(_, text)| text)),
+            Expression::Yield(yield_expr) => TokenPos { begin: yield_expr.yield_tok.pos.begin, end: yield_expr.expression.span().end },
+            Expression::Jump(jump) => TokenPos { begin: jump.keyword.pos.begin, end: jump.expression.as_ref().map_or(jump.keyword.pos.end, |e| e.span().end) },
+        }
+    }
+
+    /// Returns the token that best represents this expression, used when reporting errors
+    pub fn get_token(&self) -> &Token
+    {
+        match self 
+        {
+            Expression::Lambda(lambda) => &lambda.arrow,
+            Expression::Literal(token) => token,
+            Expression::ArrayLiteral(array) => &array.open_bracket,
+            Expression::Identifier(token) => token,
+            Expression::Grouping(grouping) => grouping.expression.get_token(),
+            Expression::SelfExpr(token) => token,
+            Expression::BlockExpr(block) => &block.open_brace,
+            Expression::TypeValue(type_value) => &type_value.name,
+            Expression::Construction(construction) => &construction.open_brace,
+            Expression::EnumConstruction(construction) => &construction.open_paren,
+            Expression::Call(call) => &call.open_paren,
+            Expression::Access(access) => &access.identifier,
+            Expression::Index(index) => &index.open_bracket,
+            Expression::Unary(unary) => &unary.operator,
+            Expression::Binary(binary) => &binary.operator,
+            Expression::IfExpr(if_expr) => &if_expr.if_tok,
+            Expression::MatchExpr(match_expr) => &match_expr.match_tok,
+            Expression::Cast(cast) => &cast.as_tok,
+            Expression::Interpolation(interpolation) => &interpolation.start,
+            Expression::Yield(yield_expr) => &yield_expr.yield_tok,
+            Expression::Jump(jump) => &jump.keyword,
+        }
+    }
+}
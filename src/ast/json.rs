@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+
+use super::Program;
+
+/// The version of the JSON layout of the syntax tree, raised whenever a change to the AST changes the layout.
+/// The layout is described in `docs/ast-json.md`.
+pub const SCHEMA_VERSION: u32 = 11;
+
+#[derive(Serialize)]
+struct DocumentRef<'a>
+{
+    version: u32,
+    program: Option<&'a Program>,
+}
+
+#[derive(Deserialize)]
+struct Document
+{
+    program: Option<Program>,
+}
+
+#[derive(Deserialize)]
+struct Header
+{
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum JsonError
+{
+    Malformed(serde_json::Error),
+    UnsupportedVersion(u32),
+}
+
+impl std::fmt::Display for JsonError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            JsonError::Malformed(error) => write!(f, "Malformed syntax tree: {}", error),
+            JsonError::UnsupportedVersion(version) => write!(f, "Unsupported syntax tree version {}, expected {}", version, SCHEMA_VERSION),
+        }
+    }
+}
+
+/// Writes a program, or the lack of one for an empty file, as a JSON document tagged with the schema version
+pub fn to_json(program: Option<&Program>) -> String
+{
+    let document = DocumentRef { version: SCHEMA_VERSION, program };
+    serde_json::to_string_pretty(&document).expect("the syntax tree only holds types JSON can represent")
+}
+
+/// Reads back a document written by `to_json`, refusing those written with another version of the schema
+pub fn from_json(text: &str) -> Result<Option<Program>, JsonError>
+{
+    let header: Header = serde_json::from_str(text).map_err(JsonError::Malformed)?;
+    if header.version != SCHEMA_VERSION
+    {
+        return Err(JsonError::UnsupportedVersion(header.version));
+    }
+
+    let document: Document = serde_json::from_str(text).map_err(JsonError::Malformed)?;
+    Ok(document.program)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::{fs, path::Path};
+
+    use crate::{lexing, parsing};
+
+    use super::{from_json, to_json};
+
+    /// Writing a program, reading it back and writing it again gives the same document
+    #[test]
+    fn round_trip_sample_programs()
+    {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let mut checked = 0;
+        for entry in fs::read_dir(dir).unwrap()
+        {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|e| e != "crs")
+            {
+                continue;
+            }
+
+            let text = fs::read_to_string(&path).unwrap();
+            let lexed = lexing::lex_text(&text);
+            let Ok(program) = parsing::parse(lexed.tokens, &lexed.comments) else { continue };
+
+            let json = to_json(program.as_ref());
+            let read = from_json(&json).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+            assert_eq!(to_json(read.as_ref()), json, "{}", path.display());
+            checked += 1;
+        }
+
+        assert!(checked > 0);
+    }
+}
@@ -1,12 +1,15 @@
 pub mod expr;
 pub mod stmt;
+pub mod json;
+pub mod yields;
 pub use expr::*;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 pub use stmt::*;
 
-use crate::lexing::token::Token;
+use crate::lexing::token::{Token, TokenPos};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum TypeName
 {
@@ -61,6 +64,29 @@ impl TypeName
         }
     }
 
+    pub fn get_token(&self) -> &Token
+    {
+        match self 
+        {
+            TypeName::Identifier { name, args: _ } => name,
+            TypeName::Array { open_bracket, close_bracket: _, type_name: _ } => open_bracket,
+            TypeName::Function { fn_tok, open_paren: _, parameter_types: _, close_paren: _, arrow: _, return_type: _ } => fn_tok,
+            TypeName::Access { inner: _, dot: _, name, args: _ } => name,
+        }
+    }
+
+    /// The text the type covers, from its first token to its last
+    pub fn span(&self) -> TokenPos
+    {
+        match self
+        {
+            TypeName::Identifier { name, args } => TokenPos { begin: name.pos.begin, end: args.as_ref().map_or(name.pos.end, |a| a.close_bracket.pos.end) },
+            TypeName::Array { open_bracket, close_bracket: _, type_name } => TokenPos { begin: open_bracket.pos.begin, end: type_name.span().end },
+            TypeName::Function { fn_tok, open_paren: _, parameter_types: _, close_paren: _, arrow: _, return_type } => TokenPos { begin: fn_tok.pos.begin, end: return_type.span().end },
+            TypeName::Access { inner, dot: _, name, args } => TokenPos { begin: inner.span().begin, end: args.as_ref().map_or(name.pos.end, |a| a.close_bracket.pos.end) },
+        }
+    }
+
     pub fn pretty_print(&self) -> String 
     {
         match self
@@ -97,7 +123,7 @@ impl TypeName
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenericParams
 {
     pub open_bracket: Token,
@@ -105,7 +131,7 @@ pub struct GenericParams
     pub close_bracket: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GenericArgs 
 {
     pub open_bracket: Token,
@@ -113,7 +139,7 @@ pub struct GenericArgs
     pub close_bracket: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Parameters
 {
     pub open_paren: Token,
@@ -121,7 +147,7 @@ pub struct Parameters
     pub close_paren: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Parameter 
 {
     pub var: Option<Token>,
@@ -132,7 +158,7 @@ pub struct Parameter
     pub expression: Option<Expression>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PatternField
 {
     pub mut_tok: Option<Token>,
@@ -141,15 +167,58 @@ pub struct PatternField
     pub inner: Option<Box<Pattern>>
 }
 
-#[derive(Debug, Clone)]
+/// A literal in a pattern. Integer and float literals may be negated.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternLiteral
+{
+    pub minus: Option<Token>,
+    pub token: Token,
+}
+
+impl PatternLiteral
+{
+    pub fn span(&self) -> TokenPos
+    {
+        TokenPos { begin: self.minus.as_ref().unwrap_or(&self.token).pos.begin, end: self.token.pos.end }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Pattern 
 {
-    Literal(Token),
+    Literal(PatternLiteral),
+    /// `_`, which matches anything and binds nothing
+    Wildcard(Token),
+    /// `start..=end`, between two integer or two character literals
+    Range
+    {
+        start: PatternLiteral,
+        dots: Token,
+        end: PatternLiteral,
+    },
     Identifier
     {
         mut_tok: Option<Token>,
         id: Token,
     },
+    /// `name @ pattern`, which binds the whole value when the inner pattern matches it. The inner pattern has no
+    /// alternatives unless they are grouped in parentheses.
+    Binding
+    {
+        mut_tok: Option<Token>,
+        id: Token,
+        at: Token,
+        inner: Box<Pattern>,
+    },
+    /// Alternatives separated by `|`, which all bind the same names
+    Or(Vec<Pattern>),
+    /// A pattern in parentheses, so that alternatives can be nested in another pattern
+    Grouping
+    {
+        open_paren: Token,
+        inner: Box<Pattern>,
+        close_paren: Token,
+    },
     TypeValue
     {
         type_name: TypeName,
@@ -178,7 +247,45 @@ pub enum Pattern
     }
 }
 
-#[derive(Debug, Clone)]
+impl Pattern
+{
+    /// The text the pattern covers, from its first token to its last
+    pub fn span(&self) -> TokenPos
+    {
+        match self
+        {
+            Pattern::Literal(literal) => literal.span(),
+            Pattern::Wildcard(token) => token.pos,
+            Pattern::Range { start, dots: _, end } => TokenPos { begin: start.span().begin, end: end.span().end },
+            Pattern::Identifier { mut_tok, id } => TokenPos { begin: mut_tok.as_ref().unwrap_or(id).pos.begin, end: id.pos.end },
+            Pattern::Binding { mut_tok, id, at: _, inner } => TokenPos { begin: mut_tok.as_ref().unwrap_or(id).pos.begin, end: inner.span().end },
+            Pattern::Or(patterns) => TokenPos { begin: patterns[0].span().begin, end: patterns[patterns.len() - 1].span().end },
+            Pattern::Grouping { open_paren, inner: _, close_paren } => TokenPos { begin: open_paren.pos.begin, end: close_paren.pos.end },
+            Pattern::TypeValue { type_name, dot: _, id } => TokenPos { begin: type_name.span().begin, end: id.pos.end },
+            Pattern::EnumConstruct { type_name, open_paren: _, inner: _, close_paren } => TokenPos { begin: type_name.span().begin, end: close_paren.pos.end },
+            Pattern::StructConstruct { type_name, open_brace: _, patterns: _, close_brace } => TokenPos { begin: type_name.span().begin, end: close_brace.pos.end },
+            Pattern::ArrayConstruct { open_bracket, patterns: _, close_bracket } => TokenPos { begin: open_bracket.pos.begin, end: close_bracket.pos.end },
+        }
+    }
+
+    /// The token errors in the pattern are reported at
+    pub fn get_token(&self) -> &Token
+    {
+        match self
+        {
+            Pattern::Literal(literal) | Pattern::Range { start: literal, .. } => &literal.token,
+            Pattern::Wildcard(token) => token,
+            Pattern::Identifier { id, .. } | Pattern::Binding { id, .. } | Pattern::TypeValue { id, .. } => id,
+            Pattern::Or(patterns) => patterns[0].get_token(),
+            Pattern::Grouping { open_paren, .. } => open_paren,
+            Pattern::EnumConstruct { open_paren, .. } => open_paren,
+            Pattern::StructConstruct { open_brace, .. } => open_brace,
+            Pattern::ArrayConstruct { open_bracket, .. } => open_bracket,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LetCondition
 {
     Expression(Box<Expression>),
@@ -191,4 +298,21 @@ pub enum LetCondition
         and: Option<Token>,
         other_cond: Option<Box<LetCondition>>,
     }
-}
\ No newline at end of file
+}
+
+impl LetCondition
+{
+    /// The text the condition covers, from its first token to its last
+    pub fn span(&self) -> TokenPos
+    {
+        match self
+        {
+            LetCondition::Expression(expression) => expression.span(),
+            LetCondition::Pattern { let_tok, pattern: _, equal: _, expression, and: _, other_cond } => TokenPos
+            {
+                begin: let_tok.pos.begin,
+                end: other_cond.as_ref().map_or(expression.span().end, |c| c.span().end),
+            },
+        }
+    }
+}
@@ -1,10 +1,11 @@
 use either::Either;
+use serde::{Deserialize, Serialize};
 
-use crate::lexing::token::Token;
+use crate::lexing::token::{Comment, Token, TokenPos};
 
 use super::{BlockExpr, Expression, GenericParams, IfExpr, LetCondition, MatchExpr, Pattern, TypeName};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UseStmt
 {
     pub use_tok: Token,
@@ -13,14 +14,14 @@ pub struct UseStmt
     pub semi_colon: Token
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExpressionStmt
 {
     pub expression: Expression,
     pub semi_colon: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LetBinding
 {
     Variable
@@ -31,7 +32,7 @@ pub enum LetBinding
     Pattern(Pattern)
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LetStmt
 {
     pub let_tok: Token,
@@ -43,7 +44,20 @@ pub struct LetStmt
     pub semi_colon: Token
 }
 
-#[derive(Debug, Clone)]
+/// A value computed while compiling, as in `const C: Int = 7;`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstDecl
+{
+    pub const_tok: Token,
+    pub id: Token,
+    pub colon: Token,
+    pub type_name: TypeName,
+    pub equal: Token,
+    pub expression: Expression,
+    pub semi_colon: Token,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AssignStmt
 {
     pub value: Expression,
@@ -52,7 +66,7 @@ pub struct AssignStmt
     pub semi_colon: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WhereSubClause
 {
     pub id: Token,
@@ -60,14 +74,14 @@ pub struct WhereSubClause
     pub types: Vec<TypeName>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WhereClause
 {
     pub where_tok: Token,
     pub sub_clauses: Vec<WhereSubClause>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FnParam
 {
     Normal
@@ -85,9 +99,12 @@ pub enum FnParam
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FnDecl
 {
+    pub docs: Vec<Comment>,
+    /// Marks a function that constants may call
+    pub const_tok: Option<Token>,
     pub fn_tok: Token,
     pub id: Token,
     pub generic_params: Option<GenericParams>,
@@ -100,9 +117,10 @@ pub struct FnDecl
     pub body: Either<BlockExpr, Token>, // either has a body or a ';'
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StructMember
 {
+    pub docs: Vec<Comment>,
     pub pub_tok: Option<Token>,
     pub mut_tok: Option<Token>,
     pub id: Token,
@@ -111,9 +129,10 @@ pub struct StructMember
     pub initializer: Option<(Token, Expression)>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StructDecl
 {
+    pub docs: Vec<Comment>,
     pub struct_tok: Token,
     pub id: Token,
     pub generic_params: Option<GenericParams>,
@@ -123,9 +142,10 @@ pub struct StructDecl
     pub close_brace: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InterfaceDecl
 {
+    pub docs: Vec<Comment>,
     pub interface_tok: Token,
     pub id: Token,
     pub generic_params: Option<GenericParams>,
@@ -135,7 +155,7 @@ pub struct InterfaceDecl
     pub close_brace: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnumStructMember
 {
     pub mut_tok: Option<Token>,
@@ -145,12 +165,18 @@ pub struct EnumStructMember
     pub initializer: Option<(Token, Expression)>
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EnumMember
 {
-    Basic(Token),
+    Basic
+    {
+        docs: Vec<Comment>,
+        id: Token,
+        discriminant: Option<(Token, Box<Expression>)>, // Token is the equal sign
+    },
     Single
     {
+        docs: Vec<Comment>,
         id: Token,
         open_paren: Token,
         type_name: TypeName,
@@ -158,6 +184,7 @@ pub enum EnumMember
     },
     Struct 
     {
+        docs: Vec<Comment>,
         id: Token,
         open_brace: Token,
         members: Vec<EnumStructMember>,
@@ -165,19 +192,21 @@ pub enum EnumMember
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnumDecl
 {
+    pub docs: Vec<Comment>,
     pub enum_tok: Token,
     pub id: Token,
     pub generic_params: Option<GenericParams>,
+    pub backing_type: Option<(Token, TypeName)>, // Token is the colon
     pub where_clause: Option<WhereClause>,
     pub open_brace: Token,
     pub members: Vec<EnumMember>,
     pub close_brace: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TypeDecl
 {
     pub type_tok: Token,
@@ -188,7 +217,7 @@ pub struct TypeDecl
     pub semi_colon: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImplStmt
 {
     pub impl_tok: Token,
@@ -201,21 +230,40 @@ pub struct ImplStmt
     pub close_brace: Token,
 }
 
-#[derive(Debug, Clone)]
+/// A module declared inside a file, either inline as `mod name { ... }` or as `mod name;`, which loads `name.crs`
+/// from the directory of the file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModDecl
+{
+    pub docs: Vec<Comment>,
+    pub mod_tok: Token,
+    pub id: Token,
+    pub body: Either<ModBody, Token>, // either has a body or a ';'
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModBody
+{
+    pub open_brace: Token,
+    pub declarations: Vec<Declaration>,
+    pub close_brace: Token,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BreakStmt
 {
     pub break_tok: Token,
     pub semi_colon: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContinueStmt
 {
     pub continue_tok: Token,
     pub semi_colon: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReturnStmt
 {
     pub return_tok: Token,
@@ -223,7 +271,7 @@ pub struct ReturnStmt
     pub semi_colon: Token,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ForStmt
 {
     pub for_tok: Token,
@@ -233,7 +281,7 @@ pub struct ForStmt
     pub body: BlockExpr,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WhileStmt
 {
     pub while_tok: Token,
@@ -241,7 +289,7 @@ pub struct WhileStmt
     pub body: BlockExpr,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Declaration
 {
     Fn(Option<Token>, FnDecl),
@@ -250,12 +298,14 @@ pub enum Declaration
     Enum(Option<Token>, EnumDecl),
     Type(Option<Token>, TypeDecl),
     Let(Option<Token>, LetStmt),
+    Const(Option<Token>, ConstDecl),
     Use(Option<Token>, UseStmt),
+    Mod(Option<Token>, ModDecl),
     Impl(ImplStmt),
 }
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Statement
 {
     While(WhileStmt),
@@ -269,6 +319,7 @@ pub enum Statement
     StructDecl(StructDecl),
     FnDecl(FnDecl),
     Let(LetStmt),
+    Const(ConstDecl),
     Assign(AssignStmt),
     If(IfExpr),
     Match(MatchExpr),
@@ -277,9 +328,109 @@ pub enum Statement
     Use(UseStmt),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Program
 {
+    /// The `//!` comments documenting the file
+    pub docs: Vec<Comment>,
     pub declarations: Vec<Declaration>,
     pub eof: Token,
-}
\ No newline at end of file
+}
+
+fn between(first: &Token, last: &Token) -> TokenPos
+{
+    TokenPos { begin: first.pos.begin, end: last.pos.end }
+}
+
+impl FnDecl
+{
+    pub fn span(&self) -> TokenPos
+    {
+        let first = self.const_tok.as_ref().unwrap_or(&self.fn_tok);
+        match &self.body
+        {
+            Either::Left(block) => between(first, &block.close_brace),
+            Either::Right(semi_colon) => between(first, semi_colon),
+        }
+    }
+
+    pub fn is_const(&self) -> bool
+    {
+        self.const_tok.is_some()
+    }
+}
+
+impl EnumMember
+{
+    pub fn id(&self) -> &Token
+    {
+        match self
+        {
+            EnumMember::Basic { id, .. } | EnumMember::Single { id, .. } | EnumMember::Struct { id, .. } => id,
+        }
+    }
+}
+
+impl ModDecl
+{
+    pub fn span(&self) -> TokenPos
+    {
+        match &self.body
+        {
+            Either::Left(body) => between(&self.mod_tok, &body.close_brace),
+            Either::Right(semi_colon) => between(&self.mod_tok, semi_colon),
+        }
+    }
+}
+
+impl Statement
+{
+    /// The text the statement covers, from its first token to its last
+    pub fn span(&self) -> TokenPos
+    {
+        match self
+        {
+            Statement::While(stmt) => between(&stmt.while_tok, &stmt.body.close_brace),
+            Statement::For(stmt) => between(&stmt.for_tok, &stmt.body.close_brace),
+            Statement::Return(stmt) => between(&stmt.return_tok, &stmt.semi_colon),
+            Statement::Continue(stmt) => between(&stmt.continue_tok, &stmt.semi_colon),
+            Statement::Break(stmt) => between(&stmt.break_tok, &stmt.semi_colon),
+            Statement::TypeDecl(stmt) => between(&stmt.type_tok, &stmt.semi_colon),
+            Statement::EnumDecl(stmt) => between(&stmt.enum_tok, &stmt.close_brace),
+            Statement::InterfaceDecl(stmt) => between(&stmt.interface_tok, &stmt.close_brace),
+            Statement::StructDecl(stmt) => between(&stmt.struct_tok, &stmt.close_brace),
+            Statement::FnDecl(stmt) => stmt.span(),
+            Statement::Let(stmt) => between(&stmt.let_tok, &stmt.semi_colon),
+            Statement::Const(stmt) => between(&stmt.const_tok, &stmt.semi_colon),
+            Statement::Assign(stmt) => TokenPos { begin: stmt.value.span().begin, end: stmt.semi_colon.pos.end },
+            Statement::If(stmt) => stmt.span(),
+            Statement::Match(stmt) => between(&stmt.match_tok, &stmt.close_brace),
+            Statement::Block(stmt) => stmt.span(),
+            Statement::Expression(stmt) => TokenPos { begin: stmt.expression.span().begin, end: stmt.semi_colon.pos.end },
+            Statement::Use(stmt) => between(&stmt.use_tok, &stmt.semi_colon),
+        }
+    }
+}
+
+impl Declaration
+{
+    /// The text the declaration covers, including its `pub`
+    pub fn span(&self) -> TokenPos
+    {
+        let (pub_tok, span) = match self
+        {
+            Declaration::Fn(pub_tok, decl) => (pub_tok, decl.span()),
+            Declaration::Struct(pub_tok, decl) => (pub_tok, between(&decl.struct_tok, &decl.close_brace)),
+            Declaration::Interface(pub_tok, decl) => (pub_tok, between(&decl.interface_tok, &decl.close_brace)),
+            Declaration::Enum(pub_tok, decl) => (pub_tok, between(&decl.enum_tok, &decl.close_brace)),
+            Declaration::Type(pub_tok, decl) => (pub_tok, between(&decl.type_tok, &decl.semi_colon)),
+            Declaration::Let(pub_tok, decl) => (pub_tok, between(&decl.let_tok, &decl.semi_colon)),
+            Declaration::Const(pub_tok, decl) => (pub_tok, between(&decl.const_tok, &decl.semi_colon)),
+            Declaration::Use(pub_tok, decl) => (pub_tok, between(&decl.use_tok, &decl.semi_colon)),
+            Declaration::Mod(pub_tok, decl) => (pub_tok, decl.span()),
+            Declaration::Impl(decl) => (&None, between(&decl.impl_tok, &decl.close_brace)),
+        };
+
+        TokenPos { begin: pub_tok.as_ref().map_or(span.begin, |t| t.pos.begin), end: span.end }
+    }
+}
@@ -0,0 +1,260 @@
+use either::Either;
+
+use crate::lexing::token::Token;
+
+use super::{BlockExpr, ElseBranch, Expression, FnDecl, IfExpr, LetCondition, MatchExpr, Statement};
+
+/// A `yield` in the body of a function
+#[derive(Debug, Clone, Copy)]
+pub struct YieldSite
+{
+    pub yield_tok: Token,
+    /// Whether a generator can be suspended at the `yield`, which is anywhere but the target of an assignment
+    pub resumable: bool,
+}
+
+impl FnDecl
+{
+    /// Whether the body contains a `yield`, which makes calling the function create a generator
+    pub fn is_generator(&self) -> bool
+    {
+        !self.yields().is_empty()
+    }
+
+    /// The `yield`s of the body, leaving out those of nested functions and lambdas
+    pub fn yields(&self) -> Vec<YieldSite>
+    {
+        let mut finder = YieldFinder::default();
+        if let Either::Left(body) = &self.body
+        {
+            finder.block(body, true);
+        }
+
+        finder.sites
+    }
+}
+
+impl Statement
+{
+    /// Whether running the statement may reach a `yield`
+    pub fn has_yield(&self) -> bool
+    {
+        let mut finder = YieldFinder::default();
+        finder.statement(self, true);
+        !finder.sites.is_empty()
+    }
+}
+
+impl Expression
+{
+    /// Whether evaluating the expression may reach a `yield`
+    pub fn has_yield(&self) -> bool
+    {
+        let mut finder = YieldFinder::default();
+        finder.expression(self, true);
+        !finder.sites.is_empty()
+    }
+}
+
+impl BlockExpr
+{
+    pub fn has_yield(&self) -> bool
+    {
+        let mut finder = YieldFinder::default();
+        finder.block(self, true);
+        !finder.sites.is_empty()
+    }
+}
+
+impl IfExpr
+{
+    pub fn has_yield(&self) -> bool
+    {
+        let mut finder = YieldFinder::default();
+        finder.if_expr(self, true);
+        !finder.sites.is_empty()
+    }
+}
+
+impl MatchExpr
+{
+    pub fn has_yield(&self) -> bool
+    {
+        let mut finder = YieldFinder::default();
+        finder.match_expr(self, true);
+        !finder.sites.is_empty()
+    }
+}
+
+impl LetCondition
+{
+    pub fn has_yield(&self) -> bool
+    {
+        let mut finder = YieldFinder::default();
+        finder.condition(self, true);
+        !finder.sites.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct YieldFinder
+{
+    sites: Vec<YieldSite>,
+}
+
+impl YieldFinder
+{
+    fn block(&mut self, block: &BlockExpr, resumable: bool)
+    {
+        for statement in &block.statements
+        {
+            self.statement(statement, resumable);
+        }
+
+        if let Some(expression) = &block.expression
+        {
+            self.expression(expression, resumable);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, resumable: bool)
+    {
+        match statement
+        {
+            Statement::While(while_stmt) =>
+            {
+                self.condition(&while_stmt.condition, resumable);
+                self.block(&while_stmt.body, resumable);
+            },
+            Statement::For(for_stmt) =>
+            {
+                self.expression(&for_stmt.expression, resumable);
+                self.block(&for_stmt.body, resumable);
+            },
+            Statement::Return(return_stmt) => if let Some(expression) = &return_stmt.expression
+            {
+                self.expression(expression, resumable);
+            },
+            Statement::Let(let_stmt) =>
+            {
+                self.expression(&let_stmt.expression, resumable);
+                if let Some((_, block)) = &let_stmt.else_clause
+                {
+                    self.block(block, resumable);
+                }
+            },
+            Statement::Const(decl) => self.expression(&decl.expression, resumable),
+            Statement::Assign(assign) =>
+            {
+                self.expression(&assign.expression, resumable);
+                self.expression(&assign.value, false);
+            },
+            Statement::If(if_expr) => self.if_expr(if_expr, resumable),
+            Statement::Match(match_expr) => self.match_expr(match_expr, resumable),
+            Statement::Block(block) => self.block(block, resumable),
+            Statement::Expression(expression_stmt) => self.expression(&expression_stmt.expression, resumable),
+            // Nested functions are generators of their own
+            Statement::FnDecl(_) => {},
+            Statement::Continue(_) | Statement::Break(_) | Statement::TypeDecl(_) | Statement::EnumDecl(_)
+            | Statement::InterfaceDecl(_) | Statement::StructDecl(_) | Statement::Use(_) => {},
+        }
+    }
+
+    fn condition(&mut self, condition: &LetCondition, resumable: bool)
+    {
+        match condition
+        {
+            LetCondition::Expression(expression) => self.expression(expression, resumable),
+            LetCondition::Pattern { expression, other_cond, .. } =>
+            {
+                self.expression(expression, resumable);
+                if let Some(other) = other_cond
+                {
+                    self.condition(other, resumable);
+                }
+            },
+        }
+    }
+
+    fn if_expr(&mut self, if_expr: &IfExpr, resumable: bool)
+    {
+        self.condition(&if_expr.condition, resumable);
+        self.block(&if_expr.block, resumable);
+        match &if_expr.else_branch
+        {
+            Some(ElseBranch { else_tok: _, body: Either::Left(if_expr) }) => self.if_expr(if_expr, resumable),
+            Some(ElseBranch { else_tok: _, body: Either::Right(block) }) => self.block(block, resumable),
+            None => {},
+        }
+    }
+
+    fn match_expr(&mut self, match_expr: &MatchExpr, resumable: bool)
+    {
+        self.expression(&match_expr.expression, resumable);
+        for branch in &match_expr.branches
+        {
+            if let Some((_, guard)) = &branch.guard
+            {
+                self.expression(guard, resumable);
+            }
+            self.expression(&branch.expression, resumable);
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression, resumable: bool)
+    {
+        match expression
+        {
+            Expression::Yield(yield_expr) =>
+            {
+                self.sites.push(YieldSite { yield_tok: yield_expr.yield_tok, resumable });
+                self.expression(&yield_expr.expression, resumable);
+            },
+            Expression::Jump(jump) => if let Some(expression) = &jump.expression
+            {
+                self.expression(expression, resumable);
+            },
+            Expression::IfExpr(if_expr) => self.if_expr(if_expr, resumable),
+            Expression::MatchExpr(match_expr) => self.match_expr(match_expr, resumable),
+            Expression::BlockExpr(block) => self.block(block, resumable),
+            // A lambda's body runs when the lambda is called, not as part of the function
+            Expression::Lambda(_) => {},
+            Expression::ArrayLiteral(array) => for expression in &array.expressions
+            {
+                self.expression(expression, resumable);
+            },
+            Expression::Grouping(grouping) => self.expression(&grouping.expression, resumable),
+            Expression::Construction(construction) => for arg in &construction.args
+            {
+                self.expression(&arg.value, resumable);
+            },
+            Expression::EnumConstruction(construction) => self.expression(&construction.expression, resumable),
+            Expression::Call(call) =>
+            {
+                self.expression(&call.expression, resumable);
+                for arg in &call.args
+                {
+                    self.expression(arg, resumable);
+                }
+            },
+            Expression::Access(access) => self.expression(&access.expression, resumable),
+            Expression::Index(index) =>
+            {
+                self.expression(&index.expression, resumable);
+                self.expression(&index.indexer, resumable);
+            },
+            Expression::Unary(unary) => self.expression(&unary.expression, resumable),
+            Expression::Binary(binary) =>
+            {
+                self.expression(&binary.left, resumable);
+                self.expression(&binary.right, resumable);
+            },
+            Expression::Cast(cast) => self.expression(&cast.expression, resumable),
+            Expression::Interpolation(interpolation) => for (expression, _) in &interpolation.parts
+            {
+                self.expression(expression, resumable);
+            },
+            Expression::Literal(_) | Expression::Identifier(_) | Expression::SelfExpr(_) | Expression::TypeValue(_) => {},
+        }
+    }
+}
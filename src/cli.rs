@@ -0,0 +1,231 @@
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: cirrus <command> [options]
+
+Commands:
+    lex <file>                           Print the tokens of a file
+    parse <file> [--format debug|json|cst]
+                                         Print the syntax tree of a file, or its lossless syntax tree with `cst`
+    check <file>                         Resolve names and check types without running anything
+    run [--vm] <file> [args...]          Run a program's `fn main`, passing it the remaining arguments
+    disasm <file> [function]             Print the bytecode of a program, or of one function such as `Board.check_win`
+    fmt [--check] [--indent N|tab] <files...>
+                                         Format source files in place, indenting by N spaces or a tab
+    bench-parse <file> [--copies N]      Time lexing and parsing N copies of a file
+    bench-edit <file> [--copies N]       Time relexing and reparsing only what small edits change against parsing
+                                         the whole file, on N copies of the file
+    repl                                 Start an interactive session
+    help                                 Print this message";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseFormat
+{
+    Debug,
+    Json,
+    /// The lossless syntax tree, with the whitespace and comments around each token
+    Cst,
+}
+
+#[derive(Debug)]
+pub enum Command
+{
+    Lex
+    {
+        file: PathBuf,
+    },
+    Parse
+    {
+        file: PathBuf,
+        format: ParseFormat,
+    },
+    Check
+    {
+        file: PathBuf,
+    },
+    Run
+    {
+        file: PathBuf,
+        /// Run on the bytecode virtual machine instead of the tree-walking interpreter
+        vm: bool,
+        args: Vec<String>,
+    },
+    Disassemble
+    {
+        file: PathBuf,
+        function: Option<String>,
+    },
+    Format
+    {
+        files: Vec<PathBuf>,
+        /// Only report files that are not formatted, without changing them
+        check: bool,
+        /// The text written for each level of indentation
+        indent: String,
+    },
+    /// Times lexing and parsing a file made larger by repeating it
+    BenchParse
+    {
+        file: PathBuf,
+        copies: usize,
+    },
+    /// Times incremental relexing and reparsing against full parses of a file made larger by repeating it
+    BenchEdit
+    {
+        file: PathBuf,
+        copies: usize,
+    },
+    Repl,
+    Help,
+}
+
+/// Reads a command from the arguments following the program name
+pub fn parse_args(args: &[String]) -> Result<Command, String>
+{
+    let Some((command, rest)) = args.split_first() else {
+        return Err("No command was given".into());
+    };
+
+    match command.as_str()
+    {
+        "lex" => Ok(Command::Lex { file: single_file(command, rest)? }),
+        "check" => Ok(Command::Check { file: single_file(command, rest)? }),
+        "parse" =>
+        {
+            let mut file = None;
+            let mut format = ParseFormat::Debug;
+            let mut rest = rest.iter();
+            while let Some(arg) = rest.next()
+            {
+                match arg.as_str()
+                {
+                    "--format" => format = match rest.next().map(String::as_str)
+                    {
+                        Some("debug") => ParseFormat::Debug,
+                        Some("json") => ParseFormat::Json,
+                        Some("cst") => ParseFormat::Cst,
+                        Some(other) => return Err(format!("Unknown format `{}`, expected `debug`, `json` or `cst`", other)),
+                        None => return Err("Expected a format after `--format`".into()),
+                    },
+                    _ => file = Some(positional(command, arg, file.is_some())?),
+                }
+            }
+
+            let file = file.ok_or_else(|| format!("`{}` expects a file", command))?;
+            Ok(Command::Parse { file, format })
+        },
+        "run" =>
+        {
+            let mut vm = false;
+            let mut rest = rest.iter();
+            // Options come before the file, and everything after it belongs to the program
+            let file = loop
+            {
+                match rest.next().map(String::as_str)
+                {
+                    Some("--vm") => vm = true,
+                    Some(arg) => break positional(command, arg, false)?,
+                    None => return Err(format!("`{}` expects a file", command)),
+                }
+            };
+
+            Ok(Command::Run { file, vm, args: rest.cloned().collect() })
+        },
+        "disasm" => match rest
+        {
+            [file] => Ok(Command::Disassemble { file: file.into(), function: None }),
+            [file, function] => Ok(Command::Disassemble { file: file.into(), function: Some(function.clone()) }),
+            _ => Err(format!("`{}` expects a file and optionally a function name", command)),
+        },
+        "fmt" =>
+        {
+            let mut files = vec![];
+            let mut check = false;
+            let mut indent = "    ".to_owned();
+            let mut rest = rest.iter();
+            while let Some(arg) = rest.next()
+            {
+                match arg.as_str()
+                {
+                    "--check" => check = true,
+                    "--indent" => indent = match rest.next().map(String::as_str)
+                    {
+                        Some("tab") => "\t".to_owned(),
+                        Some(width) => match width.parse::<usize>()
+                        {
+                            Ok(width) if width > 0 => " ".repeat(width),
+                            _ => return Err(format!("Invalid indentation `{}`, expected a number of spaces or `tab`", width)),
+                        },
+                        None => return Err("Expected an indentation after `--indent`".into()),
+                    },
+                    _ => files.push(positional(command, arg, false)?),
+                }
+            }
+
+            if files.is_empty()
+            {
+                return Err(format!("`{}` expects at least one file", command));
+            }
+
+            Ok(Command::Format { files, check, indent })
+        },
+        "bench-parse" | "bench-edit" =>
+        {
+            let mut file = None;
+            let mut copies = 1;
+            let mut rest = rest.iter();
+            while let Some(arg) = rest.next()
+            {
+                match arg.as_str()
+                {
+                    "--copies" => copies = match rest.next().map(|c| c.parse::<usize>())
+                    {
+                        Some(Ok(copies)) if copies > 0 => copies,
+                        Some(_) => return Err("Expected a positive number of copies".into()),
+                        None => return Err("Expected a number after `--copies`".into()),
+                    },
+                    _ => file = Some(positional(command, arg, file.is_some())?),
+                }
+            }
+
+            let file = file.ok_or_else(|| format!("`{}` expects a file", command))?;
+            match command.as_str()
+            {
+                "bench-parse" => Ok(Command::BenchParse { file, copies }),
+                _ => Ok(Command::BenchEdit { file, copies }),
+            }
+        },
+        "repl" => match rest
+        {
+            [] => Ok(Command::Repl),
+            _ => Err(format!("`{}` takes no arguments", command)),
+        },
+        "help" | "--help" | "-h" => Ok(Command::Help),
+        other => Err(format!("Unknown command `{}`", other)),
+    }
+}
+
+fn single_file(command: &str, args: &[String]) -> Result<PathBuf, String>
+{
+    match args
+    {
+        [file] => positional(command, file, false),
+        _ => Err(format!("`{}` expects exactly one file", command)),
+    }
+}
+
+/// A file argument, rejecting options the command does not know
+fn positional(command: &str, arg: &str, duplicate: bool) -> Result<PathBuf, String>
+{
+    if arg.starts_with("--")
+    {
+        return Err(format!("Unknown option `{}` for `{}`", arg, command));
+    }
+
+    if duplicate
+    {
+        return Err(format!("`{}` expects exactly one file", command));
+    }
+
+    Ok(arg.into())
+}
@@ -0,0 +1,273 @@
+use std::rc::Rc;
+
+use crate::lexing::token::Token;
+
+/// A single VM instruction. Jump targets are absolute offsets into the chunk of the function they appear in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instr
+{
+    /// Pushes a constant from the chunk's pool
+    Constant(u32),
+    Void,
+    True,
+    False,
+    Pop,
+    Dup,
+
+    GetLocal(u16),
+    SetLocal(u16),
+    /// Binds a new variable. Unlike `SetLocal`, this creates a fresh cell when the slot is captured by a closure.
+    DefineLocal(u16),
+    GetCell(u16),
+    SetCell(u16),
+    DefineCell(u16),
+    GetUpvalue(u16),
+    SetUpvalue(u16),
+    GetGlobal(u32),
+    SetGlobal(u32),
+    /// Creates a closure over the function with the given index, capturing the variables it lists
+    Closure(u32),
+    /// Pushes the type with the given index
+    Type(u32),
+
+    /// Replaces an object with its member named by a string constant: a field, method, variant or static
+    GetMember(u32),
+    /// Pops a value and an object, and sets the object's field named by a string constant
+    SetField(u32),
+    /// Pops a value and a type, and sets the type's static named by a string constant
+    SetStatic(u32),
+    GetIndex,
+    /// Pops a value, an index and an array
+    SetIndex,
+    /// Collects the given number of values into an array
+    Array(u32),
+    /// Pops the fields of a struct, in declaration order
+    Construct(u32, u16),
+    /// Pops the fields of an enum's struct variant, in declaration order
+    ConstructVariant(u32, u16, u16),
+
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulus,
+    /// Logical operators that evaluate both operands, used by `&=` and `|=`
+    And,
+    Or,
+    Negate,
+    Not,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    /// Fails unless the value on top of the stack is a `Bool`
+    ExpectBool,
+    Cast(CastTarget),
+
+    /// Stores the height of the stack in a slot as a loop is entered
+    MarkStack(u16),
+    /// Drops the values a `break` or `continue` leaves above the height stored in a slot, such as the operands
+    /// of the expression it was part of
+    ResetStack(u16),
+    Jump(u32),
+    /// Pops a `Bool` and jumps if it is false
+    JumpIfFalse(u32),
+    /// Jumps if the caller passed an argument for the given slot, skipping the evaluation of its default
+    JumpIfArgPassed(u16, u32),
+    /// Calls the value below the given number of arguments
+    Call(u16),
+    Return,
+    /// Pops a value and suspends the running generator, handing the value to whoever resumed it
+    Yield,
+
+    /// Pops a type (or variant constructor) and a value, and pushes whether the value is an instance of it
+    IsInstance,
+    /// Replaces an enum value with the payload of its single-value variant
+    Payload,
+    /// Pops a value and pushes whether it is an array of the given length
+    IsArray(u32),
+    /// Pops a value and pushes whether it is an `Int` or `Char` between the two constants, inclusive
+    InRange(u32, u32),
+    /// Replaces an array or string with an array of the values a `for` loop visits, leaving generators as they are
+    IntoSequence,
+    /// Pushes the next element of the sequence in the first slot, advancing the index in the second slot,
+    /// or jumps to the target when there are no more elements. Generators are resumed for their next value instead.
+    ForNext(u16, u16, u32),
+    Fail(FailKind),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CastTarget
+{
+    Int,
+    Float,
+    Bool,
+    String,
+    Char,
+    Void,
+    Iter,
+    Array,
+    Function,
+    Type(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FailKind
+{
+    PatternNotMatched,
+    LetElseDidNotDiverge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant
+{
+    Int(i64),
+    Float(f64),
+    String(String),
+    Char(char),
+}
+
+impl std::fmt::Display for Constant
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            Constant::Int(i) => write!(f, "{}", i),
+            Constant::Float(n) => write!(f, "{:?}", n),
+            Constant::String(s) => write!(f, "{:?}", s),
+            Constant::Char(c) => write!(f, "{:?}", c),
+        }
+    }
+}
+
+/// A variable a closure captures when it is created
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Capture
+{
+    /// A local slot of the function creating the closure
+    Local(u16),
+    /// An upvalue of the function creating the closure
+    Upvalue(u16),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Chunk
+{
+    pub code: Vec<Instr>,
+    /// The token each instruction was compiled from, used to report runtime errors
+    pub tokens: Vec<Token>,
+    pub constants: Vec<Constant>,
+}
+
+impl Chunk
+{
+    pub fn emit(&mut self, instr: Instr, token: &Token) -> usize
+    {
+        self.code.push(instr);
+        self.tokens.push(*token);
+        self.code.len() - 1
+    }
+
+    pub fn add_constant(&mut self, constant: Constant) -> u32
+    {
+        match self.constants.iter().position(|c| *c == constant)
+        {
+            Some(index) => index as u32,
+            None =>
+            {
+                self.constants.push(constant);
+                (self.constants.len() - 1) as u32
+            }
+        }
+    }
+
+    pub fn string(&self, index: u32) -> &str
+    {
+        match &self.constants[index as usize]
+        {
+            Constant::String(s) => s,
+            other => unreachable!("expected a string constant, found {}", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FunctionProto
+{
+    pub name: String,
+    /// Whether the first slot holds the receiver of a method
+    pub has_self: bool,
+    /// The number of parameters, not counting `self`
+    pub arity: usize,
+    /// The number of parameters without a default value
+    pub required: usize,
+    /// The total number of local slots, parameters included
+    pub locals: usize,
+    pub captures: Vec<Capture>,
+    /// Parameter slots that closures capture, which are moved into cells when the function is called
+    pub boxed_params: Vec<u16>,
+    /// Whether the body yields, so that calling the function creates a generator instead of running it
+    pub generator: bool,
+    pub chunk: Chunk,
+    /// The file the function was declared in
+    pub module: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum VariantShape
+{
+    /// A member without data, which holds its discriminant
+    Basic(i64),
+    Single,
+    Struct(Vec<String>),
+}
+
+#[derive(Debug, Clone)]
+pub enum TypeShape
+{
+    Struct(Vec<String>),
+    Enum(Vec<(String, VariantShape)>),
+    Interface,
+    /// A type provided by the runtime, such as `Console`
+    Native,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeProto
+{
+    pub name: String,
+    pub shape: TypeShape,
+    /// Methods, as indices into the program's functions
+    pub methods: Vec<(String, u32)>,
+}
+
+/// The compiled form of a program
+#[derive(Debug, Clone)]
+pub struct Bytecode
+{
+    pub functions: Vec<Rc<FunctionProto>>,
+    pub types: Vec<TypeProto>,
+    /// The name of each global slot. The first slots hold the builtins, in the order of `BUILTIN_NAMES`.
+    pub globals: Vec<String>,
+    /// The functions that initialize the globals of each file, in the order they run
+    pub inits: Vec<u32>,
+    /// The global slot of the entry file's `fn main`
+    pub main: Option<u32>,
+}
+
+impl Bytecode
+{
+    pub fn global(&self, name: &str) -> Option<u32>
+    {
+        self.globals.iter().position(|g| g == name).map(|i| i as u32)
+    }
+
+    /// Finds a function by name, with methods named `Type.method`
+    pub fn function(&self, name: &str) -> Option<&Rc<FunctionProto>>
+    {
+        self.functions.iter().find(|f| f.name == name)
+    }
+}
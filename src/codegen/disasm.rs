@@ -0,0 +1,76 @@
+use itertools::Itertools;
+
+use super::chunk::{Bytecode, Capture, CastTarget, FunctionProto, Instr, TypeShape};
+
+/// Renders every function of a program as text, one instruction per line
+pub fn disassemble(bytecode: &Bytecode) -> String
+{
+    bytecode.functions.iter().map(|f| disassemble_function(bytecode, f)).join("\n")
+}
+
+pub fn disassemble_function(bytecode: &Bytecode, function: &FunctionProto) -> String
+{
+    let receiver = if function.has_self { "self, " } else { "" };
+    let mut text = format!("fn {} ({}{} parameter(s), {} local(s))\n", function.name, receiver, function.arity, function.locals);
+
+    if function.generator
+    {
+        text += "    generator\n";
+    }
+
+    if !function.captures.is_empty()
+    {
+        let mut captures = function.captures.iter().map(|c| match c
+        {
+            Capture::Local(slot) => format!("local {}", slot),
+            Capture::Upvalue(index) => format!("upvalue {}", index),
+        });
+
+        text += &format!("    captures: {}\n", captures.join(", "));
+    }
+
+    if !function.boxed_params.is_empty()
+    {
+        text += &format!("    boxed parameters: {}\n", function.boxed_params.iter().join(", "));
+    }
+
+    for (offset, instr) in function.chunk.code.iter().enumerate()
+    {
+        let line = format!("{:?}", instr);
+        match describe(bytecode, function, instr)
+        {
+            Some(note) => text += &format!("    {:04}  {:<28} ; {}\n", offset, line, note),
+            None => text += &format!("    {:04}  {}\n", offset, line),
+        }
+    }
+
+    text
+}
+
+/// A note naming whatever an instruction's operands refer to
+fn describe(bytecode: &Bytecode, function: &FunctionProto, instr: &Instr) -> Option<String>
+{
+    let chunk = &function.chunk;
+    let type_name = |index: u32| bytecode.types[index as usize].name.clone();
+
+    match *instr
+    {
+        Instr::Constant(index) => Some(chunk.constants[index as usize].to_string()),
+        Instr::GetGlobal(slot) | Instr::SetGlobal(slot) => Some(bytecode.globals[slot as usize].clone()),
+        Instr::Closure(index) => Some(bytecode.functions[index as usize].name.clone()),
+        Instr::Type(index) | Instr::Construct(index, _) | Instr::Cast(CastTarget::Type(index)) => Some(type_name(index)),
+        Instr::ConstructVariant(index, variant, _) =>
+        {
+            let variant = match &bytecode.types[index as usize].shape
+            {
+                TypeShape::Enum(variants) => variants[variant as usize].0.clone(),
+                _ => variant.to_string(),
+            };
+
+            Some(format!("{}.{}", type_name(index), variant))
+        },
+        Instr::InRange(start, end) => Some(format!("{}..={}", chunk.constants[start as usize], chunk.constants[end as usize])),
+        Instr::GetMember(name) | Instr::SetField(name) | Instr::SetStatic(name) => Some(chunk.string(name).to_owned()),
+        _ => None,
+    }
+}
@@ -0,0 +1,482 @@
+use either::Either;
+
+use crate::{ast::*, interp::{builtins::builtin_type_kind, token_str}, lexing::token::{Token, TokenType, TokenValue}};
+
+use super::{chunk::{CastTarget, Constant, FailKind, Instr, TypeShape, VariantShape}, CompileError, Compiler, TypeTarget};
+
+impl Compiler
+{
+    /// Emits code that leaves the value of `expression` on the stack
+    pub fn compile_expression(&mut self, expression: &Expression)
+    {
+        match expression
+        {
+            Expression::Lambda(lambda) => self.compile_lambda(lambda),
+            Expression::Literal(token) | Expression::Identifier(token) | Expression::SelfExpr(token) => self.compile_literal(token),
+            Expression::ArrayLiteral(array) =>
+            {
+                for expression in &array.expressions
+                {
+                    self.compile_expression(expression);
+                }
+
+                self.emit(Instr::Array(array.expressions.len() as u32), &array.open_bracket);
+            },
+            Expression::Grouping(grouping) => self.compile_expression(&grouping.expression),
+            Expression::BlockExpr(block) => self.compile_block(block),
+            Expression::TypeValue(type_value) =>
+            {
+                self.compile_type_path(&type_value.type_name);
+                let name = self.name_constant(&token_str(&type_value.name));
+                self.emit(Instr::GetMember(name), &type_value.name);
+            },
+            Expression::Construction(construction) => self.compile_construction(construction),
+            Expression::EnumConstruction(construction) =>
+            {
+                self.compile_expression(&construction.expression);
+                self.compile_cast(&construction.type_name);
+            },
+            Expression::Call(call) =>
+            {
+                self.compile_expression(&call.expression);
+                for arg in &call.args
+                {
+                    self.compile_expression(arg);
+                }
+
+                self.emit(Instr::Call(call.args.len() as u16), &call.open_paren);
+            },
+            Expression::Access(access) => self.compile_access(access),
+            Expression::Index(index) =>
+            {
+                self.compile_expression(&index.expression);
+                self.compile_expression(&index.indexer);
+                self.emit(Instr::GetIndex, &index.open_bracket);
+            },
+            Expression::Unary(unary) =>
+            {
+                self.compile_expression(&unary.expression);
+                let instr = match unary.operator.token_type
+                {
+                    TokenType::Minus => Instr::Negate,
+                    _ => Instr::Not,
+                };
+
+                self.emit(instr, &unary.operator);
+            },
+            Expression::Binary(binary) => self.compile_binary(binary),
+            Expression::IfExpr(if_expr) => self.compile_if(if_expr),
+            Expression::MatchExpr(match_expr) => self.compile_match(match_expr),
+            Expression::Cast(cast) =>
+            {
+                self.compile_expression(&cast.expression);
+                self.compile_cast(&cast.type_name);
+            },
+            Expression::Interpolation(interpolation) => self.compile_interpolation(interpolation),
+            Expression::Yield(yield_expr) =>
+            {
+                if !self.state().proto.generator
+                {
+                    return self.error(CompileError::YieldOutsideGenerator(yield_expr.yield_tok));
+                }
+
+                self.compile_expression(&yield_expr.expression);
+                self.emit(Instr::Yield, &yield_expr.yield_tok);
+            },
+            Expression::Jump(jump) => match jump.keyword.token_type
+            {
+                TokenType::Break => self.emit_break(&jump.keyword),
+                TokenType::Continue => self.emit_continue(&jump.keyword),
+                _ =>
+                {
+                    match &jump.expression
+                    {
+                        Some(expression) => self.compile_expression(expression),
+                        None => { self.emit(Instr::Void, &jump.keyword); },
+                    }
+
+                    self.emit(Instr::Return, &jump.keyword);
+                },
+            },
+        }
+    }
+
+    pub fn compile_literal(&mut self, token: &Token)
+    {
+        let constant = match (token.token_type, &token.value)
+        {
+            (TokenType::IntegerLiteral, Some(TokenValue::Int(i))) => Constant::Int(*i as i64),
+            (TokenType::FloatLiteral, Some(TokenValue::Float(f))) => Constant::Float(*f),
+            (TokenType::StringLiteral, Some(TokenValue::String(s))) => Constant::String(s.to_string()),
+            (TokenType::CharLiteral, Some(TokenValue::Char(c))) => Constant::Char(*c),
+            (TokenType::True, _) =>
+            {
+                self.emit(Instr::True, token);
+                return;
+            },
+            (TokenType::False, _) =>
+            {
+                self.emit(Instr::False, token);
+                return;
+            },
+            (TokenType::SelfVal, _) => return self.load_self(token),
+            (TokenType::SelfType | TokenType::Identifier, _) => return self.load_name(token),
+            _ => return self.error(CompileError::NotAValue(*token)),
+        };
+
+        let index = self.constant(constant);
+        self.emit(Instr::Constant(index), token);
+    }
+
+    /// Compiles a block in its own scope, leaving its value on the stack
+    pub fn compile_block(&mut self, block: &BlockExpr)
+    {
+        self.begin_scope();
+
+        match block.statements.split_last()
+        {
+            None => match &block.expression
+            {
+                Some(expression) => self.compile_expression(expression),
+                None => { self.emit(Instr::Void, &block.close_brace); },
+            },
+            Some((last, statements)) =>
+            {
+                for statement in statements
+                {
+                    self.compile_statement(statement);
+                }
+
+                if let Some(expression) = &block.expression
+                {
+                    self.compile_statement(last);
+                    self.compile_expression(expression);
+                }
+                else
+                {
+                    // The parser reads a trailing `if`, `match` or block as a statement, but it still gives the block its value
+                    match last
+                    {
+                        Statement::If(if_expr) => self.compile_if(if_expr),
+                        Statement::Match(match_expr) => self.compile_match(match_expr),
+                        Statement::Block(block) => self.compile_block(block),
+                        _ =>
+                        {
+                            self.compile_statement(last);
+                            self.emit(Instr::Void, &block.close_brace);
+                        },
+                    }
+                }
+            },
+        }
+
+        self.end_scope();
+    }
+
+    pub fn compile_if(&mut self, if_expr: &IfExpr)
+    {
+        // Bindings made by the condition are only visible in the first block
+        self.begin_scope();
+        let mut fails = vec![];
+        self.compile_condition(&if_expr.condition, &mut fails);
+        self.compile_block(&if_expr.block);
+        self.end_scope();
+
+        let end = self.emit(Instr::Jump(0), &if_expr.if_tok);
+        for at in fails
+        {
+            self.patch_here(at);
+        }
+
+        match &if_expr.else_branch
+        {
+            Some(ElseBranch { else_tok: _, body: Either::Left(if_expr) }) => self.compile_if(if_expr),
+            Some(ElseBranch { else_tok: _, body: Either::Right(block) }) => self.compile_block(block),
+            None => { self.emit(Instr::Void, &if_expr.if_tok); },
+        }
+
+        self.patch_here(end);
+    }
+
+    pub fn compile_match(&mut self, match_expr: &MatchExpr)
+    {
+        self.compile_expression(&match_expr.expression);
+        let value = self.declare_temp();
+        self.emit(Instr::DefineLocal(value), &match_expr.match_tok);
+
+        let mut ends = vec![];
+        for branch in &match_expr.branches
+        {
+            self.begin_scope();
+            let mut fails = vec![];
+            self.compile_pattern(&branch.pattern, value, &mut fails);
+            if let Some((_, guard)) = &branch.guard
+            {
+                self.compile_expression(guard);
+                fails.push(self.emit(Instr::JumpIfFalse(0), guard.get_token()));
+            }
+            self.compile_expression(&branch.expression);
+            self.end_scope();
+
+            ends.push(self.emit(Instr::Jump(0), &branch.arrow));
+            for at in fails
+            {
+                self.patch_here(at);
+            }
+        }
+
+        self.emit(Instr::Fail(FailKind::PatternNotMatched), &match_expr.match_tok);
+        for at in ends
+        {
+            self.patch_here(at);
+        }
+    }
+
+    /// Emits a condition, recording the jumps taken when it does not hold in `fails`.
+    /// Any `let` pattern bindings are made in the current scope.
+    pub fn compile_condition(&mut self, condition: &LetCondition, fails: &mut Vec<usize>)
+    {
+        match condition
+        {
+            LetCondition::Expression(expression) =>
+            {
+                self.compile_expression(expression);
+                fails.push(self.emit(Instr::JumpIfFalse(0), expression.get_token()));
+            },
+            LetCondition::Pattern { let_tok, pattern, equal: _, expression, and: _, other_cond } =>
+            {
+                self.compile_expression(expression);
+                let value = self.declare_temp();
+                self.emit(Instr::DefineLocal(value), let_tok);
+                self.compile_pattern(pattern, value, fails);
+
+                if let Some(other) = other_cond
+                {
+                    self.compile_condition(other, fails);
+                }
+            }
+        }
+    }
+
+    fn compile_access(&mut self, access: &AccessExpr)
+    {
+        if let Some(file) = self.expression_module(&access.expression)
+        {
+            if let Some(binding) = self.module_member(file, &access.identifier)
+            {
+                self.emit_load(binding, &access.identifier);
+            }
+
+            return;
+        }
+
+        self.compile_expression(&access.expression);
+        let name = self.name_constant(&token_str(&access.identifier));
+        self.emit(Instr::GetMember(name), &access.identifier);
+    }
+
+    fn compile_binary(&mut self, binary: &BinaryExpr)
+    {
+        // Logical operators short circuit
+        match binary.operator.token_type
+        {
+            TokenType::AndAnd =>
+            {
+                self.compile_expression(&binary.left);
+                let short = self.emit(Instr::JumpIfFalse(0), binary.left.get_token());
+                self.compile_expression(&binary.right);
+                self.emit(Instr::ExpectBool, binary.right.get_token());
+                let end = self.emit(Instr::Jump(0), &binary.operator);
+                self.patch_here(short);
+                self.emit(Instr::False, &binary.operator);
+                self.patch_here(end);
+            },
+            TokenType::PipePipe =>
+            {
+                self.compile_expression(&binary.left);
+                let right = self.emit(Instr::JumpIfFalse(0), binary.left.get_token());
+                self.emit(Instr::True, &binary.operator);
+                let end = self.emit(Instr::Jump(0), &binary.operator);
+                self.patch_here(right);
+                self.compile_expression(&binary.right);
+                self.emit(Instr::ExpectBool, binary.right.get_token());
+                self.patch_here(end);
+            },
+            op =>
+            {
+                self.compile_expression(&binary.left);
+                self.compile_expression(&binary.right);
+                match binary_instr(op)
+                {
+                    Some(instr) => { self.emit(instr, &binary.operator); },
+                    None => self.error(CompileError::NotAValue(binary.operator)),
+                }
+            },
+        }
+    }
+
+    pub fn compile_cast(&mut self, type_name: &TypeName)
+    {
+        let target = match type_name
+        {
+            TypeName::Array { .. } => CastTarget::Array,
+            TypeName::Function { .. } => CastTarget::Function,
+            TypeName::Identifier { name, args: _ } if builtin_type_kind(&token_str(name)).is_some() => match builtin_type_kind(&token_str(name))
+            {
+                Some("Int") => CastTarget::Int,
+                Some("Float") => CastTarget::Float,
+                Some("Bool") => CastTarget::Bool,
+                Some("String") => CastTarget::String,
+                Some("Char") => CastTarget::Char,
+                Some("Iter") => CastTarget::Iter,
+                _ => CastTarget::Void,
+            },
+            _ => match self.static_type(type_name)
+            {
+                Some(TypeTarget::Type(index)) => CastTarget::Type(index),
+                _ => return self.error(CompileError::UndefinedType(*type_name.get_token())),
+            },
+        };
+
+        self.emit(Instr::Cast(target), type_name.get_token());
+    }
+
+    /// Joins the pieces of an interpolated string by casting each expression to a `String` and adding it on
+    fn compile_interpolation(&mut self, interpolation: &InterpolationExpr)
+    {
+        let start = self.constant(Constant::String(token_str(&interpolation.start)));
+        self.emit(Instr::Constant(start), &interpolation.start);
+
+        for (expression, text) in &interpolation.parts
+        {
+            self.compile_expression(expression);
+            self.emit(Instr::Cast(CastTarget::String), expression.get_token());
+            self.emit(Instr::Add, expression.get_token());
+
+            let text_value = token_str(text);
+            if !text_value.is_empty()
+            {
+                let index = self.constant(Constant::String(text_value));
+                self.emit(Instr::Constant(index), text);
+                self.emit(Instr::Add, text);
+            }
+        }
+    }
+
+    fn compile_construction(&mut self, construction: &ConstructionExpr)
+    {
+        let Some(target) = self.static_type(&construction.type_name) else {
+            return self.error(CompileError::UndefinedType(*construction.type_name.get_token()));
+        };
+
+        // Named from the resolved type, since `Self` has no text of its own
+        let type_name = match target
+        {
+            TypeTarget::Type(index) => self.type_proto(index).name.clone(),
+            TypeTarget::Variant(index, variant) => match &self.type_proto(index).shape
+            {
+                TypeShape::Enum(variants) => format!("{}.{}", self.type_proto(index).name, variants[variant as usize].0),
+                _ => self.type_proto(index).name.clone(),
+            },
+        };
+
+        let constructible = match target
+        {
+            TypeTarget::Type(index) => matches!(self.type_proto(index).shape, TypeShape::Struct(_)),
+            TypeTarget::Variant(index, variant) => match &self.type_proto(index).shape
+            {
+                TypeShape::Enum(variants) => matches!(variants[variant as usize].1, VariantShape::Struct(_)),
+                _ => false,
+            },
+        };
+
+        if !constructible
+        {
+            return self.error(CompileError::NotConstructible(construction.open_brace, type_name));
+        }
+
+        // Arguments are evaluated in the order they are written, and then placed in declaration order
+        let fields = self.field_initializers(target);
+        let mut args = vec![];
+        for arg in &construction.args
+        {
+            let name = token_str(&arg.name);
+            if !fields.iter().any(|(field, _)| *field == name)
+            {
+                self.error(CompileError::UndefinedMember(arg.name, type_name.clone()));
+                continue;
+            }
+
+            self.compile_expression(&arg.value);
+            let slot = self.declare_temp();
+            self.emit(Instr::DefineLocal(slot), &arg.name);
+            args.push((name, slot));
+        }
+
+        for (field, initializer) in &fields
+        {
+            match (args.iter().find(|(name, _)| name == field), initializer)
+            {
+                (Some((_, slot)), _) => { self.emit(Instr::GetLocal(*slot), &construction.open_brace); },
+                (None, Some(expression)) => self.compile_expression(expression),
+                (None, None) => self.error(CompileError::MissingField {
+                    token: construction.open_brace,
+                    field: field.clone(),
+                    type_name: type_name.clone(),
+                }),
+            }
+        }
+
+        let count = fields.len() as u16;
+        match target
+        {
+            TypeTarget::Type(index) => self.emit(Instr::Construct(index, count), &construction.open_brace),
+            TypeTarget::Variant(index, variant) => self.emit(Instr::ConstructVariant(index, variant, count), &construction.open_brace),
+        };
+    }
+
+    fn compile_lambda(&mut self, lambda: &LambdaExpr)
+    {
+        let params: Vec<&Token> = match &lambda.params
+        {
+            LambdaParams::Simple(name) => vec![name],
+            LambdaParams::Complex { parameters, .. } => parameters.iter().map(|p| &p.name).collect(),
+        };
+
+        self.begin_function("<lambda>".into(), None, false);
+        for param in params
+        {
+            self.declare_param(param);
+            let proto = &mut self.state_mut().proto;
+            proto.arity += 1;
+            proto.required += 1;
+        }
+
+        self.compile_expression(&lambda.expression);
+        self.emit(Instr::Return, &lambda.arrow);
+        let index = self.end_function();
+        self.emit(Instr::Closure(index), &lambda.arrow);
+    }
+}
+
+/// The instruction for a binary operator that evaluates both of its operands
+pub fn binary_instr(op: TokenType) -> Option<Instr>
+{
+    match op
+    {
+        TokenType::Plus => Some(Instr::Add),
+        TokenType::Minus => Some(Instr::Subtract),
+        TokenType::Multiply => Some(Instr::Multiply),
+        TokenType::Divide => Some(Instr::Divide),
+        TokenType::Modulus => Some(Instr::Modulus),
+        TokenType::EqualEqual => Some(Instr::Equal),
+        TokenType::BangEqual => Some(Instr::NotEqual),
+        TokenType::LessThan => Some(Instr::Less),
+        TokenType::LessEqual => Some(Instr::LessEqual),
+        TokenType::GreaterThan => Some(Instr::Greater),
+        TokenType::GreaterEqual => Some(Instr::GreaterEqual),
+        TokenType::AndAnd => Some(Instr::And),
+        TokenType::PipePipe => Some(Instr::Or),
+        _ => None,
+    }
+}
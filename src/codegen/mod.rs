@@ -0,0 +1,1122 @@
+pub mod chunk;
+pub mod disasm;
+pub mod expr_gen;
+pub mod stmt_gen;
+pub mod pattern_gen;
+
+use std::{collections::{HashMap, HashSet}, rc::Rc};
+
+use chunk::{Bytecode, Capture, Chunk, Constant, FunctionProto, Instr, TypeProto, TypeShape, VariantShape};
+
+use crate::{consteval::{ConstEvaluator, ConstValue}, diagnostic::Diagnostic, ast::*, interp::{builtins::BUILTIN_NAMES, token_str}, lexing::token::{Token, TokenPos, TokenType}, resolve::let_binding_tokens, workspace::{FileId, Import, Workspace}};
+
+#[derive(Debug, Clone)]
+pub enum CompileError
+{
+    UndefinedName(Token),
+    UndefinedType(Token),
+    UndefinedMember(Token, String),
+    NotAValue(Token),
+    NotConstructible(Token, String),
+    MissingField
+    {
+        token: Token,
+        field: String,
+        type_name: String,
+    },
+    InvalidAssignment(Token),
+    BreakOutsideLoop(Token),
+    ContinueOutsideLoop(Token),
+    YieldOutsideGenerator(Token),
+}
+
+impl CompileError
+{
+    pub fn get_token(&self) -> &Token
+    {
+        match self
+        {
+            CompileError::UndefinedName(token) => token,
+            CompileError::UndefinedType(token) => token,
+            CompileError::UndefinedMember(token, _) => token,
+            CompileError::NotAValue(token) => token,
+            CompileError::NotConstructible(token, _) => token,
+            CompileError::MissingField { token, .. } => token,
+            CompileError::InvalidAssignment(token) => token,
+            CompileError::BreakOutsideLoop(token) => token,
+            CompileError::ContinueOutsideLoop(token) => token,
+            CompileError::YieldOutsideGenerator(token) => token,
+        }
+    }
+}
+
+impl std::fmt::Display for CompileError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            CompileError::UndefinedName(token) => write!(f, "Undefined name `{}`", token_str(token)),
+            CompileError::UndefinedType(token) => write!(f, "Undefined type `{}`", token_str(token)),
+            CompileError::UndefinedMember(token, owner) => write!(f, "`{}` has no member `{}`", owner, token_str(token)),
+            CompileError::NotAValue(token) => write!(f, "`{}` cannot be used as a value", token_str(token)),
+            CompileError::NotConstructible(_, type_name) => write!(f, "`{}` cannot be constructed with fields", type_name),
+            CompileError::MissingField { token: _, field, type_name } => write!(f, "Missing field `{}` when constructing `{}`", field, type_name),
+            CompileError::InvalidAssignment(_) => write!(f, "Invalid assignment target"),
+            CompileError::BreakOutsideLoop(_) => write!(f, "`break` outside of a loop"),
+            CompileError::ContinueOutsideLoop(_) => write!(f, "`continue` outside of a loop"),
+            CompileError::YieldOutsideGenerator(_) => write!(f, "`yield` can only be used in the body of a `fn`"),
+        }
+    }
+}
+
+impl From<&CompileError> for Diagnostic
+{
+    fn from(error: &CompileError) -> Self
+    {
+        let (code, label) = match error
+        {
+            CompileError::UndefinedName(_) => ("E0501", "not found in this scope".to_owned()),
+            CompileError::UndefinedType(_) => ("E0502", "not found in this scope".to_owned()),
+            CompileError::UndefinedMember(_, owner) => ("E0503", format!("not a member of `{}`", owner)),
+            CompileError::NotAValue(_) => ("E0504", "not a value".to_owned()),
+            CompileError::NotConstructible(_, _) => ("E0505", "has no fields".to_owned()),
+            CompileError::MissingField { token: _, field, type_name: _ } => ("E0506", format!("missing `{}`", field)),
+            CompileError::InvalidAssignment(_) => ("E0507", "cannot be assigned to".to_owned()),
+            CompileError::BreakOutsideLoop(_) => ("E0508", "cannot `break` here".to_owned()),
+            CompileError::ContinueOutsideLoop(_) => ("E0509", "cannot `continue` here".to_owned()),
+            CompileError::YieldOutsideGenerator(_) => ("E0510", "cannot `yield` here".to_owned()),
+        };
+
+        Diagnostic::error(error.to_string()).with_code(code).with_primary(error.get_token().pos, label)
+    }
+}
+
+/// Where the value of a name lives at runtime
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Place
+{
+    Local(u16),
+    Upvalue(u16),
+    Global(u32),
+    /// A module bound by `use`, which only exists at compile time
+    Module(FileId),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Binding
+{
+    pub place: Place,
+    /// The type the name refers to, when it names one
+    pub type_index: Option<u32>,
+}
+
+/// A type name resolved at compile time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TypeTarget
+{
+    Type(u32),
+    Variant(u32, u16),
+}
+
+/// The declarations a type was compiled from, needed for field defaults and interface methods
+#[derive(Debug, Clone)]
+enum TypeSource
+{
+    Struct(Rc<StructDecl>),
+    Enum(Rc<EnumDecl>),
+    Interface(Rc<InterfaceDecl>),
+}
+
+#[derive(Debug, Clone)]
+struct Local
+{
+    name: String,
+    slot: u16,
+    depth: usize,
+    type_index: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+struct LoopState
+{
+    continue_target: usize,
+    height: u16, // the slot holding the height of the stack when the loop was entered
+    breaks: Vec<usize>,
+}
+
+/// A function whose body is being compiled
+#[derive(Debug)]
+struct FunctionState
+{
+    proto: FunctionProto,
+    locals: Vec<Local>,
+    depth: usize,
+    /// The number of slots taken by `self` and the parameters
+    params: u16,
+    /// Slots that nested closures capture, which must live in cells
+    captured: HashSet<u16>,
+    loops: Vec<LoopState>,
+    /// The type of `Self` inside methods
+    self_type: Option<u32>,
+}
+
+pub struct Compiler
+{
+    functions: Vec<Rc<FunctionProto>>,
+    types: Vec<TypeProto>,
+    sources: Vec<TypeSource>,
+    globals: Vec<String>,
+    prelude: HashMap<String, Binding>,
+    /// The names declared or imported at the top level of each file
+    modules: HashMap<FileId, HashMap<String, Binding>>,
+    exports: HashMap<FileId, HashSet<String>>,
+    /// The file being compiled
+    module: FileId,
+    /// The values of the constants of the file being compiled, by the position of their names
+    consts: HashMap<TokenPos, ConstValue>,
+    inits: Vec<u32>,
+    states: Vec<FunctionState>,
+    errors: Vec<CompileError>,
+}
+
+/// Compiles a single program into bytecode
+pub fn compile_program(program: &Program) -> Result<Bytecode, Vec<CompileError>>
+{
+    let mut compiler = Compiler::new();
+    let mut consts = ConstEvaluator::new();
+    consts.eval_program(program);
+    compiler.consts = consts.values().clone();
+    compiler.compile_module(0, None, program, &[]);
+    compiler.finish()
+}
+
+/// Compiles every file of a workspace, dependencies first. Errors are returned along with the file they occurred in.
+pub fn compile_workspace(workspace: &Workspace) -> Result<Bytecode, Vec<(FileId, CompileError)>>
+{
+    let mut compiler = Compiler::new();
+    let mut errors = vec![];
+    let consts = workspace.eval_consts().unwrap_or_default();
+    for &id in workspace.order()
+    {
+        let file = workspace.file(id);
+        let Some(program) = &file.program else { continue };
+
+        compiler.exports.insert(id, file.exports.clone());
+        compiler.consts = consts.get(&id).map(|c| c.values().clone()).unwrap_or_default();
+        let prefix = (id != workspace.entry()).then(|| file.module_name());
+        compiler.compile_module(id, prefix, program, &file.imports);
+        errors.extend(compiler.errors.drain(..).map(|e| (id, e)));
+    }
+
+    let entry = workspace.entry();
+    compiler.module = entry;
+    match errors.is_empty()
+    {
+        true => compiler.finish().map_err(|errors| errors.into_iter().map(|e| (entry, e)).collect()),
+        false => Err(errors),
+    }
+}
+
+impl Default for Compiler
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Compiler
+{
+    pub fn new() -> Self
+    {
+        let globals: Vec<String> = BUILTIN_NAMES.iter().map(|n| n.to_string()).collect();
+        let prelude = globals.iter().enumerate()
+            .map(|(i, name)| (name.clone(), Binding { place: Place::Global(i as u32), type_index: None }))
+            .collect();
+
+        Self {
+            functions: vec![],
+            types: vec![],
+            sources: vec![],
+            globals,
+            prelude,
+            modules: HashMap::new(),
+            exports: HashMap::new(),
+            module: 0,
+            consts: HashMap::new(),
+            inits: vec![],
+            states: vec![],
+            errors: vec![],
+        }
+    }
+
+    pub fn finish(self) -> Result<Bytecode, Vec<CompileError>>
+    {
+        if !self.errors.is_empty()
+        {
+            return Err(self.errors);
+        }
+
+        let main = self.modules.get(&self.module)
+            .and_then(|names| names.get("main"))
+            .and_then(|b| match b.place
+            {
+                Place::Global(slot) => Some(slot),
+                _ => None,
+            });
+
+        Ok(Bytecode { functions: self.functions, types: self.types, globals: self.globals, inits: self.inits, main })
+    }
+
+    fn error(&mut self, error: CompileError)
+    {
+        self.errors.push(error);
+    }
+
+    /// Compiles the declarations of a file into a function that initializes its globals
+    pub fn compile_module(&mut self, id: FileId, prefix: Option<String>, program: &Program, imports: &[Import])
+    {
+        self.module = id;
+
+        let mut names = HashMap::new();
+        for import in imports
+        {
+            let binding = match &import.item
+            {
+                Some(item) => self.modules.get(&import.source).and_then(|m| m.get(item)).copied(),
+                None => Some(Binding { place: Place::Module(import.source), type_index: None }),
+            };
+
+            if let Some(binding) = binding
+            {
+                names.insert(import.name.clone(), binding);
+            }
+        }
+
+        self.modules.insert(id, names);
+        self.declare_globals(program, prefix.as_deref());
+
+        let name = match &prefix
+        {
+            Some(prefix) => format!("<init {}>", prefix),
+            None => "<init>".to_string(),
+        };
+
+        self.begin_function(name, None, false);
+        let eof = &program.eof;
+
+        // Mirrors the interpreter: items first, then aliases, then constants, then impls, then top-level lets
+        for declaration in &program.declarations
+        {
+            match declaration
+            {
+                Declaration::Fn(_, decl) =>
+                {
+                    let index = self.compile_function(decl, token_str(&decl.id), None, false);
+                    self.emit(Instr::Closure(index), &decl.id);
+                    self.store_global(&decl.id);
+                },
+                Declaration::Struct(_, StructDecl { id, .. }) | Declaration::Enum(_, EnumDecl { id, .. }) | Declaration::Interface(_, InterfaceDecl { id, .. }) =>
+                {
+                    if let Some(index) = self.global(&token_str(id)).and_then(|b| b.type_index)
+                    {
+                        self.emit(Instr::Type(index), id);
+                        self.store_global(id);
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        for declaration in &program.declarations
+        {
+            if let Declaration::Type(_, decl) = declaration
+            {
+                self.compile_type_path(&decl.type_name);
+                self.store_global(&decl.id);
+            }
+        }
+
+        for declaration in &program.declarations
+        {
+            if let Declaration::Const(_, decl) = declaration
+            {
+                self.compile_const(decl);
+                self.store_global(&decl.id);
+            }
+        }
+
+        for declaration in &program.declarations
+        {
+            if let Declaration::Impl(impl_stmt) = declaration
+            {
+                self.compile_impl(impl_stmt);
+            }
+        }
+
+        for declaration in &program.declarations
+        {
+            if let Declaration::Let(_, let_stmt) = declaration
+            {
+                self.compile_let(let_stmt);
+                for token in let_binding_tokens(&let_stmt.binding)
+                {
+                    self.load_name(token);
+                    self.store_global(token);
+                }
+            }
+        }
+
+        self.emit(Instr::Void, eof);
+        self.emit(Instr::Return, eof);
+        let index = self.end_function();
+        self.inits.push(index);
+    }
+
+    /// Gives every top-level name of a file a global slot, so functions can refer to items declared after them
+    fn declare_globals(&mut self, program: &Program, prefix: Option<&str>)
+    {
+        for declaration in &program.declarations
+        {
+            let type_index = match declaration
+            {
+                Declaration::Struct(_, decl) => Some(self.declare_type(TypeSource::Struct(Rc::new(decl.clone())))),
+                Declaration::Enum(_, decl) => Some(self.declare_type(TypeSource::Enum(Rc::new(decl.clone())))),
+                Declaration::Interface(_, decl) => Some(self.declare_type(TypeSource::Interface(Rc::new(decl.clone())))),
+                _ => None,
+            };
+
+            let tokens = match declaration
+            {
+                Declaration::Fn(_, decl) => vec![&decl.id],
+                Declaration::Struct(_, decl) => vec![&decl.id],
+                Declaration::Enum(_, decl) => vec![&decl.id],
+                Declaration::Interface(_, decl) => vec![&decl.id],
+                Declaration::Type(_, decl) => vec![&decl.id],
+                Declaration::Let(_, let_stmt) => let_binding_tokens(&let_stmt.binding),
+                Declaration::Const(_, decl) => vec![&decl.id],
+                Declaration::Use(_, _) | Declaration::Mod(_, _) | Declaration::Impl(_) => vec![],
+            };
+
+            for token in tokens
+            {
+                let name = token_str(token);
+                self.globals.push(match prefix
+                {
+                    Some(prefix) => format!("{}.{}", prefix, name),
+                    None => name.clone(),
+                });
+
+                let place = Place::Global((self.globals.len() - 1) as u32);
+                self.modules.get_mut(&self.module).unwrap().insert(name, Binding { place, type_index });
+            }
+        }
+
+        // Aliases are resolved once every type of the file is known
+        for declaration in &program.declarations
+        {
+            if let Declaration::Type(_, decl) = declaration
+            {
+                let type_index = match self.static_type(&decl.type_name)
+                {
+                    Some(TypeTarget::Type(index)) => Some(index),
+                    _ => None,
+                };
+
+                if let Some(binding) = self.modules.get_mut(&self.module).unwrap().get_mut(&token_str(&decl.id))
+                {
+                    binding.type_index = type_index;
+                }
+            }
+        }
+    }
+
+    fn declare_type(&mut self, source: TypeSource) -> u32
+    {
+        let (name, shape) = match &source
+        {
+            TypeSource::Struct(decl) => (token_str(&decl.id), TypeShape::Struct(decl.members.iter().map(|m| token_str(&m.id)).collect())),
+            TypeSource::Enum(decl) => (token_str(&decl.id), TypeShape::Enum(decl.members.iter().map(|m| match m
+            {
+                EnumMember::Basic { id, .. } => match self.consts.get(&id.pos)
+                {
+                    Some(ConstValue::Int(discriminant)) => (token_str(id), VariantShape::Basic(*discriminant)),
+                    _ => (token_str(id), VariantShape::Basic(0)),
+                },
+                EnumMember::Single { id, .. } => (token_str(id), VariantShape::Single),
+                EnumMember::Struct { id, members, .. } => (token_str(id), VariantShape::Struct(members.iter().map(|m| token_str(&m.id)).collect())),
+            }).collect())),
+            TypeSource::Interface(decl) => (token_str(&decl.id), TypeShape::Interface),
+        };
+
+        self.types.push(TypeProto { name, shape, methods: vec![] });
+        self.sources.push(source);
+        (self.types.len() - 1) as u32
+    }
+
+    fn global(&self, name: &str) -> Option<Binding>
+    {
+        self.modules.get(&self.module).and_then(|m| m.get(name)).or_else(|| self.prelude.get(name)).copied()
+    }
+
+    fn store_global(&mut self, token: &Token)
+    {
+        if let Some(Binding { place: Place::Global(slot), .. }) = self.global(&token_str(token))
+        {
+            self.emit(Instr::SetGlobal(slot), token);
+        }
+    }
+
+    fn compile_impl(&mut self, impl_stmt: &ImplStmt)
+    {
+        let (target, interface) = match &impl_stmt.for_clause
+        {
+            Some((_, target)) => (target, Some(&impl_stmt.type_name)),
+            None => (&impl_stmt.type_name, None),
+        };
+
+        let Some(TypeTarget::Type(type_index)) = self.static_type(target) else {
+            self.error(CompileError::UndefinedType(*target.get_token()));
+            return;
+        };
+
+        for (_, member) in &impl_stmt.members
+        {
+            match member
+            {
+                Statement::FnDecl(decl) => self.compile_method(type_index, decl),
+                Statement::Let(let_stmt) =>
+                {
+                    self.begin_scope();
+                    self.compile_let(let_stmt);
+                    for token in let_binding_tokens(&let_stmt.binding)
+                    {
+                        let name = self.name_constant(&token_str(token));
+                        self.emit(Instr::Type(type_index), token);
+                        self.load_name(token);
+                        self.emit(Instr::SetStatic(name), token);
+                    }
+
+                    self.end_scope();
+                },
+                Statement::TypeDecl(decl) =>
+                {
+                    let name = self.name_constant(&token_str(&decl.id));
+                    self.emit(Instr::Type(type_index), &decl.id);
+                    self.compile_type_path(&decl.type_name);
+                    self.emit(Instr::SetStatic(name), &decl.id);
+                },
+                Statement::Const(decl) =>
+                {
+                    let name = self.name_constant(&token_str(&decl.id));
+                    self.emit(Instr::Type(type_index), &decl.id);
+                    self.compile_const(decl);
+                    self.emit(Instr::SetStatic(name), &decl.id);
+                },
+                _ => {},
+            }
+        }
+
+        // Interface methods that have a default body are inherited when not overridden
+        let Some(interface) = interface else { return };
+        let Some(TypeTarget::Type(interface_index)) = self.static_type(interface) else {
+            self.error(CompileError::UndefinedType(*interface.get_token()));
+            return;
+        };
+
+        let TypeSource::Interface(decl) = self.sources[interface_index as usize].clone() else { return };
+        for member in &decl.members
+        {
+            let Statement::FnDecl(fn_decl) = member else { continue };
+            let name = token_str(&fn_decl.id);
+            if fn_decl.body.is_right() || self.types[type_index as usize].methods.iter().any(|(n, _)| *n == name)
+            {
+                continue;
+            }
+
+            self.compile_method(type_index, fn_decl);
+        }
+    }
+
+    fn compile_method(&mut self, type_index: u32, decl: &FnDecl)
+    {
+        let name = token_str(&decl.id);
+        let qualified = format!("{}.{}", self.types[type_index as usize].name, name);
+        let index = self.compile_function(decl, qualified, Some(type_index), false);
+
+        let methods = &mut self.types[type_index as usize].methods;
+        match methods.iter_mut().find(|(n, _)| *n == name)
+        {
+            Some(method) => method.1 = index,
+            None => methods.push((name, index)),
+        }
+    }
+
+    /// Compiles a function declaration, returning its index. Top-level functions and methods cannot capture anything,
+    /// so they are compiled apart from the functions being compiled around them.
+    pub fn compile_function(&mut self, decl: &FnDecl, name: String, self_type: Option<u32>, nested: bool) -> u32
+    {
+        let saved = match nested
+        {
+            true => None,
+            false => Some(std::mem::take(&mut self.states)),
+        };
+
+        let has_self = decl.params.iter().any(|p| matches!(p, FnParam::SelfParam { .. }));
+        self.begin_function(name, self_type, has_self);
+        self.state_mut().proto.generator = decl.is_generator();
+
+        let mut defaults = vec![];
+        for param in &decl.params
+        {
+            match param
+            {
+                FnParam::SelfParam { mut_tok: _, self_tok } =>
+                {
+                    self.declare_param(self_tok);
+                },
+                FnParam::Normal { mut_tok: _, id, colon: _, type_name: _, default_value } =>
+                {
+                    let slot = self.declare_param(id);
+                    self.state_mut().proto.arity += 1;
+                    match default_value
+                    {
+                        Some((_, expression)) => defaults.push((slot, expression)),
+                        None => self.state_mut().proto.required += 1,
+                    }
+                }
+            }
+        }
+
+        // Defaults are evaluated in the callee, and only for the arguments the caller left out
+        for (slot, expression) in defaults
+        {
+            let skip = self.emit(Instr::JumpIfArgPassed(slot, 0), expression.get_token());
+            self.compile_expression(expression);
+            self.emit(Instr::SetLocal(slot), expression.get_token());
+            self.patch_here(skip);
+        }
+
+        match &decl.body
+        {
+            either::Either::Left(body) =>
+            {
+                self.compile_block(body);
+                self.emit(Instr::Return, &body.close_brace);
+            },
+            either::Either::Right(semi_colon) =>
+            {
+                self.emit(Instr::Void, semi_colon);
+                self.emit(Instr::Return, semi_colon);
+            },
+        }
+
+        let index = self.end_function();
+        if let Some(saved) = saved
+        {
+            self.states = saved;
+        }
+
+        index
+    }
+
+    pub fn begin_function(&mut self, name: String, self_type: Option<u32>, has_self: bool)
+    {
+        let self_type = self_type.or_else(|| self.states.last().and_then(|s| s.self_type));
+        self.states.push(FunctionState {
+            proto: FunctionProto {
+                name,
+                has_self,
+                arity: 0,
+                required: 0,
+                locals: 0,
+                captures: vec![],
+                boxed_params: vec![],
+                generator: false,
+                chunk: Chunk::default(),
+                module: self.module,
+            },
+            locals: vec![],
+            depth: 0,
+            params: 0,
+            captured: HashSet::new(),
+            loops: vec![],
+            self_type,
+        });
+    }
+
+    /// Finishes the innermost function, moving the slots its closures capture into cells
+    pub fn end_function(&mut self) -> u32
+    {
+        let state = self.states.pop().expect("a function is being compiled");
+        let mut proto = state.proto;
+        let captured = &state.captured;
+
+        for instr in &mut proto.chunk.code
+        {
+            *instr = match *instr
+            {
+                Instr::GetLocal(slot) if captured.contains(&slot) => Instr::GetCell(slot),
+                Instr::SetLocal(slot) if captured.contains(&slot) => Instr::SetCell(slot),
+                Instr::DefineLocal(slot) if captured.contains(&slot) => Instr::DefineCell(slot),
+                other => other,
+            };
+        }
+
+        proto.boxed_params = (0..state.params).filter(|s| captured.contains(s)).collect();
+        self.functions.push(Rc::new(proto));
+        (self.functions.len() - 1) as u32
+    }
+
+    fn state(&self) -> &FunctionState
+    {
+        self.states.last().expect("a function is being compiled")
+    }
+
+    fn state_mut(&mut self) -> &mut FunctionState
+    {
+        self.states.last_mut().expect("a function is being compiled")
+    }
+
+    pub fn chunk(&mut self) -> &mut Chunk
+    {
+        &mut self.state_mut().proto.chunk
+    }
+
+    pub fn emit(&mut self, instr: Instr, token: &Token) -> usize
+    {
+        self.chunk().emit(instr, token)
+    }
+
+    /// The offset of the next instruction
+    pub fn here(&self) -> usize
+    {
+        self.state().proto.chunk.code.len()
+    }
+
+    /// Points the jump at `at` to `target`
+    pub fn patch(&mut self, at: usize, target: usize)
+    {
+        let target = target as u32;
+        let instr = &mut self.chunk().code[at];
+        *instr = match *instr
+        {
+            Instr::Jump(_) => Instr::Jump(target),
+            Instr::JumpIfFalse(_) => Instr::JumpIfFalse(target),
+            Instr::JumpIfArgPassed(slot, _) => Instr::JumpIfArgPassed(slot, target),
+            Instr::ForNext(sequence, index, _) => Instr::ForNext(sequence, index, target),
+            other => unreachable!("{:?} is not a jump", other),
+        };
+    }
+
+    pub fn patch_here(&mut self, at: usize)
+    {
+        let here = self.here();
+        self.patch(at, here);
+    }
+
+    pub fn constant(&mut self, constant: Constant) -> u32
+    {
+        self.chunk().add_constant(constant)
+    }
+
+    pub fn name_constant(&mut self, name: &str) -> u32
+    {
+        self.constant(Constant::String(name.to_owned()))
+    }
+
+    pub fn begin_scope(&mut self)
+    {
+        self.state_mut().depth += 1;
+    }
+
+    pub fn end_scope(&mut self)
+    {
+        let state = self.state_mut();
+        state.depth -= 1;
+        let depth = state.depth;
+        state.locals.retain(|l| l.depth <= depth);
+    }
+
+    fn next_slot(&mut self) -> u16
+    {
+        let state = self.state_mut();
+        let slot = state.proto.locals as u16;
+        state.proto.locals += 1;
+        slot
+    }
+
+    fn declare_param(&mut self, token: &Token) -> u16
+    {
+        let name = match token.token_type
+        {
+            TokenType::SelfVal => "self".to_owned(),
+            _ => token_str(token),
+        };
+
+        let slot = self.declare_local(&name, None);
+        self.state_mut().params += 1;
+        slot
+    }
+
+    /// Gives a name a new slot in the current scope
+    pub fn declare_local(&mut self, name: &str, type_index: Option<u32>) -> u16
+    {
+        let slot = self.next_slot();
+        let state = self.state_mut();
+        let depth = state.depth;
+        state.locals.push(Local { name: name.to_owned(), slot, depth, type_index });
+        slot
+    }
+
+    /// A slot for an intermediate value, which no name refers to
+    pub fn declare_temp(&mut self) -> u16
+    {
+        self.next_slot()
+    }
+
+    /// Records the height of the stack before a loop, so its `break`s and `continue`s can get back to it
+    pub fn mark_stack(&mut self, token: &Token) -> u16
+    {
+        let height = self.declare_temp();
+        self.emit(Instr::MarkStack(height), token);
+        height
+    }
+
+    pub fn push_loop(&mut self, continue_target: usize, height: u16)
+    {
+        self.state_mut().loops.push(LoopState { continue_target, height, breaks: vec![] });
+    }
+
+    /// Ends the innermost loop, pointing its `break`s at the next instruction
+    pub fn pop_loop(&mut self)
+    {
+        let state = self.state_mut().loops.pop().expect("a loop is being compiled");
+        for at in state.breaks
+        {
+            self.patch_here(at);
+        }
+    }
+
+    pub fn emit_break(&mut self, token: &Token)
+    {
+        let Some(height) = self.state().loops.last().map(|l| l.height) else {
+            self.error(CompileError::BreakOutsideLoop(*token));
+            return;
+        };
+
+        self.emit(Instr::ResetStack(height), token);
+        let at = self.emit(Instr::Jump(0), token);
+        self.state_mut().loops.last_mut().unwrap().breaks.push(at);
+    }
+
+    pub fn emit_continue(&mut self, token: &Token)
+    {
+        let Some((target, height)) = self.state().loops.last().map(|l| (l.continue_target, l.height)) else {
+            self.error(CompileError::ContinueOutsideLoop(*token));
+            return;
+        };
+
+        self.emit(Instr::ResetStack(height), token);
+        self.emit(Instr::Jump(target as u32), token);
+    }
+
+    fn find_local(&self, state: usize, name: &str) -> Option<(u16, Option<u32>)>
+    {
+        self.states[state].locals.iter().rev().find(|l| l.name == name).map(|l| (l.slot, l.type_index))
+    }
+
+    fn find_upvalue(&mut self, state: usize, name: &str) -> Option<(u16, Option<u32>)>
+    {
+        if state == 0
+        {
+            return None;
+        }
+
+        let (capture, type_index) = if let Some((slot, type_index)) = self.find_local(state - 1, name)
+        {
+            self.states[state - 1].captured.insert(slot);
+            (Capture::Local(slot), type_index)
+        }
+        else
+        {
+            let (index, type_index) = self.find_upvalue(state - 1, name)?;
+            (Capture::Upvalue(index), type_index)
+        };
+
+        let function = &mut self.states[state];
+        if let Some(index) = function.proto.captures.iter().position(|c| *c == capture)
+        {
+            return Some((index as u16, type_index));
+        }
+
+        function.proto.captures.push(capture);
+        Some(((function.proto.captures.len() - 1) as u16, type_index))
+    }
+
+    /// Resolves a name for use in the current function, capturing it from an enclosing function if needed
+    pub fn resolve_name(&mut self, name: &str) -> Option<Binding>
+    {
+        if let Some(current) = self.states.len().checked_sub(1)
+        {
+            if let Some((slot, type_index)) = self.find_local(current, name)
+            {
+                return Some(Binding { place: Place::Local(slot), type_index });
+            }
+
+            if let Some((index, type_index)) = self.find_upvalue(current, name)
+            {
+                return Some(Binding { place: Place::Upvalue(index), type_index });
+            }
+        }
+
+        self.global(name)
+    }
+
+    /// Looks a name up without capturing it, for names that are only needed at compile time
+    pub fn peek_name(&self, name: &str) -> Option<Binding>
+    {
+        for state in self.states.iter().rev()
+        {
+            if let Some(local) = state.locals.iter().rev().find(|l| l.name == name)
+            {
+                return Some(Binding { place: Place::Local(local.slot), type_index: local.type_index });
+            }
+        }
+
+        self.global(name)
+    }
+
+    /// Whether `name` is a field of `self` inside a struct's methods, which can be referred to directly
+    pub fn is_self_field(&self, name: &str) -> bool
+    {
+        let Some(self_type) = self.states.last().and_then(|s| s.self_type) else { return false };
+        match &self.types[self_type as usize].shape
+        {
+            TypeShape::Struct(fields) => fields.iter().any(|f| f == name),
+            _ => false,
+        }
+    }
+
+    pub fn self_type(&self) -> Option<u32>
+    {
+        self.states.last().and_then(|s| s.self_type)
+    }
+
+    pub fn emit_load(&mut self, binding: Binding, token: &Token)
+    {
+        match binding.place
+        {
+            Place::Local(slot) => { self.emit(Instr::GetLocal(slot), token); },
+            Place::Upvalue(index) => { self.emit(Instr::GetUpvalue(index), token); },
+            Place::Global(slot) => { self.emit(Instr::GetGlobal(slot), token); },
+            Place::Module(_) => self.error(CompileError::NotAValue(*token)),
+        }
+    }
+
+    pub fn emit_store(&mut self, binding: Binding, token: &Token)
+    {
+        match binding.place
+        {
+            Place::Local(slot) => { self.emit(Instr::SetLocal(slot), token); },
+            Place::Upvalue(index) => { self.emit(Instr::SetUpvalue(index), token); },
+            Place::Global(slot) => { self.emit(Instr::SetGlobal(slot), token); },
+            Place::Module(_) => self.error(CompileError::InvalidAssignment(*token)),
+        }
+    }
+
+    /// Loads the value a name refers to, falling back on the fields of `self` inside methods
+    pub fn load_name(&mut self, token: &Token)
+    {
+        if token.token_type == TokenType::SelfType
+        {
+            match self.self_type()
+            {
+                Some(index) => self.emit(Instr::Type(index), token),
+                None => return self.error(CompileError::UndefinedName(*token)),
+            };
+
+            return;
+        }
+
+        let name = token_str(token);
+        if let Some(binding) = self.resolve_name(&name)
+        {
+            return self.emit_load(binding, token);
+        }
+
+        if self.is_self_field(&name)
+        {
+            self.load_self(token);
+            let name = self.name_constant(&name);
+            self.emit(Instr::GetMember(name), token);
+            return;
+        }
+
+        self.error(CompileError::UndefinedName(*token));
+    }
+
+    pub fn load_self(&mut self, token: &Token)
+    {
+        match self.resolve_name("self")
+        {
+            Some(binding) => self.emit_load(binding, token),
+            None => self.error(CompileError::UndefinedName(*token)),
+        }
+    }
+
+    /// The module a type path or expression names, when it is a module bound by `use` or `mod`
+    pub fn module_of(&self, token: &Token) -> Option<FileId>
+    {
+        match self.peek_name(&token_str(token))
+        {
+            Some(Binding { place: Place::Module(file), .. }) => Some(file),
+            _ => None,
+        }
+    }
+
+    /// The module an expression such as `shapes` or `shapes.solid` names
+    pub fn expression_module(&self, expression: &Expression) -> Option<FileId>
+    {
+        match expression
+        {
+            Expression::Literal(token) | Expression::Identifier(token) => self.module_of(token),
+            Expression::Access(access) => self.exported_module(self.expression_module(&access.expression)?, &access.identifier),
+            _ => None,
+        }
+    }
+
+    /// The module a type path such as `shapes` or `shapes.solid` names
+    pub fn type_module(&self, type_name: &TypeName) -> Option<FileId>
+    {
+        match type_name
+        {
+            TypeName::Identifier { name, args: None } => self.module_of(name),
+            TypeName::Access { inner, dot: _, name, args: None } => self.exported_module(self.type_module(inner)?, name),
+            _ => None,
+        }
+    }
+
+    /// A module that another module declares or imports and exports
+    fn exported_module(&self, file: FileId, token: &Token) -> Option<FileId>
+    {
+        let name = token_str(token);
+        let exported = self.exports.get(&file).is_some_and(|e| e.contains(&name));
+        match exported.then(|| self.modules.get(&file).and_then(|m| m.get(&name))).flatten()
+        {
+            Some(Binding { place: Place::Module(module), .. }) => Some(*module),
+            _ => None,
+        }
+    }
+
+    /// An exported name of another module
+    pub fn module_member(&mut self, file: FileId, token: &Token) -> Option<Binding>
+    {
+        let name = token_str(token);
+        let exported = self.exports.get(&file).is_some_and(|e| e.contains(&name));
+        match exported.then(|| self.modules.get(&file).and_then(|m| m.get(&name)).copied()).flatten()
+        {
+            Some(binding) => Some(binding),
+            None =>
+            {
+                self.error(CompileError::UndefinedMember(*token, "Module".into()));
+                None
+            }
+        }
+    }
+
+    /// Resolves a type name to the type or enum variant it names, without emitting anything
+    pub fn static_type(&self, type_name: &TypeName) -> Option<TypeTarget>
+    {
+        match type_name
+        {
+            TypeName::Identifier { name, args: _ } if name.token_type == TokenType::SelfType => self.self_type().map(TypeTarget::Type),
+            TypeName::Identifier { name, args: _ } => self.peek_name(&token_str(name)).and_then(|b| b.type_index).map(TypeTarget::Type),
+            TypeName::Access { inner, dot: _, name, args: _ } =>
+            {
+                if let Some(file) = self.type_module(inner)
+                {
+                    let member = self.modules.get(&file).and_then(|m| m.get(&token_str(name)));
+                    return member.and_then(|b| b.type_index).map(TypeTarget::Type);
+                }
+
+                let Some(TypeTarget::Type(owner)) = self.static_type(inner) else { return None };
+                let TypeShape::Enum(variants) = &self.types[owner as usize].shape else { return None };
+                let variant = variants.iter().position(|(n, _)| *n == token_str(name))?;
+                Some(TypeTarget::Variant(owner, variant as u16))
+            },
+            _ => None,
+        }
+    }
+
+    /// Emits the value a type name evaluates to: a type, an enum variant or a variant constructor
+    pub fn compile_type_path(&mut self, type_name: &TypeName)
+    {
+        match type_name
+        {
+            TypeName::Identifier { name, args: _ } => self.load_name(name),
+            TypeName::Access { inner, dot: _, name, args: _ } =>
+            {
+                if let Some(file) = self.type_module(inner)
+                {
+                    if let Some(binding) = self.module_member(file, name)
+                    {
+                        self.emit_load(binding, name);
+                    }
+
+                    return;
+                }
+
+                self.compile_type_path(inner);
+                let member = self.name_constant(&token_str(name));
+                self.emit(Instr::GetMember(member), name);
+            },
+            TypeName::Array { .. } | TypeName::Function { .. } => self.error(CompileError::NotAValue(*type_name.get_token())),
+        }
+    }
+
+    pub fn type_proto(&self, index: u32) -> &TypeProto
+    {
+        &self.types[index as usize]
+    }
+
+    /// The field initializers of a struct, or of an enum's struct variant
+    fn field_initializers(&self, target: TypeTarget) -> Vec<(String, Option<Expression>)>
+    {
+        match target
+        {
+            TypeTarget::Type(index) => match &self.sources[index as usize]
+            {
+                TypeSource::Struct(decl) => decl.members.iter().map(|m| (token_str(&m.id), m.initializer.as_ref().map(|(_, e)| e.clone()))).collect(),
+                _ => vec![],
+            },
+            TypeTarget::Variant(index, variant) => match &self.sources[index as usize]
+            {
+                TypeSource::Enum(decl) => match &decl.members[variant as usize]
+                {
+                    EnumMember::Struct { members, .. } => members.iter().map(|m| (token_str(&m.id), m.initializer.as_ref().map(|(_, e)| e.clone()))).collect(),
+                    _ => vec![],
+                },
+                _ => vec![],
+            },
+        }
+    }
+
+    /// Declares a type nested in a block, binding its name to a local
+    pub fn compile_local_type(&mut self, statement: &Statement)
+    {
+        let (id, source) = match statement
+        {
+            Statement::StructDecl(decl) => (&decl.id, TypeSource::Struct(Rc::new(decl.clone()))),
+            Statement::EnumDecl(decl) => (&decl.id, TypeSource::Enum(Rc::new(decl.clone()))),
+            Statement::InterfaceDecl(decl) => (&decl.id, TypeSource::Interface(Rc::new(decl.clone()))),
+            _ => return,
+        };
+
+        let index = self.declare_type(source);
+        self.emit(Instr::Type(index), id);
+        let slot = self.declare_local(&token_str(id), Some(index));
+        self.emit(Instr::DefineLocal(slot), id);
+    }
+}
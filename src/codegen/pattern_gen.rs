@@ -0,0 +1,174 @@
+use crate::{ast::*, interp::token_str, lexing::token::TokenValue, resolve::pattern_binding_tokens};
+
+use super::{chunk::{Constant, Instr}, Compiler};
+
+impl Compiler
+{
+    /// Emits a test of the value in slot `value` against `pattern`, recording the jumps taken when it does not match
+    /// in `fails`. Every binding the pattern introduces is declared in the current scope.
+    pub fn compile_pattern(&mut self, pattern: &Pattern, value: u16, fails: &mut Vec<usize>)
+    {
+        match pattern
+        {
+            Pattern::Literal(PatternLiteral { minus, token }) =>
+            {
+                self.emit(Instr::GetLocal(value), token);
+                self.compile_literal(token);
+                if let Some(minus) = minus
+                {
+                    self.emit(Instr::Negate, minus);
+                }
+                self.emit(Instr::Equal, token);
+                fails.push(self.emit(Instr::JumpIfFalse(0), token));
+            },
+            Pattern::Wildcard(_) => {},
+            Pattern::Range { start, dots, end } =>
+            {
+                let (Some(start_value), Some(end_value)) = (literal_constant(start), literal_constant(end)) else { return };
+                let start = self.constant(start_value);
+                let end = self.constant(end_value);
+                self.emit(Instr::GetLocal(value), dots);
+                self.emit(Instr::InRange(start, end), dots);
+                fails.push(self.emit(Instr::JumpIfFalse(0), dots));
+            },
+            Pattern::Identifier { mut_tok: _, id } =>
+            {
+                self.emit(Instr::GetLocal(value), id);
+                let slot = self.declare_local(&token_str(id), None);
+                self.emit(Instr::DefineLocal(slot), id);
+            },
+            Pattern::Grouping { open_paren: _, inner, close_paren: _ } => self.compile_pattern(inner, value, fails),
+            Pattern::Binding { mut_tok: _, id, at: _, inner } =>
+            {
+                self.emit(Instr::GetLocal(value), id);
+                let slot = self.declare_local(&token_str(id), None);
+                self.emit(Instr::DefineLocal(slot), id);
+                self.compile_pattern(inner, value, fails);
+            },
+            Pattern::Or(patterns) =>
+            {
+                // Each alternative binds its names in slots of its own, so they are copied into shared slots
+                // before jumping past the remaining alternatives
+                let names = patterns.first().map_or(vec![], pattern_binding_tokens);
+                let shared: Vec<u16> = names.iter().map(|_| self.declare_temp()).collect();
+
+                let mut matched = vec![];
+                for (i, pattern) in patterns.iter().enumerate()
+                {
+                    let mut alternative_fails = vec![];
+                    self.begin_scope();
+                    self.compile_pattern(pattern, value, if i + 1 == patterns.len() { fails } else { &mut alternative_fails });
+                    for (id, slot) in names.iter().zip(&shared)
+                    {
+                        let Some(local) = self.state().locals.iter().rev().find(|l| l.name == token_str(id)).map(|l| l.slot) else { continue };
+                        self.emit(Instr::GetLocal(local), id);
+                        self.emit(Instr::DefineLocal(*slot), id);
+                    }
+                    self.end_scope();
+
+                    if i + 1 < patterns.len()
+                    {
+                        matched.push(self.emit(Instr::Jump(0), pattern.get_token()));
+                        for at in alternative_fails
+                        {
+                            self.patch_here(at);
+                        }
+                    }
+                }
+
+                for at in matched
+                {
+                    self.patch_here(at);
+                }
+
+                for (id, slot) in names.iter().zip(shared)
+                {
+                    self.emit(Instr::GetLocal(slot), id);
+                    let local = self.declare_local(&token_str(id), None);
+                    self.emit(Instr::DefineLocal(local), id);
+                }
+            },
+            Pattern::TypeValue { type_name, dot: _, id } =>
+            {
+                self.emit(Instr::GetLocal(value), id);
+                self.compile_type_path(type_name);
+                let name = self.name_constant(&token_str(id));
+                self.emit(Instr::GetMember(name), id);
+                self.emit(Instr::Equal, id);
+                fails.push(self.emit(Instr::JumpIfFalse(0), id));
+            },
+            Pattern::EnumConstruct { type_name, open_paren, inner, close_paren: _ } =>
+            {
+                self.emit(Instr::GetLocal(value), open_paren);
+                self.compile_type_path(type_name);
+                self.emit(Instr::IsInstance, open_paren);
+                fails.push(self.emit(Instr::JumpIfFalse(0), open_paren));
+
+                self.emit(Instr::GetLocal(value), open_paren);
+                self.emit(Instr::Payload, open_paren);
+                let payload = self.declare_temp();
+                self.emit(Instr::DefineLocal(payload), open_paren);
+                self.compile_pattern(inner, payload, fails);
+            },
+            Pattern::StructConstruct { type_name, open_brace, patterns, close_brace: _ } =>
+            {
+                self.emit(Instr::GetLocal(value), open_brace);
+                self.compile_type_path(type_name);
+                self.emit(Instr::IsInstance, open_brace);
+                fails.push(self.emit(Instr::JumpIfFalse(0), open_brace));
+
+                for field in patterns
+                {
+                    let name = token_str(&field.id);
+                    let member = self.name_constant(&name);
+                    self.emit(Instr::GetLocal(value), &field.id);
+                    self.emit(Instr::GetMember(member), &field.id);
+
+                    match &field.inner
+                    {
+                        Some(inner) =>
+                        {
+                            let slot = self.declare_temp();
+                            self.emit(Instr::DefineLocal(slot), &field.id);
+                            self.compile_pattern(inner, slot, fails);
+                        },
+                        None =>
+                        {
+                            let slot = self.declare_local(&name, None);
+                            self.emit(Instr::DefineLocal(slot), &field.id);
+                        },
+                    }
+                }
+            },
+            Pattern::ArrayConstruct { open_bracket, patterns, close_bracket: _ } =>
+            {
+                self.emit(Instr::GetLocal(value), open_bracket);
+                self.emit(Instr::IsArray(patterns.len() as u32), open_bracket);
+                fails.push(self.emit(Instr::JumpIfFalse(0), open_bracket));
+
+                for (i, pattern) in patterns.iter().enumerate()
+                {
+                    let index = self.constant(Constant::Int(i as i64));
+                    self.emit(Instr::GetLocal(value), open_bracket);
+                    self.emit(Instr::Constant(index), open_bracket);
+                    self.emit(Instr::GetIndex, open_bracket);
+                    let slot = self.declare_temp();
+                    self.emit(Instr::DefineLocal(slot), open_bracket);
+                    self.compile_pattern(pattern, slot, fails);
+                }
+            },
+        }
+    }
+}
+
+/// The constant an `Int` or `Char` literal stands for
+fn literal_constant(literal: &PatternLiteral) -> Option<Constant>
+{
+    match literal.token.value
+    {
+        Some(TokenValue::Int(i)) if literal.minus.is_some() => Some(Constant::Int((i as i64).wrapping_neg())),
+        Some(TokenValue::Int(i)) => Some(Constant::Int(i as i64)),
+        Some(TokenValue::Char(c)) => Some(Constant::Char(c)),
+        _ => None,
+    }
+}
@@ -0,0 +1,336 @@
+use crate::{ast::*, consteval::ConstValue, interp::token_str, lexing::token::{Token, TokenType}};
+
+use super::{chunk::{Constant, FailKind, Instr}, expr_gen::binary_instr, CompileError, Compiler, TypeTarget};
+
+impl Compiler
+{
+    /// Emits a statement, which leaves the stack as it found it
+    pub fn compile_statement(&mut self, statement: &Statement)
+    {
+        match statement
+        {
+            Statement::While(while_stmt) => self.compile_while(while_stmt),
+            Statement::For(for_stmt) => self.compile_for(for_stmt),
+            Statement::Return(return_stmt) =>
+            {
+                match &return_stmt.expression
+                {
+                    Some(expression) => self.compile_expression(expression),
+                    None => { self.emit(Instr::Void, &return_stmt.return_tok); },
+                }
+
+                self.emit(Instr::Return, &return_stmt.return_tok);
+            },
+            Statement::Continue(continue_stmt) => self.emit_continue(&continue_stmt.continue_tok),
+            Statement::Break(break_stmt) => self.emit_break(&break_stmt.break_tok),
+            Statement::TypeDecl(type_decl) =>
+            {
+                self.compile_type_path(&type_decl.type_name);
+                let type_index = match self.static_type(&type_decl.type_name)
+                {
+                    Some(TypeTarget::Type(index)) => Some(index),
+                    _ => None,
+                };
+
+                let slot = self.declare_local(&token_str(&type_decl.id), type_index);
+                self.emit(Instr::DefineLocal(slot), &type_decl.id);
+            },
+            Statement::EnumDecl(_) | Statement::InterfaceDecl(_) | Statement::StructDecl(_) => self.compile_local_type(statement),
+            Statement::FnDecl(decl) =>
+            {
+                // The name is bound before the body is compiled so the function can call itself
+                let name = token_str(&decl.id);
+                let slot = self.declare_local(&name, None);
+                self.emit(Instr::Void, &decl.id);
+                self.emit(Instr::DefineLocal(slot), &decl.id);
+
+                let index = self.compile_function(decl, name, None, true);
+                self.emit(Instr::Closure(index), &decl.id);
+                self.emit(Instr::SetLocal(slot), &decl.id);
+            },
+            Statement::Let(let_stmt) => self.compile_let(let_stmt),
+            Statement::Const(decl) =>
+            {
+                self.compile_const(decl);
+                let slot = self.declare_local(&token_str(&decl.id), None);
+                self.emit(Instr::DefineLocal(slot), &decl.id);
+            },
+            Statement::Assign(assign) => self.compile_assign(assign),
+            Statement::If(if_expr) =>
+            {
+                self.compile_if(if_expr);
+                self.emit(Instr::Pop, &if_expr.if_tok);
+            },
+            Statement::Match(match_expr) =>
+            {
+                self.compile_match(match_expr);
+                self.emit(Instr::Pop, &match_expr.match_tok);
+            },
+            Statement::Block(block) =>
+            {
+                self.compile_block(block);
+                self.emit(Instr::Pop, &block.close_brace);
+            },
+            Statement::Expression(expression_stmt) =>
+            {
+                self.compile_expression(&expression_stmt.expression);
+                self.emit(Instr::Pop, &expression_stmt.semi_colon);
+            },
+            Statement::Use(_) => {},
+        }
+    }
+
+    pub fn compile_let(&mut self, let_stmt: &LetStmt)
+    {
+        self.compile_expression(&let_stmt.expression);
+        match &let_stmt.binding
+        {
+            LetBinding::Variable { mut_tok: _, id } =>
+            {
+                let slot = self.declare_local(&token_str(id), None);
+                self.emit(Instr::DefineLocal(slot), id);
+            },
+            LetBinding::Pattern(pattern) =>
+            {
+                let value = self.declare_temp();
+                self.emit(Instr::DefineLocal(value), &let_stmt.let_tok);
+
+                let mut fails = vec![];
+                self.compile_pattern(pattern, value, &mut fails);
+                if fails.is_empty()
+                {
+                    return;
+                }
+
+                let matched = self.emit(Instr::Jump(0), &let_stmt.let_tok);
+                for at in fails
+                {
+                    self.patch_here(at);
+                }
+
+                match &let_stmt.else_clause
+                {
+                    Some((else_tok, block)) =>
+                    {
+                        self.compile_block(block);
+                        self.emit(Instr::Pop, else_tok);
+                        self.emit(Instr::Fail(FailKind::LetElseDidNotDiverge), else_tok);
+                    },
+                    None => { self.emit(Instr::Fail(FailKind::PatternNotMatched), &let_stmt.let_tok); },
+                }
+
+                self.patch_here(matched);
+            }
+        }
+    }
+
+    /// Pushes the value of a constant, folded when the const evaluator computed it
+    pub fn compile_const(&mut self, decl: &ConstDecl)
+    {
+        match self.consts.get(&decl.id.pos).cloned()
+        {
+            Some(value) => self.compile_const_value(&value, &decl.id),
+            None => self.compile_expression(&decl.expression),
+        }
+    }
+
+    fn compile_const_value(&mut self, value: &ConstValue, token: &Token)
+    {
+        let constant = match value
+        {
+            ConstValue::Int(i) => Constant::Int(*i),
+            ConstValue::Float(f) => Constant::Float(*f),
+            ConstValue::String(s) => Constant::String(s.clone()),
+            ConstValue::Char(c) => Constant::Char(*c),
+            ConstValue::Bool(b) =>
+            {
+                self.emit(if *b { Instr::True } else { Instr::False }, token);
+                return;
+            },
+            ConstValue::Array(values) =>
+            {
+                for value in values
+                {
+                    self.compile_const_value(value, token);
+                }
+
+                self.emit(Instr::Array(values.len() as u32), token);
+                return;
+            },
+        };
+
+        let index = self.constant(constant);
+        self.emit(Instr::Constant(index), token);
+    }
+
+    fn compile_assign(&mut self, assign: &AssignStmt)
+    {
+        let op = compound_operator(assign.equal.token_type).and_then(binary_instr);
+        self.compile_assign_to(&assign.value, &assign.expression, op, &assign.equal);
+    }
+
+    /// Emits an assignment of `value` to `target`. Compound assignments load the current value of the target first.
+    fn compile_assign_to(&mut self, target: &Expression, value: &Expression, op: Option<Instr>, equal: &Token)
+    {
+        match target
+        {
+            Expression::Literal(token) | Expression::Identifier(token) if token.token_type == TokenType::Identifier =>
+            {
+                let name = token_str(token);
+                if let Some(binding) = self.resolve_name(&name)
+                {
+                    if op.is_some()
+                    {
+                        self.emit_load(binding, token);
+                    }
+
+                    self.compile_operand(value, op, equal);
+                    self.emit_store(binding, token);
+                }
+                else if self.is_self_field(&name)
+                {
+                    let field = self.name_constant(&name);
+                    self.load_self(token);
+                    if op.is_some()
+                    {
+                        self.emit(Instr::Dup, token);
+                        self.emit(Instr::GetMember(field), token);
+                    }
+
+                    self.compile_operand(value, op, equal);
+                    self.emit(Instr::SetField(field), token);
+                }
+                else
+                {
+                    self.error(CompileError::UndefinedName(*token));
+                }
+            },
+            Expression::Access(access) =>
+            {
+                self.compile_expression(&access.expression);
+                let field = self.name_constant(&token_str(&access.identifier));
+                if op.is_some()
+                {
+                    self.emit(Instr::Dup, &access.identifier);
+                    self.emit(Instr::GetMember(field), &access.identifier);
+                }
+
+                self.compile_operand(value, op, equal);
+                self.emit(Instr::SetField(field), &access.identifier);
+            },
+            Expression::Index(index) =>
+            {
+                self.compile_expression(&index.expression);
+                self.compile_expression(&index.indexer);
+                if op.is_some()
+                {
+                    let indexer = self.declare_temp();
+                    let owner = self.declare_temp();
+                    self.emit(Instr::DefineLocal(indexer), &index.open_bracket);
+                    self.emit(Instr::DefineLocal(owner), &index.open_bracket);
+                    for _ in 0..2
+                    {
+                        self.emit(Instr::GetLocal(owner), &index.open_bracket);
+                        self.emit(Instr::GetLocal(indexer), &index.open_bracket);
+                    }
+
+                    self.emit(Instr::GetIndex, &index.open_bracket);
+                }
+
+                self.compile_operand(value, op, equal);
+                self.emit(Instr::SetIndex, &index.open_bracket);
+            },
+            Expression::Grouping(grouping) => self.compile_assign_to(&grouping.expression, value, op, equal),
+            _ => self.error(CompileError::InvalidAssignment(*equal)),
+        }
+    }
+
+    /// Emits the value being assigned, combining it with the current value already on the stack for compound assignments
+    fn compile_operand(&mut self, value: &Expression, op: Option<Instr>, equal: &Token)
+    {
+        self.compile_expression(value);
+        if let Some(op) = op
+        {
+            self.emit(op, equal);
+        }
+    }
+
+    fn compile_while(&mut self, while_stmt: &WhileStmt)
+    {
+        let height = self.mark_stack(&while_stmt.while_tok);
+        let start = self.here();
+        self.push_loop(start, height);
+        self.begin_scope();
+
+        let mut fails = vec![];
+        self.compile_condition(&while_stmt.condition, &mut fails);
+        self.compile_block(&while_stmt.body);
+        self.emit(Instr::Pop, &while_stmt.body.close_brace);
+        self.emit(Instr::Jump(start as u32), &while_stmt.while_tok);
+
+        self.end_scope();
+        for at in fails
+        {
+            self.patch_here(at);
+        }
+
+        self.pop_loop();
+    }
+
+    fn compile_for(&mut self, for_stmt: &ForStmt)
+    {
+        self.compile_expression(&for_stmt.expression);
+        self.emit(Instr::IntoSequence, &for_stmt.in_tok);
+
+        let sequence = self.declare_temp();
+        let index = self.declare_temp();
+        self.emit(Instr::DefineLocal(sequence), &for_stmt.in_tok);
+        let zero = self.constant(Constant::Int(0));
+        self.emit(Instr::Constant(zero), &for_stmt.for_tok);
+        self.emit(Instr::DefineLocal(index), &for_stmt.for_tok);
+
+        let height = self.mark_stack(&for_stmt.for_tok);
+        let start = self.emit(Instr::ForNext(sequence, index, 0), &for_stmt.for_tok);
+        self.push_loop(start, height);
+        self.begin_scope();
+
+        let item = self.declare_temp();
+        self.emit(Instr::DefineLocal(item), &for_stmt.for_tok);
+        let mut fails = vec![];
+        self.compile_pattern(&for_stmt.pattern, item, &mut fails);
+        if !fails.is_empty()
+        {
+            let matched = self.emit(Instr::Jump(0), &for_stmt.for_tok);
+            for at in fails
+            {
+                self.patch_here(at);
+            }
+
+            self.emit(Instr::Fail(FailKind::PatternNotMatched), &for_stmt.for_tok);
+            self.patch_here(matched);
+        }
+
+        self.compile_block(&for_stmt.body);
+        self.emit(Instr::Pop, &for_stmt.body.close_brace);
+        self.emit(Instr::Jump(start as u32), &for_stmt.for_tok);
+
+        self.end_scope();
+        self.patch_here(start);
+        self.pop_loop();
+    }
+}
+
+fn compound_operator(token_type: TokenType) -> Option<TokenType>
+{
+    match token_type
+    {
+        TokenType::PlusEqual => Some(TokenType::Plus),
+        TokenType::MinusEqual => Some(TokenType::Minus),
+        TokenType::MultiplyEqual => Some(TokenType::Multiply),
+        TokenType::DivideEqual => Some(TokenType::Divide),
+        TokenType::ModulusEqual => Some(TokenType::Modulus),
+        TokenType::AndEqual => Some(TokenType::AndAnd),
+        TokenType::OrEqual => Some(TokenType::PipePipe),
+        _ => None,
+    }
+}
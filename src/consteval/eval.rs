@@ -0,0 +1,514 @@
+use std::{collections::HashMap, rc::Rc};
+
+use either::Either;
+
+use crate::{ast::*, interp::{builtins::builtin_type_kind, token_str}, lexing::token::{Token, TokenType, TokenValue}};
+
+use super::{ConstEnv, ConstError, ConstItem, ConstResult, ConstValue};
+
+/// How deep calls to `const fn`s may nest, so runaway recursion is reported instead of overflowing the stack
+pub const MAX_CALL_DEPTH: usize = 128;
+
+/// Why evaluation stopped before reaching the end of an expression
+enum Exit
+{
+    Error(ConstError),
+    Return(ConstValue),
+}
+
+impl From<ConstError> for Exit
+{
+    fn from(value: ConstError) -> Self
+    {
+        Exit::Error(value)
+    }
+}
+
+type EvalResult<T> = Result<T, Exit>;
+
+/// Evaluates an expression at compile time, looking up the names it uses in `env`
+pub fn eval_expression(env: &mut dyn ConstEnv, expression: &Expression) -> ConstResult<ConstValue>
+{
+    let mut eval = ConstEval { env, scopes: vec![], depth: 0 };
+    match eval.expression(expression)
+    {
+        Ok(value) => Ok(value),
+        Err(Exit::Error(error)) => Err(error),
+        Err(Exit::Return(_)) => unreachable!("`return` is only evaluated inside a `const fn`"),
+    }
+}
+
+struct ConstEval<'e>
+{
+    env: &'e mut dyn ConstEnv,
+    /// The parameters, `let`s and constants of the `const fn` being called
+    scopes: Vec<HashMap<String, ConstItem>>,
+    depth: usize,
+}
+
+impl ConstEval<'_>
+{
+    fn lookup(&mut self, token: &Token) -> ConstResult<Option<ConstItem>>
+    {
+        let name = token_str(token);
+        match self.scopes.iter().rev().find_map(|s| s.get(&name))
+        {
+            Some(item) => Ok(Some(item.clone())),
+            None => self.env.lookup(&name, token),
+        }
+    }
+
+    /// Looks up a name or a member of a type, as in `SIZE` or `Config.SIZE`, returning what it refers to and the token
+    /// that names it
+    fn lookup_path<'a>(&mut self, expression: &'a Expression) -> EvalResult<(Option<ConstItem>, &'a Token)>
+    {
+        let (type_name, member) = match expression
+        {
+            Expression::Identifier(token) => return Ok((self.lookup(token)?, token)),
+            Expression::Literal(token) if token.token_type == TokenType::Identifier => return Ok((self.lookup(token)?, token)),
+            Expression::Access(AccessExpr { expression, dot: _, identifier }) => match &**expression
+            {
+                Expression::Identifier(token) | Expression::Literal(token) if token.token_type == TokenType::Identifier => (token, identifier),
+                _ => return Err(ConstError::NotConstant(*identifier).into()),
+            },
+            Expression::TypeValue(TypeValueExpr { type_name: TypeName::Identifier { name, args: None }, dot: _, name: member }) => (name, member),
+            other => return Err(ConstError::NotConstant(*other.get_token()).into()),
+        };
+
+        // Members of types are never shadowed by the parameters of a `const fn`, so they are looked up directly
+        let qualified = format!("{}.{}", token_str(type_name), token_str(member));
+        Ok((self.env.lookup(&qualified, member)?, member))
+    }
+
+    fn define(&mut self, id: &Token, item: ConstItem)
+    {
+        if let Some(scope) = self.scopes.last_mut()
+        {
+            scope.insert(token_str(id), item);
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) -> EvalResult<ConstValue>
+    {
+        match expression
+        {
+            Expression::Literal(token) if token.token_type != TokenType::Identifier => self.literal(token),
+            Expression::ArrayLiteral(array) =>
+            {
+                let mut values = vec![];
+                for expression in &array.expressions
+                {
+                    values.push(self.expression(expression)?);
+                }
+
+                Ok(ConstValue::Array(values))
+            },
+            Expression::Literal(_) | Expression::Identifier(_) | Expression::Access(_) | Expression::TypeValue(_) => match self.lookup_path(expression)?
+            {
+                (Some(ConstItem::Value(value)), _) => Ok(value),
+                (Some(ConstItem::Fn(_)), token) => Err(ConstError::NotConstant(*token).into()),
+                (None, token) => Err(ConstError::NotConstName(*token).into()),
+            },
+            Expression::Grouping(grouping) => self.expression(&grouping.expression),
+            Expression::BlockExpr(block) => valued(self.block(block), &block.close_brace),
+            Expression::Call(call) => self.call(call),
+            Expression::Index(index) =>
+            {
+                let value = self.expression(&index.expression)?;
+                let indexer = self.expression(&index.indexer)?;
+                self.index(value, indexer, &index.open_bracket)
+            },
+            Expression::Unary(unary) => self.unary(unary),
+            Expression::Binary(binary) => self.binary(binary),
+            Expression::IfExpr(if_expr) => valued(self.if_expr(if_expr), &if_expr.if_tok),
+            Expression::Cast(cast) =>
+            {
+                let value = self.expression(&cast.expression)?;
+                Ok(cast_value(value, &cast.type_name)?)
+            },
+            Expression::Interpolation(interpolation) =>
+            {
+                let mut result = token_str(&interpolation.start);
+                for (expression, text) in &interpolation.parts
+                {
+                    result.push_str(&self.expression(expression)?.to_string());
+                    result.push_str(&token_str(text));
+                }
+
+                Ok(ConstValue::String(result))
+            },
+            Expression::Lambda(_)
+            | Expression::SelfExpr(_)
+            | Expression::Construction(_)
+            | Expression::EnumConstruction(_)
+            | Expression::MatchExpr(_)
+            | Expression::Yield(_)
+            | Expression::Jump(_) => Err(ConstError::NotConstant(*expression.get_token()).into()),
+        }
+    }
+
+    fn literal(&mut self, token: &Token) -> EvalResult<ConstValue>
+    {
+        match (token.token_type, &token.value)
+        {
+            (TokenType::IntegerLiteral, Some(TokenValue::Int(i))) => Ok(ConstValue::Int(*i as i64)),
+            (TokenType::FloatLiteral, Some(TokenValue::Float(f))) => Ok(ConstValue::Float(*f)),
+            (TokenType::StringLiteral, Some(TokenValue::String(s))) => Ok(ConstValue::String(s.to_string())),
+            (TokenType::CharLiteral, Some(TokenValue::Char(c))) => Ok(ConstValue::Char(*c)),
+            (TokenType::True, _) => Ok(ConstValue::Bool(true)),
+            (TokenType::False, _) => Ok(ConstValue::Bool(false)),
+            _ => Err(ConstError::NotConstant(*token).into()),
+        }
+    }
+
+    fn block(&mut self, block: &BlockExpr) -> EvalResult<Option<ConstValue>>
+    {
+        self.scopes.push(HashMap::new());
+        let value = self.block_body(block);
+        self.scopes.pop();
+        value
+    }
+
+    fn block_body(&mut self, block: &BlockExpr) -> EvalResult<Option<ConstValue>>
+    {
+        let Some((last, statements)) = block.statements.split_last() else {
+            return block.expression.as_ref().map(|e| self.expression(e)).transpose();
+        };
+
+        for statement in statements
+        {
+            self.statement(statement)?;
+        }
+
+        if let Some(expression) = &block.expression
+        {
+            self.statement(last)?;
+            return self.expression(expression).map(Some);
+        }
+
+        // A trailing `if` or block is read as a statement, but it still gives the block its value
+        match last
+        {
+            Statement::If(if_expr) => self.if_expr(if_expr),
+            Statement::Block(block) => self.block(block),
+            _ => self.statement(last).map(|_| None),
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) -> EvalResult<()>
+    {
+        match statement
+        {
+            Statement::Let(LetStmt { binding: LetBinding::Variable { mut_tok: None, id }, else_clause: None, expression, .. }) =>
+            {
+                let value = self.expression(expression)?;
+                self.define(id, ConstItem::Value(value));
+            },
+            Statement::Const(decl) =>
+            {
+                let value = self.expression(&decl.expression)?;
+                self.define(&decl.id, ConstItem::Value(value));
+            },
+            Statement::FnDecl(decl) if decl.is_const() => self.define(&decl.id, ConstItem::Fn(Rc::new(decl.clone()))),
+            Statement::Expression(stmt) => { self.expression(&stmt.expression)?; },
+            Statement::If(if_expr) => { self.if_expr(if_expr)?; },
+            Statement::Block(block) => { self.block(block)?; },
+            Statement::Return(ReturnStmt { return_tok: _, expression: Some(expression), semi_colon: _ }) if self.depth > 0 =>
+            {
+                return Err(Exit::Return(self.expression(expression)?));
+            },
+            other => return Err(ConstError::NotConstant(statement_token(other)).into()),
+        }
+
+        Ok(())
+    }
+
+    fn if_expr(&mut self, if_expr: &IfExpr) -> EvalResult<Option<ConstValue>>
+    {
+        let LetCondition::Expression(condition) = &if_expr.condition else {
+            return Err(ConstError::NotConstant(if_expr.if_tok).into());
+        };
+
+        match self.expression(condition)?
+        {
+            ConstValue::Bool(true) => self.block(&if_expr.block),
+            ConstValue::Bool(false) => match &if_expr.else_branch
+            {
+                Some(ElseBranch { else_tok: _, body: Either::Left(if_expr) }) => self.if_expr(if_expr),
+                Some(ElseBranch { else_tok: _, body: Either::Right(block) }) => self.block(block),
+                None => Ok(None),
+            },
+            other => Err(ConstError::InvalidOperands { operator: if_expr.if_tok, left: other.type_name(), right: None }.into()),
+        }
+    }
+
+    fn call(&mut self, call: &CallExpr) -> EvalResult<ConstValue>
+    {
+        let (callee, token) = self.lookup_path(&call.expression)?;
+
+        let Some(ConstItem::Fn(decl)) = callee else {
+            return Err(ConstError::NotConstFn(*token).into());
+        };
+
+        let mut args = vec![];
+        for arg in &call.args
+        {
+            args.push(self.expression(arg)?);
+        }
+
+        if self.depth >= MAX_CALL_DEPTH
+        {
+            return Err(ConstError::RecursionLimit(call.open_paren).into());
+        }
+
+        // The callee sees its own parameters, not the locals of its caller
+        let caller = std::mem::replace(&mut self.scopes, vec![HashMap::new()]);
+        self.depth += 1;
+        let result = self.call_body(&decl, args, token, &call.open_paren);
+        self.depth -= 1;
+        self.scopes = caller;
+
+        match result
+        {
+            Ok(value) | Err(Exit::Return(value)) => Ok(value),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn call_body(&mut self, decl: &FnDecl, args: Vec<ConstValue>, name: &Token, open_paren: &Token) -> EvalResult<ConstValue>
+    {
+        let Either::Left(body) = &decl.body else {
+            return Err(ConstError::NotConstFn(*name).into());
+        };
+
+        let normal_count = decl.params.len();
+        let required_count = decl.params.iter().filter(|p| matches!(p, FnParam::Normal { default_value: None, .. })).count();
+        if args.len() < required_count || args.len() > normal_count
+        {
+            return Err(ConstError::ArgumentCount { token: *open_paren, expected: normal_count, found: args.len() }.into());
+        }
+
+        let mut args = args.into_iter();
+        for param in &decl.params
+        {
+            let FnParam::Normal { mut_tok: _, id, colon: _, type_name: _, default_value } = param else {
+                return Err(ConstError::NotConstFn(*name).into());
+            };
+
+            let value = match (args.next(), default_value)
+            {
+                (Some(value), _) => value,
+                (None, Some((_, expression))) => self.expression(expression)?,
+                (None, None) => unreachable!("arity was checked above"),
+            };
+
+            self.define(id, ConstItem::Value(value));
+        }
+
+        valued(self.block(body), &body.close_brace)
+    }
+
+    fn index(&mut self, value: ConstValue, indexer: ConstValue, token: &Token) -> EvalResult<ConstValue>
+    {
+        let check = |index: i64, length: usize| match index < 0 || index as usize >= length
+        {
+            true => Err(ConstError::IndexOutOfBounds { token: *token, index, length }),
+            false => Ok(index as usize),
+        };
+
+        match (value, indexer)
+        {
+            (ConstValue::Array(values), ConstValue::Int(index)) => Ok(values[check(index, values.len())?].clone()),
+            (ConstValue::String(s), ConstValue::Int(index)) =>
+            {
+                let chars: Vec<char> = s.chars().collect();
+                Ok(ConstValue::String(chars[check(index, chars.len())?].to_string()))
+            },
+            (value, indexer) => Err(ConstError::InvalidOperands { operator: *token, left: value.type_name(), right: Some(indexer.type_name()) }.into()),
+        }
+    }
+
+    fn unary(&mut self, unary: &UnaryExpr) -> EvalResult<ConstValue>
+    {
+        match (unary.operator.token_type, self.expression(&unary.expression)?)
+        {
+            (TokenType::Minus, ConstValue::Int(i)) => Ok(ConstValue::Int(i.wrapping_neg())),
+            (TokenType::Minus, ConstValue::Float(f)) => Ok(ConstValue::Float(-f)),
+            (TokenType::Bang, ConstValue::Bool(b)) => Ok(ConstValue::Bool(!b)),
+            (_, value) => Err(ConstError::InvalidOperands { operator: unary.operator, left: value.type_name(), right: None }.into()),
+        }
+    }
+
+    fn binary(&mut self, binary: &BinaryExpr) -> EvalResult<ConstValue>
+    {
+        let left = self.expression(&binary.left)?;
+
+        // Logical operators short circuit, as they do at runtime
+        let short_circuit = match (binary.operator.token_type, &left)
+        {
+            (TokenType::AndAnd, ConstValue::Bool(false)) => Some(false),
+            (TokenType::PipePipe, ConstValue::Bool(true)) => Some(true),
+            _ => None,
+        };
+
+        if let Some(value) = short_circuit
+        {
+            return Ok(ConstValue::Bool(value));
+        }
+
+        let right = self.expression(&binary.right)?;
+        Ok(apply_binary_op(&binary.operator, left, right)?)
+    }
+}
+
+/// Applies a binary operator as the runtime does, integers wrapping on overflow
+fn apply_binary_op(operator: &Token, left: ConstValue, right: ConstValue) -> ConstResult<ConstValue>
+{
+    let op = operator.token_type;
+    let mismatch = |left: &ConstValue, right: &ConstValue| ConstError::InvalidOperands {
+        operator: *operator,
+        left: left.type_name(),
+        right: Some(right.type_name()),
+    };
+
+    match op
+    {
+        TokenType::EqualEqual => return Ok(ConstValue::Bool(left == right)),
+        TokenType::BangEqual => return Ok(ConstValue::Bool(left != right)),
+        _ => {}
+    }
+
+    match (&left, &right)
+    {
+        (ConstValue::Int(a), ConstValue::Int(b)) => match op
+        {
+            TokenType::Plus => Ok(ConstValue::Int(a.wrapping_add(*b))),
+            TokenType::Minus => Ok(ConstValue::Int(a.wrapping_sub(*b))),
+            TokenType::Multiply => Ok(ConstValue::Int(a.wrapping_mul(*b))),
+            TokenType::Divide | TokenType::Modulus if *b == 0 => Err(ConstError::DivisionByZero(*operator)),
+            TokenType::Divide => Ok(ConstValue::Int(a.wrapping_div(*b))),
+            TokenType::Modulus => Ok(ConstValue::Int(a.wrapping_rem(*b))),
+            TokenType::LessThan => Ok(ConstValue::Bool(a < b)),
+            TokenType::LessEqual => Ok(ConstValue::Bool(a <= b)),
+            TokenType::GreaterThan => Ok(ConstValue::Bool(a > b)),
+            TokenType::GreaterEqual => Ok(ConstValue::Bool(a >= b)),
+            _ => Err(mismatch(&left, &right)),
+        },
+        (ConstValue::Int(_) | ConstValue::Float(_), ConstValue::Int(_) | ConstValue::Float(_)) =>
+        {
+            let (a, b) = (as_float(&left), as_float(&right));
+            match op
+            {
+                TokenType::Plus => Ok(ConstValue::Float(a + b)),
+                TokenType::Minus => Ok(ConstValue::Float(a - b)),
+                TokenType::Multiply => Ok(ConstValue::Float(a * b)),
+                TokenType::Divide => Ok(ConstValue::Float(a / b)),
+                TokenType::Modulus => Ok(ConstValue::Float(a % b)),
+                TokenType::LessThan => Ok(ConstValue::Bool(a < b)),
+                TokenType::LessEqual => Ok(ConstValue::Bool(a <= b)),
+                TokenType::GreaterThan => Ok(ConstValue::Bool(a > b)),
+                TokenType::GreaterEqual => Ok(ConstValue::Bool(a >= b)),
+                _ => Err(mismatch(&left, &right)),
+            }
+        },
+        (ConstValue::String(a), ConstValue::String(b)) => match op
+        {
+            TokenType::Plus => Ok(ConstValue::String(format!("{}{}", a, b))),
+            TokenType::LessThan => Ok(ConstValue::Bool(a < b)),
+            TokenType::LessEqual => Ok(ConstValue::Bool(a <= b)),
+            TokenType::GreaterThan => Ok(ConstValue::Bool(a > b)),
+            TokenType::GreaterEqual => Ok(ConstValue::Bool(a >= b)),
+            _ => Err(mismatch(&left, &right)),
+        },
+        (ConstValue::Char(a), ConstValue::Char(b)) => match op
+        {
+            TokenType::LessThan => Ok(ConstValue::Bool(a < b)),
+            TokenType::LessEqual => Ok(ConstValue::Bool(a <= b)),
+            TokenType::GreaterThan => Ok(ConstValue::Bool(a > b)),
+            TokenType::GreaterEqual => Ok(ConstValue::Bool(a >= b)),
+            _ => Err(mismatch(&left, &right)),
+        },
+        (ConstValue::Bool(a), ConstValue::Bool(b)) => match op
+        {
+            TokenType::AndAnd => Ok(ConstValue::Bool(*a && *b)),
+            TokenType::PipePipe => Ok(ConstValue::Bool(*a || *b)),
+            _ => Err(mismatch(&left, &right)),
+        },
+        _ => Err(mismatch(&left, &right)),
+    }
+}
+
+/// The value of a block or `if`, which has none when it ends without an expression
+fn valued(value: EvalResult<Option<ConstValue>>, token: &Token) -> EvalResult<ConstValue>
+{
+    value?.ok_or_else(|| ConstError::NotConstant(*token).into())
+}
+
+fn as_float(value: &ConstValue) -> f64
+{
+    match value
+    {
+        ConstValue::Int(i) => *i as f64,
+        ConstValue::Float(f) => *f,
+        _ => unreachable!(),
+    }
+}
+
+/// Casts between the builtin types as the runtime does. Casts to types the program declares need values that only
+/// exist at runtime.
+fn cast_value(value: ConstValue, type_name: &TypeName) -> ConstResult<ConstValue>
+{
+    let error = |value: &ConstValue| ConstError::InvalidCast { token: *type_name.get_token(), from: value.type_name(), to: type_name.pretty_print() };
+
+    let TypeName::Identifier { name, args: _ } = type_name else {
+        return match (type_name, &value)
+        {
+            (TypeName::Array { .. }, ConstValue::Array(_)) => Ok(value),
+            _ => Err(error(&value)),
+        };
+    };
+
+    match (builtin_type_kind(&token_str(name)), &value)
+    {
+        (Some("Int"), ConstValue::Int(_)) => Ok(value),
+        (Some("Int"), ConstValue::Float(f)) => Ok(ConstValue::Int(*f as i64)),
+        (Some("Int"), ConstValue::Bool(b)) => Ok(ConstValue::Int(*b as i64)),
+        (Some("Int"), ConstValue::Char(c)) => Ok(ConstValue::Int(*c as i64)),
+        (Some("Int"), ConstValue::String(s)) => s.trim().parse().map(ConstValue::Int).map_err(|_| error(&value)),
+        (Some("Float"), ConstValue::Int(i)) => Ok(ConstValue::Float(*i as f64)),
+        (Some("Float"), ConstValue::Float(_)) => Ok(value),
+        (Some("Float"), ConstValue::String(s)) => s.trim().parse().map(ConstValue::Float).map_err(|_| error(&value)),
+        (Some("Bool"), ConstValue::Bool(_)) => Ok(value),
+        (Some("Char"), ConstValue::Char(_)) => Ok(value),
+        (Some("Char"), ConstValue::Int(i)) => u32::try_from(*i).ok().and_then(char::from_u32).map(ConstValue::Char).ok_or_else(|| error(&value)),
+        (Some("String"), _) => Ok(ConstValue::String(value.to_string())),
+        (Some(_), _) => Err(error(&value)),
+        (None, _) => Err(ConstError::NotConstant(*type_name.get_token())),
+    }
+}
+
+/// The token that starts a statement, to point at when it cannot be evaluated
+fn statement_token(statement: &Statement) -> Token
+{
+    match statement
+    {
+        Statement::While(stmt) => stmt.while_tok,
+        Statement::For(stmt) => stmt.for_tok,
+        Statement::Return(stmt) => stmt.return_tok,
+        Statement::Continue(stmt) => stmt.continue_tok,
+        Statement::Break(stmt) => stmt.break_tok,
+        Statement::TypeDecl(decl) => decl.type_tok,
+        Statement::EnumDecl(decl) => decl.enum_tok,
+        Statement::InterfaceDecl(decl) => decl.interface_tok,
+        Statement::StructDecl(decl) => decl.struct_tok,
+        Statement::FnDecl(decl) => decl.fn_tok,
+        Statement::Let(stmt) => stmt.let_tok,
+        Statement::Const(decl) => decl.const_tok,
+        Statement::Assign(stmt) => stmt.equal,
+        Statement::If(if_expr) => if_expr.if_tok,
+        Statement::Match(match_expr) => match_expr.match_tok,
+        Statement::Block(block) => block.open_brace,
+        Statement::Expression(stmt) => *stmt.expression.get_token(),
+        Statement::Use(stmt) => stmt.use_tok,
+    }
+}
@@ -0,0 +1,782 @@
+pub mod eval;
+
+use std::{collections::HashMap, rc::Rc};
+
+use either::Either;
+use itertools::Itertools;
+
+use crate::{ast::*, diagnostic::Diagnostic, interp::token_str, lexing::token::{Token, TokenPos}};
+
+pub use eval::eval_expression;
+
+/// A value computed while compiling
+#[derive(Debug, Clone)]
+pub enum ConstValue
+{
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Char(char),
+    String(String),
+    Array(Vec<ConstValue>),
+}
+
+impl ConstValue
+{
+    pub fn type_name(&self) -> &'static str
+    {
+        match self
+        {
+            ConstValue::Int(_) => "Int",
+            ConstValue::Float(_) => "Float",
+            ConstValue::Bool(_) => "Bool",
+            ConstValue::Char(_) => "Char",
+            ConstValue::String(_) => "String",
+            ConstValue::Array(_) => "Array",
+        }
+    }
+}
+
+/// Compares as the runtime does, so an `Int` equals the `Float` with the same value
+impl PartialEq for ConstValue
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        match (self, other)
+        {
+            (ConstValue::Int(a), ConstValue::Int(b)) => a == b,
+            (ConstValue::Float(a), ConstValue::Float(b)) => a == b,
+            (ConstValue::Int(a), ConstValue::Float(b)) | (ConstValue::Float(b), ConstValue::Int(a)) => (*a as f64) == *b,
+            (ConstValue::Bool(a), ConstValue::Bool(b)) => a == b,
+            (ConstValue::Char(a), ConstValue::Char(b)) => a == b,
+            (ConstValue::String(a), ConstValue::String(b)) => a == b,
+            (ConstValue::Array(a), ConstValue::Array(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Shows the value as the runtime shows it, so casts to `String` and interpolation fold to the text they would produce
+impl std::fmt::Display for ConstValue
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            ConstValue::Int(i) => write!(f, "{}", i),
+            ConstValue::Float(n) => write!(f, "{:?}", n),
+            ConstValue::Bool(b) => write!(f, "{}", b),
+            ConstValue::Char(c) => write!(f, "{}", c),
+            ConstValue::String(s) => write!(f, "{}", s),
+            ConstValue::Array(values) => write!(f, "[{}]", values.iter().join(", ")),
+        }
+    }
+}
+
+/// What a name used in a constant expression refers to
+#[derive(Debug, Clone)]
+pub enum ConstItem
+{
+    Value(ConstValue),
+    Fn(Rc<FnDecl>),
+}
+
+#[derive(Debug, Clone)]
+pub enum ConstError
+{
+    /// An expression of a kind that cannot be evaluated while compiling
+    NotConstant(Token),
+    /// A name that does not refer to a constant
+    NotConstName(Token),
+    /// A call to something other than a `const fn`
+    NotConstFn(Token),
+    Cycle
+    {
+        token: Token,
+        definition: Token,
+    },
+    DivisionByZero(Token),
+    IndexOutOfBounds
+    {
+        token: Token,
+        index: i64,
+        length: usize,
+    },
+    InvalidOperands
+    {
+        operator: Token,
+        left: &'static str,
+        right: Option<&'static str>,
+    },
+    InvalidCast
+    {
+        token: Token,
+        from: &'static str,
+        to: String,
+    },
+    ArgumentCount
+    {
+        token: Token,
+        expected: usize,
+        found: usize,
+    },
+    RecursionLimit(Token),
+    /// An explicit discriminant of an enum member that is not an `Int`
+    DiscriminantType
+    {
+        token: Token,
+        found: &'static str,
+    },
+    /// A discriminant outside the range of the enum's backing type
+    DiscriminantOverflow
+    {
+        token: Token,
+        value: i128,
+        type_name: String,
+    },
+    /// Two members of an enum with the same discriminant
+    DiscriminantCollision
+    {
+        token: Token,
+        other: Token,
+        value: i64,
+    },
+    /// A use of a constant that failed to evaluate. The failure has already been reported, so this one is not.
+    Invalid(Token),
+}
+
+impl ConstError
+{
+    pub fn get_token(&self) -> &Token
+    {
+        match self
+        {
+            ConstError::NotConstant(token) => token,
+            ConstError::NotConstName(token) => token,
+            ConstError::NotConstFn(token) => token,
+            ConstError::Cycle { token, .. } => token,
+            ConstError::DivisionByZero(token) => token,
+            ConstError::IndexOutOfBounds { token, .. } => token,
+            ConstError::InvalidOperands { operator, .. } => operator,
+            ConstError::InvalidCast { token, .. } => token,
+            ConstError::ArgumentCount { token, .. } => token,
+            ConstError::RecursionLimit(token) => token,
+            ConstError::DiscriminantType { token, .. } => token,
+            ConstError::DiscriminantOverflow { token, .. } => token,
+            ConstError::DiscriminantCollision { token, .. } => token,
+            ConstError::Invalid(token) => token,
+        }
+    }
+}
+
+impl std::fmt::Display for ConstError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            ConstError::NotConstant(_) => write!(f, "This expression cannot be evaluated at compile time"),
+            ConstError::NotConstName(token) => write!(f, "`{}` is not a constant", token_str(token)),
+            ConstError::NotConstFn(token) => write!(f, "`{}` is not a `const fn`", token_str(token)),
+            ConstError::Cycle { token: _, definition } => write!(f, "The value of `{}` depends on itself", token_str(definition)),
+            ConstError::DivisionByZero(_) => write!(f, "Division by zero in a constant"),
+            ConstError::IndexOutOfBounds { token: _, index, length } => write!(f, "Index {} is out of bounds for an array of length {}", index, length),
+            ConstError::InvalidOperands { operator, left, right: Some(right) } => write!(f, "Cannot apply {} to `{}` and `{}`", operator.token_type.describe(), left, right),
+            ConstError::InvalidOperands { operator, left, right: None } => write!(f, "Cannot apply {} to `{}`", operator.token_type.describe(), left),
+            ConstError::InvalidCast { token: _, from, to } => write!(f, "Cannot cast `{}` as `{}` at compile time", from, to),
+            ConstError::ArgumentCount { token: _, expected, found } => write!(f, "Expected {} argument(s) but found {}", expected, found),
+            ConstError::RecursionLimit(_) => write!(f, "Calls to `const fn`s nest more than {} deep", eval::MAX_CALL_DEPTH),
+            ConstError::DiscriminantType { token: _, found } => write!(f, "A discriminant must be an `Int`, found `{}`", found),
+            ConstError::DiscriminantOverflow { token, value, type_name } => write!(f, "The discriminant of `{}` is {}, which does not fit in `{}`", token_str(token), value, type_name),
+            ConstError::DiscriminantCollision { token, other, value } => write!(f, "`{}` and `{}` both have the discriminant {}", token_str(other), token_str(token), value),
+            ConstError::Invalid(token) => write!(f, "`{}` has no value because it failed to evaluate", token_str(token)),
+        }
+    }
+}
+
+impl From<&ConstError> for Diagnostic
+{
+    fn from(error: &ConstError) -> Self
+    {
+        let (code, label) = match error
+        {
+            ConstError::NotConstant(_) => ("E0701", "not a constant expression".to_owned()),
+            ConstError::NotConstName(_) => ("E0702", "not a constant".to_owned()),
+            ConstError::NotConstFn(_) => ("E0703", "not a `const fn`".to_owned()),
+            ConstError::Cycle { .. } => ("E0704", "used here while it is being evaluated".to_owned()),
+            ConstError::DivisionByZero(_) => ("E0705", "division by zero".to_owned()),
+            ConstError::IndexOutOfBounds { .. } => ("E0706", "index out of bounds".to_owned()),
+            ConstError::InvalidOperands { .. } => ("E0707", "no implementation for these operands".to_owned()),
+            ConstError::InvalidCast { token: _, from: _, to } => ("E0708", format!("cannot become `{}`", to)),
+            ConstError::ArgumentCount { token: _, expected, found: _ } => ("E0709", format!("expected {} argument(s)", expected)),
+            ConstError::RecursionLimit(_) => ("E0710", "the call that went too deep".to_owned()),
+            ConstError::Invalid(_) => ("E0711", "has no value".to_owned()),
+            ConstError::DiscriminantType { .. } => ("E0712", "expected an `Int`".to_owned()),
+            ConstError::DiscriminantOverflow { token: _, value: _, type_name } => ("E0713", format!("out of range for `{}`", type_name)),
+            ConstError::DiscriminantCollision { token: _, other: _, value } => ("E0714", format!("also {}", value)),
+        };
+
+        let diagnostic = Diagnostic::error(error.to_string()).with_code(code).with_primary(error.get_token().pos, label);
+        match error
+        {
+            ConstError::NotConstant(_) => diagnostic.with_help("constants may use literals, operators, casts, arrays, `if`, other constants and calls to `const fn`s"),
+            ConstError::NotConstName(_) => diagnostic.with_help("only constants and the parameters and `let`s of a `const fn` are known at compile time"),
+            ConstError::NotConstFn(token) => diagnostic.with_help(format!("declare it with `const fn {}` to call it from constants", token_str(token))),
+            ConstError::Cycle { token: _, definition } => diagnostic.with_label(definition.pos, "defined here"),
+            ConstError::DiscriminantCollision { token: _, other, value } => diagnostic.with_label(other.pos, format!("{} here", value)),
+            ConstError::DiscriminantOverflow { type_name, .. } if type_name == "Int" => diagnostic
+                .with_help("every discriminant is an `Int` at runtime, whatever type backs the enum"),
+            _ => diagnostic,
+        }
+    }
+}
+
+pub type ConstResult<T> = Result<T, ConstError>;
+
+/// Where a constant expression finds the names it does not bind itself
+pub trait ConstEnv
+{
+    /// The constant or `const fn` called `name`, which is qualified by a type for those in `impl` blocks, as in
+    /// `Config.SIZE`. `token` is where the name is used.
+    fn lookup(&mut self, name: &str, token: &Token) -> ConstResult<Option<ConstItem>>;
+}
+
+#[derive(Debug, Clone)]
+enum Slot
+{
+    Pending(Rc<ConstDecl>),
+    /// Being evaluated, so using it again means it depends on itself
+    Evaluating(Token),
+    Done(ConstItem),
+    Failed,
+}
+
+/// Evaluates every constant of a program: those at the top level, in `impl` blocks and in blocks. Top level constants
+/// are evaluated on first use, so they may refer to ones declared after them.
+#[derive(Default)]
+pub struct ConstEvaluator
+{
+    /// The constants and `const fn`s of the top level and of `impl` blocks, the latter qualified by their type
+    items: HashMap<String, Slot>,
+    /// Where names the program does not declare are looked up
+    fallback: Option<Box<dyn ConstEnv>>,
+    /// The value of every constant evaluated, by the position of its name
+    values: HashMap<TokenPos, ConstValue>,
+    errors: Vec<ConstError>,
+}
+
+/// Evaluates the constants of `program`
+pub fn eval_program(program: &Program) -> Result<ConstEvaluator, Vec<ConstError>>
+{
+    let mut evaluator = ConstEvaluator::new();
+    evaluator.eval_program(program);
+    match evaluator.errors.is_empty()
+    {
+        true => Ok(evaluator),
+        false => Err(evaluator.errors),
+    }
+}
+
+impl ConstEvaluator
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Looks up names the program does not declare in `env`, as the interpreter does with its globals
+    pub fn with_fallback(env: impl ConstEnv + 'static) -> Self
+    {
+        Self { fallback: Some(Box::new(env)), ..Self::default() }
+    }
+
+    pub fn errors(&self) -> &[ConstError]
+    {
+        &self.errors
+    }
+
+    pub fn values(&self) -> &HashMap<TokenPos, ConstValue>
+    {
+        &self.values
+    }
+
+    /// The value of the constant whose name is `id`
+    pub fn value(&self, id: &Token) -> Option<&ConstValue>
+    {
+        self.values.get(&id.pos)
+    }
+
+    /// Binds a name to a constant or `const fn` from outside the program
+    pub fn define(&mut self, name: &str, item: ConstItem)
+    {
+        self.items.insert(name.to_owned(), Slot::Done(item));
+    }
+
+    /// Brings the constants of an already evaluated module into scope. `item` is the name in that module, or `None`
+    /// when the module itself is bound, whose constants cannot be reached at compile time.
+    pub fn import(&mut self, name: &str, from: &ConstEvaluator, item: Option<&str>)
+    {
+        let Some(item) = item else { return };
+        if let Some(Slot::Done(imported)) = from.items.get(item)
+        {
+            self.define(name, imported.clone());
+        }
+
+        // The constants in the `impl` blocks of an imported type come with it
+        let prefix = format!("{}.", item);
+        for (member, slot) in &from.items
+        {
+            if let (Some(member), Slot::Done(imported)) = (member.strip_prefix(&prefix), slot)
+            {
+                self.define(&format!("{}.{}", name, member), imported.clone());
+            }
+        }
+    }
+
+    pub fn eval_program(&mut self, program: &Program)
+    {
+        let mut order = vec![];
+        for declaration in &program.declarations
+        {
+            match declaration
+            {
+                Declaration::Const(_, decl) => order.push(self.declare(token_str(&decl.id), decl)),
+                Declaration::Fn(_, decl) if decl.is_const() => self.define(&token_str(&decl.id), ConstItem::Fn(Rc::new(decl.clone()))),
+                Declaration::Impl(impl_stmt) =>
+                {
+                    let Some(name) = impl_target(impl_stmt) else { continue };
+                    for (_, member) in &impl_stmt.members
+                    {
+                        match member
+                        {
+                            Statement::Const(decl) => order.push(self.declare(format!("{}.{}", token_str(name), token_str(&decl.id)), decl)),
+                            Statement::FnDecl(decl) if decl.is_const() =>
+                            {
+                                self.define(&format!("{}.{}", token_str(name), token_str(&decl.id)), ConstItem::Fn(Rc::new(decl.clone())));
+                            },
+                            _ => {},
+                        }
+                    }
+                },
+                _ => {},
+            }
+        }
+
+        for (name, id) in order
+        {
+            let _ = self.force(&name, &id);
+        }
+
+        let mut scopes = vec![];
+        for declaration in &program.declarations
+        {
+            match declaration
+            {
+                Declaration::Fn(_, decl) => self.walk_fn(decl, &mut scopes),
+                Declaration::Const(_, decl) => self.walk_expression(&decl.expression, &mut scopes),
+                Declaration::Let(_, let_stmt) => self.walk_let(let_stmt, &mut scopes),
+                Declaration::Impl(impl_stmt) => for (_, member) in &impl_stmt.members
+                {
+                    match member
+                    {
+                        Statement::Const(decl) => self.walk_expression(&decl.expression, &mut scopes),
+                        other => self.walk_statement(other, &mut scopes),
+                    }
+                },
+                Declaration::Struct(_, decl) => for (_, initializer) in decl.members.iter().filter_map(|m| m.initializer.as_ref())
+                {
+                    self.walk_expression(initializer, &mut scopes);
+                },
+                Declaration::Interface(_, decl) => for member in &decl.members
+                {
+                    self.walk_statement(member, &mut scopes);
+                },
+                Declaration::Enum(_, decl) => self.eval_discriminants(decl, &mut scopes),
+                Declaration::Type(_, _) | Declaration::Use(_, _) | Declaration::Mod(_, _) => {},
+            }
+        }
+    }
+
+    fn declare(&mut self, name: String, decl: &ConstDecl) -> (String, Token)
+    {
+        self.items.insert(name.clone(), Slot::Pending(Rc::new(decl.clone())));
+        (name, decl.id)
+    }
+
+    fn report(&mut self, error: ConstError)
+    {
+        // A constant used by several others can fail in the same place for each of them
+        let pos = error.get_token().pos;
+        if !matches!(error, ConstError::Invalid(_)) && !self.errors.iter().any(|e| e.get_token().pos == pos)
+        {
+            self.errors.push(error);
+        }
+    }
+
+    /// Looks up a name, evaluating it first if it is a constant that has not been evaluated yet
+    fn force(&mut self, name: &str, token: &Token) -> ConstResult<Option<ConstItem>>
+    {
+        let decl = match self.items.get(name)
+        {
+            None => return match &mut self.fallback
+            {
+                Some(fallback) => fallback.lookup(name, token),
+                None => Ok(None),
+            },
+            Some(Slot::Done(item)) => return Ok(Some(item.clone())),
+            Some(Slot::Failed) => return Err(ConstError::Invalid(*token)),
+            Some(Slot::Evaluating(definition)) => return Err(ConstError::Cycle { token: *token, definition: *definition }),
+            Some(Slot::Pending(decl)) => decl.clone(),
+        };
+
+        self.items.insert(name.to_owned(), Slot::Evaluating(decl.id));
+        match eval_expression(self, &decl.expression)
+        {
+            Ok(value) =>
+            {
+                self.values.insert(decl.id.pos, value.clone());
+                self.items.insert(name.to_owned(), Slot::Done(ConstItem::Value(value.clone())));
+                Ok(Some(ConstItem::Value(value)))
+            },
+            Err(error) =>
+            {
+                self.items.insert(name.to_owned(), Slot::Failed);
+                self.report(error);
+                Err(ConstError::Invalid(*token))
+            },
+        }
+    }
+
+    /// Evaluates a constant declared in a block, which sees the constants of the blocks around it
+    fn eval_local(&mut self, decl: &ConstDecl, scopes: &mut Vec<HashMap<String, ConstItem>>)
+    {
+        let mut env = Scoped { evaluator: self, scopes };
+        match eval_expression(&mut env, &decl.expression)
+        {
+            Ok(value) =>
+            {
+                self.values.insert(decl.id.pos, value.clone());
+                if let Some(scope) = scopes.last_mut()
+                {
+                    scope.insert(token_str(&decl.id), ConstItem::Value(value));
+                }
+            },
+            Err(error) => self.report(error),
+        }
+    }
+
+    /// Evaluates the discriminants of an enum, recording each by the position of its member's name
+    fn eval_discriminants(&mut self, decl: &EnumDecl, scopes: &mut Vec<HashMap<String, ConstItem>>)
+    {
+        let mut env = Scoped { evaluator: self, scopes };
+        match discriminants(&mut env, decl)
+        {
+            Ok(values) => for (member, value) in decl.members.iter().zip(values)
+            {
+                self.values.insert(member.id().pos, ConstValue::Int(value));
+            },
+            Err(error) => self.report(error),
+        }
+    }
+
+    fn walk_fn(&mut self, decl: &FnDecl, scopes: &mut Vec<HashMap<String, ConstItem>>)
+    {
+        for param in &decl.params
+        {
+            if let FnParam::Normal { default_value: Some((_, expression)), .. } = param
+            {
+                self.walk_expression(expression, scopes);
+            }
+        }
+
+        if let Either::Left(body) = &decl.body
+        {
+            self.walk_block(body, scopes);
+        }
+    }
+
+    fn walk_block(&mut self, block: &BlockExpr, scopes: &mut Vec<HashMap<String, ConstItem>>)
+    {
+        scopes.push(HashMap::new());
+        for statement in &block.statements
+        {
+            self.walk_statement(statement, scopes);
+        }
+
+        if let Some(expression) = &block.expression
+        {
+            self.walk_expression(expression, scopes);
+        }
+
+        scopes.pop();
+    }
+
+    fn walk_let(&mut self, let_stmt: &LetStmt, scopes: &mut Vec<HashMap<String, ConstItem>>)
+    {
+        self.walk_expression(&let_stmt.expression, scopes);
+        if let Some((_, block)) = &let_stmt.else_clause
+        {
+            self.walk_block(block, scopes);
+        }
+    }
+
+    /// Finds the constants declared in blocks inside a statement, and evaluates them
+    fn walk_statement(&mut self, statement: &Statement, scopes: &mut Vec<HashMap<String, ConstItem>>)
+    {
+        match statement
+        {
+            Statement::Const(decl) =>
+            {
+                self.walk_expression(&decl.expression, scopes);
+                self.eval_local(decl, scopes);
+            },
+            Statement::FnDecl(decl) =>
+            {
+                if let (true, Some(scope)) = (decl.is_const(), scopes.last_mut())
+                {
+                    scope.insert(token_str(&decl.id), ConstItem::Fn(Rc::new(decl.clone())));
+                }
+
+                self.walk_fn(decl, scopes);
+            },
+            Statement::While(stmt) =>
+            {
+                self.walk_let_condition(&stmt.condition, scopes);
+                self.walk_block(&stmt.body, scopes);
+            },
+            Statement::For(stmt) =>
+            {
+                self.walk_expression(&stmt.expression, scopes);
+                self.walk_block(&stmt.body, scopes);
+            },
+            Statement::Return(stmt) => if let Some(expression) = &stmt.expression
+            {
+                self.walk_expression(expression, scopes);
+            },
+            Statement::StructDecl(decl) => for (_, initializer) in decl.members.iter().filter_map(|m| m.initializer.as_ref())
+            {
+                self.walk_expression(initializer, scopes);
+            },
+            Statement::InterfaceDecl(decl) => for member in &decl.members
+            {
+                self.walk_statement(member, scopes);
+            },
+            Statement::Let(let_stmt) => self.walk_let(let_stmt, scopes),
+            Statement::Assign(stmt) =>
+            {
+                self.walk_expression(&stmt.value, scopes);
+                self.walk_expression(&stmt.expression, scopes);
+            },
+            Statement::If(if_expr) => self.walk_if(if_expr, scopes),
+            Statement::Match(match_expr) => self.walk_match(match_expr, scopes),
+            Statement::Block(block) => self.walk_block(block, scopes),
+            Statement::Expression(stmt) => self.walk_expression(&stmt.expression, scopes),
+            Statement::EnumDecl(decl) => self.eval_discriminants(decl, scopes),
+            Statement::Continue(_) | Statement::Break(_) | Statement::TypeDecl(_) | Statement::Use(_) => {},
+        }
+    }
+
+    fn walk_expression(&mut self, expression: &Expression, scopes: &mut Vec<HashMap<String, ConstItem>>)
+    {
+        match expression
+        {
+            Expression::Lambda(lambda) => self.walk_expression(&lambda.expression, scopes),
+            Expression::ArrayLiteral(array) => for expression in &array.expressions
+            {
+                self.walk_expression(expression, scopes);
+            },
+            Expression::Grouping(grouping) => self.walk_expression(&grouping.expression, scopes),
+            Expression::BlockExpr(block) => self.walk_block(block, scopes),
+            Expression::Construction(construction) => for arg in &construction.args
+            {
+                self.walk_expression(&arg.value, scopes);
+            },
+            Expression::EnumConstruction(construction) => self.walk_expression(&construction.expression, scopes),
+            Expression::Call(call) =>
+            {
+                self.walk_expression(&call.expression, scopes);
+                for arg in &call.args
+                {
+                    self.walk_expression(arg, scopes);
+                }
+            },
+            Expression::Access(access) => self.walk_expression(&access.expression, scopes),
+            Expression::Index(index) =>
+            {
+                self.walk_expression(&index.expression, scopes);
+                self.walk_expression(&index.indexer, scopes);
+            },
+            Expression::Unary(unary) => self.walk_expression(&unary.expression, scopes),
+            Expression::Binary(binary) =>
+            {
+                self.walk_expression(&binary.left, scopes);
+                self.walk_expression(&binary.right, scopes);
+            },
+            Expression::IfExpr(if_expr) => self.walk_if(if_expr, scopes),
+            Expression::MatchExpr(match_expr) => self.walk_match(match_expr, scopes),
+            Expression::Cast(cast) => self.walk_expression(&cast.expression, scopes),
+            Expression::Yield(yield_expr) => self.walk_expression(&yield_expr.expression, scopes),
+            Expression::Jump(jump) => if let Some(expression) = &jump.expression
+            {
+                self.walk_expression(expression, scopes);
+            },
+            Expression::Interpolation(interpolation) => for (expression, _) in &interpolation.parts
+            {
+                self.walk_expression(expression, scopes);
+            },
+            Expression::Literal(_) | Expression::Identifier(_) | Expression::SelfExpr(_) | Expression::TypeValue(_) => {},
+        }
+    }
+
+    fn walk_let_condition(&mut self, condition: &LetCondition, scopes: &mut Vec<HashMap<String, ConstItem>>)
+    {
+        match condition
+        {
+            LetCondition::Expression(expression) => self.walk_expression(expression, scopes),
+            LetCondition::Pattern { expression, other_cond, .. } =>
+            {
+                self.walk_expression(expression, scopes);
+                if let Some(other_cond) = other_cond
+                {
+                    self.walk_let_condition(other_cond, scopes);
+                }
+            },
+        }
+    }
+
+    fn walk_if(&mut self, if_expr: &IfExpr, scopes: &mut Vec<HashMap<String, ConstItem>>)
+    {
+        self.walk_let_condition(&if_expr.condition, scopes);
+        self.walk_block(&if_expr.block, scopes);
+        match if_expr.else_branch.as_ref().map(|e| &e.body)
+        {
+            Some(Either::Left(if_expr)) => self.walk_if(if_expr, scopes),
+            Some(Either::Right(block)) => self.walk_block(block, scopes),
+            None => {},
+        }
+    }
+
+    fn walk_match(&mut self, match_expr: &MatchExpr, scopes: &mut Vec<HashMap<String, ConstItem>>)
+    {
+        self.walk_expression(&match_expr.expression, scopes);
+        for branch in &match_expr.branches
+        {
+            if let Some((_, guard)) = &branch.guard
+            {
+                self.walk_expression(guard, scopes);
+            }
+            self.walk_expression(&branch.expression, scopes);
+        }
+    }
+}
+
+/// The values an enum's discriminants may take for each backing type. Every integer is an `Int` at runtime, so none
+/// reach past its range.
+const BACKING_RANGES: &[(&str, i64, i64)] = &[
+    ("Int", i64::MIN, i64::MAX),
+    ("i8", i8::MIN as i64, i8::MAX as i64),
+    ("i16", i16::MIN as i64, i16::MAX as i64),
+    ("i32", i32::MIN as i64, i32::MAX as i64),
+    ("i64", i64::MIN, i64::MAX),
+    ("u8", 0, u8::MAX as i64),
+    ("u16", 0, u16::MAX as i64),
+    ("u32", 0, u32::MAX as i64),
+    ("u64", 0, i64::MAX),
+];
+
+/// The discriminant of each member of an enum, in order. A basic member may give its own with `= value`, and any other
+/// takes the one after the member before it, the first starting at 0.
+pub fn discriminants(env: &mut impl ConstEnv, decl: &EnumDecl) -> ConstResult<Vec<i64>>
+{
+    let type_name = match &decl.backing_type
+    {
+        Some((_, TypeName::Identifier { name, args: _ })) => token_str(name),
+        _ => "Int".to_owned(),
+    };
+    let (min, max) = BACKING_RANGES.iter()
+        .find(|(name, _, _)| *name == type_name)
+        .map_or((i64::MIN, i64::MAX), |(_, min, max)| (*min, *max));
+
+    let mut values: Vec<i64> = vec![];
+    let mut next = 0i128;
+    for member in &decl.members
+    {
+        let id = member.id();
+        let value = match member
+        {
+            EnumMember::Basic { discriminant: Some((_, expression)), .. } => match eval_expression(env, expression)?
+            {
+                ConstValue::Int(i) => i as i128,
+                other => return Err(ConstError::DiscriminantType { token: *id, found: other.type_name() }),
+            },
+            _ => next,
+        };
+
+        // Checked against `Int` first, so a `u64` discriminant past `i64::MAX` is not reported as out of range for `u64`
+        let limit = match (min as i128..=max as i128).contains(&value)
+        {
+            true => None,
+            false if !(i64::MIN as i128..=i64::MAX as i128).contains(&value) => Some("Int".to_owned()),
+            false => Some(type_name.clone()),
+        };
+
+        if let Some(type_name) = limit
+        {
+            return Err(ConstError::DiscriminantOverflow { token: *id, value, type_name });
+        }
+
+        let value = value as i64;
+        if let Some(other) = values.iter().position(|v| *v == value)
+        {
+            return Err(ConstError::DiscriminantCollision { token: *id, other: *decl.members[other].id(), value });
+        }
+
+        values.push(value);
+        next = value as i128 + 1;
+    }
+
+    Ok(values)
+}
+
+/// The name of the type an `impl` block adds members to
+fn impl_target(impl_stmt: &ImplStmt) -> Option<&Token>
+{
+    let target = match &impl_stmt.for_clause
+    {
+        Some((_, target)) => target,
+        None => &impl_stmt.type_name,
+    };
+
+    match target
+    {
+        TypeName::Identifier { name, args: _ } => Some(name),
+        _ => None,
+    }
+}
+
+impl ConstEnv for ConstEvaluator
+{
+    fn lookup(&mut self, name: &str, token: &Token) -> ConstResult<Option<ConstItem>>
+    {
+        self.force(name, token)
+    }
+}
+
+/// The constants of the blocks around a constant, in front of those of the whole program
+struct Scoped<'a>
+{
+    evaluator: &'a mut ConstEvaluator,
+    scopes: &'a [HashMap<String, ConstItem>],
+}
+
+impl ConstEnv for Scoped<'_>
+{
+    fn lookup(&mut self, name: &str, token: &Token) -> ConstResult<Option<ConstItem>>
+    {
+        match self.scopes.iter().rev().find_map(|s| s.get(name))
+        {
+            Some(item) => Ok(Some(item.clone())),
+            None => self.evaluator.force(name, token),
+        }
+    }
+}
@@ -0,0 +1,589 @@
+use std::rc::Rc;
+
+use either::Either;
+
+use crate::ast::*;
+use crate::lexing::token::{Token, TokenPos};
+use crate::lexing::LexerResult;
+use crate::source::SourceFile;
+
+use super::{GreenElement, GreenNode, GreenToken, SyntaxKind, SyntaxNode};
+
+/// Builds the concrete syntax tree of a file lexed from `source`. Its nodes follow the nodes of the program parsed from it, and
+/// every token lands in the innermost node covering it, so tokens the AST drops such as commas are still in the tree.
+/// Without a program, as when the file did not parse, the tokens are all children of the root.
+pub fn build(result: &LexerResult, source: &SourceFile, program: Option<&Program>) -> SyntaxNode
+{
+    let mut spans = Spans { nodes: vec![] };
+    if let Some(program) = program
+    {
+        program.declarations.iter().for_each(|d| spans.declaration(d));
+    }
+
+    let mut nodes = spans.nodes;
+    nodes.sort_by_key(|(_, span)| (span.begin, std::cmp::Reverse(span.end)));
+    let mut nodes = nodes.into_iter().peekable();
+
+    // The nodes still open, innermost last, each with where it ends and the children found so far
+    let mut open: Vec<(SyntaxKind, usize, Vec<GreenElement>)> = vec![(SyntaxKind::Program, usize::MAX, vec![])];
+    for token in result.with_trivia(source)
+    {
+        let begin = token.token.pos.begin;
+        while open.len() > 1 && open.last().unwrap().1 < begin
+        {
+            close(&mut open);
+        }
+
+        while let Some((kind, span)) = nodes.next_if(|(_, span)| span.begin <= begin)
+        {
+            if span.end >= begin
+            {
+                open.push((kind, span.end, vec![]));
+            }
+        }
+
+        let green = GreenToken
+        {
+            token_type: token.token.token_type,
+            text: token.text(source).to_owned(),
+            leading: token.leading,
+            trailing: token.trailing,
+        };
+
+        open.last_mut().unwrap().2.push(GreenElement::Token(Rc::new(green)));
+    }
+
+    while open.len() > 1
+    {
+        close(&mut open);
+    }
+
+    let (kind, _, children) = open.pop().unwrap();
+    SyntaxNode::new_root(Rc::new(GreenNode::new(kind, children)))
+}
+
+fn close(open: &mut Vec<(SyntaxKind, usize, Vec<GreenElement>)>)
+{
+    let (kind, _, children) = open.pop().unwrap();
+    let node = GreenElement::Node(Rc::new(GreenNode::new(kind, children)));
+    open.last_mut().unwrap().2.push(node);
+}
+
+fn between(first: &Token, last: &Token) -> TokenPos
+{
+    TokenPos { begin: first.pos.begin, end: last.pos.end }
+}
+
+/// Collects the kind and span of every AST node of a program
+struct Spans
+{
+    nodes: Vec<(SyntaxKind, TokenPos)>,
+}
+
+impl Spans
+{
+    fn node(&mut self, kind: SyntaxKind, span: TokenPos)
+    {
+        self.nodes.push((kind, span));
+    }
+
+    fn declaration(&mut self, declaration: &Declaration)
+    {
+        let span = declaration.span();
+        match declaration
+        {
+            Declaration::Fn(_, decl) => self.fn_decl(decl, span),
+            Declaration::Struct(_, decl) => self.struct_decl(decl, span),
+            Declaration::Interface(_, decl) => self.interface_decl(decl, span),
+            Declaration::Enum(_, decl) => self.enum_decl(decl, span),
+            Declaration::Type(_, decl) => self.type_decl(decl, span),
+            Declaration::Let(_, decl) => self.let_stmt(decl, span),
+            Declaration::Const(_, decl) => self.const_decl(decl, span),
+            Declaration::Use(_, _) => self.node(SyntaxKind::UseStmt, span),
+            Declaration::Mod(_, decl) =>
+            {
+                self.node(SyntaxKind::ModDecl, span);
+                if let Either::Left(body) = &decl.body
+                {
+                    for declaration in &body.declarations
+                    {
+                        self.declaration(declaration);
+                    }
+                }
+            },
+            Declaration::Impl(decl) =>
+            {
+                self.node(SyntaxKind::ImplDecl, span);
+                self.generic_params(&decl.generic_params);
+                self.type_name(&decl.type_name);
+                if let Some((_, type_name)) = &decl.for_clause
+                {
+                    self.type_name(type_name);
+                }
+
+                self.where_clause(&decl.where_clause);
+                for (pub_tok, member) in &decl.members
+                {
+                    let span = member.span();
+                    self.statement(member, TokenPos { begin: pub_tok.as_ref().map_or(span.begin, |t| t.pos.begin), end: span.end });
+                }
+            },
+        }
+    }
+
+    fn fn_decl(&mut self, decl: &FnDecl, span: TokenPos)
+    {
+        self.node(SyntaxKind::FnDecl, span);
+        self.generic_params(&decl.generic_params);
+        for param in &decl.params
+        {
+            match param
+            {
+                FnParam::Normal { mut_tok, id, colon: _, type_name, default_value } =>
+                {
+                    let end = default_value.as_ref().map_or(type_name.span().end, |(_, e)| e.span().end);
+                    self.node(SyntaxKind::FnParam, TokenPos { begin: mut_tok.as_ref().unwrap_or(id).pos.begin, end });
+                    self.type_name(type_name);
+                    if let Some((_, expression)) = default_value
+                    {
+                        self.expression(expression);
+                    }
+                },
+                FnParam::SelfParam { mut_tok, self_tok } => self.node(SyntaxKind::FnParam, between(mut_tok.as_ref().unwrap_or(self_tok), self_tok)),
+            }
+        }
+
+        self.type_name(&decl.return_type);
+        self.where_clause(&decl.where_clause);
+        if let Either::Left(body) = &decl.body
+        {
+            self.block(body);
+        }
+    }
+
+    fn struct_decl(&mut self, decl: &StructDecl, span: TokenPos)
+    {
+        self.node(SyntaxKind::StructDecl, span);
+        self.generic_params(&decl.generic_params);
+        self.where_clause(&decl.where_clause);
+        for member in &decl.members
+        {
+            let first = member.pub_tok.as_ref().or(member.mut_tok.as_ref()).unwrap_or(&member.id);
+            let end = member.initializer.as_ref().map_or(member.type_name.span().end, |(_, e)| e.span().end);
+            self.node(SyntaxKind::StructMember, TokenPos { begin: first.pos.begin, end });
+            self.type_name(&member.type_name);
+            if let Some((_, expression)) = &member.initializer
+            {
+                self.expression(expression);
+            }
+        }
+    }
+
+    fn interface_decl(&mut self, decl: &InterfaceDecl, span: TokenPos)
+    {
+        self.node(SyntaxKind::InterfaceDecl, span);
+        self.generic_params(&decl.generic_params);
+        self.where_clause(&decl.where_clause);
+        for member in &decl.members
+        {
+            self.statement(member, member.span());
+        }
+    }
+
+    fn enum_decl(&mut self, decl: &EnumDecl, span: TokenPos)
+    {
+        self.node(SyntaxKind::EnumDecl, span);
+        self.generic_params(&decl.generic_params);
+        if let Some((_, type_name)) = &decl.backing_type
+        {
+            self.type_name(type_name);
+        }
+        self.where_clause(&decl.where_clause);
+        for member in &decl.members
+        {
+            match member
+            {
+                EnumMember::Basic { docs: _, id, discriminant: None } => self.node(SyntaxKind::EnumMember, id.pos),
+                EnumMember::Basic { docs: _, id, discriminant: Some((_, expression)) } =>
+                {
+                    self.node(SyntaxKind::EnumMember, TokenPos { begin: id.pos.begin, end: expression.span().end });
+                    self.expression(expression);
+                },
+                EnumMember::Single { docs: _, id, open_paren: _, type_name, close_paren } =>
+                {
+                    self.node(SyntaxKind::EnumMember, between(id, close_paren));
+                    self.type_name(type_name);
+                },
+                EnumMember::Struct { docs: _, id, open_brace: _, members, close_brace } =>
+                {
+                    self.node(SyntaxKind::EnumMember, between(id, close_brace));
+                    for member in members
+                    {
+                        let end = member.initializer.as_ref().map_or(member.type_name.span().end, |(_, e)| e.span().end);
+                        self.node(SyntaxKind::EnumStructMember, TokenPos { begin: member.mut_tok.as_ref().unwrap_or(&member.id).pos.begin, end });
+                        self.type_name(&member.type_name);
+                        if let Some((_, expression)) = &member.initializer
+                        {
+                            self.expression(expression);
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    fn type_decl(&mut self, decl: &TypeDecl, span: TokenPos)
+    {
+        self.node(SyntaxKind::TypeDecl, span);
+        self.generic_params(&decl.generic_params);
+        self.type_name(&decl.type_name);
+    }
+
+    fn let_stmt(&mut self, stmt: &LetStmt, span: TokenPos)
+    {
+        self.node(SyntaxKind::LetStmt, span);
+        if let LetBinding::Pattern(pattern) = &stmt.binding
+        {
+            self.pattern(pattern);
+        }
+
+        if let Some((_, type_name)) = &stmt.type_name
+        {
+            self.type_name(type_name);
+        }
+
+        self.expression(&stmt.expression);
+        if let Some((_, block)) = &stmt.else_clause
+        {
+            self.block(block);
+        }
+    }
+
+    fn const_decl(&mut self, decl: &ConstDecl, span: TokenPos)
+    {
+        self.node(SyntaxKind::ConstDecl, span);
+        self.type_name(&decl.type_name);
+        self.expression(&decl.expression);
+    }
+
+    fn generic_params(&mut self, params: &Option<GenericParams>)
+    {
+        if let Some(params) = params
+        {
+            self.node(SyntaxKind::GenericParams, between(&params.open_bracket, &params.close_bracket));
+        }
+    }
+
+    fn generic_args(&mut self, args: &Option<GenericArgs>)
+    {
+        if let Some(args) = args
+        {
+            self.node(SyntaxKind::GenericArgs, between(&args.open_bracket, &args.close_bracket));
+            args.args.iter().for_each(|a| self.type_name(a));
+        }
+    }
+
+    fn where_clause(&mut self, clause: &Option<WhereClause>)
+    {
+        if let Some(clause) = clause
+        {
+            let last = clause.sub_clauses.last().map(|c| c.types.last().map_or(c.colon.pos.end, |t| t.span().end));
+            self.node(SyntaxKind::WhereClause, TokenPos { begin: clause.where_tok.pos.begin, end: last.unwrap_or(clause.where_tok.pos.end) });
+            for sub_clause in &clause.sub_clauses
+            {
+                sub_clause.types.iter().for_each(|t| self.type_name(t));
+            }
+        }
+    }
+
+    fn type_name(&mut self, type_name: &TypeName)
+    {
+        self.node(SyntaxKind::TypeName, type_name.span());
+        match type_name
+        {
+            TypeName::Identifier { name: _, args } => self.generic_args(args),
+            TypeName::Array { open_bracket: _, close_bracket: _, type_name } => self.type_name(type_name),
+            TypeName::Function { fn_tok: _, open_paren: _, parameter_types, close_paren: _, arrow: _, return_type } =>
+            {
+                parameter_types.iter().for_each(|t| self.type_name(t));
+                self.type_name(return_type);
+            },
+            TypeName::Access { inner, dot: _, name: _, args } =>
+            {
+                self.type_name(inner);
+                self.generic_args(args);
+            },
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern)
+    {
+        self.node(SyntaxKind::Pattern, pattern.span());
+        match pattern
+        {
+            Pattern::Literal(_) | Pattern::Wildcard(_) | Pattern::Range { .. } | Pattern::Identifier { .. } => {},
+            Pattern::Binding { mut_tok: _, id: _, at: _, inner } => self.pattern(inner),
+            Pattern::Or(patterns) => patterns.iter().for_each(|p| self.pattern(p)),
+            Pattern::Grouping { open_paren: _, inner, close_paren: _ } => self.pattern(inner),
+            Pattern::TypeValue { type_name, dot: _, id: _ } => self.type_name(type_name),
+            Pattern::EnumConstruct { type_name, open_paren: _, inner, close_paren: _ } =>
+            {
+                self.type_name(type_name);
+                self.pattern(inner);
+            },
+            Pattern::StructConstruct { type_name, open_brace: _, patterns, close_brace: _ } =>
+            {
+                self.type_name(type_name);
+                for field in patterns
+                {
+                    let end = field.inner.as_ref().map_or(field.id.pos.end, |p| p.span().end);
+                    self.node(SyntaxKind::PatternField, TokenPos { begin: field.mut_tok.as_ref().unwrap_or(&field.id).pos.begin, end });
+                    if let Some(inner) = &field.inner
+                    {
+                        self.pattern(inner);
+                    }
+                }
+            },
+            Pattern::ArrayConstruct { open_bracket: _, patterns, close_bracket: _ } => patterns.iter().for_each(|p| self.pattern(p)),
+        }
+    }
+
+    fn let_condition(&mut self, condition: &LetCondition)
+    {
+        match condition
+        {
+            LetCondition::Expression(expression) => self.expression(expression),
+            LetCondition::Pattern { let_tok: _, pattern, equal: _, expression, and: _, other_cond } =>
+            {
+                self.node(SyntaxKind::LetCondition, condition.span());
+                self.pattern(pattern);
+                self.expression(expression);
+                if let Some(other) = other_cond
+                {
+                    self.let_condition(other);
+                }
+            },
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement, span: TokenPos)
+    {
+        match statement
+        {
+            Statement::While(stmt) =>
+            {
+                self.node(SyntaxKind::WhileStmt, span);
+                self.let_condition(&stmt.condition);
+                self.block(&stmt.body);
+            },
+            Statement::For(stmt) =>
+            {
+                self.node(SyntaxKind::ForStmt, span);
+                self.pattern(&stmt.pattern);
+                self.expression(&stmt.expression);
+                self.block(&stmt.body);
+            },
+            Statement::Return(stmt) =>
+            {
+                self.node(SyntaxKind::ReturnStmt, span);
+                if let Some(expression) = &stmt.expression
+                {
+                    self.expression(expression);
+                }
+            },
+            Statement::Continue(_) => self.node(SyntaxKind::ContinueStmt, span),
+            Statement::Break(_) => self.node(SyntaxKind::BreakStmt, span),
+            Statement::TypeDecl(decl) => self.type_decl(decl, span),
+            Statement::EnumDecl(decl) => self.enum_decl(decl, span),
+            Statement::InterfaceDecl(decl) => self.interface_decl(decl, span),
+            Statement::StructDecl(decl) => self.struct_decl(decl, span),
+            Statement::FnDecl(decl) => self.fn_decl(decl, span),
+            Statement::Let(stmt) => self.let_stmt(stmt, span),
+            Statement::Const(decl) => self.const_decl(decl, span),
+            Statement::Assign(stmt) =>
+            {
+                self.node(SyntaxKind::AssignStmt, span);
+                self.expression(&stmt.value);
+                self.expression(&stmt.expression);
+            },
+            Statement::If(if_expr) => self.if_expr(if_expr),
+            Statement::Match(match_expr) => self.match_expr(match_expr),
+            Statement::Block(block) => self.block(block),
+            Statement::Expression(stmt) =>
+            {
+                self.node(SyntaxKind::ExpressionStmt, span);
+                self.expression(&stmt.expression);
+            },
+            Statement::Use(_) => self.node(SyntaxKind::UseStmt, span),
+        }
+    }
+
+    fn block(&mut self, block: &BlockExpr)
+    {
+        self.node(SyntaxKind::BlockExpr, block.span());
+        block.statements.iter().for_each(|s| self.statement(s, s.span()));
+        if let Some(expression) = &block.expression
+        {
+            self.expression(expression);
+        }
+    }
+
+    fn if_expr(&mut self, if_expr: &IfExpr)
+    {
+        self.node(SyntaxKind::IfExpr, if_expr.span());
+        self.let_condition(&if_expr.condition);
+        self.block(&if_expr.block);
+        if let Some(else_branch) = &if_expr.else_branch
+        {
+            match &else_branch.body
+            {
+                Either::Left(if_expr) =>
+                {
+                    self.node(SyntaxKind::ElseBranch, TokenPos { begin: else_branch.else_tok.pos.begin, end: if_expr.span().end });
+                    self.if_expr(if_expr);
+                },
+                Either::Right(block) =>
+                {
+                    self.node(SyntaxKind::ElseBranch, between(&else_branch.else_tok, &block.close_brace));
+                    self.block(block);
+                },
+            }
+        }
+    }
+
+    fn match_expr(&mut self, match_expr: &MatchExpr)
+    {
+        self.node(SyntaxKind::MatchExpr, between(&match_expr.match_tok, &match_expr.close_brace));
+        self.expression(&match_expr.expression);
+        for branch in &match_expr.branches
+        {
+            self.node(SyntaxKind::MatchBranch, TokenPos { begin: branch.pattern.span().begin, end: branch.expression.span().end });
+            self.pattern(&branch.pattern);
+            if let Some((_, guard)) = &branch.guard
+            {
+                self.expression(guard);
+            }
+            self.expression(&branch.expression);
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression)
+    {
+        let span = expression.span();
+        match expression
+        {
+            Expression::Lambda(lambda) =>
+            {
+                self.node(SyntaxKind::LambdaExpr, span);
+                if let LambdaParams::Complex { open_pipe: _, parameters, close_pipe: _, arrow: _, return_type } = &lambda.params
+                {
+                    for param in parameters
+                    {
+                        let end = param.type_name.as_ref().map_or(param.name.pos.end, |t| t.span().end);
+                        self.node(SyntaxKind::LambdaParam, TokenPos { begin: param.name.pos.begin, end });
+                        if let Some(type_name) = &param.type_name
+                        {
+                            self.type_name(type_name);
+                        }
+                    }
+
+                    if let Some(return_type) = return_type
+                    {
+                        self.type_name(return_type);
+                    }
+                }
+
+                self.expression(&lambda.expression);
+            },
+            Expression::Literal(_) => self.node(SyntaxKind::LiteralExpr, span),
+            Expression::Identifier(_) => self.node(SyntaxKind::IdentifierExpr, span),
+            Expression::SelfExpr(_) => self.node(SyntaxKind::SelfExpr, span),
+            Expression::ArrayLiteral(array) =>
+            {
+                self.node(SyntaxKind::ArrayLiteral, span);
+                array.expressions.iter().for_each(|e| self.expression(e));
+            },
+            Expression::Grouping(grouping) =>
+            {
+                self.node(SyntaxKind::GroupingExpr, span);
+                self.expression(&grouping.expression);
+            },
+            Expression::BlockExpr(block) => self.block(block),
+            Expression::TypeValue(type_value) =>
+            {
+                self.node(SyntaxKind::TypeValueExpr, span);
+                self.type_name(&type_value.type_name);
+            },
+            Expression::Construction(construction) =>
+            {
+                self.node(SyntaxKind::ConstructionExpr, span);
+                self.type_name(&construction.type_name);
+                for arg in &construction.args
+                {
+                    self.node(SyntaxKind::ConstructionArg, TokenPos { begin: arg.name.pos.begin, end: arg.value.span().end });
+                    self.expression(&arg.value);
+                }
+            },
+            Expression::EnumConstruction(construction) =>
+            {
+                self.node(SyntaxKind::EnumConstructionExpr, span);
+                self.type_name(&construction.type_name);
+                self.expression(&construction.expression);
+            },
+            Expression::Call(call) =>
+            {
+                self.node(SyntaxKind::CallExpr, span);
+                self.expression(&call.expression);
+                call.args.iter().for_each(|e| self.expression(e));
+            },
+            Expression::Access(access) =>
+            {
+                self.node(SyntaxKind::AccessExpr, span);
+                self.expression(&access.expression);
+            },
+            Expression::Index(index) =>
+            {
+                self.node(SyntaxKind::IndexExpr, span);
+                self.expression(&index.expression);
+                self.expression(&index.indexer);
+            },
+            Expression::Unary(unary) =>
+            {
+                self.node(SyntaxKind::UnaryExpr, span);
+                self.expression(&unary.expression);
+            },
+            Expression::Binary(binary) =>
+            {
+                self.node(SyntaxKind::BinaryExpr, span);
+                self.expression(&binary.left);
+                self.expression(&binary.right);
+            },
+            Expression::IfExpr(if_expr) => self.if_expr(if_expr),
+            Expression::MatchExpr(match_expr) => self.match_expr(match_expr),
+            Expression::Cast(cast) =>
+            {
+                self.node(SyntaxKind::CastExpr, span);
+                self.expression(&cast.expression);
+                self.type_name(&cast.type_name);
+            },
+            Expression::Interpolation(interpolation) =>
+            {
+                self.node(SyntaxKind::InterpolationExpr, span);
+                interpolation.parts.iter().for_each(|(e, _)| self.expression(e));
+            },
+            Expression::Yield(yield_expr) =>
+            {
+                self.node(SyntaxKind::YieldExpr, span);
+                self.expression(&yield_expr.expression);
+            },
+            Expression::Jump(jump) =>
+            {
+                self.node(SyntaxKind::JumpExpr, span);
+                if let Some(expression) = &jump.expression
+                {
+                    self.expression(expression);
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,466 @@
+//! A lossless concrete syntax tree, which holds every character of a file so it can be rewritten without losing
+//! its layout or comments. The tree has two layers: green nodes own their text and know only their width, so
+//! untouched parts can be shared between versions of a tree, and red nodes wrap them with their place in the file
+//! and their parent. The typed AST stays the view used to understand a program, each of its nodes covers the text of
+//! a node here, found with `SyntaxNode::node_for`.
+
+use std::rc::Rc;
+
+use crate::lexing::token::{TokenPos, TokenType};
+use crate::lexing::trivia::Trivia;
+
+mod build;
+
+pub use build::build;
+
+/// The kind of a node, named after the AST node it covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxKind
+{
+    Program,
+    FnDecl,
+    FnParam,
+    StructDecl,
+    StructMember,
+    InterfaceDecl,
+    EnumDecl,
+    EnumMember,
+    EnumStructMember,
+    TypeDecl,
+    ImplDecl,
+    ModDecl,
+    GenericParams,
+    GenericArgs,
+    WhereClause,
+    UseStmt,
+    LetStmt,
+    ConstDecl,
+    AssignStmt,
+    ExpressionStmt,
+    WhileStmt,
+    ForStmt,
+    ReturnStmt,
+    BreakStmt,
+    ContinueStmt,
+    LetCondition,
+    IfExpr,
+    ElseBranch,
+    MatchExpr,
+    MatchBranch,
+    BlockExpr,
+    LambdaExpr,
+    LambdaParam,
+    LiteralExpr,
+    ArrayLiteral,
+    IdentifierExpr,
+    GroupingExpr,
+    SelfExpr,
+    TypeValueExpr,
+    ConstructionExpr,
+    ConstructionArg,
+    EnumConstructionExpr,
+    CallExpr,
+    AccessExpr,
+    IndexExpr,
+    UnaryExpr,
+    BinaryExpr,
+    CastExpr,
+    InterpolationExpr,
+    YieldExpr,
+    JumpExpr,
+    TypeName,
+    Pattern,
+    PatternField,
+}
+
+/// A token with its trivia, without a position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenToken
+{
+    pub token_type: TokenType,
+    pub leading: Vec<Trivia>,
+    pub text: String,
+    pub trailing: Vec<Trivia>,
+}
+
+impl GreenToken
+{
+    fn leading_width(&self) -> usize
+    {
+        self.leading.iter().map(|t| t.text.chars().count()).sum()
+    }
+
+    /// The number of characters in the token and its trivia
+    pub fn width(&self) -> usize
+    {
+        self.leading_width() + self.text.chars().count() + self.trailing.iter().map(|t| t.text.chars().count()).sum::<usize>()
+    }
+
+    fn write(&self, out: &mut String)
+    {
+        self.leading.iter().for_each(|t| *out += &t.text);
+        *out += &self.text;
+        self.trailing.iter().for_each(|t| *out += &t.text);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement
+{
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement
+{
+    pub fn width(&self) -> usize
+    {
+        match self
+        {
+            GreenElement::Node(node) => node.width,
+            GreenElement::Token(token) => token.width(),
+        }
+    }
+}
+
+/// A node owning its children, without a position, so equal subtrees can be shared
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenNode
+{
+    kind: SyntaxKind,
+    width: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode
+{
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self
+    {
+        let width = children.iter().map(GreenElement::width).sum();
+        Self { kind, width, children }
+    }
+
+    pub fn kind(&self) -> SyntaxKind
+    {
+        self.kind
+    }
+
+    pub fn width(&self) -> usize
+    {
+        self.width
+    }
+
+    pub fn children(&self) -> &[GreenElement]
+    {
+        &self.children
+    }
+
+    /// A copy of the node with one child replaced, sharing the others
+    fn with_child(&self, index: usize, child: GreenElement) -> Self
+    {
+        let mut children = self.children.clone();
+        children[index] = child;
+        Self::new(self.kind, children)
+    }
+
+    fn write(&self, out: &mut String)
+    {
+        for child in &self.children
+        {
+            match child
+            {
+                GreenElement::Node(node) => node.write(out),
+                GreenElement::Token(token) => token.write(out),
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for GreenNode
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        let mut text = String::new();
+        self.write(&mut text);
+        f.write_str(&text)
+    }
+}
+
+#[derive(Debug)]
+struct NodeData
+{
+    green: Rc<GreenNode>,
+    /// The index of the first character of the node, counting its leading trivia
+    offset: usize,
+    /// The parent and the index of this node among its children
+    parent: Option<(SyntaxNode, usize)>,
+}
+
+/// A green node at its place in a file
+#[derive(Debug, Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+/// A green token at its place in a file
+#[derive(Debug, Clone)]
+pub struct SyntaxToken
+{
+    green: Rc<GreenToken>,
+    offset: usize,
+    parent: SyntaxNode,
+    index: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum SyntaxElement
+{
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode
+{
+    pub fn new_root(green: Rc<GreenNode>) -> Self
+    {
+        Self(Rc::new(NodeData { green, offset: 0, parent: None }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind
+    {
+        self.0.green.kind
+    }
+
+    pub fn green(&self) -> &Rc<GreenNode>
+    {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode>
+    {
+        self.0.parent.as_ref().map(|(parent, _)| parent)
+    }
+
+    /// The index of the first character of the node, counting its leading trivia
+    pub fn offset(&self) -> usize
+    {
+        self.0.offset
+    }
+
+    pub fn children(&self) -> Vec<SyntaxElement>
+    {
+        let mut offset = self.0.offset;
+        let mut children = vec![];
+        for (index, child) in self.0.green.children.iter().enumerate()
+        {
+            children.push(match child
+            {
+                GreenElement::Node(green) => SyntaxElement::Node(SyntaxNode(Rc::new(NodeData
+                {
+                    green: green.clone(),
+                    offset,
+                    parent: Some((self.clone(), index)),
+                }))),
+                GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken { green: green.clone(), offset, parent: self.clone(), index }),
+            });
+
+            offset += child.width();
+        }
+
+        children
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = SyntaxNode>
+    {
+        self.children().into_iter().filter_map(|c| match c
+        {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Every token in the node, in the order they appear
+    pub fn tokens(&self) -> Vec<SyntaxToken>
+    {
+        let mut tokens = vec![];
+        for child in self.children()
+        {
+            match child
+            {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+
+        tokens
+    }
+
+    /// The text the node covers without the trivia around it, as the span of its AST node
+    pub fn span(&self) -> TokenPos
+    {
+        let tokens = self.tokens();
+        let first = tokens.first().expect("nodes hold at least one token").pos();
+        let last = tokens.last().expect("nodes hold at least one token").pos();
+        TokenPos { begin: first.begin, end: last.end }
+    }
+
+    /// Finds the innermost node of a kind covering exactly a span, such as the node of an AST node
+    pub fn node_for(&self, kind: SyntaxKind, span: TokenPos) -> Option<SyntaxNode>
+    {
+        if self.0.offset > span.begin || self.0.offset + self.0.green.width <= span.end
+        {
+            return None;
+        }
+
+        let inner = self.child_nodes().find_map(|child| child.node_for(kind, span));
+        inner.or_else(|| (self.kind() == kind && self.span() == span).then(|| self.clone()))
+    }
+
+    /// Returns the root of a tree where this node is replaced, sharing every node the replacement does not touch
+    pub fn replace_with(&self, green: GreenNode) -> SyntaxNode
+    {
+        match &self.0.parent
+        {
+            Some((parent, index)) => parent.replace_with(parent.0.green.with_child(*index, GreenElement::Node(Rc::new(green)))),
+            None => SyntaxNode::new_root(Rc::new(green)),
+        }
+    }
+
+    /// Writes the tree out with one line for each node and token, showing where each is and the trivia around tokens
+    pub fn dump(&self) -> String
+    {
+        let mut out = String::new();
+        self.dump_into(&mut out, 0);
+        out
+    }
+
+    fn dump_into(&self, out: &mut String, depth: usize)
+    {
+        let span = self.span();
+        *out += &format!("{}{:?} {}..={}\n", "  ".repeat(depth), self.kind(), span.begin, span.end);
+        for child in self.children()
+        {
+            match child
+            {
+                SyntaxElement::Node(node) => node.dump_into(out, depth + 1),
+                SyntaxElement::Token(token) =>
+                {
+                    let trivia = |trivia: &[Trivia]| trivia.iter().map(|t| format!(" {:?} {:?}", t.kind, t.text)).collect::<String>();
+                    let pos = token.pos();
+                    *out += &format!("{}{:?} {}..={} {:?}", "  ".repeat(depth + 1), token.kind(), pos.begin, pos.end, token.text());
+                    if !token.leading().is_empty()
+                    {
+                        *out += &format!(" leading [{} ]", trivia(token.leading()));
+                    }
+
+                    if !token.trailing().is_empty()
+                    {
+                        *out += &format!(" trailing [{} ]", trivia(token.trailing()));
+                    }
+
+                    out.push('\n');
+                },
+            }
+        }
+    }
+}
+
+/// The text of the node with all of its trivia, exactly as it was in the source
+impl std::fmt::Display for SyntaxNode
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        self.0.green.fmt(f)
+    }
+}
+
+impl SyntaxToken
+{
+    pub fn kind(&self) -> TokenType
+    {
+        self.green.token_type
+    }
+
+    pub fn text(&self) -> &str
+    {
+        &self.green.text
+    }
+
+    pub fn leading(&self) -> &[Trivia]
+    {
+        &self.green.leading
+    }
+
+    pub fn trailing(&self) -> &[Trivia]
+    {
+        &self.green.trailing
+    }
+
+    pub fn parent(&self) -> &SyntaxNode
+    {
+        &self.parent
+    }
+
+    /// The text the token covers without its trivia. The end of file covers the index after the text.
+    pub fn pos(&self) -> TokenPos
+    {
+        let begin = self.offset + self.green.leading_width();
+        TokenPos { begin, end: begin + self.green.text.chars().count().max(1) - 1 }
+    }
+
+    /// Returns the root of a tree where the text of this token is replaced, keeping its trivia
+    pub fn replace_text(&self, text: &str) -> SyntaxNode
+    {
+        let green = GreenToken { text: text.to_owned(), ..(*self.green).clone() };
+        self.parent.replace_with(self.parent.0.green.with_child(self.index, GreenElement::Token(Rc::new(green))))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::{fs, path::Path};
+
+    use crate::{lexing, parsing, source::SourceFile};
+
+    /// Builds the tree of a text the way `cirrus parse --format cst` does and writes it back out
+    fn round_trip(text: &str) -> String
+    {
+        let source = SourceFile::new(text);
+        let lexed = lexing::lex_text(text);
+        let program = parsing::parse(lexed.tokens.clone(), &lexed.comments).ok().flatten();
+        super::build(&lexed, &source, program.as_ref()).to_string()
+    }
+
+    /// The tree of every sample program holds its text exactly
+    #[test]
+    fn round_trip_sample_programs()
+    {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let mut checked = 0;
+        for entry in fs::read_dir(dir).unwrap()
+        {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|e| e != "crs")
+            {
+                continue;
+            }
+
+            let text = fs::read_to_string(&path).unwrap();
+            assert_eq!(round_trip(&text), text, "{}", path.display());
+            checked += 1;
+        }
+
+        assert!(checked > 0);
+    }
+
+    /// Line endings, tabs, characters outside ASCII and comments all stay as they were, in a file that parses and
+    /// in one that does not
+    #[test]
+    fn round_trip_layout()
+    {
+        let text = "//! The file\r\n\r\n/// Greets\r\nfn greet(name: String) -> String\r\n{\r\n\tlet café = \"héllo ${name} ✓\"; /* ünïcode */\r\n\tcafé // done\r\n}\r\n";
+        assert_eq!(round_trip(text), text);
+
+        let broken = "fn broken(\t\r\n  // never closed\r\n  x: Ïnt, /* 🦀 */";
+        assert_eq!(round_trip(broken), broken);
+    }
+}
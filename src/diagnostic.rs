@@ -0,0 +1,237 @@
+use std::io::IsTerminal;
+
+use crate::lexing::token::TokenPos;
+use crate::source::SourceFile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity
+{
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity
+{
+    fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+
+    fn colour(&self) -> &'static str
+    {
+        match self
+        {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+            Severity::Note => GREEN,
+        }
+    }
+}
+
+/// A span of source text with a message explaining its part in a diagnostic
+#[derive(Debug, Clone)]
+pub struct Label
+{
+    pub pos: TokenPos,
+    pub message: String,
+}
+
+/// A problem found in a source file, with everything needed to show it next to the code it concerns
+#[derive(Debug, Clone)]
+pub struct Diagnostic
+{
+    pub severity: Severity,
+    /// A stable identifier for the kind of problem, such as `E0101`
+    pub code: Option<&'static str>,
+    pub message: String,
+    /// Where the problem is, or `None` when it has no place in the source, such as a missing `fn main`
+    pub primary: Option<Label>,
+    /// Other places that help explain the problem, such as an earlier declaration of the same name
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Whether diagnostics written to stderr should be coloured
+pub fn use_colour() -> bool
+{
+    std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+impl Diagnostic
+{
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self
+    {
+        Self { severity, code: None, message: message.into(), primary: None, labels: vec![], notes: vec![], help: vec![] }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self
+    {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self
+    {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_primary(mut self, pos: TokenPos, message: impl Into<String>) -> Self
+    {
+        self.primary = Some(Label { pos, message: message.into() });
+        self
+    }
+
+    pub fn with_label(mut self, pos: TokenPos, message: impl Into<String>) -> Self
+    {
+        self.labels.push(Label { pos, message: message.into() });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self
+    {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self
+    {
+        self.help.push(help.into());
+        self
+    }
+
+    /// Renders the diagnostic in the style of rustc: a header, the location, the source lines involved with their
+    /// spans underlined, and then any notes.
+    ///
+    /// ```text
+    /// error[E0103]: Expected `;` but found `}`
+    ///  --> tests/test.crs:4:1
+    ///   |
+    /// 4 | }
+    ///   | ^ expected `;`
+    /// ```
+    pub fn render(&self, source: &SourceFile, file: &str, colour: bool) -> String
+    {
+        let paint = |style: &str, content: &str| match colour
+        {
+            true => format!("{}{}{}", style, content, RESET),
+            false => content.to_owned(),
+        };
+
+        let header = match self.code
+        {
+            Some(code) => format!("{}[{}]", self.severity.name(), code),
+            None => self.severity.name().to_owned(),
+        };
+
+        let mut result = format!("{}{}\n", paint(self.severity.colour(), &header), paint(BOLD, &format!(": {}", self.message)));
+
+        // Every labelled line, with the primary label first among those on the same line
+        let mut labels: Vec<(Location, &Label, bool)> = self.primary.iter().map(|l| (locate(source, l.pos.begin), l, true))
+            .chain(self.labels.iter().map(|l| (locate(source, l.pos.begin), l, false)))
+            .collect();
+        labels.sort_by_key(|(location, _, primary)| (location.line, !primary, location.column));
+
+        let width = labels.iter().map(|(l, _, _)| l.line.to_string().len()).max().unwrap_or(1);
+        let gutter = |line: &str| paint(BLUE, &format!("{:>width$} |", line, width = width));
+
+        match labels.iter().find(|(_, _, primary)| *primary).or(labels.first())
+        {
+            Some((location, _, _)) => result += &format!("{}{} {}:{}:{}\n", " ".repeat(width), paint(BLUE, "-->"), file, location.line, location.column),
+            None => result += &format!("{}{} {}\n", " ".repeat(width), paint(BLUE, "-->"), file),
+        }
+
+        if !labels.is_empty()
+        {
+            result += &format!("{}\n", gutter(""));
+        }
+
+        let mut previous_line = None;
+        for (location, label, primary) in &labels
+        {
+            if previous_line != Some(location.line)
+            {
+                if previous_line.is_some_and(|p| location.line > p + 1)
+                {
+                    result += &format!("{}\n", paint(BLUE, "..."));
+                }
+
+                result += &format!("{} {}\n", paint(BLUE, &format!("{:>width$} |", location.line, width = width)), source.line_text(location.line));
+                previous_line = Some(location.line);
+            }
+
+            // Tabs are kept under the source so the marks line up however wide the terminal shows them
+            let indent: String = source.line_text(location.line)
+                .chars()
+                .take(location.column - 1)
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            // Spans running onto later lines are underlined to the end of their first line
+            let begin = location.line_start + location.column - 1;
+            let last = label.pos.end.max(begin).min(location.line_end.saturating_sub(1).max(begin));
+            let length = last - begin + 1;
+            let (mark, style) = match primary
+            {
+                true => ("^", self.severity.colour()),
+                false => ("-", BLUE),
+            };
+
+            let underline = paint(style, format!("{} {}", mark.repeat(length), label.message).trim_end());
+            result += &format!("{} {}{}\n", gutter(""), indent, underline);
+        }
+
+        if (!self.notes.is_empty() || !self.help.is_empty()) && !labels.is_empty()
+        {
+            result += &format!("{}\n", gutter(""));
+        }
+
+        for note in &self.notes
+        {
+            result += &format!("{} {} {}\n", " ".repeat(width), paint(BLUE, "="), paint(BOLD, "note:") + " " + note);
+        }
+
+        for help in &self.help
+        {
+            result += &format!("{} {} {}\n", " ".repeat(width), paint(BLUE, "="), paint(BOLD, "help:") + " " + help);
+        }
+
+        result
+    }
+}
+
+/// Where a character index falls in the text
+#[derive(Debug, Clone, Copy)]
+struct Location
+{
+    /// 1-based line number
+    line: usize,
+    /// 1-based column, counted in characters
+    column: usize,
+    /// The index of the first character of the line
+    line_start: usize,
+    /// The index just past the line's text, where its newline starts, or the end of the text
+    line_end: usize,
+}
+
+/// Finds the line and column of a character index. Indexes past the end of the text, as the end of file token has,
+/// point just after its last character.
+fn locate(source: &SourceFile, index: usize) -> Location
+{
+    let location = source.location(index);
+    let line = source.line_range(location.line);
+    Location { line: location.line, column: location.column, line_start: line.start, line_end: line.end }
+}
@@ -0,0 +1,336 @@
+use either::Either;
+
+use crate::{ast::*, lexing::token::{Token, TokenPos, TokenType}};
+
+use super::Formatter;
+
+impl<'a> Formatter<'a>
+{
+    pub fn expression(&mut self, expression: &Expression)
+    {
+        match expression
+        {
+            Expression::Lambda(lambda) => self.lambda(lambda),
+            Expression::Literal(token) | Expression::Identifier(token) | Expression::SelfExpr(token) => self.token(token),
+            Expression::ArrayLiteral(array) => self.list("[", &array.expressions, "]", &array.close_bracket, Formatter::expression),
+            Expression::Grouping(grouping) =>
+            {
+                self.write("(");
+                self.expression(&grouping.expression);
+                self.write(")");
+            },
+            Expression::BlockExpr(block) => self.block(block),
+            Expression::TypeValue(type_value) =>
+            {
+                self.type_name(&type_value.type_name);
+                self.write(".");
+                self.token(&type_value.name);
+            },
+            Expression::Construction(construction) => self.construction(construction),
+            Expression::EnumConstruction(construction) =>
+            {
+                self.type_name(&construction.type_name);
+                self.write("(");
+                self.expression(&construction.expression);
+                self.write(")");
+            },
+            Expression::Call(call) =>
+            {
+                self.expression(&call.expression);
+                self.list("(", &call.args, ")", &call.close_paren, Formatter::expression);
+            },
+            Expression::Access(access) =>
+            {
+                self.expression(&access.expression);
+                self.write(".");
+                self.token(&access.identifier);
+            },
+            Expression::Index(index) =>
+            {
+                self.expression(&index.expression);
+                self.write("[");
+                self.expression(&index.indexer);
+                self.write("]");
+            },
+            Expression::Unary(unary) =>
+            {
+                self.token(&unary.operator);
+                self.expression(&unary.expression);
+            },
+            Expression::Binary(binary) => self.binary(binary),
+            Expression::IfExpr(if_expr) => self.if_expr(if_expr),
+            Expression::MatchExpr(match_expr) => self.match_expr(match_expr),
+            Expression::Cast(cast) =>
+            {
+                self.expression(&cast.expression);
+                self.write(" as ");
+                self.type_name(&cast.type_name);
+            },
+            Expression::Interpolation(interpolation) =>
+            {
+                self.token(&interpolation.start);
+                for (expression, text) in &interpolation.parts
+                {
+                    self.expression(expression);
+                    self.token(text);
+                }
+            },
+            Expression::Yield(yield_expr) =>
+            {
+                self.write("yield ");
+                self.expression(&yield_expr.expression);
+            },
+            Expression::Jump(jump) =>
+            {
+                self.token(&jump.keyword);
+                if let Some(expression) = &jump.expression
+                {
+                    self.write(" ");
+                    self.expression(expression);
+                }
+            },
+        }
+    }
+
+    /// Writes a block with its statements and final expression indented between braces on lines of their own
+    pub fn block(&mut self, block: &BlockExpr)
+    {
+        let items: Vec<Either<&Statement, &Expression>> = block.statements.iter().map(Either::Left)
+            .chain(block.expression.iter().map(|e| Either::Right(e.as_ref())))
+            .collect();
+
+        self.open_brace(&block.open_brace);
+        self.lines(
+            &items,
+            |item| item.either(|s| s.span(), |e| e.span()),
+            |_, _| false,
+            |f, item| match item
+            {
+                Either::Left(statement) => f.statement(statement),
+                Either::Right(expression) => f.expression(expression),
+            },
+            block.close_brace.pos.begin
+        );
+        self.close_brace(&block.close_brace);
+    }
+
+    pub fn if_expr(&mut self, if_expr: &IfExpr)
+    {
+        self.write("if ");
+        self.let_condition(&if_expr.condition);
+        self.block(&if_expr.block);
+
+        if let Some(else_branch) = &if_expr.else_branch
+        {
+            self.printer.end_line();
+            self.write("else");
+            match &else_branch.body
+            {
+                Either::Left(if_expr) =>
+                {
+                    self.write(" ");
+                    self.if_expr(if_expr);
+                },
+                Either::Right(block) => self.block(block),
+            }
+        }
+    }
+
+    pub fn match_expr(&mut self, match_expr: &MatchExpr)
+    {
+        self.write("match ");
+        self.expression(&match_expr.expression);
+        self.open_brace(&match_expr.open_brace);
+        self.lines(
+            &match_expr.branches,
+            |branch| TokenPos { begin: branch.pattern.span().begin, end: branch.expression.span().end },
+            |_, _| false,
+            |f, branch| {
+                f.pattern(&branch.pattern);
+                if let Some((_, guard)) = &branch.guard
+                {
+                    f.write(" if ");
+                    f.expression(guard);
+                }
+                f.write(" => ");
+                f.expression(&branch.expression);
+                f.write(",");
+            },
+            match_expr.close_brace.pos.begin
+        );
+        self.close_brace(&match_expr.close_brace);
+    }
+
+    pub fn let_condition(&mut self, condition: &LetCondition)
+    {
+        match condition
+        {
+            LetCondition::Expression(expression) => self.expression(expression),
+            LetCondition::Pattern { let_tok: _, pattern, equal: _, expression, and: _, other_cond } =>
+            {
+                self.write("let ");
+                self.pattern(pattern);
+                self.write(" = ");
+                self.expression(expression);
+                if let Some(other_cond) = other_cond
+                {
+                    self.write(" && ");
+                    self.let_condition(other_cond);
+                }
+            },
+        }
+    }
+
+    fn lambda(&mut self, lambda: &LambdaExpr)
+    {
+        match &lambda.params
+        {
+            LambdaParams::Simple(name) => self.token(name),
+            // Without the space, the pipes of an empty parameter list would lex as `||`
+            LambdaParams::Complex { open_pipe: _, parameters, close_pipe: _, arrow: _, return_type } if parameters.is_empty() =>
+            {
+                self.write("| |");
+                self.return_type(return_type);
+            },
+            LambdaParams::Complex { open_pipe: _, parameters, close_pipe, arrow: _, return_type } =>
+            {
+                self.list("|", parameters, "|", close_pipe, |f, param| {
+                    f.token(&param.name);
+                    if let Some(type_name) = &param.type_name
+                    {
+                        f.write(": ");
+                        f.type_name(type_name);
+                    }
+                });
+                self.return_type(return_type);
+            },
+        }
+
+        self.write(" => ");
+        self.expression(&lambda.expression);
+    }
+
+    fn return_type(&mut self, return_type: &Option<TypeName>)
+    {
+        if let Some(return_type) = return_type
+        {
+            self.write(" -> ");
+            self.type_name(return_type);
+        }
+    }
+
+    /// Writes `Type { field: value }` on one line if it fits, or with one field per line between braces otherwise
+    fn construction(&mut self, construction: &ConstructionExpr)
+    {
+        self.type_name(&construction.type_name);
+        let write_arg = |f: &mut Self, arg: &ConstructionArg| {
+            f.token(&arg.name);
+            f.write(": ");
+            f.expression(&arg.value);
+        };
+
+        if construction.args.is_empty()
+        {
+            self.write(" {}");
+            return;
+        }
+
+        let fits = self.write_if_fits(|f| {
+            f.write(" { ");
+            for (i, arg) in construction.args.iter().enumerate()
+            {
+                if i > 0
+                {
+                    f.write(", ");
+                }
+
+                write_arg(f, arg);
+            }
+            f.write(" }");
+        });
+
+        if !fits
+        {
+            self.open_brace(&construction.open_brace);
+            for arg in &construction.args
+            {
+                write_arg(self, arg);
+                self.write(",");
+                self.printer.end_line();
+            }
+            self.close_brace(&construction.close_brace);
+        }
+    }
+
+    /// Writes a binary expression. Chains of `&&` and `||` too long for the line are split after each operator, with
+    /// the following lines indented when the chain does not start its line.
+    fn binary(&mut self, binary: &BinaryExpr)
+    {
+        let logical = |e: &BinaryExpr| matches!(e.operator.token_type, TokenType::AndAnd | TokenType::PipePipe);
+        let write_simple = |f: &mut Self, e: &BinaryExpr| {
+            f.expression(&e.left);
+            f.write(" ");
+            f.token(&e.operator);
+            f.write(" ");
+            f.expression(&e.right);
+        };
+
+        if !logical(binary)
+        {
+            return write_simple(self, binary);
+        }
+
+        if self.write_if_fits(|f| write_simple(f, binary))
+        {
+            return;
+        }
+
+        // The operands of the chain in order, each with the operator that follows it
+        fn operands<'e>(expression: &'e Expression, result: &mut Vec<(&'e Expression, Option<&'e Token>)>)
+        {
+            match expression
+            {
+                Expression::Binary(binary) if matches!(binary.operator.token_type, TokenType::AndAnd | TokenType::PipePipe) =>
+                {
+                    operands(&binary.left, result);
+                    if let Some(last) = result.last_mut()
+                    {
+                        last.1 = Some(&binary.operator);
+                    }
+                    operands(&binary.right, result);
+                },
+                _ => result.push((expression, None)),
+            }
+        }
+
+        let mut chain = vec![];
+        operands(&binary.left, &mut chain);
+        if let Some(last) = chain.last_mut()
+        {
+            last.1 = Some(&binary.operator);
+        }
+        operands(&binary.right, &mut chain);
+
+        let hanging = !self.printer.at_line_start();
+        if hanging
+        {
+            self.printer.indent();
+        }
+
+        for (expression, operator) in chain
+        {
+            self.expression(expression);
+            if let Some(operator) = operator
+            {
+                self.write(" ");
+                self.token(operator);
+                self.printer.new_line();
+            }
+        }
+
+        if hanging
+        {
+            self.printer.unindent();
+        }
+    }
+}
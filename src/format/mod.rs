@@ -0,0 +1,434 @@
+pub mod expr_format;
+pub mod stmt_format;
+pub mod pattern_format;
+
+use either::Either;
+
+use crate::{ast::*, diagnostic::Diagnostic, lexing::{self, token::{Comment, CommentKind, Token, TokenPos}}, parsing, source::SourceFile, utils::PrettyPrinter};
+
+#[derive(Debug, Clone)]
+pub struct FormatOptions
+{
+    /// The text written once per level of indentation
+    pub indent: String,
+    /// Lists and chains of `&&` and `||` longer than this are split over several lines
+    pub max_width: usize,
+}
+
+impl Default for FormatOptions
+{
+    fn default() -> Self
+    {
+        Self { indent: "    ".to_owned(), max_width: 100 }
+    }
+}
+
+/// Formats a whole source file, or returns the diagnostics of the errors that stopped it from being parsed
+pub fn format_text(text: &str, options: &FormatOptions) -> Result<String, Vec<Diagnostic>>
+{
+    let source = SourceFile::new(text);
+    let result = lexing::lex_text(text);
+    if !result.errors.is_empty()
+    {
+        return Err(result.errors.iter().map(Diagnostic::from).collect());
+    }
+
+    let eof = result.tokens.last().cloned().expect("lexing always produces an end of file token");
+    let program = match parsing::parse(result.tokens, &result.comments)
+    {
+        Ok(program) => program.unwrap_or(Program { docs: vec![], declarations: vec![], eof }),
+        Err(errors) => return Err(errors.iter().map(Diagnostic::from).collect()),
+    };
+
+    Ok(format_program(&program, &source, &result.comments, options))
+}
+
+/// Prints a program in the canonical style, putting back the comments of the text it was parsed from
+pub fn format_program(program: &Program, source: &SourceFile, comments: &[Comment], options: &FormatOptions) -> String
+{
+    let mut formatter = Formatter::new(source, comments, options);
+    formatter.lines(&program.declarations, Declaration::span, separate_declarations, Formatter::declaration, source.char_count());
+    formatter.printer.end_line();
+    formatter.printer.to_string()
+}
+
+/// Whether a blank line always goes between two declarations, rather than only where the source had one
+fn separate_declarations(previous: &Declaration, next: &Declaration) -> bool
+{
+    let single_line = |declaration: &Declaration| matches!(declaration, Declaration::Use(_, _) | Declaration::Let(_, _) | Declaration::Const(_, _) | Declaration::Type(_, _)
+        | Declaration::Mod(_, ModDecl { body: Either::Right(_), .. }));
+    !(single_line(previous) && single_line(next))
+}
+
+pub struct Formatter<'a>
+{
+    source: &'a SourceFile,
+    comments: &'a [Comment],
+    /// The index of the first comment not yet written
+    next_comment: usize,
+    options: &'a FormatOptions,
+    printer: PrettyPrinter,
+    /// The index in the text of the end of the last thing written, used to find the blank lines and comments after it
+    last_end: usize,
+    /// Whether everything is being written on one line, to measure it
+    flat: bool,
+}
+
+impl<'a> Formatter<'a>
+{
+    pub fn new(source: &'a SourceFile, comments: &'a [Comment], options: &'a FormatOptions) -> Self
+    {
+        Self { source, comments, next_comment: 0, options, printer: PrettyPrinter::plain(&options.indent), last_end: 0, flat: false }
+    }
+
+    fn write(&mut self, text: &str)
+    {
+        self.printer.write(text);
+    }
+
+    /// Writes a token as it appears in the source, so literals keep their spelling, after the comments in front of it
+    fn token(&mut self, token: &Token)
+    {
+        let continued = self.inline_comments(token.pos.begin);
+        if continued
+        {
+            self.printer.indent();
+        }
+
+        self.printer.write(self.source.slice(token.pos));
+        self.last_end = self.last_end.max(token.pos.end);
+        if continued
+        {
+            self.printer.unindent();
+        }
+    }
+
+    /// Writes the comments that start before `index` where they are, in the middle of a line. A line comment ends the
+    /// line, so returns true when what follows continues on the next one.
+    fn inline_comments(&mut self, index: usize) -> bool
+    {
+        let mut continued = false;
+        while let Some(comment) = self.comments.get(self.next_comment).filter(|c| c.pos.begin < index)
+        {
+            self.write(&comment.text);
+            continued = comment.kind != CommentKind::Block;
+            if continued
+            {
+                self.printer.new_line();
+            }
+            else
+            {
+                self.write(" ");
+            }
+            self.last_end = comment.pos.end;
+            self.next_comment += 1;
+        }
+        continued
+    }
+
+    /// Writes something on one line and returns the formatter that wrote it without touching the output, or `None`
+    /// if it cannot fit on one line, as anything holding a block or a line comment cannot
+    fn flat<F>(&self, write: F) -> Option<Formatter<'a>>
+        where F : FnOnce(&mut Self)
+    {
+        let mut formatter = Formatter
+        {
+            source: self.source,
+            comments: self.comments,
+            next_comment: self.next_comment,
+            options: self.options,
+            printer: PrettyPrinter::plain(&self.options.indent),
+            last_end: self.last_end,
+            flat: true,
+        };
+
+        write(&mut formatter);
+        (!formatter.printer.to_string().contains('\n')).then_some(formatter)
+    }
+
+    /// Writes something on the current line if it fits there and returns true, or returns false without writing
+    fn write_if_fits<F>(&mut self, write: F) -> bool
+        where F : FnOnce(&mut Self)
+    {
+        if self.flat
+        {
+            write(self);
+            return true;
+        }
+
+        let Some(formatter) = self.flat(write) else { return false };
+        let text = formatter.printer.to_string();
+        if self.printer.column() + text.chars().count() > self.options.max_width
+        {
+            return false;
+        }
+
+        self.write(&text);
+        self.next_comment = formatter.next_comment;
+        self.last_end = formatter.last_end;
+        true
+    }
+
+    /// Writes items on lines of their own, keeping the comments between them. Blank lines are kept where the source
+    /// had them, at most one at a time, and always put between items `separate` picks out. `close` is where the
+    /// list ends, so the comments before it are written too.
+    fn lines<T, S, P, W>(&mut self, items: &[T], span: S, separate: P, write: W, close: usize)
+        where S : Fn(&T) -> TokenPos,
+              P : Fn(&T, &T) -> bool,
+              W : Fn(&mut Self, &T)
+    {
+        let mut first = true;
+        for (i, item) in items.iter().enumerate()
+        {
+            let item_span = span(item);
+            if i > 0 && separate(&items[i - 1], item)
+            {
+                self.printer.blank_line();
+            }
+
+            self.comments_before(item_span.begin, &mut first);
+            if !first && self.blank_between(self.last_end, item_span.begin)
+            {
+                self.printer.blank_line();
+            }
+
+            write(self, item);
+            first = false;
+            self.last_end = self.last_end.max(item_span.end);
+            self.trailing_comments(close);
+            self.printer.end_line();
+        }
+
+        self.comments_before(close, &mut first);
+    }
+
+    /// Writes the comments that start before `index` on lines of their own
+    fn comments_before(&mut self, index: usize, first: &mut bool)
+    {
+        while let Some(comment) = self.comments.get(self.next_comment).filter(|c| c.pos.begin < index)
+        {
+            if !*first && self.blank_between(self.last_end, comment.pos.begin)
+            {
+                self.printer.blank_line();
+            }
+
+            self.printer.end_line();
+            self.write(&comment.text);
+            self.printer.new_line();
+            self.last_end = comment.pos.end;
+            self.next_comment += 1;
+            *first = false;
+        }
+    }
+
+    /// Writes the comments before `index` that follow what was just written on the same line of the source
+    fn trailing_comments(&mut self, index: usize)
+    {
+        while let Some(comment) = self.comments.get(self.next_comment)
+            .filter(|c| c.pos.begin >= self.last_end && c.pos.begin < index && self.source.lines_between(self.last_end, c.pos.begin) == 0)
+        {
+            self.write(" ");
+            self.write(&comment.text);
+            self.last_end = comment.pos.end;
+            self.next_comment += 1;
+        }
+    }
+
+    /// Whether the source has an empty line between two indexes
+    fn blank_between(&self, begin: usize, end: usize) -> bool
+    {
+        begin < end && self.source.lines_between(begin, end) > 1
+    }
+
+    /// Writes items separated by `, `, split one per line with trailing commas when they do not fit on the line or
+    /// have comments between them. `end` is the token closing the list.
+    fn list<T, W>(&mut self, open: &str, items: &[T], close: &str, end: &Token, write: W)
+        where T : Spanned,
+              W : Fn(&mut Self, &T)
+    {
+        let has_comments = self.comments.get(self.next_comment).is_some_and(|c| c.pos.begin < end.pos.begin);
+        if has_comments && self.flat
+        {
+            // Comments end their line, so a list holding one can never be measured as fitting on one
+            self.printer.new_line();
+        }
+
+        let fits = !has_comments && self.write_if_fits(|f| {
+            f.write(open);
+            for (i, item) in items.iter().enumerate()
+            {
+                if i > 0
+                {
+                    f.write(", ");
+                }
+
+                write(f, item);
+            }
+            f.write(close);
+        });
+
+        if fits
+        {
+            return;
+        }
+
+        self.write(open.trim_end());
+        self.printer.new_line();
+        self.printer.indent();
+        self.lines(items, Spanned::span, |_, _| false, |f, item| {
+            write(f, item);
+            f.write(",");
+        }, end.pos.begin);
+        self.printer.unindent();
+        self.write(close.trim_start());
+        self.last_end = end.pos.end;
+    }
+
+    /// Opens a braced body on a line of its own, leaving a comment that ended the line before it there
+    fn open_brace(&mut self, open_brace: &Token)
+    {
+        self.trailing_comments(open_brace.pos.begin);
+        self.printer.end_line();
+        self.write("{");
+        self.printer.new_line();
+        self.printer.indent();
+        self.last_end = open_brace.pos.end;
+    }
+
+    fn close_brace(&mut self, close_brace: &Token)
+    {
+        self.printer.unindent();
+        self.printer.end_line();
+        self.write("}");
+        self.last_end = close_brace.pos.end;
+    }
+
+    pub fn type_name(&mut self, type_name: &TypeName)
+    {
+        match type_name
+        {
+            TypeName::Identifier { name, args } =>
+            {
+                self.token(name);
+                self.generic_args(args);
+            },
+            TypeName::Array { open_bracket: _, close_bracket: _, type_name } =>
+            {
+                self.write("[]");
+                self.type_name(type_name);
+            },
+            TypeName::Function { fn_tok: _, open_paren: _, parameter_types, close_paren, arrow: _, return_type } =>
+            {
+                self.list("fn(", parameter_types, ")", close_paren, Formatter::type_name);
+                self.write(" -> ");
+                self.type_name(return_type);
+            },
+            TypeName::Access { inner, dot: _, name, args } =>
+            {
+                self.type_name(inner);
+                self.write(".");
+                self.token(name);
+                self.generic_args(args);
+            },
+        }
+    }
+
+    fn generic_args(&mut self, args: &Option<GenericArgs>)
+    {
+        if let Some(args) = args
+        {
+            self.list("[", &args.args, "]", &args.close_bracket, Formatter::type_name);
+        }
+    }
+
+    fn generic_params(&mut self, params: &Option<GenericParams>)
+    {
+        if let Some(params) = params
+        {
+            self.list("[", &params.params, "]", &params.close_bracket, Formatter::token);
+        }
+    }
+}
+
+/// Something whose place in the source is known, so the comments around it can be found
+trait Spanned
+{
+    fn span(&self) -> TokenPos;
+}
+
+impl Spanned for Token
+{
+    fn span(&self) -> TokenPos
+    {
+        self.pos
+    }
+}
+
+impl Spanned for Expression
+{
+    fn span(&self) -> TokenPos
+    {
+        Expression::span(self)
+    }
+}
+
+impl Spanned for TypeName
+{
+    fn span(&self) -> TokenPos
+    {
+        TypeName::span(self)
+    }
+}
+
+impl Spanned for Pattern
+{
+    fn span(&self) -> TokenPos
+    {
+        Pattern::span(self)
+    }
+}
+
+impl Spanned for PatternField
+{
+    fn span(&self) -> TokenPos
+    {
+        let first = self.mut_tok.as_ref().unwrap_or(&self.id);
+        TokenPos { begin: first.pos.begin, end: self.inner.as_ref().map_or(self.id.pos.end, |p| p.span().end) }
+    }
+}
+
+impl Spanned for LambdaParam
+{
+    fn span(&self) -> TokenPos
+    {
+        TokenPos { begin: self.name.pos.begin, end: self.type_name.as_ref().map_or(self.name.pos.end, |t| t.span().end) }
+    }
+}
+
+impl Spanned for FnParam
+{
+    fn span(&self) -> TokenPos
+    {
+        match self
+        {
+            FnParam::Normal { mut_tok, id, colon: _, type_name, default_value } => TokenPos
+            {
+                begin: mut_tok.as_ref().unwrap_or(id).pos.begin,
+                end: default_value.as_ref().map_or(type_name.span().end, |(_, e)| e.span().end),
+            },
+            FnParam::SelfParam { mut_tok, self_tok } => TokenPos { begin: mut_tok.as_ref().unwrap_or(self_tok).pos.begin, end: self_tok.pos.end },
+        }
+    }
+}
+
+impl Spanned for EnumStructMember
+{
+    fn span(&self) -> TokenPos
+    {
+        TokenPos
+        {
+            begin: self.mut_tok.as_ref().unwrap_or(&self.id).pos.begin,
+            end: self.initializer.as_ref().map_or(self.type_name.span().end, |(_, e)| e.span().end),
+        }
+    }
+}
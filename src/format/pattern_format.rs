@@ -0,0 +1,100 @@
+use crate::ast::*;
+
+use super::Formatter;
+
+impl<'a> Formatter<'a>
+{
+    pub fn pattern(&mut self, pattern: &Pattern)
+    {
+        match pattern
+        {
+            Pattern::Literal(literal) => self.pattern_literal(literal),
+            Pattern::Wildcard(token) => self.token(token),
+            Pattern::Range { start, dots: _, end } =>
+            {
+                self.pattern_literal(start);
+                self.write("..=");
+                self.pattern_literal(end);
+            },
+            Pattern::Grouping { open_paren: _, inner, close_paren: _ } =>
+            {
+                self.write("(");
+                self.pattern(inner);
+                self.write(")");
+            },
+            Pattern::Identifier { mut_tok, id } =>
+            {
+                if mut_tok.is_some()
+                {
+                    self.write("mut ");
+                }
+                self.token(id);
+            },
+            Pattern::Binding { mut_tok, id, at: _, inner } =>
+            {
+                if mut_tok.is_some()
+                {
+                    self.write("mut ");
+                }
+                self.token(id);
+                self.write(" @ ");
+                self.pattern(inner);
+            },
+            Pattern::Or(patterns) => for (i, pattern) in patterns.iter().enumerate()
+            {
+                if i > 0
+                {
+                    self.write(" | ");
+                }
+                self.pattern(pattern);
+            },
+            Pattern::TypeValue { type_name, dot: _, id } =>
+            {
+                self.type_name(type_name);
+                self.write(".");
+                self.token(id);
+            },
+            Pattern::EnumConstruct { type_name, open_paren: _, inner, close_paren: _ } =>
+            {
+                self.type_name(type_name);
+                self.write("(");
+                self.pattern(inner);
+                self.write(")");
+            },
+            Pattern::StructConstruct { type_name, open_brace: _, patterns, close_brace } =>
+            {
+                self.type_name(type_name);
+                match patterns.is_empty()
+                {
+                    true => self.write(" {}"),
+                    false => self.list(" { ", patterns, " }", close_brace, Formatter::pattern_field),
+                }
+            },
+            Pattern::ArrayConstruct { open_bracket: _, patterns, close_bracket } => self.list("[", patterns, "]", close_bracket, Formatter::pattern),
+        }
+    }
+
+    fn pattern_literal(&mut self, literal: &PatternLiteral)
+    {
+        if literal.minus.is_some()
+        {
+            self.write("-");
+        }
+        self.token(&literal.token);
+    }
+
+    fn pattern_field(&mut self, field: &PatternField)
+    {
+        if field.mut_tok.is_some()
+        {
+            self.write("mut ");
+        }
+        self.token(&field.id);
+
+        if let Some(inner) = &field.inner
+        {
+            self.write(": ");
+            self.pattern(inner);
+        }
+    }
+}
@@ -0,0 +1,436 @@
+use either::Either;
+
+use crate::{ast::*, lexing::token::{Token, TokenPos}};
+
+use super::Formatter;
+
+impl<'a> Formatter<'a>
+{
+    pub fn declaration(&mut self, declaration: &Declaration)
+    {
+        match declaration
+        {
+            Declaration::Fn(pub_tok, decl) =>
+            {
+                self.visibility(pub_tok);
+                self.fn_decl(decl);
+            },
+            Declaration::Struct(pub_tok, decl) =>
+            {
+                self.visibility(pub_tok);
+                self.struct_decl(decl);
+            },
+            Declaration::Interface(pub_tok, decl) =>
+            {
+                self.visibility(pub_tok);
+                self.interface_decl(decl);
+            },
+            Declaration::Enum(pub_tok, decl) =>
+            {
+                self.visibility(pub_tok);
+                self.enum_decl(decl);
+            },
+            Declaration::Type(pub_tok, decl) =>
+            {
+                self.visibility(pub_tok);
+                self.type_decl(decl);
+            },
+            Declaration::Let(pub_tok, stmt) =>
+            {
+                self.visibility(pub_tok);
+                self.let_stmt(stmt);
+            },
+            Declaration::Const(pub_tok, decl) =>
+            {
+                self.visibility(pub_tok);
+                self.const_decl(decl);
+            },
+            Declaration::Use(pub_tok, stmt) =>
+            {
+                self.visibility(pub_tok);
+                self.use_stmt(stmt);
+            },
+            Declaration::Mod(pub_tok, decl) =>
+            {
+                self.visibility(pub_tok);
+                self.mod_decl(decl);
+            },
+            Declaration::Impl(stmt) => self.impl_stmt(stmt),
+        }
+    }
+
+    pub fn statement(&mut self, statement: &Statement)
+    {
+        match statement
+        {
+            Statement::While(stmt) =>
+            {
+                self.write("while ");
+                self.let_condition(&stmt.condition);
+                self.block(&stmt.body);
+            },
+            Statement::For(stmt) =>
+            {
+                self.write("for ");
+                self.pattern(&stmt.pattern);
+                self.write(" in ");
+                self.expression(&stmt.expression);
+                self.block(&stmt.body);
+            },
+            Statement::Return(stmt) =>
+            {
+                self.write("return");
+                if let Some(expression) = &stmt.expression
+                {
+                    self.write(" ");
+                    self.expression(expression);
+                }
+                self.write(";");
+            },
+            Statement::Continue(_) => self.write("continue;"),
+            Statement::Break(_) => self.write("break;"),
+            Statement::TypeDecl(decl) => self.type_decl(decl),
+            Statement::EnumDecl(decl) => self.enum_decl(decl),
+            Statement::InterfaceDecl(decl) => self.interface_decl(decl),
+            Statement::StructDecl(decl) => self.struct_decl(decl),
+            Statement::FnDecl(decl) => self.fn_decl(decl),
+            Statement::Let(stmt) => self.let_stmt(stmt),
+            Statement::Const(decl) => self.const_decl(decl),
+            Statement::Assign(stmt) =>
+            {
+                self.expression(&stmt.value);
+                self.write(" ");
+                self.token(&stmt.equal);
+                self.write(" ");
+                self.expression(&stmt.expression);
+                self.write(";");
+            },
+            Statement::If(if_expr) => self.if_expr(if_expr),
+            Statement::Match(match_expr) => self.match_expr(match_expr),
+            Statement::Block(block) => self.block(block),
+            Statement::Expression(stmt) =>
+            {
+                self.expression(&stmt.expression);
+                self.write(";");
+            },
+            Statement::Use(stmt) => self.use_stmt(stmt),
+        }
+    }
+
+    fn visibility(&mut self, pub_tok: &Option<Token>)
+    {
+        if pub_tok.is_some()
+        {
+            self.write("pub ");
+        }
+    }
+
+    fn fn_decl(&mut self, decl: &FnDecl)
+    {
+        if decl.const_tok.is_some()
+        {
+            self.write("const ");
+        }
+
+        self.write("fn ");
+        self.token(&decl.id);
+        self.generic_params(&decl.generic_params);
+        self.list("(", &decl.params, ")", &decl.close_paren, |f, param| match param
+        {
+            FnParam::Normal { mut_tok, id, colon: _, type_name, default_value } =>
+            {
+                f.mutability(mut_tok);
+                f.token(id);
+                f.write(": ");
+                f.type_name(type_name);
+                if let Some((_, expression)) = default_value
+                {
+                    f.write(" = ");
+                    f.expression(expression);
+                }
+            },
+            FnParam::SelfParam { mut_tok, self_tok } =>
+            {
+                f.mutability(mut_tok);
+                f.token(self_tok);
+            },
+        });
+        self.write(" -> ");
+        self.type_name(&decl.return_type);
+        self.where_clause(&decl.where_clause);
+
+        match &decl.body
+        {
+            Either::Left(block) => self.block(block),
+            Either::Right(_) => self.write(";"),
+        }
+    }
+
+    fn struct_decl(&mut self, decl: &StructDecl)
+    {
+        self.write("struct ");
+        self.token(&decl.id);
+        self.generic_params(&decl.generic_params);
+        self.where_clause(&decl.where_clause);
+
+        self.open_brace(&decl.open_brace);
+        self.lines(
+            &decl.members,
+            |member| TokenPos
+            {
+                begin: member.pub_tok.as_ref().or(member.mut_tok.as_ref()).unwrap_or(&member.id).pos.begin,
+                end: member.initializer.as_ref().map_or(member.type_name.span().end, |(_, e)| e.span().end),
+            },
+            |_, _| false,
+            |f, member| {
+                f.visibility(&member.pub_tok);
+                f.mutability(&member.mut_tok);
+                f.token(&member.id);
+                f.write(": ");
+                f.type_name(&member.type_name);
+                if let Some((_, expression)) = &member.initializer
+                {
+                    f.write(" = ");
+                    f.expression(expression);
+                }
+                f.write(",");
+            },
+            decl.close_brace.pos.begin
+        );
+        self.close_brace(&decl.close_brace);
+    }
+
+    fn interface_decl(&mut self, decl: &InterfaceDecl)
+    {
+        self.write("interface ");
+        self.token(&decl.id);
+        self.generic_params(&decl.generic_params);
+        self.where_clause(&decl.where_clause);
+
+        self.open_brace(&decl.open_brace);
+        self.lines(&decl.members, Statement::span, |_, _| false, Formatter::statement, decl.close_brace.pos.begin);
+        self.close_brace(&decl.close_brace);
+    }
+
+    fn enum_decl(&mut self, decl: &EnumDecl)
+    {
+        self.write("enum ");
+        self.token(&decl.id);
+        self.generic_params(&decl.generic_params);
+        if let Some((_, type_name)) = &decl.backing_type
+        {
+            self.write(": ");
+            self.type_name(type_name);
+        }
+        self.where_clause(&decl.where_clause);
+
+        self.open_brace(&decl.open_brace);
+        self.lines(
+            &decl.members,
+            |member| match member
+            {
+                EnumMember::Basic { docs: _, id, discriminant } => TokenPos { begin: id.pos.begin, end: discriminant.as_ref().map_or(id.pos.end, |(_, e)| e.span().end) },
+                EnumMember::Single { docs: _, id, open_paren: _, type_name: _, close_paren } => TokenPos { begin: id.pos.begin, end: close_paren.pos.end },
+                EnumMember::Struct { docs: _, id, open_brace: _, members: _, close_brace } => TokenPos { begin: id.pos.begin, end: close_brace.pos.end },
+            },
+            |_, _| false,
+            |f, member| {
+                match member
+                {
+                    EnumMember::Basic { docs: _, id, discriminant } =>
+                    {
+                        f.token(id);
+                        if let Some((_, expression)) = discriminant
+                        {
+                            f.write(" = ");
+                            f.expression(expression);
+                        }
+                    },
+                    EnumMember::Single { docs: _, id, open_paren: _, type_name, close_paren: _ } =>
+                    {
+                        f.token(id);
+                        f.write("(");
+                        f.type_name(type_name);
+                        f.write(")");
+                    },
+                    EnumMember::Struct { docs: _, id, open_brace: _, members, close_brace } =>
+                    {
+                        f.token(id);
+                        f.write(" ");
+                        f.list("{ ", members, " }", close_brace, |f, member| {
+                            f.mutability(&member.mut_tok);
+                            f.token(&member.id);
+                            f.write(": ");
+                            f.type_name(&member.type_name);
+                            if let Some((_, expression)) = &member.initializer
+                            {
+                                f.write(" = ");
+                                f.expression(expression);
+                            }
+                        });
+                    },
+                }
+                f.write(",");
+            },
+            decl.close_brace.pos.begin
+        );
+        self.close_brace(&decl.close_brace);
+    }
+
+    fn type_decl(&mut self, decl: &TypeDecl)
+    {
+        self.write("type ");
+        self.token(&decl.id);
+        self.generic_params(&decl.generic_params);
+        self.write(" = ");
+        self.type_name(&decl.type_name);
+        self.write(";");
+    }
+
+    fn const_decl(&mut self, decl: &ConstDecl)
+    {
+        self.write("const ");
+        self.token(&decl.id);
+        self.write(": ");
+        self.type_name(&decl.type_name);
+        self.write(" = ");
+        self.expression(&decl.expression);
+        self.write(";");
+    }
+
+    fn let_stmt(&mut self, stmt: &LetStmt)
+    {
+        self.write("let ");
+        match &stmt.binding
+        {
+            LetBinding::Variable { mut_tok, id } =>
+            {
+                self.mutability(mut_tok);
+                self.token(id);
+            },
+            LetBinding::Pattern(pattern) => self.pattern(pattern),
+        }
+
+        if let Some((_, type_name)) = &stmt.type_name
+        {
+            self.write(": ");
+            self.type_name(type_name);
+        }
+
+        self.write(" ");
+        self.token(&stmt.equal);
+        self.write(" ");
+        self.expression(&stmt.expression);
+
+        if let Some((_, block)) = &stmt.else_clause
+        {
+            self.write(" else");
+            self.block(block);
+        }
+
+        self.write(";");
+    }
+
+    fn use_stmt(&mut self, stmt: &UseStmt)
+    {
+        self.write("use ");
+        for (i, id) in stmt.ids.iter().enumerate()
+        {
+            if i > 0
+            {
+                self.write(".");
+            }
+
+            self.token(id);
+        }
+
+        if stmt.star.is_some()
+        {
+            self.write(".*");
+        }
+
+        self.write(";");
+    }
+
+    fn mod_decl(&mut self, decl: &ModDecl)
+    {
+        self.write("mod ");
+        self.token(&decl.id);
+
+        match &decl.body
+        {
+            Either::Left(body) =>
+            {
+                self.open_brace(&body.open_brace);
+                self.lines(&body.declarations, Declaration::span, super::separate_declarations, Formatter::declaration, body.close_brace.pos.begin);
+                self.close_brace(&body.close_brace);
+            },
+            Either::Right(_) => self.write(";"),
+        }
+    }
+
+    fn impl_stmt(&mut self, stmt: &ImplStmt)
+    {
+        self.write("impl");
+        self.generic_params(&stmt.generic_params);
+        self.write(" ");
+        self.type_name(&stmt.type_name);
+        if let Some((_, type_name)) = &stmt.for_clause
+        {
+            self.write(" for ");
+            self.type_name(type_name);
+        }
+        self.where_clause(&stmt.where_clause);
+
+        self.open_brace(&stmt.open_brace);
+        self.lines(
+            &stmt.members,
+            |(pub_tok, member)| TokenPos { begin: pub_tok.as_ref().map_or(member.span().begin, |t| t.pos.begin), end: member.span().end },
+            |(_, previous), (_, next)| matches!(previous, Statement::FnDecl(_)) || matches!(next, Statement::FnDecl(_)),
+            |f, (pub_tok, member)| {
+                f.visibility(pub_tok);
+                f.statement(member);
+            },
+            stmt.close_brace.pos.begin
+        );
+        self.close_brace(&stmt.close_brace);
+    }
+
+    /// Writes a `where` clause on a line of its own, indented under the declaration it belongs to
+    fn where_clause(&mut self, where_clause: &Option<WhereClause>)
+    {
+        let Some(where_clause) = where_clause else { return };
+
+        self.printer.end_line();
+        self.printer.indent();
+        self.write("where ");
+        for (i, sub_clause) in where_clause.sub_clauses.iter().enumerate()
+        {
+            if i > 0
+            {
+                self.write(", ");
+            }
+
+            self.token(&sub_clause.id);
+            self.write(": ");
+            for (i, type_name) in sub_clause.types.iter().enumerate()
+            {
+                if i > 0
+                {
+                    self.write(" + ");
+                }
+
+                self.type_name(type_name);
+            }
+        }
+        self.printer.unindent();
+    }
+
+    fn mutability(&mut self, mut_tok: &Option<Token>)
+    {
+        if mut_tok.is_some()
+        {
+            self.write("mut ");
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::{cell::RefCell, collections::HashMap, io::{BufRead, Write}, rc::Rc};
+
+use itertools::Itertools;
+
+use crate::lexing::token::Token;
+
+use super::{env::Environment, value::{NativeFn, NativeFnPtr, TypeDef, TypeKind, Value}, EvalResult, Interpreter, RuntimeError};
+
+pub const BUILTIN_TYPES: &[(&str, &str)] = &[
+    ("Int", "Int"),
+    ("Float", "Float"),
+    ("Bool", "Bool"),
+    ("bool", "Bool"),
+    ("String", "String"),
+    ("Char", "Char"),
+    ("Void", "Void"),
+    ("Iter", "Iter"),
+    ("i8", "Int"),
+    ("i16", "Int"),
+    ("i32", "Int"),
+    ("i64", "Int"),
+    ("u8", "Int"),
+    ("u16", "Int"),
+    ("u32", "Int"),
+    ("u64", "Int"),
+    ("f32", "Float"),
+    ("f64", "Float"),
+];
+
+/// The functions and types that `define_builtins` puts in the global scope
+pub const BUILTIN_NAMES: &[&str] = &["print", "println", "range", "Console"];
+
+/// Maps a builtin type name (including the sized numeric aliases) onto the runtime type that represents it
+pub fn builtin_type_kind(name: &str) -> Option<&'static str>
+{
+    BUILTIN_TYPES.iter().find(|(n, _)| *n == name).map(|(_, k)| *k)
+}
+
+fn native(name: &'static str, arity: Option<usize>, func: NativeFnPtr) -> Value
+{
+    Value::Native(Rc::new(NativeFn { name, arity, func }))
+}
+
+fn bound_native(receiver: &Value, name: &'static str, arity: Option<usize>, func: NativeFnPtr) -> Value
+{
+    Value::BoundNative(Box::new(receiver.clone()), Rc::new(NativeFn { name, arity, func }))
+}
+
+pub fn define_builtins(env: &Environment)
+{
+    env.define("print", native("print", None, builtin_print));
+    env.define("println", native("println", None, builtin_println));
+    env.define("range", native("range", Some(2), builtin_range));
+
+    let console = TypeDef {
+        name: "Console".into(),
+        kind: TypeKind::Native,
+        env: env.clone(),
+        methods: RefCell::new(HashMap::new()),
+        statics: RefCell::new(HashMap::new()),
+        discriminants: RefCell::new(HashMap::new()),
+    };
+
+    console.statics.borrow_mut().insert("ReadLine".into(), native("ReadLine", Some(0), builtin_read_line));
+    console.statics.borrow_mut().insert("ReadKey".into(), native("ReadKey", Some(0), builtin_read_key));
+    env.define("Console", Value::Type(Rc::new(console)));
+}
+
+/// Methods that builtin values expose, such as `array.length()`
+pub fn get_builtin_method(value: &Value, name: &str) -> Option<Value>
+{
+    match (value, name)
+    {
+        (Value::Array(_), "length") => Some(bound_native(value, "length", Some(0), builtin_length)),
+        (Value::Array(_), "push") => Some(bound_native(value, "push", Some(1), builtin_push)),
+        (Value::Array(_), "pop") => Some(bound_native(value, "pop", Some(0), builtin_pop)),
+        (Value::String(_), "length") => Some(bound_native(value, "length", Some(0), builtin_length)),
+        (Value::Int(_), "is_even") => Some(bound_native(value, "is_even", Some(0), builtin_is_even)),
+        (Value::Int(_), "is_odd") => Some(bound_native(value, "is_odd", Some(0), builtin_is_odd)),
+        _ => None,
+    }
+}
+
+fn builtin_print(_: &mut Interpreter, args: Vec<Value>, _: &Token) -> EvalResult<Value>
+{
+    print!("{}", args.iter().map(|a| a.to_string()).join(" "));
+    let _ = std::io::stdout().flush();
+    Ok(Value::Void)
+}
+
+fn builtin_println(_: &mut Interpreter, args: Vec<Value>, _: &Token) -> EvalResult<Value>
+{
+    println!("{}", args.iter().map(|a| a.to_string()).join(" "));
+    Ok(Value::Void)
+}
+
+fn builtin_range(_: &mut Interpreter, args: Vec<Value>, token: &Token) -> EvalResult<Value>
+{
+    match (&args[0], &args[1])
+    {
+        (Value::Int(begin), Value::Int(end)) => Ok(Value::new_array((*begin..*end).map(Value::Int).collect())),
+        (a, b) => Err(RuntimeError::TypeMismatch(Some(*token), format!("`range` expects `Int` bounds, found `{}` and `{}`", a.type_name(), b.type_name())).into()),
+    }
+}
+
+fn read_line(token: &Token) -> EvalResult<String>
+{
+    let mut line = String::new();
+    match std::io::stdin().lock().read_line(&mut line)
+    {
+        Ok(_) => Ok(line.trim_end_matches(['\n', '\r']).to_owned()),
+        Err(e) => Err(RuntimeError::Native(Some(*token), format!("Failed to read from stdin: {}", e)).into()),
+    }
+}
+
+fn builtin_read_line(_: &mut Interpreter, _: Vec<Value>, token: &Token) -> EvalResult<Value>
+{
+    read_line(token).map(Value::String)
+}
+
+fn builtin_read_key(_: &mut Interpreter, _: Vec<Value>, token: &Token) -> EvalResult<Value>
+{
+    let line = read_line(token)?;
+    Ok(Value::String(line.chars().next().map(|c| c.to_string()).unwrap_or_default()))
+}
+
+fn builtin_length(_: &mut Interpreter, args: Vec<Value>, _: &Token) -> EvalResult<Value>
+{
+    match &args[0]
+    {
+        Value::Array(array) => Ok(Value::Int(array.borrow().len() as i64)),
+        Value::String(s) => Ok(Value::Int(s.chars().count() as i64)),
+        _ => unreachable!("only bound to arrays and strings"),
+    }
+}
+
+fn builtin_push(_: &mut Interpreter, args: Vec<Value>, _: &Token) -> EvalResult<Value>
+{
+    let Value::Array(array) = &args[0] else { unreachable!("only bound to arrays") };
+    array.borrow_mut().push(args[1].clone());
+    Ok(Value::Void)
+}
+
+fn builtin_pop(_: &mut Interpreter, args: Vec<Value>, token: &Token) -> EvalResult<Value>
+{
+    let Value::Array(array) = &args[0] else { unreachable!("only bound to arrays") };
+    let popped = array.borrow_mut().pop();
+    popped.ok_or_else(|| RuntimeError::IndexOutOfBounds { token: Some(*token), index: 0, length: 0 }.into())
+}
+
+fn builtin_is_even(_: &mut Interpreter, args: Vec<Value>, _: &Token) -> EvalResult<Value>
+{
+    let Value::Int(i) = args[0] else { unreachable!("only bound to integers") };
+    Ok(Value::Bool(i % 2 == 0))
+}
+
+fn builtin_is_odd(_: &mut Interpreter, args: Vec<Value>, _: &Token) -> EvalResult<Value>
+{
+    let Value::Int(i) = args[0] else { unreachable!("only bound to integers") };
+    Ok(Value::Bool(i % 2 != 0))
+}
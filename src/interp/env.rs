@@ -0,0 +1,117 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{consteval::{ConstEnv, ConstItem, ConstResult}, lexing::token::Token};
+
+use super::value::{Function, Value};
+
+struct Scope
+{
+    values: HashMap<String, Value>,
+    parent: Option<Environment>,
+}
+
+/// A chain of lexical scopes. Cloning an environment shares the underlying scope,
+/// which is how closures and methods capture the place they were declared in.
+#[derive(Clone)]
+pub struct Environment
+{
+    scope: Rc<RefCell<Scope>>,
+}
+
+impl std::fmt::Debug for Environment
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "Environment")
+    }
+}
+
+impl Default for Environment
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Environment
+{
+    pub fn new() -> Self
+    {
+        Self
+        {
+            scope: Rc::new(RefCell::new(Scope { values: HashMap::new(), parent: None }))
+        }
+    }
+
+    pub fn child(&self) -> Self
+    {
+        Self
+        {
+            scope: Rc::new(RefCell::new(Scope { values: HashMap::new(), parent: Some(self.clone()) }))
+        }
+    }
+
+    pub fn define(&self, name: &str, value: Value)
+    {
+        self.scope.borrow_mut().values.insert(name.to_owned(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value>
+    {
+        let scope = self.scope.borrow();
+        match scope.values.get(name)
+        {
+            Some(value) => Some(value.clone()),
+            None => scope.parent.as_ref().and_then(|p| p.get(name)),
+        }
+    }
+
+    /// Overwrites an existing binding in the nearest scope that declares it.
+    /// Returns `false` if no scope declares `name`.
+    pub fn assign(&self, name: &str, value: Value) -> bool
+    {
+        let mut scope = self.scope.borrow_mut();
+        if let Some(slot) = scope.values.get_mut(name)
+        {
+            *slot = value;
+            true
+        }
+        else
+        {
+            match &scope.parent
+            {
+                Some(parent) => parent.assign(name, value),
+                None => false,
+            }
+        }
+    }
+}
+
+
+/// Lets constants use the values already in scope, as when a program is loaded without being checked first
+impl ConstEnv for Environment
+{
+    fn lookup(&mut self, name: &str, _token: &Token) -> ConstResult<Option<ConstItem>>
+    {
+        let const_fn = |function: &Function| match function.decl.is_const()
+        {
+            true => Some(ConstItem::Fn(function.decl.clone())),
+            false => None,
+        };
+
+        if let Some((type_name, member)) = name.split_once('.')
+        {
+            let Some(Value::Type(type_def)) = self.get(type_name) else { return Ok(None) };
+            let value = type_def.statics.borrow().get(member).and_then(Value::to_const).map(ConstItem::Value);
+            return Ok(value.or_else(|| type_def.get_method(member).and_then(|f| const_fn(&f))));
+        }
+
+        Ok(match self.get(name)
+        {
+            Some(Value::Function(function)) => const_fn(&function),
+            Some(value) => value.to_const().map(ConstItem::Value),
+            None => None,
+        })
+    }
+}
@@ -0,0 +1,655 @@
+use std::rc::Rc;
+
+use either::Either;
+
+use crate::{ast::*, lexing::token::{Token, TokenType, TokenValue}};
+
+use super::{env::Environment, value::{EnumPayload, EnumValue, Lambda, StructValue, TypeKind, Value}, token_str, EvalResult, Interpreter, Interrupt, RuntimeError};
+
+impl Interpreter
+{
+    pub fn eval_expression(&mut self, expression: &Expression, env: &Environment) -> EvalResult<Value>
+    {
+        match expression
+        {
+            Expression::Lambda(lambda) => Ok(Value::Lambda(Rc::new(Lambda {
+                expr: Rc::new(lambda.clone()),
+                closure: env.clone(),
+            }))),
+            Expression::Literal(token) => self.eval_literal(token, env),
+            Expression::ArrayLiteral(array) =>
+            {
+                let mut values = vec![];
+                for expression in &array.expressions
+                {
+                    values.push(self.eval_expression(expression, env)?);
+                }
+
+                Ok(Value::new_array(values))
+            },
+            Expression::Identifier(token) => self.eval_literal(token, env),
+            Expression::Grouping(grouping) => self.eval_expression(&grouping.expression, env),
+            Expression::SelfExpr(token) => self.eval_literal(token, env),
+            Expression::BlockExpr(block) => self.eval_block(block, &env.child()),
+            Expression::TypeValue(type_value) =>
+            {
+                let owner = self.resolve_type_path(&type_value.type_name, env)?;
+                self.get_member(owner, &type_value.name)
+            },
+            Expression::Construction(construction) => self.eval_construction(construction, env),
+            Expression::EnumConstruction(construction) =>
+            {
+                let value = self.eval_expression(&construction.expression, env)?;
+                self.cast_value(value, &construction.type_name, env)
+            },
+            Expression::Call(call) =>
+            {
+                let callee = self.eval_expression(&call.expression, env)?;
+                let mut args = vec![];
+                for arg in &call.args
+                {
+                    args.push(self.eval_expression(arg, env)?);
+                }
+
+                self.call_value(callee, args, &call.open_paren)
+            },
+            Expression::Access(access) =>
+            {
+                let value = self.eval_expression(&access.expression, env)?;
+                self.get_member(value, &access.identifier)
+            },
+            Expression::Index(index) =>
+            {
+                let value = self.eval_expression(&index.expression, env)?;
+                let indexer = self.eval_expression(&index.indexer, env)?;
+                self.index_value(&value, &indexer, &index.open_bracket)
+            },
+            Expression::Unary(unary) => self.eval_unary(unary, env),
+            Expression::Binary(binary) => self.eval_binary(binary, env),
+            Expression::IfExpr(if_expr) => self.eval_if(if_expr, env),
+            Expression::MatchExpr(match_expr) => self.eval_match(match_expr, env),
+            Expression::Cast(cast) =>
+            {
+                let value = self.eval_expression(&cast.expression, env)?;
+                self.cast_value(value, &cast.type_name, env)
+            },
+            Expression::Interpolation(interpolation) =>
+            {
+                let mut result = token_str(&interpolation.start);
+                for (expression, text) in &interpolation.parts
+                {
+                    result.push_str(&self.eval_expression(expression, env)?.to_string());
+                    result.push_str(&token_str(text));
+                }
+
+                Ok(Value::String(result))
+            },
+            // Generators run the `yield`s of their bodies themselves, so any that gets here cannot suspend anything
+            Expression::Yield(yield_expr) => Err(RuntimeError::MisplacedYield(yield_expr.yield_tok).into()),
+            Expression::Jump(jump) => match jump.keyword.token_type
+            {
+                TokenType::Break => Err(Interrupt::Break(jump.keyword)),
+                TokenType::Continue => Err(Interrupt::Continue(jump.keyword)),
+                _ =>
+                {
+                    let value = match &jump.expression
+                    {
+                        Some(expression) => self.eval_expression(expression, env)?,
+                        None => Value::Void,
+                    };
+
+                    Err(Interrupt::Return(value))
+                },
+            },
+        }
+    }
+
+    pub fn eval_block(&mut self, block: &BlockExpr, env: &Environment) -> EvalResult<Value>
+    {
+        let Some((last, statements)) = block.statements.split_last() else {
+            return match &block.expression
+            {
+                Some(expression) => self.eval_expression(expression, env),
+                None => Ok(Value::Void),
+            };
+        };
+
+        for statement in statements
+        {
+            self.execute_statement(statement, env)?;
+        }
+
+        if let Some(expression) = &block.expression
+        {
+            self.execute_statement(last, env)?;
+            return self.eval_expression(expression, env);
+        }
+
+        // The parser reads a trailing `if`, `match` or block as a statement, but it still gives the block its value
+        match last
+        {
+            Statement::If(if_expr) => self.eval_if(if_expr, env),
+            Statement::Match(match_expr) => self.eval_match(match_expr, env),
+            Statement::Block(block) => self.eval_block(block, &env.child()),
+            _ => self.execute_statement(last, env).map(|_| Value::Void),
+        }
+    }
+
+    pub fn eval_if(&mut self, if_expr: &IfExpr, env: &Environment) -> EvalResult<Value>
+    {
+        let condition_env = env.child();
+        if self.eval_let_condition(&if_expr.condition, &condition_env)?
+        {
+            self.eval_block(&if_expr.block, &condition_env)
+        }
+        else
+        {
+            match &if_expr.else_branch
+            {
+                Some(ElseBranch { else_tok: _, body: Either::Left(if_expr) }) => self.eval_if(if_expr, env),
+                Some(ElseBranch { else_tok: _, body: Either::Right(block) }) => self.eval_block(block, &env.child()),
+                None => Ok(Value::Void),
+            }
+        }
+    }
+
+    pub fn eval_match(&mut self, match_expr: &MatchExpr, env: &Environment) -> EvalResult<Value>
+    {
+        let value = self.eval_expression(&match_expr.expression, env)?;
+        for branch in &match_expr.branches
+        {
+            let branch_env = env.child();
+            if self.match_branch(branch, &value, &branch_env)?
+            {
+                return self.eval_expression(&branch.expression, &branch_env);
+            }
+        }
+
+        Err(RuntimeError::PatternNotMatched(Some(match_expr.match_tok)).into())
+    }
+
+    /// Evaluates a condition, binding any `let` pattern variables into `env`.
+    pub fn eval_let_condition(&mut self, condition: &LetCondition, env: &Environment) -> EvalResult<bool>
+    {
+        match condition
+        {
+            LetCondition::Expression(expression) =>
+            {
+                let value = self.eval_expression(expression, env)?;
+                self.expect_bool(&value, expression.get_token())
+            },
+            LetCondition::Pattern { let_tok: _, pattern, equal: _, expression, and: _, other_cond } =>
+            {
+                let value = self.eval_expression(expression, env)?;
+                if !self.match_pattern(pattern, &value, env)?
+                {
+                    return Ok(false);
+                }
+
+                match other_cond
+                {
+                    Some(other) => self.eval_let_condition(other, env),
+                    None => Ok(true),
+                }
+            }
+        }
+    }
+
+    pub fn expect_bool(&self, value: &Value, token: &Token) -> EvalResult<bool>
+    {
+        match value.is_truthy()
+        {
+            Some(b) => Ok(b),
+            None => Err(RuntimeError::TypeMismatch(Some(*token), format!("expected `Bool` but found `{}`", value.type_name())).into()),
+        }
+    }
+
+    fn eval_literal(&mut self, token: &Token, env: &Environment) -> EvalResult<Value>
+    {
+        match (token.token_type, &token.value)
+        {
+            (TokenType::IntegerLiteral, Some(TokenValue::Int(i))) => Ok(Value::Int(*i as i64)),
+            (TokenType::FloatLiteral, Some(TokenValue::Float(f))) => Ok(Value::Float(*f)),
+            (TokenType::StringLiteral, Some(TokenValue::String(s))) => Ok(Value::String(s.to_string())),
+            (TokenType::CharLiteral, Some(TokenValue::Char(c))) => Ok(Value::Char(*c)),
+            (TokenType::True, _) => Ok(Value::Bool(true)),
+            (TokenType::False, _) => Ok(Value::Bool(false)),
+            (TokenType::SelfVal, _) => env.get("self").ok_or_else(|| RuntimeError::UndefinedName(*token).into()),
+            (TokenType::SelfType, _) => env.get("Self").ok_or_else(|| RuntimeError::UndefinedName(*token).into()),
+            (TokenType::Identifier, Some(TokenValue::String(name))) => self.lookup(&name.as_str(), token, env),
+            _ => Err(RuntimeError::TypeMismatch(Some(*token), format!("`{}` is not a value", token)).into()),
+        }
+    }
+
+    /// Looks up a name, falling back on the fields of `self` so methods can refer to them directly.
+    fn lookup(&self, name: &str, token: &Token, env: &Environment) -> EvalResult<Value>
+    {
+        if let Some(value) = env.get(name)
+        {
+            return Ok(value);
+        }
+
+        if let Some(Value::Struct(s)) = env.get("self")
+        {
+            if let Some(value) = s.get_field(name)
+            {
+                return Ok(value);
+            }
+        }
+
+        Err(RuntimeError::UndefinedName(*token).into())
+    }
+
+    /// Evaluates a type name to the value that represents it: a type, an enum variant or a variant constructor.
+    pub fn resolve_type_path(&mut self, type_name: &TypeName, env: &Environment) -> EvalResult<Value>
+    {
+        match type_name
+        {
+            TypeName::Identifier { name, args: _ } => self.eval_literal(name, env),
+            TypeName::Access { inner, dot: _, name, args: _ } =>
+            {
+                let owner = self.resolve_type_path(inner, env)?;
+                self.get_member(owner, name)
+            },
+            TypeName::Array { .. } | TypeName::Function { .. } => Err(RuntimeError::TypeMismatch(
+                Some(*type_name.get_token()),
+                format!("`{}` cannot be used as a value", type_name.pretty_print())
+            ).into()),
+        }
+    }
+
+    pub fn get_member(&mut self, value: Value, name_tok: &Token) -> EvalResult<Value>
+    {
+        let name = token_str(name_tok);
+        match &value
+        {
+            Value::Struct(s) =>
+            {
+                if let Some(field) = s.get_field(&name)
+                {
+                    return Ok(field);
+                }
+
+                if let Some(method) = s.type_def.get_method(&name)
+                {
+                    return Ok(Value::BoundMethod(Box::new(value.clone()), method));
+                }
+            },
+            Value::Enum(e) =>
+            {
+                if let Some(field) = e.get_field(&name)
+                {
+                    return Ok(field);
+                }
+
+                if let Some(method) = e.type_def.get_method(&name)
+                {
+                    return Ok(Value::BoundMethod(Box::new(value.clone()), method));
+                }
+            },
+            Value::Type(type_def) =>
+            {
+                if let Some(member) = type_def.get_variant(&name)
+                {
+                    return Ok(match member
+                    {
+                        EnumMember::Basic { .. } => Value::Enum(Rc::new(EnumValue {
+                            type_def: type_def.clone(),
+                            variant: name,
+                            payload: EnumPayload::None,
+                        })),
+                        _ => Value::VariantConstructor(type_def.clone(), name),
+                    });
+                }
+
+                if let Some(method) = type_def.get_method(&name)
+                {
+                    return Ok(Value::Function(method));
+                }
+
+                if let Some(value) = type_def.statics.borrow().get(&name)
+                {
+                    return Ok(value.clone());
+                }
+            },
+            Value::Module(module) =>
+            {
+                if let Some(value) = module.get(&name)
+                {
+                    return Ok(value);
+                }
+            },
+            _ =>
+            {
+                if let Some(method) = super::builtins::get_builtin_method(&value, &name)
+                {
+                    return Ok(method);
+                }
+            },
+        }
+
+        Err(RuntimeError::UndefinedMember(*name_tok, value.type_name()).into())
+    }
+
+    pub fn index_value(&self, value: &Value, indexer: &Value, token: &Token) -> EvalResult<Value>
+    {
+        match (value, indexer)
+        {
+            (Value::Array(array), Value::Int(index)) =>
+            {
+                let array = array.borrow();
+                let i = self.check_index(*index, array.len(), token)?;
+                Ok(array[i].clone())
+            },
+            (Value::String(s), Value::Int(index)) =>
+            {
+                let chars: Vec<char> = s.chars().collect();
+                let i = self.check_index(*index, chars.len(), token)?;
+                Ok(Value::String(chars[i].to_string()))
+            },
+            _ => Err(RuntimeError::TypeMismatch(
+                Some(*token),
+                format!("cannot index `{}` with `{}`", value.type_name(), indexer.type_name())
+            ).into()),
+        }
+    }
+
+    pub fn check_index(&self, index: i64, length: usize, token: &Token) -> EvalResult<usize>
+    {
+        if index < 0 || index as usize >= length
+        {
+            Err(RuntimeError::IndexOutOfBounds { token: Some(*token), index, length }.into())
+        }
+        else
+        {
+            Ok(index as usize)
+        }
+    }
+
+    fn eval_construction(&mut self, construction: &ConstructionExpr, env: &Environment) -> EvalResult<Value>
+    {
+        let target = self.resolve_type_path(&construction.type_name, env)?;
+
+        let mut args = vec![];
+        for arg in &construction.args
+        {
+            args.push((arg.name, self.eval_expression(&arg.value, env)?));
+        }
+
+        self.construct(construction, target, args)
+    }
+
+    /// Builds the struct or struct variant `target` that `construction` names out of the values of its arguments
+    pub fn construct(&mut self, construction: &ConstructionExpr, target: Value, args: Vec<(Token, Value)>) -> EvalResult<Value>
+    {
+        match target
+        {
+            Value::Type(type_def) =>
+            {
+                let TypeKind::Struct(decl) = &type_def.kind else {
+                    return Err(RuntimeError::TypeMismatch(
+                        Some(construction.open_brace),
+                        format!("`{}` is not a struct", type_def.name)
+                    ).into());
+                };
+
+                let members: Vec<(&Token, Option<&Expression>)> = decl.members.iter()
+                    .map(|m| (&m.id, m.initializer.as_ref().map(|(_, e)| e)))
+                    .collect();
+
+                let fields = self.build_fields(&members, args, &type_def.env, &construction.open_brace, &type_def.name)?;
+                Ok(Value::Struct(Rc::new(StructValue { type_def, fields: fields.into() })))
+            },
+            Value::VariantConstructor(type_def, variant) =>
+            {
+                let Some(EnumMember::Struct { members, .. }) = type_def.get_variant(&variant) else {
+                    return Err(RuntimeError::TypeMismatch(
+                        Some(construction.open_brace),
+                        format!("`{}.{}` is not a struct variant", type_def.name, variant)
+                    ).into());
+                };
+
+                let members: Vec<(&Token, Option<&Expression>)> = members.iter()
+                    .map(|m| (&m.id, m.initializer.as_ref().map(|(_, e)| e)))
+                    .collect();
+
+                let fields = self.build_fields(&members, args, &type_def.env, &construction.open_brace, &type_def.name)?;
+                Ok(Value::Enum(Rc::new(EnumValue { type_def: type_def.clone(), variant, payload: EnumPayload::Struct(fields.into()) })))
+            },
+            other => Err(RuntimeError::TypeMismatch(
+                Some(construction.open_brace),
+                format!("cannot construct a value of `{}`", other)
+            ).into()),
+        }
+    }
+
+    fn build_fields(&mut self, members: &[(&Token, Option<&Expression>)], args: Vec<(Token, Value)>, env: &Environment, token: &Token, type_name: &str) -> EvalResult<Vec<(String, Value)>>
+    {
+        for (arg_name, _) in &args
+        {
+            if !members.iter().any(|(id, _)| token_str(id) == token_str(arg_name))
+            {
+                return Err(RuntimeError::UndefinedMember(*arg_name, type_name.to_owned()).into());
+            }
+        }
+
+        let mut fields = vec![];
+        for (id, initializer) in members
+        {
+            let name = token_str(id);
+            let value = match args.iter().find(|(n, _)| token_str(n) == name)
+            {
+                Some((_, value)) => value.clone(),
+                None => match initializer
+                {
+                    Some(expression) => self.eval_expression(expression, env)?,
+                    None => return Err(RuntimeError::Native(Some(*token), format!("Missing field `{}` when constructing `{}`", name, type_name)).into()),
+                }
+            };
+
+            fields.push((name, value));
+        }
+
+        Ok(fields)
+    }
+
+    fn eval_unary(&mut self, unary: &UnaryExpr, env: &Environment) -> EvalResult<Value>
+    {
+        let value = self.eval_expression(&unary.expression, env)?;
+        self.apply_unary_op(unary, value)
+    }
+
+    pub fn apply_unary_op(&self, unary: &UnaryExpr, value: Value) -> EvalResult<Value>
+    {
+        match (unary.operator.token_type, &value)
+        {
+            (TokenType::Minus, Value::Int(i)) => Ok(Value::Int(i.wrapping_neg())),
+            (TokenType::Minus, Value::Float(f)) => Ok(Value::Float(-f)),
+            (TokenType::Bang, Value::Bool(b)) => Ok(Value::Bool(!b)),
+            _ => Err(RuntimeError::TypeMismatch(
+                Some(unary.operator),
+                format!("cannot apply {} to `{}`", unary.operator.token_type.describe(), value.type_name())
+            ).into()),
+        }
+    }
+
+    fn eval_binary(&mut self, binary: &BinaryExpr, env: &Environment) -> EvalResult<Value>
+    {
+        let left = self.eval_expression(&binary.left, env)?;
+
+        // Logical operators short circuit
+        match binary.operator.token_type
+        {
+            TokenType::AndAnd =>
+            {
+                if !self.expect_bool(&left, binary.left.get_token())?
+                {
+                    return Ok(Value::Bool(false));
+                }
+
+                let right = self.eval_expression(&binary.right, env)?;
+                return Ok(Value::Bool(self.expect_bool(&right, binary.right.get_token())?));
+            },
+            TokenType::PipePipe =>
+            {
+                if self.expect_bool(&left, binary.left.get_token())?
+                {
+                    return Ok(Value::Bool(true));
+                }
+
+                let right = self.eval_expression(&binary.right, env)?;
+                return Ok(Value::Bool(self.expect_bool(&right, binary.right.get_token())?));
+            },
+            _ => {}
+        }
+
+        let right = self.eval_expression(&binary.right, env)?;
+        self.apply_binary_op(binary.operator.token_type, left, right, &binary.operator)
+    }
+
+    pub fn apply_binary_op(&self, op: TokenType, left: Value, right: Value, token: &Token) -> EvalResult<Value>
+    {
+        let mismatch = |left: &Value, right: &Value| -> super::Interrupt {
+            RuntimeError::TypeMismatch(
+                Some(*token),
+                format!("cannot apply {} to `{}` and `{}`", op.describe(), left.type_name(), right.type_name())
+            ).into()
+        };
+
+        match op
+        {
+            TokenType::EqualEqual => return Ok(Value::Bool(left == right)),
+            TokenType::BangEqual => return Ok(Value::Bool(left != right)),
+            _ => {}
+        }
+
+        match (&left, &right)
+        {
+            (Value::Int(a), Value::Int(b)) => match op
+            {
+                TokenType::Plus => Ok(Value::Int(a.wrapping_add(*b))),
+                TokenType::Minus => Ok(Value::Int(a.wrapping_sub(*b))),
+                TokenType::Multiply => Ok(Value::Int(a.wrapping_mul(*b))),
+                TokenType::Divide | TokenType::Modulus if *b == 0 => Err(RuntimeError::DivisionByZero(Some(*token)).into()),
+                TokenType::Divide => Ok(Value::Int(a.wrapping_div(*b))),
+                TokenType::Modulus => Ok(Value::Int(a.wrapping_rem(*b))),
+                TokenType::LessThan => Ok(Value::Bool(a < b)),
+                TokenType::LessEqual => Ok(Value::Bool(a <= b)),
+                TokenType::GreaterThan => Ok(Value::Bool(a > b)),
+                TokenType::GreaterEqual => Ok(Value::Bool(a >= b)),
+                _ => Err(mismatch(&left, &right)),
+            },
+            (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) | (Value::Float(_), Value::Float(_)) =>
+            {
+                let a = as_float(&left);
+                let b = as_float(&right);
+                match op
+                {
+                    TokenType::Plus => Ok(Value::Float(a + b)),
+                    TokenType::Minus => Ok(Value::Float(a - b)),
+                    TokenType::Multiply => Ok(Value::Float(a * b)),
+                    TokenType::Divide => Ok(Value::Float(a / b)),
+                    TokenType::Modulus => Ok(Value::Float(a % b)),
+                    TokenType::LessThan => Ok(Value::Bool(a < b)),
+                    TokenType::LessEqual => Ok(Value::Bool(a <= b)),
+                    TokenType::GreaterThan => Ok(Value::Bool(a > b)),
+                    TokenType::GreaterEqual => Ok(Value::Bool(a >= b)),
+                    _ => Err(mismatch(&left, &right)),
+                }
+            },
+            (Value::String(a), Value::String(b)) => match op
+            {
+                TokenType::Plus => Ok(Value::String(format!("{}{}", a, b))),
+                TokenType::LessThan => Ok(Value::Bool(a < b)),
+                TokenType::LessEqual => Ok(Value::Bool(a <= b)),
+                TokenType::GreaterThan => Ok(Value::Bool(a > b)),
+                TokenType::GreaterEqual => Ok(Value::Bool(a >= b)),
+                _ => Err(mismatch(&left, &right)),
+            },
+            (Value::Char(a), Value::Char(b)) => match op
+            {
+                TokenType::LessThan => Ok(Value::Bool(a < b)),
+                TokenType::LessEqual => Ok(Value::Bool(a <= b)),
+                TokenType::GreaterThan => Ok(Value::Bool(a > b)),
+                TokenType::GreaterEqual => Ok(Value::Bool(a >= b)),
+                _ => Err(mismatch(&left, &right)),
+            },
+            (Value::Bool(a), Value::Bool(b)) => match op
+            {
+                TokenType::AndAnd => Ok(Value::Bool(*a && *b)),
+                TokenType::PipePipe => Ok(Value::Bool(*a || *b)),
+                _ => Err(mismatch(&left, &right)),
+            },
+            _ => Err(mismatch(&left, &right)),
+        }
+    }
+
+    pub fn cast_value(&mut self, value: Value, type_name: &TypeName, env: &Environment) -> EvalResult<Value>
+    {
+        let error = |value: &Value| -> super::Interrupt {
+            RuntimeError::TypeMismatch(
+                Some(*type_name.get_token()),
+                format!("cannot cast `{}` to `{}`", value.type_name(), type_name.pretty_print())
+            ).into()
+        };
+
+        let TypeName::Identifier { name, args: _ } = type_name else {
+            return match (type_name, &value)
+            {
+                (TypeName::Array { .. }, Value::Array(_)) => Ok(value),
+                (TypeName::Function { .. }, Value::Function(_) | Value::Lambda(_) | Value::Native(_) | Value::BoundMethod(_, _)) => Ok(value),
+                (TypeName::Access { .. }, _) => self.cast_to_type(value, type_name, env),
+                _ => Err(error(&value)),
+            };
+        };
+
+        let target = token_str(name);
+        match (super::builtins::builtin_type_kind(&target), &value)
+        {
+            (Some("Int"), Value::Int(_)) => Ok(value),
+            (Some("Int"), Value::Float(f)) => Ok(Value::Int(*f as i64)),
+            (Some("Int"), Value::Bool(b)) => Ok(Value::Int(*b as i64)),
+            (Some("Int"), Value::Char(c)) => Ok(Value::Int(*c as i64)),
+            (Some("Int"), Value::String(s)) => s.trim().parse().map(Value::Int).map_err(|_| error(&value)),
+            (Some("Int"), Value::Enum(e)) => e.type_def.discriminants.borrow().get(&e.variant).copied().map(Value::Int).ok_or_else(|| error(&value)),
+            (Some("Float"), Value::Int(i)) => Ok(Value::Float(*i as f64)),
+            (Some("Float"), Value::Float(_)) => Ok(value),
+            (Some("Float"), Value::String(s)) => s.trim().parse().map(Value::Float).map_err(|_| error(&value)),
+            (Some("Bool"), Value::Bool(_)) => Ok(value),
+            (Some("Char"), Value::Char(_)) => Ok(value),
+            (Some("Char"), Value::Int(i)) => u32::try_from(*i).ok().and_then(char::from_u32).map(Value::Char).ok_or_else(|| error(&value)),
+            (Some("String"), _) => Ok(Value::String(value.to_string())),
+            (Some("Iter"), Value::Generator(_)) => Ok(value),
+            (Some(_), _) => Err(error(&value)),
+            (None, _) => self.cast_to_type(value, type_name, env),
+        }
+    }
+
+    /// Casts to a user defined type, which only accepts values that already have that type, and enums, which also
+    /// accept the discriminant of one of their members
+    fn cast_to_type(&mut self, value: Value, type_name: &TypeName, env: &Environment) -> EvalResult<Value>
+    {
+        match (self.resolve_type_path(type_name, env)?, &value)
+        {
+            (Value::Type(t), Value::Struct(s)) if Rc::ptr_eq(&t, &s.type_def) => Ok(value),
+            (Value::Type(t), Value::Enum(e)) if Rc::ptr_eq(&t, &e.type_def) => Ok(value),
+            (Value::Type(t), Value::Int(i)) if matches!(t.kind, TypeKind::Enum(_)) => t.from_discriminant(*i).ok_or_else(|| {
+                RuntimeError::InvalidDiscriminant { token: Some(*type_name.get_token()), type_name: t.name.clone(), value: *i }.into()
+            }),
+            _ => Err(RuntimeError::TypeMismatch(
+                Some(*type_name.get_token()),
+                format!("cannot cast `{}` to `{}`", value.type_name(), type_name.pretty_print())
+            ).into()),
+        }
+    }
+}
+
+fn as_float(value: &Value) -> f64
+{
+    match value
+    {
+        Value::Int(i) => *i as f64,
+        Value::Float(f) => *f,
+        _ => unreachable!(),
+    }
+}
@@ -0,0 +1,798 @@
+use std::{cell::RefCell, rc::Rc};
+
+use either::Either;
+
+use crate::{ast::*, lexing::token::{Token, TokenType}, workspace::FileId};
+
+use super::{env::Environment, token_str, value::{Function, Value}, EvalResult, Interpreter, Interrupt, RuntimeError};
+
+/// The state of a call to a function that yields. The body runs a piece at a time, each time the generator is resumed.
+#[derive(Debug)]
+pub struct Generator
+{
+    pub name: String,
+    decl: Rc<FnDecl>,
+    module: FileId,
+    state: GeneratorState,
+}
+
+#[derive(Debug)]
+enum GeneratorState
+{
+    /// Waiting to be resumed, with the parts of the body it was in when it stopped
+    Suspended(Vec<Frame>),
+    Running,
+    Finished,
+}
+
+impl Generator
+{
+    /// A generator that starts running `function`'s body in `env`, which holds its arguments
+    pub fn new(function: &Function, env: Environment) -> Self
+    {
+        Self
+        {
+            name: function.name.clone(),
+            decl: function.decl.clone(),
+            module: function.module,
+            state: GeneratorState::Suspended(vec![Frame::new(vec![], env)]),
+        }
+    }
+}
+
+/// The values a `for` loop visits
+#[derive(Debug)]
+pub enum Sequence
+{
+    Values(std::vec::IntoIter<Value>),
+    Generator(Rc<RefCell<Generator>>),
+}
+
+/// A part of a generator's body that is being run. Only the parts that contain a `yield` get frames of their own,
+/// everything else runs to completion as it would in any other function.
+#[derive(Debug)]
+struct Frame
+{
+    path: Vec<Step>,
+    env: Environment,
+    /// How far the part has got, such as the index of the next statement of a block
+    next: usize,
+    /// The values of the pieces the part has worked out so far, such as the operands of a call
+    values: Vec<Value>,
+    /// The scope the part binds names in, such as the variables of an `if let` or of a `match` branch
+    scope: Option<Environment>,
+    /// The rest of what a `for` loop visits
+    sequence: Option<Sequence>,
+}
+
+impl Frame
+{
+    fn new(path: Vec<Step>, env: Environment) -> Self
+    {
+        Self { path, env, next: 0, values: vec![], scope: None, sequence: None }
+    }
+
+    /// Whether the frame is a loop running its body, which is what a `break` or `continue` leaves
+    fn in_loop_body(&self, body: &BlockExpr) -> bool
+    {
+        let is_loop = matches!(node_at(body, &self.path), Node::Statement(Statement::While(_) | Statement::For(_)));
+        is_loop && self.next == LOOP_BODY
+    }
+}
+
+/// The stage of a `while` or `for` frame while its body runs
+const LOOP_BODY: usize = 2;
+
+/// The stage of a `match` frame once it is running the chosen branch
+const MATCH_BRANCH: usize = usize::MAX;
+
+/// One step of the path from the body of a generator to a part inside it
+#[derive(Debug, Clone, Copy)]
+enum Step
+{
+    Statement(usize),
+    /// The final expression of a block
+    Tail,
+    /// An expression the part works out before doing its own work, such as the arguments of a call
+    Operand(usize),
+    Condition,
+    Then,
+    /// The `else` of an `if`, or the block of a `let ... else`
+    Else,
+    Guard(usize),
+    Branch(usize),
+    /// The condition after the `&&` of an `if let`
+    Next,
+    /// The body of a loop
+    Body,
+}
+
+/// What a frame does next
+enum Advance
+{
+    Next,
+    Push(Frame),
+    /// Ends the frame, handing its value to the frame below
+    Done(Value),
+    Yield(Value),
+}
+
+/// A part of a generator's body, seen through the statements and expressions that only wrap it
+#[derive(Clone, Copy)]
+enum Node<'a>
+{
+    Block(&'a BlockExpr),
+    If(&'a IfExpr),
+    Match(&'a MatchExpr),
+    Condition(&'a LetCondition),
+    Statement(&'a Statement),
+    Expression(&'a Expression),
+}
+
+impl<'a> Node<'a>
+{
+    fn statement(statement: &'a Statement) -> Self
+    {
+        match statement
+        {
+            Statement::If(if_expr) => Node::If(if_expr),
+            Statement::Match(match_expr) => Node::Match(match_expr),
+            Statement::Block(block) => Node::Block(block),
+            Statement::Expression(expression_stmt) => Node::expression(&expression_stmt.expression),
+            other => Node::Statement(other),
+        }
+    }
+
+    fn expression(expression: &'a Expression) -> Self
+    {
+        match expression
+        {
+            Expression::IfExpr(if_expr) => Node::If(if_expr),
+            Expression::MatchExpr(match_expr) => Node::Match(match_expr),
+            Expression::BlockExpr(block) => Node::Block(block),
+            other => Node::Expression(other),
+        }
+    }
+
+    fn has_yield(self) -> bool
+    {
+        match self
+        {
+            Node::Block(block) => block.has_yield(),
+            Node::If(if_expr) => if_expr.has_yield(),
+            Node::Match(match_expr) => match_expr.has_yield(),
+            Node::Condition(condition) => condition.has_yield(),
+            Node::Statement(statement) => statement.has_yield(),
+            Node::Expression(expression) => expression.has_yield(),
+        }
+    }
+
+    fn step(self, step: Step) -> Self
+    {
+        match (self, step)
+        {
+            (Node::Block(block), Step::Statement(index)) => Node::statement(&block.statements[index]),
+            (Node::Block(block), Step::Tail) => Node::expression(block.expression.as_ref().expect("the block has a final expression")),
+            (Node::If(if_expr), Step::Condition) => Node::Condition(&if_expr.condition),
+            (Node::If(if_expr), Step::Then) => Node::Block(&if_expr.block),
+            (Node::If(if_expr), Step::Else) => match &if_expr.else_branch
+            {
+                Some(ElseBranch { else_tok: _, body: Either::Left(if_expr) }) => Node::If(if_expr),
+                Some(ElseBranch { else_tok: _, body: Either::Right(block) }) => Node::Block(block),
+                None => unreachable!("only `if`s with an `else` are stepped into"),
+            },
+            (Node::Match(match_expr), Step::Operand(0)) => Node::expression(&match_expr.expression),
+            (Node::Match(match_expr), Step::Guard(index)) => match &match_expr.branches[index].guard
+            {
+                Some((_, guard)) => Node::expression(guard),
+                None => unreachable!("only branches with a guard are stepped into"),
+            },
+            (Node::Match(match_expr), Step::Branch(index)) => Node::expression(&match_expr.branches[index].expression),
+            (Node::Condition(LetCondition::Expression(expression)), Step::Operand(0)) => Node::expression(expression),
+            (Node::Condition(LetCondition::Pattern { expression, .. }), Step::Operand(0)) => Node::expression(expression),
+            (Node::Condition(LetCondition::Pattern { other_cond: Some(other), .. }), Step::Next) => Node::Condition(other),
+            (Node::Statement(Statement::While(while_stmt)), Step::Condition) => Node::Condition(&while_stmt.condition),
+            (Node::Statement(Statement::While(while_stmt)), Step::Body) => Node::Block(&while_stmt.body),
+            (Node::Statement(Statement::For(for_stmt)), Step::Operand(0)) => Node::expression(&for_stmt.expression),
+            (Node::Statement(Statement::For(for_stmt)), Step::Body) => Node::Block(&for_stmt.body),
+            (Node::Statement(Statement::Let(let_stmt)), Step::Operand(0)) => Node::expression(&let_stmt.expression),
+            (Node::Statement(Statement::Let(LetStmt { else_clause: Some((_, block)), .. })), Step::Else) => Node::Block(block),
+            (Node::Statement(Statement::Return(ReturnStmt { expression: Some(expression), .. })), Step::Operand(0)) => Node::expression(expression),
+            (Node::Statement(Statement::Assign(assign)), Step::Operand(0)) => Node::expression(&assign.expression),
+            (Node::Expression(expression), Step::Operand(index)) => Node::expression(operands(expression)[index]),
+            _ => unreachable!("paths only lead to the parts of a node"),
+        }
+    }
+}
+
+/// The expressions an expression works out before its own work, in the order they are evaluated
+fn operands(expression: &Expression) -> Vec<&Expression>
+{
+    match expression
+    {
+        Expression::ArrayLiteral(array) => array.expressions.iter().collect(),
+        Expression::Grouping(grouping) => vec![&grouping.expression],
+        Expression::Construction(construction) => construction.args.iter().map(|arg| arg.value.as_ref()).collect(),
+        Expression::EnumConstruction(construction) => vec![&construction.expression],
+        Expression::Call(call) => std::iter::once(call.expression.as_ref()).chain(call.args.iter()).collect(),
+        Expression::Access(access) => vec![&access.expression],
+        Expression::Index(index) => vec![&index.expression, &index.indexer],
+        Expression::Unary(unary) => vec![&unary.expression],
+        Expression::Binary(binary) => vec![&binary.left, &binary.right],
+        Expression::Cast(cast) => vec![&cast.expression],
+        Expression::Interpolation(interpolation) => interpolation.parts.iter().map(|(expression, _)| expression).collect(),
+        Expression::Yield(yield_expr) => vec![&yield_expr.expression],
+        Expression::Jump(jump) => jump.expression.iter().map(|expression| expression.as_ref()).collect(),
+        Expression::Lambda(_) | Expression::Literal(_) | Expression::Identifier(_) | Expression::SelfExpr(_)
+        | Expression::TypeValue(_) | Expression::BlockExpr(_) | Expression::IfExpr(_) | Expression::MatchExpr(_) => vec![],
+    }
+}
+
+fn node_at<'a>(body: &'a BlockExpr, path: &[Step]) -> Node<'a>
+{
+    path.iter().fold(Node::Block(body), |node, step| node.step(*step))
+}
+
+fn with_step(path: &[Step], step: Step) -> Vec<Step>
+{
+    let mut path = path.to_vec();
+    path.push(step);
+    path
+}
+
+impl Interpreter
+{
+    /// The values a `for` loop over `value` visits. Arrays are copied so that changing one inside the loop
+    /// does not change what the loop visits.
+    pub fn sequence(&self, value: Value, token: &Token) -> EvalResult<Sequence>
+    {
+        match value
+        {
+            Value::Array(array) => Ok(Sequence::Values(array.borrow().clone().into_iter())),
+            Value::String(s) => Ok(Sequence::Values(s.chars().map(|c| Value::String(c.to_string())).collect::<Vec<_>>().into_iter())),
+            Value::Generator(generator) => Ok(Sequence::Generator(generator)),
+            other => Err(RuntimeError::TypeMismatch(Some(*token), format!("`{}` is not iterable", other.type_name())).into()),
+        }
+    }
+
+    pub fn next_item(&mut self, sequence: &mut Sequence, token: &Token) -> EvalResult<Option<Value>>
+    {
+        match sequence
+        {
+            Sequence::Values(values) => Ok(values.next()),
+            Sequence::Generator(generator) => self.resume(generator, token),
+        }
+    }
+
+    /// Runs a generator until its next `yield`, producing the yielded value, or `None` once its body has finished
+    fn resume(&mut self, generator: &Rc<RefCell<Generator>>, token: &Token) -> EvalResult<Option<Value>>
+    {
+        let (decl, module, mut frames) =
+        {
+            let mut generator = generator.borrow_mut();
+            match std::mem::replace(&mut generator.state, GeneratorState::Running)
+            {
+                GeneratorState::Suspended(frames) => (generator.decl.clone(), generator.module, frames),
+                GeneratorState::Running => return Err(RuntimeError::GeneratorRunning(Some(*token)).into()),
+                GeneratorState::Finished =>
+                {
+                    generator.state = GeneratorState::Finished;
+                    return Ok(None);
+                },
+            }
+        };
+
+        let result = self.run_frames(&decl, &mut frames);
+        let mut generator = generator.borrow_mut();
+        match result
+        {
+            Ok(Some(value)) =>
+            {
+                generator.state = GeneratorState::Suspended(frames);
+                Ok(Some(value))
+            },
+            Ok(None) =>
+            {
+                generator.state = GeneratorState::Finished;
+                Ok(None)
+            },
+            Err(error) =>
+            {
+                generator.state = GeneratorState::Finished;
+                Err(self.in_module(error, module))
+            },
+        }
+    }
+
+    fn run_frames(&mut self, decl: &FnDecl, frames: &mut Vec<Frame>) -> EvalResult<Option<Value>>
+    {
+        let Either::Left(body) = &decl.body else { return Ok(None) };
+
+        while let Some(frame) = frames.last_mut()
+        {
+            let node = node_at(body, &frame.path);
+            match self.advance(node, frame)
+            {
+                Ok(Advance::Next) => {},
+                Ok(Advance::Push(frame)) => frames.push(frame),
+                Ok(Advance::Done(value)) =>
+                {
+                    frames.pop();
+                    if let Some(frame) = frames.last_mut()
+                    {
+                        frame.values.push(value);
+                    }
+                },
+                Ok(Advance::Yield(value)) => return Ok(Some(value)),
+                Err(interrupt) => unwind(body, frames, interrupt)?,
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Does the next piece of work of the part of the body that `frame` is running
+    fn advance(&mut self, node: Node, frame: &mut Frame) -> EvalResult<Advance>
+    {
+        match node
+        {
+            Node::Block(block) => self.advance_block(block, frame),
+            Node::If(if_expr) => self.advance_if(if_expr, frame),
+            Node::Match(match_expr) => self.advance_match(match_expr, frame),
+            Node::Condition(condition) => self.advance_condition(condition, frame),
+            Node::Statement(statement) => match statement
+            {
+                Statement::While(_) => self.advance_while(statement, frame),
+                Statement::For(for_stmt) => self.advance_for(statement, for_stmt, frame),
+                _ => self.advance_statement(statement, frame),
+            },
+            Node::Expression(expression) => self.advance_expression(expression, frame),
+        }
+    }
+
+    /// Works out the part of `node` at `step`, right away when it has no `yield`, leaving its value in the frame,
+    /// or else in a frame of its own
+    fn child(&mut self, node: Node, frame: &mut Frame, step: Step, env: &Environment) -> EvalResult<Advance>
+    {
+        let child = node.step(step);
+        if child.has_yield()
+        {
+            let env = match child
+            {
+                Node::Block(_) => env.child(),
+                _ => env.clone(),
+            };
+
+            return Ok(Advance::Push(Frame::new(with_step(&frame.path, step), env)));
+        }
+
+        let value = match child
+        {
+            Node::Block(block) => self.eval_block(block, &env.child())?,
+            Node::If(if_expr) => self.eval_if(if_expr, env)?,
+            Node::Match(match_expr) => self.eval_match(match_expr, env)?,
+            Node::Condition(condition) => Value::Bool(self.eval_let_condition(condition, env)?),
+            Node::Statement(statement) =>
+            {
+                self.execute_statement(statement, env)?;
+                Value::Void
+            },
+            Node::Expression(expression) => self.eval_expression(expression, env)?,
+        };
+
+        frame.values.push(value);
+        Ok(Advance::Next)
+    }
+
+    fn advance_block(&mut self, block: &BlockExpr, frame: &mut Frame) -> EvalResult<Advance>
+    {
+        let env = frame.env.clone();
+        let index = frame.next;
+        frame.next += 1;
+
+        if index < block.statements.len()
+        {
+            frame.values.clear();
+            return self.child(Node::Block(block), frame, Step::Statement(index), &env);
+        }
+
+        if index == block.statements.len() && block.expression.is_some()
+        {
+            frame.values.clear();
+            return self.child(Node::Block(block), frame, Step::Tail, &env);
+        }
+
+        // As in `eval_block`, a trailing `if`, `match` or block gives the block its value
+        let value = match (&block.expression, block.statements.last())
+        {
+            (Some(_), _) | (None, Some(Statement::If(_) | Statement::Match(_) | Statement::Block(_))) => frame.values.pop(),
+            _ => None,
+        };
+
+        Ok(Advance::Done(value.unwrap_or(Value::Void)))
+    }
+
+    fn advance_if(&mut self, if_expr: &IfExpr, frame: &mut Frame) -> EvalResult<Advance>
+    {
+        let env = frame.env.clone();
+        let node = Node::If(if_expr);
+        match frame.next
+        {
+            0 =>
+            {
+                frame.next = 1;
+                let scope = env.child();
+                frame.scope = Some(scope.clone());
+                self.child(node, frame, Step::Condition, &scope)
+            },
+            1 =>
+            {
+                frame.next = 2;
+                if let Some(Value::Bool(true)) = frame.values.pop()
+                {
+                    let scope = frame.scope.take().expect("the condition has a scope");
+                    return self.child(node, frame, Step::Then, &scope);
+                }
+
+                match &if_expr.else_branch
+                {
+                    Some(_) => self.child(node, frame, Step::Else, &env),
+                    None => Ok(Advance::Done(Value::Void)),
+                }
+            },
+            _ => Ok(Advance::Done(frame.values.pop().unwrap_or(Value::Void))),
+        }
+    }
+
+    fn advance_match(&mut self, match_expr: &MatchExpr, frame: &mut Frame) -> EvalResult<Advance>
+    {
+        let env = frame.env.clone();
+        let node = Node::Match(match_expr);
+        match frame.next
+        {
+            0 =>
+            {
+                frame.next = 1;
+                return self.child(node, frame, Step::Operand(0), &env);
+            },
+            MATCH_BRANCH => return Ok(Advance::Done(frame.values.pop().unwrap_or(Value::Void))),
+            _ => {},
+        }
+
+        // Stage 2i + 1 tries the pattern of branch i, and stage 2i + 2 has the value of its guard
+        let index = (frame.next - 1) / 2;
+        let Some(branch) = match_expr.branches.get(index) else {
+            return Err(RuntimeError::PatternNotMatched(Some(match_expr.match_tok)).into());
+        };
+
+        let scope = match (frame.next % 2, &branch.guard)
+        {
+            (1, guard) =>
+            {
+                let scope = env.child();
+                if !self.match_pattern(&branch.pattern, &frame.values[0], &scope)?
+                {
+                    frame.next += 2;
+                    return Ok(Advance::Next);
+                }
+
+                if guard.is_some()
+                {
+                    frame.next += 1;
+                    frame.scope = Some(scope.clone());
+                    return self.child(node, frame, Step::Guard(index), &scope);
+                }
+
+                scope
+            },
+            (_, Some((_, guard))) =>
+            {
+                let condition = frame.values.pop().unwrap_or(Value::Void);
+                if !self.expect_bool(&condition, guard.get_token())?
+                {
+                    frame.next += 1;
+                    return Ok(Advance::Next);
+                }
+
+                frame.scope.take().expect("the guard has a scope")
+            },
+            (_, None) => unreachable!("only branches with a guard wait for one"),
+        };
+
+        frame.next = MATCH_BRANCH;
+        self.child(node, frame, Step::Branch(index), &scope)
+    }
+
+    fn advance_condition(&mut self, condition: &LetCondition, frame: &mut Frame) -> EvalResult<Advance>
+    {
+        let env = frame.env.clone();
+        let node = Node::Condition(condition);
+        match frame.next
+        {
+            0 =>
+            {
+                frame.next = 1;
+                return self.child(node, frame, Step::Operand(0), &env);
+            },
+            1 => {},
+            _ => return Ok(Advance::Done(frame.values.pop().unwrap_or(Value::Bool(false)))),
+        }
+
+        let value = frame.values.pop().unwrap_or(Value::Void);
+        match condition
+        {
+            LetCondition::Expression(expression) => Ok(Advance::Done(Value::Bool(self.expect_bool(&value, expression.get_token())?))),
+            LetCondition::Pattern { pattern, other_cond, .. } =>
+            {
+                if !self.match_pattern(pattern, &value, &env)?
+                {
+                    return Ok(Advance::Done(Value::Bool(false)));
+                }
+
+                match other_cond
+                {
+                    Some(_) =>
+                    {
+                        frame.next = 2;
+                        self.child(node, frame, Step::Next, &env)
+                    },
+                    None => Ok(Advance::Done(Value::Bool(true))),
+                }
+            },
+        }
+    }
+
+    fn advance_while(&mut self, statement: &Statement, frame: &mut Frame) -> EvalResult<Advance>
+    {
+        let env = frame.env.clone();
+        let node = Node::Statement(statement);
+        match frame.next
+        {
+            0 =>
+            {
+                frame.next = 1;
+                frame.values.clear();
+                let scope = env.child();
+                frame.scope = Some(scope.clone());
+                self.child(node, frame, Step::Condition, &scope)
+            },
+            1 =>
+            {
+                let Some(Value::Bool(true)) = frame.values.pop() else { return Ok(Advance::Done(Value::Void)) };
+                frame.next = LOOP_BODY;
+                let scope = frame.scope.take().expect("the condition has a scope");
+                self.child(node, frame, Step::Body, &scope)
+            },
+            _ =>
+            {
+                frame.next = 0;
+                Ok(Advance::Next)
+            },
+        }
+    }
+
+    fn advance_for(&mut self, statement: &Statement, for_stmt: &ForStmt, frame: &mut Frame) -> EvalResult<Advance>
+    {
+        let env = frame.env.clone();
+        let node = Node::Statement(statement);
+        match frame.next
+        {
+            0 =>
+            {
+                frame.next = 1;
+                self.child(node, frame, Step::Operand(0), &env)
+            },
+            1 =>
+            {
+                let iterable = frame.values.pop().unwrap_or(Value::Void);
+                frame.sequence = Some(self.sequence(iterable, &for_stmt.in_tok)?);
+                frame.next = LOOP_BODY;
+                Ok(Advance::Next)
+            },
+            _ =>
+            {
+                frame.values.clear();
+                let sequence = frame.sequence.as_mut().expect("the loop has a sequence");
+                let Some(item) = self.next_item(sequence, &for_stmt.for_tok)? else { return Ok(Advance::Done(Value::Void)) };
+
+                let loop_env = env.child();
+                if !self.match_pattern(&for_stmt.pattern, &item, &loop_env)?
+                {
+                    return Err(RuntimeError::PatternNotMatched(Some(for_stmt.for_tok)).into());
+                }
+
+                self.child(node, frame, Step::Body, &loop_env)
+            },
+        }
+    }
+
+    fn advance_statement(&mut self, statement: &Statement, frame: &mut Frame) -> EvalResult<Advance>
+    {
+        let env = frame.env.clone();
+        let node = Node::Statement(statement);
+        match (statement, frame.next)
+        {
+            (Statement::Let(_) | Statement::Assign(_) | Statement::Return(ReturnStmt { expression: Some(_), .. }), 0) =>
+            {
+                frame.next = 1;
+                self.child(node, frame, Step::Operand(0), &env)
+            },
+            (Statement::Let(let_stmt), 1) =>
+            {
+                let value = frame.values.pop().unwrap_or(Value::Void);
+                if self.bind_let(let_stmt, value, &env)?
+                {
+                    return Ok(Advance::Done(Value::Void));
+                }
+
+                match &let_stmt.else_clause
+                {
+                    Some(_) =>
+                    {
+                        frame.next = 2;
+                        self.child(node, frame, Step::Else, &env)
+                    },
+                    None => Err(RuntimeError::PatternNotMatched(Some(let_stmt.let_tok)).into()),
+                }
+            },
+            // The `else` of a `let` has to leave the block
+            (Statement::Let(LetStmt { else_clause: Some((else_tok, _)), .. }), _) => Err(RuntimeError::LetElseDidNotDiverge(*else_tok).into()),
+            (Statement::Assign(assign), _) =>
+            {
+                let value = frame.values.pop().unwrap_or(Value::Void);
+                self.assign_value(assign, value, &env)?;
+                Ok(Advance::Done(Value::Void))
+            },
+            (Statement::Return(_), _) => Err(Interrupt::Return(frame.values.pop().unwrap_or(Value::Void))),
+            (other, _) =>
+            {
+                self.execute_statement(other, &env)?;
+                Ok(Advance::Done(Value::Void))
+            },
+        }
+    }
+
+    fn advance_expression(&mut self, expression: &Expression, frame: &mut Frame) -> EvalResult<Advance>
+    {
+        let env = frame.env.clone();
+        let node = Node::Expression(expression);
+        match expression
+        {
+            Expression::Yield(_) => return match frame.next
+            {
+                0 =>
+                {
+                    frame.next = 1;
+                    self.child(node, frame, Step::Operand(0), &env)
+                },
+                1 =>
+                {
+                    frame.next = 2;
+                    Ok(Advance::Yield(frame.values.pop().unwrap_or(Value::Void)))
+                },
+                // The `yield` itself has no value
+                _ => Ok(Advance::Done(Value::Void)),
+            },
+            // The type is looked up before the arguments are worked out, and kept in front of them
+            Expression::Construction(construction) if frame.next == 0 =>
+            {
+                frame.next = 1;
+                let target = self.resolve_type_path(&construction.type_name, &env)?;
+                frame.values.push(target);
+                return Ok(Advance::Next);
+            },
+            // Logical operators short circuit
+            Expression::Binary(binary) if frame.values.len() == 1 && matches!(binary.operator.token_type, TokenType::AndAnd | TokenType::PipePipe) =>
+            {
+                let left = self.expect_bool(&frame.values[0], binary.left.get_token())?;
+                if left == (binary.operator.token_type == TokenType::PipePipe)
+                {
+                    return Ok(Advance::Done(Value::Bool(left)));
+                }
+            },
+            _ => {},
+        }
+
+        let operands = operands(expression);
+        let done = frame.values.len() - frame.next;
+        if done < operands.len()
+        {
+            return self.child(node, frame, Step::Operand(done), &env);
+        }
+
+        let mut values = std::mem::take(&mut frame.values);
+        let value = match expression
+        {
+            Expression::ArrayLiteral(_) => Value::new_array(values),
+            Expression::Construction(construction) =>
+            {
+                let target = values.remove(0);
+                let args = construction.args.iter().map(|arg| arg.name).zip(values).collect();
+                self.construct(construction, target, args)?
+            },
+            Expression::Call(call) =>
+            {
+                let callee = values.remove(0);
+                self.call_value(callee, values, &call.open_paren)?
+            },
+            Expression::Index(index) => self.index_value(&values[0], &values[1], &index.open_bracket)?,
+            Expression::Binary(binary) => match binary.operator.token_type
+            {
+                TokenType::AndAnd | TokenType::PipePipe => Value::Bool(self.expect_bool(&values[1], binary.right.get_token())?),
+                op =>
+                {
+                    let right = values.pop().unwrap_or(Value::Void);
+                    let left = values.pop().unwrap_or(Value::Void);
+                    self.apply_binary_op(op, left, right, &binary.operator)?
+                },
+            },
+            Expression::Interpolation(interpolation) =>
+            {
+                let mut result = token_str(&interpolation.start);
+                for (value, (_, text)) in values.iter().zip(&interpolation.parts)
+                {
+                    result.push_str(&value.to_string());
+                    result.push_str(&token_str(text));
+                }
+
+                Value::String(result)
+            },
+            Expression::Jump(_) => return Err(Interrupt::Return(values.pop().unwrap_or(Value::Void))),
+            other =>
+            {
+                let value = values.pop().unwrap_or(Value::Void);
+                match other
+                {
+                    Expression::Grouping(_) => value,
+                    Expression::EnumConstruction(construction) => self.cast_value(value, &construction.type_name, &env)?,
+                    Expression::Access(access) => self.get_member(value, &access.identifier)?,
+                    Expression::Unary(unary) => self.apply_unary_op(unary, value)?,
+                    Expression::Cast(cast) => self.cast_value(value, &cast.type_name, &env)?,
+                    _ => unreachable!("only expressions with operands get frames"),
+                }
+            },
+        };
+
+        Ok(Advance::Done(value))
+    }
+}
+
+/// Leaves the frames that a `break`, `continue` or `return` jumps out of. Errors are passed on.
+fn unwind(body: &BlockExpr, frames: &mut Vec<Frame>, interrupt: Interrupt) -> EvalResult<()>
+{
+    match interrupt
+    {
+        Interrupt::Break(token) =>
+        {
+            while let Some(frame) = frames.pop()
+            {
+                if frame.in_loop_body(body)
+                {
+                    return Ok(());
+                }
+            }
+
+            Err(RuntimeError::BreakOutsideLoop(token).into())
+        },
+        Interrupt::Continue(token) =>
+        {
+            while let Some(frame) = frames.last()
+            {
+                if frame.in_loop_body(body)
+                {
+                    return Ok(());
+                }
+
+                frames.pop();
+            }
+
+            Err(RuntimeError::ContinueOutsideLoop(token).into())
+        },
+        Interrupt::Return(_) =>
+        {
+            frames.clear();
+            Ok(())
+        },
+        error => Err(error),
+    }
+}
@@ -0,0 +1,701 @@
+pub mod value;
+pub mod env;
+pub mod builtins;
+pub mod expr_eval;
+pub mod stmt_eval;
+pub mod pattern_eval;
+pub mod generator;
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use either::Either;
+use env::Environment;
+use generator::Generator;
+use value::{Function, Module, TypeDef, TypeKind, Value};
+
+use crate::{consteval::{ConstError, ConstEvaluator, ConstValue}, diagnostic::Diagnostic, ast::*, lexing::token::Token, workspace::{FileId, Workspace}};
+
+#[derive(Debug, Clone)]
+pub enum RuntimeError
+{
+    UndefinedName(Token),
+    UndefinedMember(Token, String),
+    TypeMismatch(Option<Token>, String),
+    NotCallable(Option<Token>, String),
+    ArityMismatch
+    {
+        token: Option<Token>,
+        expected: usize,
+        found: usize,
+    },
+    IndexOutOfBounds
+    {
+        token: Option<Token>,
+        index: i64,
+        length: usize,
+    },
+    DivisionByZero(Option<Token>),
+    PatternNotMatched(Option<Token>),
+    InvalidAssignment(Option<Token>),
+    LetElseDidNotDiverge(Token),
+    BreakOutsideLoop(Token),
+    ContinueOutsideLoop(Token),
+    NoMainFunction,
+    Native(Option<Token>, String),
+    /// A generator resumed by the code it is running, such as a `for` loop over itself inside its own body
+    GeneratorRunning(Option<Token>),
+    /// A `yield` where a generator cannot be suspended, found in a program that was not checked first
+    MisplacedYield(Token),
+    /// A cast from an `Int` to an enum that has no member with that discriminant
+    InvalidDiscriminant
+    {
+        token: Option<Token>,
+        type_name: String,
+        value: i64,
+    },
+    /// A constant that could not be evaluated, found when loading a program that was not checked first
+    Const(ConstError),
+    /// Calls nested deeper than `MAX_CALL_DEPTH`, most likely through recursion that never stops
+    StackOverflow(Option<Token>),
+}
+
+impl RuntimeError
+{
+    pub fn get_token(&self) -> Option<&Token>
+    {
+        match self
+        {
+            RuntimeError::UndefinedName(token) => Some(token),
+            RuntimeError::UndefinedMember(token, _) => Some(token),
+            RuntimeError::TypeMismatch(token, _) => token.as_ref(),
+            RuntimeError::NotCallable(token, _) => token.as_ref(),
+            RuntimeError::ArityMismatch { token, .. } => token.as_ref(),
+            RuntimeError::IndexOutOfBounds { token, .. } => token.as_ref(),
+            RuntimeError::DivisionByZero(token) => token.as_ref(),
+            RuntimeError::PatternNotMatched(token) => token.as_ref(),
+            RuntimeError::InvalidAssignment(token) => token.as_ref(),
+            RuntimeError::LetElseDidNotDiverge(token) => Some(token),
+            RuntimeError::BreakOutsideLoop(token) => Some(token),
+            RuntimeError::ContinueOutsideLoop(token) => Some(token),
+            RuntimeError::NoMainFunction => None,
+            RuntimeError::Native(token, _) => token.as_ref(),
+            RuntimeError::GeneratorRunning(token) => token.as_ref(),
+            RuntimeError::MisplacedYield(token) => Some(token),
+            RuntimeError::InvalidDiscriminant { token, .. } => token.as_ref(),
+            RuntimeError::Const(error) => Some(error.get_token()),
+            RuntimeError::StackOverflow(token) => token.as_ref(),
+        }
+    }
+}
+
+impl std::fmt::Display for RuntimeError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            RuntimeError::UndefinedName(token) => write!(f, "Undefined name `{}`", token_str(token)),
+            RuntimeError::UndefinedMember(token, type_name) => write!(f, "`{}` has no member `{}`", type_name, token_str(token)),
+            RuntimeError::TypeMismatch(_, message) => write!(f, "Type mismatch: {}", message),
+            RuntimeError::NotCallable(_, type_name) => write!(f, "A value of type `{}` is not callable", type_name),
+            RuntimeError::ArityMismatch { token: _, expected, found } => write!(f, "Expected {} argument(s) but found {}", expected, found),
+            RuntimeError::IndexOutOfBounds { token: _, index, length } => write!(f, "Index {} is out of bounds for an array of length {}", index, length),
+            RuntimeError::DivisionByZero(_) => write!(f, "Division by zero"),
+            RuntimeError::PatternNotMatched(_) => write!(f, "Value did not match any pattern"),
+            RuntimeError::InvalidAssignment(_) => write!(f, "Invalid assignment target"),
+            RuntimeError::LetElseDidNotDiverge(_) => write!(f, "The `else` block of a `let` statement must return, break or continue"),
+            RuntimeError::BreakOutsideLoop(_) => write!(f, "`break` outside of a loop"),
+            RuntimeError::ContinueOutsideLoop(_) => write!(f, "`continue` outside of a loop"),
+            RuntimeError::NoMainFunction => write!(f, "No `fn main` declaration was found"),
+            RuntimeError::Native(_, message) => write!(f, "{}", message),
+            RuntimeError::GeneratorRunning(_) => write!(f, "A generator cannot be resumed while it is running"),
+            RuntimeError::MisplacedYield(_) => write!(f, "`yield` cannot be used in the target of an assignment or outside a generator"),
+            RuntimeError::InvalidDiscriminant { token: _, type_name, value } => write!(f, "`{}` has no member with the discriminant {}", type_name, value),
+            RuntimeError::Const(error) => write!(f, "{}", error),
+            RuntimeError::StackOverflow(_) => write!(f, "Stack overflow: calls nest more than {} deep", MAX_CALL_DEPTH),
+        }
+    }
+}
+
+impl From<&RuntimeError> for Diagnostic
+{
+    fn from(error: &RuntimeError) -> Self
+    {
+        if let RuntimeError::Const(error) = error
+        {
+            return error.into();
+        }
+
+        let (code, label) = match error
+        {
+            RuntimeError::UndefinedName(_) => ("E0601", "not found"),
+            RuntimeError::UndefinedMember(_, _) => ("E0602", "no such member"),
+            RuntimeError::TypeMismatch(_, _) => ("E0603", "mismatched types"),
+            RuntimeError::NotCallable(_, _) => ("E0604", "not a function"),
+            RuntimeError::ArityMismatch { .. } => ("E0605", "wrong number of arguments"),
+            RuntimeError::IndexOutOfBounds { .. } => ("E0606", "index out of bounds"),
+            RuntimeError::DivisionByZero(_) => ("E0607", "division by zero"),
+            RuntimeError::PatternNotMatched(_) => ("E0608", "no pattern matched"),
+            RuntimeError::InvalidAssignment(_) => ("E0609", "cannot be assigned to"),
+            RuntimeError::LetElseDidNotDiverge(_) => ("E0610", "this block finished without leaving"),
+            RuntimeError::BreakOutsideLoop(_) => ("E0611", "cannot `break` here"),
+            RuntimeError::ContinueOutsideLoop(_) => ("E0612", "cannot `continue` here"),
+            RuntimeError::NoMainFunction => ("E0613", ""),
+            RuntimeError::Native(_, _) => ("E0614", ""),
+            RuntimeError::GeneratorRunning(_) => ("E0615", "already running"),
+            RuntimeError::MisplacedYield(_) => ("E0616", "cannot `yield` here"),
+            RuntimeError::InvalidDiscriminant { .. } => ("E0617", "no such member"),
+            RuntimeError::StackOverflow(_) => ("E0618", "the call that went too deep"),
+            RuntimeError::Const(_) => unreachable!("constants have their own diagnostics"),
+        };
+
+        let diagnostic = Diagnostic::error(error.to_string()).with_code(code);
+        match error.get_token()
+        {
+            Some(token) => diagnostic.with_primary(token.pos, label),
+            None => diagnostic,
+        }
+    }
+}
+
+/// Non-local control flow that unwinds through the evaluator until something handles it.
+#[derive(Debug)]
+pub enum Interrupt
+{
+    Break(Token),
+    Continue(Token),
+    Return(Value),
+    Error(RuntimeError),
+}
+
+impl From<RuntimeError> for Interrupt
+{
+    fn from(value: RuntimeError) -> Self
+    {
+        Interrupt::Error(value)
+    }
+}
+
+pub type EvalResult<T> = Result<T, Interrupt>;
+
+pub fn token_str(token: &Token) -> String
+{
+    match &token.value
+    {
+        Some(value) => value.to_string(),
+        None => format!("{:?}", token.token_type),
+    }
+}
+
+/// How deep calls may nest, so runaway recursion is reported instead of overflowing the stack. The driver runs
+/// programs on a thread with a stack large enough for this many calls.
+pub const MAX_CALL_DEPTH: usize = 10_000;
+
+pub struct Interpreter
+{
+    globals: Environment,
+    /// The file whose declarations are being loaded
+    module: FileId,
+    /// The file of the function a runtime error was raised in, once the error has left that function
+    error_module: Option<FileId>,
+    /// The command-line arguments passed on to `fn main`
+    args: Vec<String>,
+    /// How many function and lambda calls are running
+    depth: usize,
+}
+
+impl Default for Interpreter
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Interpreter
+{
+    pub fn new() -> Self
+    {
+        let globals = Environment::new();
+        builtins::define_builtins(&globals);
+        Self { globals, module: 0, error_module: None, args: vec![], depth: 0 }
+    }
+
+    /// Sets the arguments `fn main` receives if it declares a parameter for them
+    pub fn set_args(&mut self, args: Vec<String>)
+    {
+        self.args = args;
+    }
+
+    /// The arguments to call `main` with: none, or the command-line arguments as a `[]String`
+    fn main_args(&self, main: &Value) -> Vec<Value>
+    {
+        match main
+        {
+            Value::Function(function) if !function.decl.params.is_empty() =>
+            {
+                vec![Value::new_array(self.args.iter().cloned().map(Value::String).collect())]
+            },
+            _ => vec![],
+        }
+    }
+
+    pub fn globals(&self) -> &Environment
+    {
+        &self.globals
+    }
+
+    /// Registers every declaration of `program` and then calls its `fn main`.
+    pub fn run_program(&mut self, program: &Program) -> Result<Value, RuntimeError>
+    {
+        self.load_program(program)?;
+
+        let Some(main) = self.globals.get("main") else {
+            return Err(RuntimeError::NoMainFunction);
+        };
+
+        let args = self.main_args(&main);
+        match self.call_value(main, args, &program.eof)
+        {
+            Ok(value) => Ok(value),
+            Err(interrupt) => Err(self.interrupt_to_error(interrupt)),
+        }
+    }
+
+    /// Loads every file of a workspace into its own module, dependencies first, and then calls the entry file's `fn main`.
+    /// Errors are returned along with the file they occurred in.
+    pub fn run_workspace(&mut self, workspace: &Workspace) -> Result<Value, (FileId, RuntimeError)>
+    {
+        let mut modules: HashMap<FileId, Environment> = HashMap::new();
+        for &id in workspace.order()
+        {
+            let file = workspace.file(id);
+            let Some(program) = &file.program else { continue };
+
+            let env = self.globals.child();
+            for import in &file.imports
+            {
+                let source = &modules[&import.source];
+                let value = match &import.item
+                {
+                    Some(item) => source.get(item).unwrap_or(Value::Void),
+                    None => Value::Module(Rc::new(Module {
+                        name: import.name.clone(),
+                        env: source.clone(),
+                        exports: workspace.file(import.source).exports.iter().cloned().collect(),
+                    })),
+                };
+
+                env.define(&import.name, value);
+            }
+
+            self.module = id;
+            self.load_into(program, &env).map_err(|e| (self.error_module.take().unwrap_or(id), e))?;
+            modules.insert(id, env);
+        }
+
+        let entry = workspace.entry();
+        let (Some(main), Some(program)) = (modules.get(&entry).and_then(|env| env.get("main")), &workspace.file(entry).program) else {
+            return Err((entry, RuntimeError::NoMainFunction));
+        };
+
+        let args = self.main_args(&main);
+        self.call_value(main, args, &program.eof).map_err(|interrupt| {
+            let error = self.interrupt_to_error(interrupt);
+            (self.error_module.take().unwrap_or(entry), error)
+        })
+    }
+
+    /// Executes a statement directly in the global environment, as the REPL does with each line
+    pub fn execute_global(&mut self, statement: &Statement) -> Result<(), RuntimeError>
+    {
+        let globals = self.globals.clone();
+        match self.execute_statement(statement, &globals)
+        {
+            Ok(()) | Err(Interrupt::Return(_)) => Ok(()),
+            Err(interrupt) => Err(self.interrupt_to_error(interrupt)),
+        }
+    }
+
+    /// Evaluates an expression in the global environment
+    pub fn eval_global(&mut self, expression: &Expression) -> Result<Value, RuntimeError>
+    {
+        let globals = self.globals.clone();
+        match self.eval_expression(expression, &globals)
+        {
+            Ok(value) | Err(Interrupt::Return(value)) => Ok(value),
+            Err(interrupt) => Err(self.interrupt_to_error(interrupt)),
+        }
+    }
+
+    pub fn load_program(&mut self, program: &Program) -> Result<(), RuntimeError>
+    {
+        let globals = self.globals.clone();
+        self.load_into(program, &globals)
+    }
+
+    /// Registers the declarations of `program` in `globals`
+    fn load_into(&mut self, program: &Program, globals: &Environment) -> Result<(), RuntimeError>
+    {
+        let statements = program.declarations.iter().filter_map(|d| match d
+        {
+            Declaration::Fn(_, decl) => Some(Statement::FnDecl(decl.clone())),
+            Declaration::Struct(_, decl) => Some(Statement::StructDecl(decl.clone())),
+            Declaration::Interface(_, decl) => Some(Statement::InterfaceDecl(decl.clone())),
+            Declaration::Type(_, decl) => Some(Statement::TypeDecl(decl.clone())),
+            Declaration::Enum(_, _) | Declaration::Let(_, _) | Declaration::Const(_, _) | Declaration::Use(_, _) | Declaration::Mod(_, _) | Declaration::Impl(_) => None,
+        }).collect::<Vec<_>>();
+
+        // Types and functions are hoisted so declaration order at the top level does not matter. The discriminants of
+        // enums may use constants, so they are filled in once those are evaluated.
+        for declaration in &program.declarations
+        {
+            if let Declaration::Enum(_, decl) = declaration
+            {
+                let type_def = self.make_type(&decl.id, TypeKind::Enum(decl.clone().into()), globals);
+                globals.define(&type_def.name.clone(), Value::Type(type_def));
+            }
+        }
+
+        for statement in statements.iter().filter(|s| !matches!(s, Statement::TypeDecl(_)))
+        {
+            self.execute_statement(statement, globals).map_err(|i| self.interrupt_to_error(i))?;
+        }
+
+        for statement in statements.iter().filter(|s| matches!(s, Statement::TypeDecl(_)))
+        {
+            self.execute_statement(statement, globals).map_err(|i| self.interrupt_to_error(i))?;
+        }
+
+        // Constants only need the functions and types above, and statics may use them
+        let mut consts = ConstEvaluator::with_fallback(globals.clone());
+        consts.eval_program(program);
+        if let Some(error) = consts.errors().first()
+        {
+            return Err(RuntimeError::Const(error.clone()));
+        }
+
+        for declaration in &program.declarations
+        {
+            if let Declaration::Const(_, decl) = declaration
+            {
+                globals.define(&token_str(&decl.id), consts.value(&decl.id).cloned().map_or(Value::Void, Value::from));
+            }
+        }
+
+        for declaration in &program.declarations
+        {
+            let Declaration::Enum(_, decl) = declaration else { continue };
+            if let Some(Value::Type(type_def)) = globals.get(&token_str(&decl.id))
+            {
+                let mut discriminants = type_def.discriminants.borrow_mut();
+                for member in &decl.members
+                {
+                    if let Some(ConstValue::Int(value)) = consts.value(member.id())
+                    {
+                        discriminants.insert(token_str(member.id()), *value);
+                    }
+                }
+            }
+        }
+
+        for declaration in &program.declarations
+        {
+            if let Declaration::Impl(impl_stmt) = declaration
+            {
+                self.register_impl(impl_stmt, globals, &consts).map_err(|i| self.interrupt_to_error(i))?;
+            }
+        }
+
+        for declaration in &program.declarations
+        {
+            if let Declaration::Let(_, let_stmt) = declaration
+            {
+                self.execute_statement(&Statement::Let(let_stmt.clone()), globals).map_err(|i| self.interrupt_to_error(i))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn interrupt_to_error(&self, interrupt: Interrupt) -> RuntimeError
+    {
+        match interrupt
+        {
+            Interrupt::Break(token) => RuntimeError::BreakOutsideLoop(token),
+            Interrupt::Continue(token) => RuntimeError::ContinueOutsideLoop(token),
+            Interrupt::Return(_) => unreachable!("returns are handled by function calls"),
+            Interrupt::Error(error) => error,
+        }
+    }
+
+    fn register_impl(&mut self, impl_stmt: &ImplStmt, env: &Environment, consts: &ConstEvaluator) -> EvalResult<()>
+    {
+        let (target, interface) = match &impl_stmt.for_clause
+        {
+            Some((_, target)) => (target, Some(&impl_stmt.type_name)),
+            None => (&impl_stmt.type_name, None),
+        };
+
+        let Value::Type(type_def) = self.resolve_type_path(target, env)? else {
+            return Err(RuntimeError::TypeMismatch(Some(*target.get_token()), format!("`{}` is not a type", target.pretty_print())).into());
+        };
+
+        let impl_env = env.child();
+        impl_env.define("Self", Value::Type(type_def.clone()));
+
+        for (_, member) in &impl_stmt.members
+        {
+            match member
+            {
+                Statement::FnDecl(decl) =>
+                {
+                    let function = self.make_function(decl, &impl_env, Some(type_def.clone()));
+                    type_def.methods.borrow_mut().insert(function.name.clone(), function);
+                },
+                Statement::Let(_) | Statement::TypeDecl(_) =>
+                {
+                    let statics_env = impl_env.child();
+                    self.execute_statement(member, &statics_env)?;
+                    let mut statics = type_def.statics.borrow_mut();
+                    let names = match member
+                    {
+                        Statement::Let(let_stmt) => pattern_eval::let_binding_names(&let_stmt.binding),
+                        Statement::TypeDecl(type_decl) => vec![token_str(&type_decl.id)],
+                        _ => unreachable!(),
+                    };
+
+                    for name in names
+                    {
+                        if let Some(value) = statics_env.get(&name)
+                        {
+                            statics.insert(name, value);
+                        }
+                    }
+                },
+                Statement::Const(decl) =>
+                {
+                    let value = consts.value(&decl.id).cloned().map_or(Value::Void, Value::from);
+                    type_def.statics.borrow_mut().insert(token_str(&decl.id), value);
+                },
+                _ => {},
+            }
+        }
+
+        // Interface methods that have a default body are inherited when not overridden
+        if let Some(interface) = interface
+        {
+            if let Value::Type(interface_def) = self.resolve_type_path(interface, env)?
+            {
+                if let TypeKind::Interface(decl) = &interface_def.kind
+                {
+                    for member in &decl.members
+                    {
+                        let Statement::FnDecl(fn_decl) = member else { continue };
+                        if fn_decl.body.is_right() || type_def.get_method(&token_str(&fn_decl.id)).is_some()
+                        {
+                            continue;
+                        }
+
+                        let function = self.make_function(fn_decl, &impl_env, Some(type_def.clone()));
+                        type_def.methods.borrow_mut().insert(function.name.clone(), function);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn make_function(&self, decl: &FnDecl, env: &Environment, self_type: Option<Rc<TypeDef>>) -> Rc<Function>
+    {
+        Rc::new(Function {
+            name: token_str(&decl.id),
+            decl: Rc::new(decl.clone()),
+            closure: env.clone(),
+            self_type,
+            module: self.module,
+            generator: decl.is_generator(),
+        })
+    }
+
+    fn make_type(&self, name: &Token, kind: TypeKind, env: &Environment) -> Rc<TypeDef>
+    {
+        Rc::new(TypeDef {
+            name: token_str(name),
+            kind,
+            env: env.clone(),
+            methods: RefCell::new(HashMap::new()),
+            statics: RefCell::new(HashMap::new()),
+            discriminants: RefCell::new(HashMap::new()),
+        })
+    }
+
+    pub fn call_value(&mut self, callee: Value, args: Vec<Value>, token: &Token) -> EvalResult<Value>
+    {
+        match callee
+        {
+            Value::Function(function) => self.nested(token, |this| this.call_function(&function, None, args, token)),
+            Value::BoundMethod(receiver, function) => self.nested(token, |this| this.call_function(&function, Some(*receiver), args, token)),
+            Value::Lambda(lambda) => self.nested(token, |this|
+            {
+                let params: Vec<&Token> = match &lambda.expr.params
+                {
+                    LambdaParams::Simple(name) => vec![name],
+                    LambdaParams::Complex { parameters, .. } => parameters.iter().map(|p| &p.name).collect(),
+                };
+
+                if params.len() != args.len()
+                {
+                    return Err(RuntimeError::ArityMismatch { token: Some(*token), expected: params.len(), found: args.len() }.into());
+                }
+
+                let env = lambda.closure.child();
+                for (param, arg) in params.into_iter().zip(args)
+                {
+                    env.define(&token_str(param), arg);
+                }
+
+                match this.eval_expression(&lambda.expr.expression, &env)
+                {
+                    Ok(value) | Err(Interrupt::Return(value)) => Ok(value),
+                    Err(other) => Err(other),
+                }
+            }),
+            Value::Native(native) =>
+            {
+                if native.arity.is_some_and(|a| a != args.len())
+                {
+                    return Err(RuntimeError::ArityMismatch { token: Some(*token), expected: native.arity.unwrap(), found: args.len() }.into());
+                }
+
+                (native.func)(self, args, token)
+            },
+            Value::BoundNative(receiver, native) =>
+            {
+                if native.arity.is_some_and(|a| a != args.len())
+                {
+                    return Err(RuntimeError::ArityMismatch { token: Some(*token), expected: native.arity.unwrap(), found: args.len() }.into());
+                }
+
+                let mut args = args;
+                args.insert(0, *receiver);
+                (native.func)(self, args, token)
+            },
+            Value::VariantConstructor(type_def, variant) =>
+            {
+                if args.len() != 1
+                {
+                    return Err(RuntimeError::ArityMismatch { token: Some(*token), expected: 1, found: args.len() }.into());
+                }
+
+                Ok(Value::Enum(Rc::new(value::EnumValue {
+                    type_def,
+                    variant,
+                    payload: value::EnumPayload::Single(args.into_iter().next().unwrap()),
+                })))
+            },
+            other => Err(RuntimeError::NotCallable(Some(*token), other.type_name()).into()),
+        }
+    }
+
+    /// Runs a call one level deeper, failing instead if calls already nest `MAX_CALL_DEPTH` deep
+    fn nested(&mut self, token: &Token, call: impl FnOnce(&mut Self) -> EvalResult<Value>) -> EvalResult<Value>
+    {
+        if self.depth >= MAX_CALL_DEPTH
+        {
+            return Err(RuntimeError::StackOverflow(Some(*token)).into());
+        }
+
+        self.depth += 1;
+        let result = call(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Records that an error raised while running code from `module` belongs to that file
+    fn in_module(&mut self, interrupt: Interrupt, module: FileId) -> Interrupt
+    {
+        if let Interrupt::Error(_) = &interrupt
+        {
+            self.error_module.get_or_insert(module);
+        }
+
+        interrupt
+    }
+
+    fn call_function(&mut self, function: &Function, receiver: Option<Value>, args: Vec<Value>, token: &Token) -> EvalResult<Value>
+    {
+        let decl = function.decl.clone();
+        let Either::Left(body) = &decl.body else {
+            return Err(RuntimeError::Native(Some(*token), format!("Function `{}` has no body", function.name)).into());
+        };
+
+        let env = function.closure.child();
+        let mut args = args.into_iter();
+        let mut receiver = receiver;
+
+        let normal_count = decl.params.iter().filter(|p| matches!(p, FnParam::Normal { .. })).count();
+        let required_count = decl.params.iter().filter(|p| matches!(p, FnParam::Normal { default_value: None, .. })).count();
+        let mut arg_count = args.len();
+
+        // Methods called through their type (`Type.method(value, ...)`) take the receiver as the first argument
+        if receiver.is_none() && function.has_self()
+        {
+            receiver = args.next();
+            arg_count = arg_count.saturating_sub(1);
+        }
+
+        if arg_count < required_count || arg_count > normal_count
+        {
+            return Err(RuntimeError::ArityMismatch { token: Some(*token), expected: normal_count, found: arg_count }.into());
+        }
+
+        if let Some(self_type) = &function.self_type
+        {
+            env.define("Self", Value::Type(self_type.clone()));
+        }
+
+        for param in &decl.params
+        {
+            match param
+            {
+                FnParam::SelfParam { mut_tok: _, self_tok } =>
+                {
+                    let Some(receiver) = receiver.take() else {
+                        return Err(RuntimeError::ArityMismatch { token: Some(*self_tok), expected: normal_count + 1, found: arg_count }.into());
+                    };
+
+                    env.define("self", receiver);
+                },
+                FnParam::Normal { mut_tok: _, id, colon: _, type_name: _, default_value } =>
+                {
+                    let value = match args.next()
+                    {
+                        Some(value) => value,
+                        None => match default_value
+                        {
+                            Some((_, expression)) => self.eval_expression(expression, &env).map_err(|e| self.in_module(e, function.module))?,
+                            None => unreachable!("arity was checked above"),
+                        }
+                    };
+
+                    env.define(&token_str(id), value);
+                }
+            }
+        }
+
+        if function.generator
+        {
+            return Ok(Value::Generator(Rc::new(RefCell::new(Generator::new(function, env)))));
+        }
+
+        match self.eval_block(body, &env)
+        {
+            Ok(value) | Err(Interrupt::Return(value)) => Ok(value),
+            Err(Interrupt::Break(token)) => Err(self.in_module(RuntimeError::BreakOutsideLoop(token).into(), function.module)),
+            Err(Interrupt::Continue(token)) => Err(self.in_module(RuntimeError::ContinueOutsideLoop(token).into(), function.module)),
+            Err(error) => Err(self.in_module(error, function.module)),
+        }
+    }
+}
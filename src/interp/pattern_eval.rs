@@ -0,0 +1,199 @@
+use std::rc::Rc;
+
+use crate::ast::*;
+
+use super::{env::Environment, value::{EnumPayload, TypeKind, Value}, token_str, EvalResult, Interpreter};
+
+impl Interpreter
+{
+    /// Tests `value` against `pattern`, defining every binding the pattern introduces in `env`.
+    pub fn match_pattern(&mut self, pattern: &Pattern, value: &Value, env: &Environment) -> EvalResult<bool>
+    {
+        match pattern
+        {
+            Pattern::Literal(literal) =>
+            {
+                let literal = self.eval_pattern_literal(literal, env)?;
+                Ok(literal == *value)
+            },
+            Pattern::Wildcard(_) => Ok(true),
+            Pattern::Range { start, dots: _, end } =>
+            {
+                let start = self.eval_pattern_literal(start, env)?;
+                let end = self.eval_pattern_literal(end, env)?;
+                Ok(match (&start, value, &end)
+                {
+                    (Value::Int(a), Value::Int(v), Value::Int(b)) => a <= v && v <= b,
+                    (Value::Char(a), Value::Char(v), Value::Char(b)) => a <= v && v <= b,
+                    _ => false,
+                })
+            },
+            Pattern::Identifier { mut_tok: _, id } =>
+            {
+                env.define(&token_str(id), value.clone());
+                Ok(true)
+            },
+            Pattern::Binding { mut_tok: _, id, at: _, inner } =>
+            {
+                env.define(&token_str(id), value.clone());
+                self.match_pattern(inner, value, env)
+            },
+            Pattern::Grouping { open_paren: _, inner, close_paren: _ } => self.match_pattern(inner, value, env),
+            Pattern::Or(patterns) =>
+            {
+                for pattern in patterns
+                {
+                    // An alternative that fails part way must not leave its bindings behind
+                    let alternative_env = env.child();
+                    if self.match_pattern(pattern, value, &alternative_env)?
+                    {
+                        for name in pattern_names(pattern)
+                        {
+                            env.define(&name, alternative_env.get(&name).unwrap_or(Value::Void));
+                        }
+
+                        return Ok(true);
+                    }
+                }
+
+                Ok(false)
+            },
+            Pattern::TypeValue { type_name, dot: _, id } =>
+            {
+                let owner = self.resolve_type_path(type_name, env)?;
+                let expected = self.get_member(owner, id)?;
+                Ok(expected == *value)
+            },
+            Pattern::EnumConstruct { type_name, open_paren: _, inner, close_paren: _ } =>
+            {
+                let Value::VariantConstructor(type_def, variant) = self.resolve_type_path(type_name, env)? else {
+                    return Ok(false);
+                };
+
+                match value
+                {
+                    Value::Enum(e) if Rc::ptr_eq(&e.type_def, &type_def) && e.variant == variant => match &e.payload
+                    {
+                        EnumPayload::Single(payload) => self.match_pattern(inner, payload, env),
+                        _ => Ok(false),
+                    },
+                    _ => Ok(false),
+                }
+            },
+            Pattern::StructConstruct { type_name, open_brace: _, patterns, close_brace: _ } =>
+            {
+                let matches_type = match (self.resolve_type_path(type_name, env)?, value)
+                {
+                    (Value::Type(t), Value::Struct(s)) => matches!(t.kind, TypeKind::Struct(_)) && Rc::ptr_eq(&t, &s.type_def),
+                    (Value::VariantConstructor(t, variant), Value::Enum(e)) => Rc::ptr_eq(&t, &e.type_def) && e.variant == variant,
+                    _ => false,
+                };
+
+                if !matches_type
+                {
+                    return Ok(false);
+                }
+
+                for field in patterns
+                {
+                    let name = token_str(&field.id);
+                    let field_value = match value
+                    {
+                        Value::Struct(s) => s.get_field(&name),
+                        Value::Enum(e) => e.get_field(&name),
+                        _ => None,
+                    };
+
+                    let Some(field_value) = field_value else { return Ok(false) };
+                    match &field.inner
+                    {
+                        Some(inner) => if !self.match_pattern(inner, &field_value, env)? { return Ok(false) },
+                        None => env.define(&name, field_value),
+                    }
+                }
+
+                Ok(true)
+            },
+            Pattern::ArrayConstruct { open_bracket: _, patterns, close_bracket: _ } =>
+            {
+                let Value::Array(array) = value else { return Ok(false) };
+                let items = array.borrow().clone();
+                if items.len() != patterns.len()
+                {
+                    return Ok(false);
+                }
+
+                for (pattern, item) in patterns.iter().zip(items.iter())
+                {
+                    if !self.match_pattern(pattern, item, env)?
+                    {
+                        return Ok(false);
+                    }
+                }
+
+                Ok(true)
+            },
+        }
+    }
+
+    fn eval_pattern_literal(&mut self, literal: &PatternLiteral, env: &Environment) -> EvalResult<Value>
+    {
+        let value = self.eval_expression(&Expression::Literal(literal.token), env)?;
+        Ok(match (literal.minus, value)
+        {
+            (Some(_), Value::Int(i)) => Value::Int(i.wrapping_neg()),
+            (Some(_), Value::Float(f)) => Value::Float(-f),
+            (_, value) => value,
+        })
+    }
+
+    /// Tests `value` against a match branch's pattern and then its guard, if it has one
+    pub fn match_branch(&mut self, branch: &MatchBranch, value: &Value, env: &Environment) -> EvalResult<bool>
+    {
+        if !self.match_pattern(&branch.pattern, value, env)?
+        {
+            return Ok(false);
+        }
+
+        match &branch.guard
+        {
+            Some((_, guard)) =>
+            {
+                let condition = self.eval_expression(guard, env)?;
+                self.expect_bool(&condition, guard.get_token())
+            },
+            None => Ok(true),
+        }
+    }
+}
+
+/// The names a pattern binds when it matches
+pub fn pattern_names(pattern: &Pattern) -> Vec<String>
+{
+    match pattern
+    {
+        Pattern::Literal(_) | Pattern::Wildcard(_) | Pattern::Range { .. } => vec![],
+        Pattern::Identifier { mut_tok: _, id } => vec![token_str(id)],
+        Pattern::Binding { mut_tok: _, id, at: _, inner } => std::iter::once(token_str(id)).chain(pattern_names(inner)).collect(),
+        // Every alternative binds the same names
+        Pattern::Or(patterns) => patterns.first().map_or(vec![], pattern_names),
+        Pattern::Grouping { inner, .. } => pattern_names(inner),
+        Pattern::TypeValue { .. } => vec![],
+        Pattern::EnumConstruct { inner, .. } => pattern_names(inner),
+        Pattern::StructConstruct { patterns, .. } => patterns.iter().flat_map(|f| match &f.inner
+        {
+            Some(inner) => pattern_names(inner),
+            None => vec![token_str(&f.id)],
+        }).collect(),
+        Pattern::ArrayConstruct { patterns, .. } => patterns.iter().flat_map(pattern_names).collect(),
+    }
+}
+
+pub fn let_binding_names(binding: &LetBinding) -> Vec<String>
+{
+    match binding
+    {
+        LetBinding::Variable { mut_tok: _, id } => vec![token_str(id)],
+        LetBinding::Pattern(pattern) => pattern_names(pattern),
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{ast::*, consteval, lexing::token::{Token, TokenType}};
+
+use super::{env::Environment, value::{TypeKind, Value}, token_str, EvalResult, Interpreter, Interrupt, RuntimeError};
+
+impl Interpreter
+{
+    pub fn execute_statement(&mut self, statement: &Statement, env: &Environment) -> EvalResult<()>
+    {
+        match statement
+        {
+            Statement::While(while_stmt) => self.execute_while(while_stmt, env),
+            Statement::For(for_stmt) => self.execute_for(for_stmt, env),
+            Statement::Return(return_stmt) =>
+            {
+                let value = match &return_stmt.expression
+                {
+                    Some(expression) => self.eval_expression(expression, env)?,
+                    None => Value::Void,
+                };
+
+                Err(Interrupt::Return(value))
+            },
+            Statement::Continue(continue_stmt) => Err(Interrupt::Continue(continue_stmt.continue_tok)),
+            Statement::Break(break_stmt) => Err(Interrupt::Break(break_stmt.break_tok)),
+            Statement::TypeDecl(type_decl) =>
+            {
+                let value = self.resolve_type_path(&type_decl.type_name, env)?;
+                env.define(&token_str(&type_decl.id), value);
+                Ok(())
+            },
+            Statement::EnumDecl(decl) =>
+            {
+                let type_def = self.make_type(&decl.id, TypeKind::Enum(decl.clone().into()), env);
+                let discriminants = consteval::discriminants(&mut env.clone(), decl).map_err(RuntimeError::Const)?;
+                type_def.discriminants.borrow_mut().extend(decl.members.iter().map(|m| token_str(m.id())).zip(discriminants));
+                env.define(&type_def.name.clone(), Value::Type(type_def));
+                Ok(())
+            },
+            Statement::InterfaceDecl(decl) =>
+            {
+                let type_def = self.make_type(&decl.id, TypeKind::Interface(decl.clone().into()), env);
+                env.define(&type_def.name.clone(), Value::Type(type_def));
+                Ok(())
+            },
+            Statement::StructDecl(decl) =>
+            {
+                let type_def = self.make_type(&decl.id, TypeKind::Struct(decl.clone().into()), env);
+                env.define(&type_def.name.clone(), Value::Type(type_def));
+                Ok(())
+            },
+            Statement::FnDecl(decl) =>
+            {
+                let function = self.make_function(decl, env, None);
+                env.define(&function.name.clone(), Value::Function(function));
+                Ok(())
+            },
+            Statement::Let(let_stmt) => self.execute_let(let_stmt, env),
+            Statement::Const(decl) =>
+            {
+                let value = consteval::eval_expression(&mut env.clone(), &decl.expression).map_err(RuntimeError::Const)?;
+                env.define(&token_str(&decl.id), value.into());
+                Ok(())
+            },
+            Statement::Assign(assign) => self.execute_assign(assign, env),
+            Statement::If(if_expr) => self.eval_if(if_expr, env).map(|_| ()),
+            Statement::Match(match_expr) => self.eval_match(match_expr, env).map(|_| ()),
+            Statement::Block(block) => self.eval_block(block, &env.child()).map(|_| ()),
+            Statement::Expression(expression_stmt) => self.eval_expression(&expression_stmt.expression, env).map(|_| ()),
+            Statement::Use(_) => Ok(()),
+        }
+    }
+
+    fn execute_let(&mut self, let_stmt: &LetStmt, env: &Environment) -> EvalResult<()>
+    {
+        let value = self.eval_expression(&let_stmt.expression, env)?;
+        if self.bind_let(let_stmt, value, env)?
+        {
+            return Ok(());
+        }
+
+        match &let_stmt.else_clause
+        {
+            Some((else_tok, block)) =>
+            {
+                self.eval_block(block, &env.child())?;
+                Err(RuntimeError::LetElseDidNotDiverge(*else_tok).into())
+            },
+            None => Err(RuntimeError::PatternNotMatched(Some(let_stmt.let_tok)).into()),
+        }
+    }
+
+    /// Binds the value of a `let` to its names, returning whether its pattern matched
+    pub fn bind_let(&mut self, let_stmt: &LetStmt, value: Value, env: &Environment) -> EvalResult<bool>
+    {
+        match &let_stmt.binding
+        {
+            LetBinding::Variable { mut_tok: _, id } =>
+            {
+                env.define(&token_str(id), value);
+                Ok(true)
+            },
+            LetBinding::Pattern(pattern) =>
+            {
+                // Bind into a scratch scope first so a failed match leaves no partial bindings behind
+                let pattern_env = env.child();
+                if self.match_pattern(pattern, &value, &pattern_env)?
+                {
+                    for name in super::pattern_eval::pattern_names(pattern)
+                    {
+                        if let Some(value) = pattern_env.get(&name)
+                        {
+                            env.define(&name, value);
+                        }
+                    }
+
+                    return Ok(true);
+                }
+
+                Ok(false)
+            }
+        }
+    }
+
+    fn execute_assign(&mut self, assign: &AssignStmt, env: &Environment) -> EvalResult<()>
+    {
+        let value = self.eval_expression(&assign.expression, env)?;
+        self.assign_value(assign, value, env)
+    }
+
+    /// Stores the value of the right hand side of an assignment, combining it with the current value for `+=` and
+    /// the like
+    pub fn assign_value(&mut self, assign: &AssignStmt, mut value: Value, env: &Environment) -> EvalResult<()>
+    {
+        if let Some(op) = compound_operator(assign.equal.token_type)
+        {
+            let current = self.eval_expression(&assign.value, env)?;
+            value = self.apply_binary_op(op, current, value, &assign.equal)?;
+        }
+
+        self.assign_to(&assign.value, value, env, &assign.equal)
+    }
+
+    fn assign_to(&mut self, target: &Expression, value: Value, env: &Environment, equal: &Token) -> EvalResult<()>
+    {
+        match target
+        {
+            Expression::Literal(token) | Expression::Identifier(token) if token.token_type == TokenType::Identifier =>
+            {
+                let name = token_str(token);
+                if env.assign(&name, value.clone())
+                {
+                    return Ok(());
+                }
+
+                if let Some(Value::Struct(s)) = env.get("self")
+                {
+                    if s.set_field(&name, value)
+                    {
+                        return Ok(());
+                    }
+                }
+
+                Err(RuntimeError::UndefinedName(*token).into())
+            },
+            Expression::Access(access) =>
+            {
+                let owner = self.eval_expression(&access.expression, env)?;
+                let name = token_str(&access.identifier);
+                let assigned = match &owner
+                {
+                    Value::Struct(s) => s.set_field(&name, value),
+                    _ => false,
+                };
+
+                if assigned
+                {
+                    Ok(())
+                }
+                else
+                {
+                    Err(RuntimeError::UndefinedMember(access.identifier, owner.type_name()).into())
+                }
+            },
+            Expression::Index(index) =>
+            {
+                let owner = self.eval_expression(&index.expression, env)?;
+                let indexer = self.eval_expression(&index.indexer, env)?;
+                match (&owner, &indexer)
+                {
+                    (Value::Array(array), Value::Int(i)) =>
+                    {
+                        let length = array.borrow().len();
+                        let i = self.check_index(*i, length, &index.open_bracket)?;
+                        array.borrow_mut()[i] = value;
+                        Ok(())
+                    },
+                    _ => Err(RuntimeError::TypeMismatch(
+                        Some(index.open_bracket),
+                        format!("cannot assign to an index of `{}`", owner.type_name())
+                    ).into()),
+                }
+            },
+            Expression::Grouping(grouping) => self.assign_to(&grouping.expression, value, env, equal),
+            _ => Err(RuntimeError::InvalidAssignment(Some(*equal)).into()),
+        }
+    }
+
+    fn execute_while(&mut self, while_stmt: &WhileStmt, env: &Environment) -> EvalResult<()>
+    {
+        loop
+        {
+            let loop_env = env.child();
+            if !self.eval_let_condition(&while_stmt.condition, &loop_env)?
+            {
+                return Ok(());
+            }
+
+            match self.eval_block(&while_stmt.body, &loop_env)
+            {
+                Ok(_) | Err(Interrupt::Continue(_)) => {},
+                Err(Interrupt::Break(_)) => return Ok(()),
+                Err(other) => return Err(other),
+            }
+        }
+    }
+
+    fn execute_for(&mut self, for_stmt: &ForStmt, env: &Environment) -> EvalResult<()>
+    {
+        let iterable = self.eval_expression(&for_stmt.expression, env)?;
+        let mut sequence = self.sequence(iterable, &for_stmt.in_tok)?;
+
+        while let Some(item) = self.next_item(&mut sequence, &for_stmt.for_tok)?
+        {
+            let loop_env = env.child();
+            if !self.match_pattern(&for_stmt.pattern, &item, &loop_env)?
+            {
+                return Err(RuntimeError::PatternNotMatched(Some(for_stmt.for_tok)).into());
+            }
+
+            match self.eval_block(&for_stmt.body, &loop_env)
+            {
+                Ok(_) | Err(Interrupt::Continue(_)) => {},
+                Err(Interrupt::Break(_)) => return Ok(()),
+                Err(other) => return Err(other),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn compound_operator(token_type: TokenType) -> Option<TokenType>
+{
+    match token_type
+    {
+        TokenType::PlusEqual => Some(TokenType::Plus),
+        TokenType::MinusEqual => Some(TokenType::Minus),
+        TokenType::MultiplyEqual => Some(TokenType::Multiply),
+        TokenType::DivideEqual => Some(TokenType::Divide),
+        TokenType::ModulusEqual => Some(TokenType::Modulus),
+        TokenType::AndEqual => Some(TokenType::AndAnd),
+        TokenType::OrEqual => Some(TokenType::PipePipe),
+        _ => None,
+    }
+}
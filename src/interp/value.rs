@@ -0,0 +1,345 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use itertools::Itertools;
+
+use crate::{consteval::ConstValue, ast::{EnumDecl, EnumMember, FnDecl, InterfaceDecl, LambdaExpr, StructDecl}, lexing::token::Token};
+
+use super::{env::Environment, generator::Generator, EvalResult, Interpreter};
+
+pub type NativeFnPtr = fn(&mut Interpreter, Vec<Value>, &Token) -> EvalResult<Value>;
+
+#[derive(Debug, Clone)]
+pub enum Value
+{
+    Void,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Char(char),
+    Array(Rc<RefCell<Vec<Value>>>),
+    Struct(Rc<StructValue>),
+    Enum(Rc<EnumValue>),
+    Function(Rc<Function>),
+    Lambda(Rc<Lambda>),
+    BoundMethod(Box<Value>, Rc<Function>),
+    Native(Rc<NativeFn>),
+    BoundNative(Box<Value>, Rc<NativeFn>),
+    Type(Rc<TypeDef>),
+    VariantConstructor(Rc<TypeDef>, String),
+    Module(Rc<Module>),
+    Generator(Rc<RefCell<Generator>>),
+}
+
+impl Value
+{
+    pub fn type_name(&self) -> String
+    {
+        match self
+        {
+            Value::Void => "Void".into(),
+            Value::Int(_) => "Int".into(),
+            Value::Float(_) => "Float".into(),
+            Value::Bool(_) => "Bool".into(),
+            Value::String(_) => "String".into(),
+            Value::Char(_) => "Char".into(),
+            Value::Array(_) => "Array".into(),
+            Value::Struct(s) => s.type_def.name.clone(),
+            Value::Enum(e) => e.type_def.name.clone(),
+            Value::Function(_) | Value::Lambda(_) | Value::BoundMethod(_, _) | Value::Native(_) | Value::BoundNative(_, _) => "Function".into(),
+            Value::Type(_) => "Type".into(),
+            Value::VariantConstructor(_, _) => "Function".into(),
+            Value::Module(_) => "Module".into(),
+            Value::Generator(_) => "Iter".into(),
+        }
+    }
+
+    pub fn is_truthy(&self) -> Option<bool>
+    {
+        match self
+        {
+            Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn new_array(values: Vec<Value>) -> Self
+    {
+        Value::Array(Rc::new(RefCell::new(values)))
+    }
+
+    /// The value as a constant, or `None` if it is of a kind constants cannot hold
+    pub fn to_const(&self) -> Option<ConstValue>
+    {
+        match self
+        {
+            Value::Int(i) => Some(ConstValue::Int(*i)),
+            Value::Float(f) => Some(ConstValue::Float(*f)),
+            Value::Bool(b) => Some(ConstValue::Bool(*b)),
+            Value::Char(c) => Some(ConstValue::Char(*c)),
+            Value::String(s) => Some(ConstValue::String(s.clone())),
+            Value::Array(values) => values.borrow().iter().map(Value::to_const).collect::<Option<_>>().map(ConstValue::Array),
+            _ => None,
+        }
+    }
+}
+
+impl From<ConstValue> for Value
+{
+    fn from(value: ConstValue) -> Self
+    {
+        match value
+        {
+            ConstValue::Int(i) => Value::Int(i),
+            ConstValue::Float(f) => Value::Float(f),
+            ConstValue::Bool(b) => Value::Bool(b),
+            ConstValue::Char(c) => Value::Char(c),
+            ConstValue::String(s) => Value::String(s),
+            ConstValue::Array(values) => Value::new_array(values.into_iter().map(Value::from).collect()),
+        }
+    }
+}
+
+impl PartialEq for Value
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        match (self, other)
+        {
+            (Value::Void, Value::Void) => true,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Int(a), Value::Float(b)) | (Value::Float(b), Value::Int(a)) => (*a as f64) == *b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Char(a), Value::Char(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+            (Value::Struct(a), Value::Struct(b)) => Rc::ptr_eq(a, b) || (Rc::ptr_eq(&a.type_def, &b.type_def) && *a.fields.borrow() == *b.fields.borrow()),
+            (Value::Enum(a), Value::Enum(b)) => Rc::ptr_eq(&a.type_def, &b.type_def) && a.variant == b.variant && a.payload == b.payload,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::Lambda(a), Value::Lambda(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a), Value::Native(b)) => Rc::ptr_eq(a, b),
+            (Value::Type(a), Value::Type(b)) => Rc::ptr_eq(a, b),
+            (Value::Module(a), Value::Module(b)) => Rc::ptr_eq(a, b),
+            (Value::Generator(a), Value::Generator(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Value
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            Value::Void => write!(f, "void"),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(n) => write!(f, "{:?}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Char(c) => write!(f, "{}", c),
+            Value::Array(values) => write!(f, "[{}]", values.borrow().iter().map(|v| v.to_string()).join(", ")),
+            Value::Struct(s) =>
+            {
+                let fields = s.fields.borrow();
+                if fields.is_empty()
+                {
+                    write!(f, "{} {{}}", s.type_def.name)
+                }
+                else
+                {
+                    write!(f, "{} {{ {} }}", s.type_def.name, fields.iter().map(|(n, v)| format!("{}: {}", n, v)).join(", "))
+                }
+            },
+            Value::Enum(e) => match &e.payload
+            {
+                EnumPayload::None => write!(f, "{}.{}", e.type_def.name, e.variant),
+                EnumPayload::Single(v) => write!(f, "{}.{}({})", e.type_def.name, e.variant, v),
+                EnumPayload::Struct(fields) => write!(f, "{}.{} {{ {} }}", e.type_def.name, e.variant, fields.borrow().iter().map(|(n, v)| format!("{}: {}", n, v)).join(", ")),
+            },
+            Value::Function(func) => write!(f, "<fn {}>", func.name),
+            Value::Lambda(_) => write!(f, "<lambda>"),
+            Value::BoundMethod(_, func) => write!(f, "<method {}>", func.name),
+            Value::Native(native) => write!(f, "<native fn {}>", native.name),
+            Value::BoundNative(_, native) => write!(f, "<native method {}>", native.name),
+            Value::Type(t) => write!(f, "<type {}>", t.name),
+            Value::VariantConstructor(t, variant) => write!(f, "<constructor {}.{}>", t.name, variant),
+            Value::Module(module) => write!(f, "<module {}>", module.name),
+            Value::Generator(generator) => write!(f, "<iter {}>", generator.borrow().name),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct StructValue
+{
+    pub type_def: Rc<TypeDef>,
+    pub fields: RefCell<Vec<(String, Value)>>,
+}
+
+impl StructValue
+{
+    pub fn get_field(&self, name: &str) -> Option<Value>
+    {
+        self.fields.borrow().iter().find(|(n, _)| n == name).map(|(_, v)| v.clone())
+    }
+
+    pub fn set_field(&self, name: &str, value: Value) -> bool
+    {
+        let mut fields = self.fields.borrow_mut();
+        match fields.iter_mut().find(|(n, _)| n == name)
+        {
+            Some(field) => { field.1 = value; true },
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnumPayload
+{
+    None,
+    Single(Value),
+    Struct(RefCell<Vec<(String, Value)>>),
+}
+
+#[derive(Debug)]
+pub struct EnumValue
+{
+    pub type_def: Rc<TypeDef>,
+    pub variant: String,
+    pub payload: EnumPayload,
+}
+
+impl EnumValue
+{
+    pub fn get_field(&self, name: &str) -> Option<Value>
+    {
+        match &self.payload
+        {
+            EnumPayload::Struct(fields) => fields.borrow().iter().find(|(n, _)| n == name).map(|(_, v)| v.clone()),
+            _ => None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Function
+{
+    pub name: String,
+    pub decl: Rc<FnDecl>,
+    pub closure: Environment,
+    pub self_type: Option<Rc<TypeDef>>,
+    /// The file the function was declared in
+    pub module: usize,
+    /// Whether the body yields, so that calling the function creates a generator instead of running it
+    pub generator: bool,
+}
+
+impl Function
+{
+    pub fn has_self(&self) -> bool
+    {
+        self.decl.params.iter().any(|p| matches!(p, crate::ast::FnParam::SelfParam { .. }))
+    }
+}
+
+#[derive(Debug)]
+pub struct Lambda
+{
+    pub expr: Rc<LambdaExpr>,
+    pub closure: Environment,
+}
+
+pub struct NativeFn
+{
+    pub name: &'static str,
+    pub arity: Option<usize>,
+    pub func: NativeFnPtr,
+}
+
+impl std::fmt::Debug for NativeFn
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "NativeFn({})", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub enum TypeKind
+{
+    Struct(Rc<StructDecl>),
+    Enum(Rc<EnumDecl>),
+    Interface(Rc<InterfaceDecl>),
+    Native,
+}
+
+#[derive(Debug)]
+pub struct TypeDef
+{
+    pub name: String,
+    pub kind: TypeKind,
+    pub env: Environment,
+    pub methods: RefCell<HashMap<String, Rc<Function>>>,
+    pub statics: RefCell<HashMap<String, Value>>,
+    /// The discriminant of each member of an enum, by name
+    pub discriminants: RefCell<HashMap<String, i64>>,
+}
+
+impl TypeDef
+{
+    pub fn get_method(&self, name: &str) -> Option<Rc<Function>>
+    {
+        self.methods.borrow().get(name).cloned()
+    }
+
+    /// The basic member of an enum whose discriminant is `value`, as a value of the enum
+    pub fn from_discriminant(self: &Rc<Self>, value: i64) -> Option<Value>
+    {
+        let discriminants = self.discriminants.borrow();
+        let (name, _) = discriminants.iter().find(|(_, d)| **d == value)?;
+        match self.get_variant(name)?
+        {
+            EnumMember::Basic { .. } => Some(Value::Enum(Rc::new(EnumValue { type_def: self.clone(), variant: name.clone(), payload: EnumPayload::None }))),
+            _ => None,
+        }
+    }
+
+    pub fn get_variant(&self, name: &str) -> Option<&EnumMember>
+    {
+        let TypeKind::Enum(decl) = &self.kind else { return None };
+        decl.members.iter().find(|m| {
+            let id = match m
+            {
+                EnumMember::Basic { id, .. } => id,
+                EnumMember::Single { id, .. } => id,
+                EnumMember::Struct { id, .. } => id,
+            };
+
+            id.value.as_ref().is_some_and(|v| v.to_string() == name)
+        })
+    }
+}
+
+/// A module bound by `use`, whose exported names are accessed as members
+#[derive(Debug)]
+pub struct Module
+{
+    pub name: String,
+    pub env: Environment,
+    pub exports: Vec<String>,
+}
+
+impl Module
+{
+    pub fn get(&self, name: &str) -> Option<Value>
+    {
+        match self.exports.iter().any(|e| e == name)
+        {
+            true => self.env.get(name),
+            false => None,
+        }
+    }
+}
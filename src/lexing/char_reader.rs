@@ -1,54 +1,62 @@
-pub struct CharReader
+/// Reads the characters of a text one at a time, straight from the string. Positions are kept both as character
+/// indexes, which is what tokens hold, and as byte offsets, so what has been read can be sliced out without copying.
+pub struct CharReader<'src>
 {
-    chars: Vec<char>,
+    text: &'src str,
+    byte: usize,
     index: usize,
 }
 
-impl CharReader
+impl<'src> CharReader<'src>
 {
-    pub fn chars(&self) -> &[char]
+    pub fn text(&self) -> &'src str
     {
-        &self.chars
+        self.text
     }
 
+    /// The index of the current character, counted in characters
     pub fn index(&self) -> usize
     {
         self.index
     }
 
-    pub fn new(text: &str) -> Option<Self>
+    /// The byte offset of the current character
+    pub fn byte(&self) -> usize
     {
-        if text.len() == 0 { return None; }
+        self.byte
+    }
 
-        Some(Self 
-        {
-            chars: text.chars().collect(),
-            index: 0,
-        })
+    pub fn new(text: &'src str) -> Self
+    {
+        Self { text, byte: 0, index: 0 }
+    }
+
+    /// Reads on from part way through a text, from a character index and the byte offset it is at
+    pub fn starting_at(text: &'src str, byte: usize, index: usize) -> Self
+    {
+        Self { text, byte, index }
+    }
+
+    /// The text that has not been read yet
+    pub fn rest(&self) -> &'src str
+    {
+        &self.text[self.byte..]
+    }
+
+    /// The text read since a byte offset
+    pub fn since(&self, byte: usize) -> &'src str
+    {
+        &self.text[byte..self.byte]
     }
 
     pub fn peek(&self, count: usize) -> Option<char>
     {
-        if self.index + count < self.chars.len()
-        {
-            Some(self.chars[self.index + count])
-        }
-        else 
-        {
-            None    
-        }
+        self.rest().chars().nth(count)
     }
 
     pub fn current(&self) -> Option<char> 
     {
-        if self.index < self.chars.len()
-        {
-            Some(self.chars[self.index])
-        }
-        else 
-        {
-            None    
-        }
+        self.rest().chars().next()
     }
 
     pub fn current_is(&self, chars: &[char]) -> bool
@@ -61,6 +69,7 @@ impl CharReader
         if !self.at_end()
         {
             let c = self.current().unwrap();
+            self.byte += c.len_utf8();
             self.index += 1;
             Some(c)
         }
@@ -72,7 +81,7 @@ impl CharReader
 
     pub fn at_end(&self) -> bool 
     {
-        self.index >= self.chars.len()
+        self.byte >= self.text.len()
     }
 
     pub fn check(&mut self, cs: &[char]) -> Option<char> 
@@ -89,17 +98,16 @@ impl CharReader
         }
     }
 
-    pub fn check_many(&mut self, cs: &str) -> Option<String>
+    /// Reads past `cs` if the text goes on with it
+    pub fn check_many(&mut self, cs: &str) -> Option<&'src str>
     {
-        let all_match = cs.chars().enumerate().map(|(i, c)| self.peek(i) == Some(c)).all(|b| b);
-        if all_match
+        if self.rest().starts_with(cs)
         {
-            for _ in 0..cs.len()
-            {
-                self.advance();
-            }
+            let begin = self.byte;
+            self.byte += cs.len();
+            self.index += cs.chars().count();
             
-            Some(cs.to_owned())
+            Some(self.since(begin))
         }
         else 
         {
@@ -0,0 +1,200 @@
+use std::ops::Range;
+
+use super::char_reader::CharReader;
+use super::token::{Token, TokenPos, TokenType};
+use super::{Lexer, LexerError, LexerResult};
+use crate::source::SourceFile;
+
+/// A change to a text, replacing the characters in `range` with `text`
+#[derive(Debug, Clone)]
+pub struct TextEdit
+{
+    /// The character indexes replaced, which is empty for an insertion
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit
+{
+    pub fn new(range: Range<usize>, text: impl Into<String>) -> Self
+    {
+        Self { range, text: text.into() }
+    }
+
+    /// How far the edit moves the text after it, in characters
+    pub fn delta(&self) -> isize
+    {
+        self.text.chars().count() as isize - self.range.len() as isize
+    }
+}
+
+/// The part of a lexed text that relexing replaced, everything else is as it was, moved by `delta` after the edit
+#[derive(Debug, Clone)]
+pub struct Relexed
+{
+    /// The replaced tokens, as indexes into the tokens before the edit
+    pub old_tokens: Range<usize>,
+    /// The tokens that replaced them, as indexes into the tokens after the edit
+    pub new_tokens: Range<usize>,
+    /// The characters read again, as indexes into the text before the edit
+    pub old_text: Range<usize>,
+    pub delta: isize,
+}
+
+/// How many interpolations are open after a token, given how many were open before it
+fn depth_after(depth: usize, token: &Token) -> usize
+{
+    match token.token_type
+    {
+        TokenType::InterpolationStart => depth + 1,
+        TokenType::InterpolationEnd => depth.saturating_sub(1),
+        _ => depth,
+    }
+}
+
+fn error_pos(error: &LexerError) -> TokenPos
+{
+    match error
+    {
+        LexerError::UnknownToken { token: _, index }
+        | LexerError::UnterminatedString { index }
+        | LexerError::UnterminatedComment { index } => (*index).into(),
+        LexerError::InvalidEscape { sequence: _, pos }
+        | LexerError::NumberOutOfRange { type_name: _, pos }
+        | LexerError::InvalidNumber { reason: _, pos }
+        | LexerError::InvalidChar { pos } => *pos,
+    }
+}
+
+fn shift_error(error: &mut LexerError, delta: isize)
+{
+    match error
+    {
+        LexerError::UnknownToken { token: _, index }
+        | LexerError::UnterminatedString { index }
+        | LexerError::UnterminatedComment { index } => *index = index.wrapping_add_signed(delta),
+        LexerError::InvalidEscape { sequence: _, pos }
+        | LexerError::NumberOutOfRange { type_name: _, pos }
+        | LexerError::InvalidNumber { reason: _, pos }
+        | LexerError::InvalidChar { pos } => *pos = pos.shifted(delta),
+    }
+}
+
+impl LexerResult
+{
+    /// Lexes again only the tokens an edit can change, given the text with the edit applied. Lexing restarts after the token
+    /// before the first one touching the edit, since a token can look a character or two past its end, and outside
+    /// any interpolation. It stops at the first token past the edit that matches an old one, with no interpolation
+    /// open on either side, from where the old tokens are kept as they were.
+    pub fn relex(&mut self, edit: &TextEdit, source: &SourceFile) -> Relexed
+    {
+        let delta = edit.delta();
+        let edit_begin = edit.range.start.min(source.char_count().wrapping_add_signed(-delta));
+
+        let touching = self.tokens.partition_point(|t| t.pos.end + 1 < edit_begin);
+        let mut first = 0;
+        let mut depth = 0;
+        for (index, token) in self.tokens[..touching.saturating_sub(1)].iter().enumerate()
+        {
+            if depth == 0
+            {
+                first = index;
+            }
+
+            depth = depth_after(depth, token);
+        }
+
+        if depth == 0
+        {
+            first = touching.saturating_sub(1);
+        }
+
+        let restart = match first
+        {
+            0 => 0,
+            _ => self.tokens[first - 1].pos.end + 1,
+        };
+
+        // Lexing never looks back, so it reads straight on from the restart
+        let inserted_end = edit_begin + edit.text.chars().count();
+        let mut lexer = Lexer::starting_at(CharReader::starting_at(source.text(), source.byte_offset(restart), restart));
+        let (mut tokens, mut errors) = (vec![], vec![]);
+        let mut old = first;
+        let mut old_depth = 0;
+        let mut synced = None;
+        while let Some(lexeme) = lexer.next()
+        {
+            let token: Token = match lexeme
+            {
+                Ok(ok) => ok.into(),
+                Err(err) =>
+                {
+                    errors.push(err);
+                    continue;
+                },
+            };
+
+            tokens.push(token);
+            let token = tokens.last().unwrap();
+            if token.pos.begin < inserted_end || lexer.in_interpolation()
+            {
+                continue;
+            }
+
+            let old_begin = token.pos.begin.wrapping_add_signed(-delta);
+            while self.tokens[old].pos.begin < old_begin && self.tokens[old].token_type != TokenType::EOF
+            {
+                old_depth = depth_after(old_depth, &self.tokens[old]);
+                old += 1;
+            }
+
+            let candidate = &self.tokens[old];
+            if candidate.pos.shifted(delta) == token.pos
+                && candidate.token_type == token.token_type
+                && candidate.value == token.value
+                && depth_after(old_depth, candidate) == 0
+            {
+                synced = Some(old);
+                break;
+            }
+        }
+
+        let (old_tokens, old_end) = match synced
+        {
+            Some(index) => (first..index + 1, self.tokens[index].pos.end + 1),
+            None => (first..self.tokens.len(), self.tokens.last().map_or(0, |t| t.pos.end + 1)),
+        };
+
+        let new_tokens = first..first + tokens.len();
+        let tail = self.tokens.split_off(old_tokens.end);
+        self.tokens.truncate(first);
+        self.tokens.extend(tokens);
+        self.tokens.extend(tail.into_iter().map(|t| Token { pos: t.pos.shifted(delta), ..t }));
+
+        let comments_before = self.comments.partition_point(|c| c.pos.begin < restart);
+        let comments_after = self.comments.partition_point(|c| c.pos.begin < old_end);
+        let tail = self.comments.split_off(comments_after);
+        self.comments.truncate(comments_before);
+        self.comments.extend(lexer.take_comments());
+        self.comments.extend(tail.into_iter().map(|mut c| { c.pos = c.pos.shifted(delta); c }));
+
+        let mut kept = std::mem::take(&mut self.errors);
+        let mut after = vec![];
+        kept.retain_mut(|error| match error_pos(error).begin
+        {
+            begin if begin < restart => true,
+            begin if begin >= old_end && synced.is_some() =>
+            {
+                shift_error(error, delta);
+                after.push(error.clone());
+                false
+            },
+            _ => false,
+        });
+        kept.extend(errors);
+        kept.extend(after);
+        self.errors = kept;
+
+        Relexed { old_tokens, new_tokens, old_text: restart..old_end, delta }
+    }
+}
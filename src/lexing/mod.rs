@@ -1,10 +1,18 @@
 use char_reader::CharReader;
 use keywords::KEYWORDS;
-use token::{Token, TokenPos, TokenType, TokenValue};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+
+use token::{Comment, CommentKind, Lexeme, LexemeValue, Token, TokenPos, TokenType};
+
+use crate::diagnostic::Diagnostic;
 
 pub mod keywords;
 pub mod token;
 pub mod char_reader;
+pub mod trivia;
+pub mod incremental;
+pub mod symbol;
 
 #[derive(Debug, Clone)]
 pub enum LexerError 
@@ -17,7 +25,32 @@ pub enum LexerError
     UnterminatedString
     {
         index: usize,
-    }
+    },
+    InvalidEscape
+    {
+        sequence: String,
+        pos: TokenPos,
+    },
+    /// A number literal too large for its type, which is `Int` or `Float` without a suffix
+    NumberOutOfRange
+    {
+        type_name: String,
+        pos: TokenPos,
+    },
+    InvalidNumber
+    {
+        reason: String,
+        pos: TokenPos,
+    },
+    /// A character literal that is empty, unterminated or holds more than one character
+    InvalidChar
+    {
+        pos: TokenPos,
+    },
+    UnterminatedComment
+    {
+        index: usize,
+    },
 }
 
 impl std::fmt::Display for LexerError
@@ -28,6 +61,47 @@ impl std::fmt::Display for LexerError
         {
             LexerError::UnknownToken { token, index: _ } => write!(f, "Unknown token `{}`", token),
             LexerError::UnterminatedString { index: _ } => write!(f, "Unterminated string"),
+            LexerError::InvalidEscape { sequence, pos: _ } => write!(f, "Invalid escape sequence `{}`", sequence),
+            LexerError::NumberOutOfRange { type_name, pos: _ } => write!(f, "Number does not fit in `{}`", type_name),
+            LexerError::InvalidNumber { reason, pos: _ } => write!(f, "Invalid number: {}", reason),
+            LexerError::InvalidChar { pos: _ } => write!(f, "Character literals hold exactly one character"),
+            LexerError::UnterminatedComment { index: _ } => write!(f, "Unterminated block comment"),
+        }
+    }
+}
+
+impl From<&LexerError> for Diagnostic
+{
+    fn from(error: &LexerError) -> Self
+    {
+        match error
+        {
+            LexerError::UnknownToken { token: _, index } => Diagnostic::error(error.to_string())
+                .with_code("E0001")
+                .with_primary((*index).into(), "not a valid token"),
+            LexerError::UnterminatedString { index } => Diagnostic::error(error.to_string())
+                .with_code("E0002")
+                .with_primary((*index).into(), "the string starts here")
+                .with_help("add a closing `\"`"),
+            LexerError::InvalidEscape { sequence: _, pos } => Diagnostic::error(error.to_string())
+                .with_code("E0003")
+                .with_primary(*pos, "not a valid escape")
+                .with_note("the escapes are `\\n`, `\\t`, `\\r`, `\\0`, `\\\\`, `\\\"`, `\\'`, `\\$` and `\\u{...}` with up to 6 hex digits")
+                .with_help("to write backslashes as they are, use a raw string such as `r\"C:\\path\"`"),
+            LexerError::NumberOutOfRange { type_name, pos } => Diagnostic::error(error.to_string())
+                .with_code("E0004")
+                .with_primary(*pos, format!("out of range for `{}`", type_name)),
+            LexerError::InvalidNumber { reason: _, pos } => Diagnostic::error(error.to_string())
+                .with_code("E0005")
+                .with_primary(*pos, "not a valid number"),
+            LexerError::InvalidChar { pos } => Diagnostic::error(error.to_string())
+                .with_code("E0006")
+                .with_primary(*pos, "not a single character")
+                .with_help("use double quotes for a string"),
+            LexerError::UnterminatedComment { index } => Diagnostic::error(error.to_string())
+                .with_code("E0007")
+                .with_primary((*index).into(), "the comment starts here")
+                .with_help("add a closing `*/`, block comments nest so each `/*` needs its own"),
         }
     }
 }
@@ -35,93 +109,233 @@ impl std::fmt::Display for LexerError
 #[derive(Debug)]
 pub struct LexerResult
 {
-    pub text: Vec<char>,
     pub tokens: Vec<Token>,
+    /// Comments in the order they appear, which the parser never sees
+    pub comments: Vec<Comment>,
     pub errors: Vec<LexerError>,
 }
 
+/// Lexes a whole text
 pub fn lex_text(text: &str) -> LexerResult
 {
-    let Some(mut reader) = CharReader::new(text) else {
-        return LexerResult {
-            text: vec![],
-            tokens: vec![Token { token_type: TokenType::EOF, pos: 0.into(), value: None }],
-            errors: vec![]
-        };
-    };
-
+    let mut lexer = Lexer::new(text);
     let mut tokens = vec![];
     let mut errors = vec![];
-
-    while !reader.at_end()
+    for lexeme in &mut lexer
     {
-        if reader.read_spaces()
+        match lexeme
         {
-            continue;
+            Ok(ok) => tokens.push(ok.into()),
+            Err(err) => errors.push(err),
         }
-        else if lex_comments(&mut reader)
+    }
+
+    LexerResult { tokens, comments: lexer.take_comments(), errors }
+}
+
+/// Reads the tokens of a text one at a time, straight from the borrowed string, ending with the end of file token.
+/// Errors come in the order they are found, before the token they were found in. Comments are not tokens, so they
+/// are kept aside until taken with `take_comments`.
+pub struct Lexer<'src>
+{
+    reader: CharReader<'src>,
+    interpolations: Vec<Interpolation>,
+    comments: Vec<Comment>,
+    /// What the last step read and has not been handed out yet
+    pending: VecDeque<Result<Lexeme<'src>, LexerError>>,
+    finished: bool,
+}
+
+impl<'src> Lexer<'src>
+{
+    pub fn new(text: &'src str) -> Self
+    {
+        Self::starting_at(CharReader::new(text))
+    }
+
+    /// Lexes from wherever the reader is, which must be outside any token or comment
+    fn starting_at(reader: CharReader<'src>) -> Self
+    {
+        Self { reader, interpolations: vec![], comments: vec![], pending: VecDeque::new(), finished: false }
+    }
+
+    /// Takes the comments read so far
+    pub fn take_comments(&mut self) -> Vec<Comment>
+    {
+        std::mem::take(&mut self.comments)
+    }
+
+    /// Whether the lexer is inside the expression of an interpolated string
+    fn in_interpolation(&self) -> bool
+    {
+        !self.interpolations.is_empty()
+    }
+
+    /// Reads the whitespace, comment, token or error at the reader
+    fn step(&mut self)
+    {
+        let reader = &mut self.reader;
+        let interpolations = &mut self.interpolations;
+        if reader.read_spaces()
         {
-            continue;
+            return;
         }
-        else if let Some(symbol) = check_symbol(&mut reader)
+
+        let mut errors = vec![];
+        let lexeme = if let Some(comment) = lex_comments(reader)
         {
-            tokens.push(symbol);
+            match comment
+            {
+                Ok(ok) => 
+                {
+                    self.comments.push(ok);
+                    return;
+                },
+                Err(err) => Err(err),
+            }
         }
-        else if let Some(identifier) = check_identifier(&mut reader)
+        else if reader.current_is(&['}']) && interpolations.last().is_some_and(|i| i.depth == 0)
         {
-            tokens.push(identifier);
+            // The `}` closing an interpolation carries on the string it is in
+            let interpolation = interpolations.pop().unwrap();
+            let begin = reader.index();
+            reader.advance();
+            lex_string_segment(reader, begin, Some(interpolation.string_begin), interpolations, &mut errors)
         }
-        else if let Some(literal) = check_string_literal(&mut reader)
+        else if let Some(symbol) = check_symbol(reader)
         {
-            match literal
+            if let Some(interpolation) = interpolations.last_mut()
             {
-                Ok(ok) => tokens.push(ok),
-                Err(err) => errors.push(err),
+                match symbol.token_type
+                {
+                    TokenType::OpenBrace => interpolation.depth += 1,
+                    TokenType::CloseBrace => interpolation.depth -= 1,
+                    _ => {},
+                }
             }
+
+            Ok(symbol)
+        }
+        else if let Some(literal) = check_raw_string_literal(reader)
+        {
+            literal
         }
-        else if let Some(literal) = check_number_literal(&mut reader)
+        else if let Some(identifier) = check_identifier(reader)
         {
-            tokens.push(literal);
+            Ok(identifier)
+        }
+        else if let Some(literal) = check_string_literal(reader, interpolations, &mut errors)
+        {
+            literal
+        }
+        else if let Some(literal) = check_char_literal(reader, &mut errors)
+        {
+            literal
+        }
+        else if let Some(literal) = check_number_literal(reader)
+        {
+            literal
         }
         else 
         {
-            errors.push(LexerError::UnknownToken { 
+            let index = reader.index();
+            Err(LexerError::UnknownToken { 
                 token: reader.advance().unwrap(), 
-                index: reader.index() 
-            });
-        }
+                index
+            })
+        };
+
+        self.pending.extend(errors.into_iter().map(Err));
+        self.pending.push_back(lexeme);
     }
+}
+
+impl<'src> Iterator for Lexer<'src>
+{
+    type Item = Result<Lexeme<'src>, LexerError>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        loop
+        {
+            if let Some(lexeme) = self.pending.pop_front()
+            {
+                return Some(lexeme);
+            }
+
+            if self.finished
+            {
+                return None;
+            }
 
-    tokens.push(Token {
-        pos: reader.index().into(),
-        token_type: TokenType::EOF,
-        value: None,
-    });
+            if self.reader.at_end()
+            {
+                // Interpolations still open are strings that never ended
+                for interpolation in self.interpolations.drain(..)
+                {
+                    self.pending.push_back(Err(LexerError::UnterminatedString { index: interpolation.string_begin }));
+                }
 
-    LexerResult { text: reader.chars().to_vec(), tokens, errors }
+                self.pending.push_back(Ok(Lexeme { pos: self.reader.index().into(), token_type: TokenType::EOF, value: None }));
+                self.finished = true;
+            }
+            else
+            {
+                self.step();
+            }
+        }
+    }
 }
 
-pub fn lex_comments(reader: &mut CharReader) -> bool
+pub fn lex_comments(reader: &mut CharReader) -> Option<Result<Comment, LexerError>>
 {
+    let begin = reader.index();
+    let begin_byte = reader.byte();
     if reader.check_many("//").is_some()
     {
-        while let Some(c) = reader.advance()
+        while reader.current().is_some_and(|c| c != '\n')
+        {
+            reader.advance();
+        }
+
+        let text = reader.since(begin_byte).trim_end().to_owned();
+        let kind = match text.chars().nth(2)
+        {
+            Some('/') if text.chars().nth(3) != Some('/') => CommentKind::Doc,
+            Some('!') => CommentKind::InnerDoc,
+            _ => CommentKind::Line,
+        };
+
+        Some(Ok(Comment { pos: TokenPos { begin, end: begin + text.chars().count() - 1 }, kind, text }))
+    }
+    else if reader.check_many("/*").is_some()
+    {
+        let mut depth = 1;
+        while depth > 0
         {
-            if c == '\n'
+            if reader.check_many("/*").is_some()
+            {
+                depth += 1;
+            }
+            else if reader.check_many("*/").is_some()
+            {
+                depth -= 1;
+            }
+            else if reader.advance().is_none()
             {
-                break;
+                return Some(Err(LexerError::UnterminatedComment { index: begin }));
             }
         }
 
-        true
+        Some(Ok(Comment { pos: TokenPos { begin, end: reader.index() - 1 }, kind: CommentKind::Block, text: reader.since(begin_byte).to_owned() }))
     }
     else 
     {
-        false    
+        None
     }
 }
 
-pub fn check_symbol(reader: &mut CharReader) -> Option<Token>
+pub fn check_symbol<'src>(reader: &mut CharReader<'src>) -> Option<Lexeme<'src>>
 {
     let Some(c) = reader.current() else { return None };
 
@@ -234,7 +448,17 @@ pub fn check_symbol(reader: &mut CharReader) -> Option<Token>
             }
         },
 
-        '.' => Some(make_token(reader, 1, TokenType::Dot)),
+        '.' => {
+            if reader.peek(1).is_some_and(|c| c == '.') && reader.peek(2).is_some_and(|c| c == '=')
+            {
+                Some(make_token(reader, 3, TokenType::DotDotEqual))
+            }
+            else 
+            {
+                Some(make_token(reader, 1, TokenType::Dot))
+            }
+        },
+        '@' => Some(make_token(reader, 1, TokenType::At)),
         ',' => Some(make_token(reader, 1, TokenType::Comma)),
         ';' => Some(make_token(reader, 1, TokenType::SemiColon)),
         ':' => Some(make_token(reader, 1, TokenType::Colon)),
@@ -271,10 +495,10 @@ pub fn check_symbol(reader: &mut CharReader) -> Option<Token>
     }
 }
 
-pub fn check_identifier(reader: &mut CharReader) -> Option<Token>
+pub fn check_identifier<'src>(reader: &mut CharReader<'src>) -> Option<Lexeme<'src>>
 {
     let begin = reader.index();
-    let mut text = String::new();
+    let begin_byte = reader.byte();
 
     if !reader.current().is_some_and(|c| c.is_alphabetic() || c == '_')
     {
@@ -283,13 +507,15 @@ pub fn check_identifier(reader: &mut CharReader) -> Option<Token>
 
     while reader.current().is_some_and(|c| c.is_alphanumeric() || c == '_')
     {
-        text.push(reader.advance().unwrap());
+        reader.advance();
     }
 
+    let text = reader.since(begin_byte);
+
     if text.len() > 0
     {
         let end = reader.index() - 1;
-        let token_type = match KEYWORDS.get(&text)
+        let token_type = match KEYWORDS.get(text)
         {
             Some(t) => *t,
             None => TokenType::Identifier,
@@ -297,11 +523,11 @@ pub fn check_identifier(reader: &mut CharReader) -> Option<Token>
 
         let value = match token_type
         {
-            TokenType::Identifier => Some(TokenValue::String(text)),
+            TokenType::Identifier => Some(LexemeValue::String(Cow::Borrowed(text))),
             _ => None,
         };
 
-        Some(Token { pos: TokenPos { begin, end }, token_type, value })
+        Some(Lexeme { pos: TokenPos { begin, end }, token_type, value })
     }
     else 
     {
@@ -309,78 +535,365 @@ pub fn check_identifier(reader: &mut CharReader) -> Option<Token>
     }
 }
 
-pub fn check_string_literal(reader: &mut CharReader) -> Option<Result<Token, LexerError>> 
+/// An interpolation whose closing `}` has not been reached yet
+pub struct Interpolation
+{
+    /// Where the string holding the interpolation starts
+    string_begin: usize,
+    /// How many braces opened inside the interpolation are still open
+    depth: usize,
+}
+
+pub fn check_string_literal<'src>(reader: &mut CharReader<'src>, interpolations: &mut Vec<Interpolation>, errors: &mut Vec<LexerError>) -> Option<Result<Lexeme<'src>, LexerError>> 
 {
     if !reader.current().is_some_and(|c| c == '\"') { return None };
 
     let begin = reader.index();
-    let mut text = String::new();
-    text.push(reader.advance().unwrap());
-    while !reader.current_is(&['\"']) && !reader.at_end()
+    reader.advance();
+    Some(lex_string_segment(reader, begin, None, interpolations, errors))
+}
+
+/// Reads a string from its opening `"`, or the `}` of the interpolation before, up to its closing `"` or the `${` of
+/// the next interpolation, decoding its escapes. `continues` is where the string started when reading on after an
+/// interpolation. Invalid escapes are added to `errors` without stopping the string. The text is borrowed from the
+/// source until an escape has to be decoded.
+fn lex_string_segment<'src>(
+    reader: &mut CharReader<'src>, 
+    begin: usize, 
+    continues: Option<usize>, 
+    interpolations: &mut Vec<Interpolation>, 
+    errors: &mut Vec<LexerError>
+) -> Result<Lexeme<'src>, LexerError>
+{
+    let text_begin = reader.byte();
+    let mut decoded: Option<String> = None;
+    let mut text_end;
+    let token_type = loop
     {
-        let c = reader.advance().unwrap();
-        text.push(c);
-        if c == '\\'
+        text_end = reader.byte();
+        match reader.current()
         {
-            if let Some(c) = reader.advance() { text.push(c); }
+            None => return Err(LexerError::UnterminatedString { index: continues.unwrap_or(begin) }),
+            Some('\"') =>
+            {
+                reader.advance();
+                break match continues
+                {
+                    Some(_) => TokenType::InterpolationEnd,
+                    None => TokenType::StringLiteral,
+                };
+            },
+            Some('$') if reader.peek(1) == Some('{') =>
+            {
+                reader.advance();
+                reader.advance();
+                interpolations.push(Interpolation { string_begin: continues.unwrap_or(begin), depth: 0 });
+                break match continues
+                {
+                    Some(_) => TokenType::InterpolationMiddle,
+                    None => TokenType::InterpolationStart,
+                };
+            },
+            // A `\` at the very end is left for the string to be reported as unterminated
+            Some('\\') if reader.peek(1).is_some() =>
+            {
+                let text = decoded.get_or_insert_with(|| reader.since(text_begin).to_owned());
+                match lex_escape(reader)
+                {
+                    Ok(c) => text.push(c),
+                    Err(err) => errors.push(err),
+                }
+            },
+            Some(c) =>
+            {
+                if let Some(text) = &mut decoded
+                {
+                    text.push(c);
+                }
+
+                reader.advance();
+            },
         }
-    }
+    };
+
+    let text = match decoded
+    {
+        Some(text) => Cow::Owned(text),
+        None => Cow::Borrowed(&reader.text()[text_begin..text_end]),
+    };
+
+    Ok(Lexeme { 
+        pos: TokenPos { begin, end: reader.index() - 1 }, 
+        token_type, 
+        value: Some(LexemeValue::String(text)) 
+    })
+}
+
+/// Reads an escape sequence from its `\`, returning the character it stands for
+fn lex_escape(reader: &mut CharReader) -> Result<char, LexerError>
+{
+    let begin = reader.index();
+    let begin_byte = reader.byte();
+    reader.advance();
+
+    let escaped = match reader.advance()
+    {
+        Some('n') => Some('\n'),
+        Some('t') => Some('\t'),
+        Some('r') => Some('\r'),
+        Some('0') => Some('\0'),
+        Some('\\') => Some('\\'),
+        Some('"') => Some('"'),
+        Some('\'') => Some('\''),
+        Some('$') => Some('$'),
+        Some('u') if reader.current_is(&['{']) =>
+        {
+            reader.advance();
+            let mut digits = String::new();
+            while reader.current().is_some_and(|c| c.is_ascii_hexdigit())
+            {
+                digits.push(reader.advance().unwrap());
+            }
+
+            let closed = reader.check(&['}']).is_some();
+            match closed && (1..=6).contains(&digits.len())
+            {
+                true => u32::from_str_radix(&digits, 16).ok().and_then(char::from_u32),
+                false => None,
+            }
+        },
+        _ => None,
+    };
 
-    if reader.current().is_some()
+    escaped.ok_or_else(|| LexerError::InvalidEscape 
+    { 
+        sequence: reader.since(begin_byte).to_owned(), 
+        pos: TokenPos { begin, end: reader.index() - 1 } 
+    })
+}
+
+/// Checks for a character literal such as `'a'` or `'\n'`, which takes the same escapes as strings
+pub fn check_char_literal<'src>(reader: &mut CharReader<'src>, errors: &mut Vec<LexerError>) -> Option<Result<Lexeme<'src>, LexerError>>
+{
+    if !reader.current_is(&['\'']) { return None };
+
+    let begin = reader.index();
+    reader.advance();
+
+    let mut chars = vec![];
+    while let Some(c) = reader.current().filter(|c| *c != '\'' && *c != '\n')
     {
-        let end = reader.index();
-        text.push(reader.advance().unwrap());
-        Some(Ok(Token { 
-            pos: TokenPos { begin, end }, 
-            token_type: TokenType::StringLiteral, 
-            value: Some(TokenValue::String(text)) 
-        }))
+        match c
+        {
+            '\\' if reader.peek(1).is_some() => match lex_escape(reader)
+            {
+                Ok(c) => chars.push(c),
+                Err(err) =>
+                {
+                    // Still counts as a character, so the literal is not also reported as empty
+                    chars.push(char::REPLACEMENT_CHARACTER);
+                    errors.push(err);
+                },
+            },
+            _ =>
+            {
+                chars.push(c);
+                reader.advance();
+            },
+        }
     }
-    else 
+
+    let closed = reader.check(&['\'']).is_some();
+    let pos = TokenPos { begin, end: reader.index() - 1 };
+    match (closed, chars.as_slice())
     {
-        Some(Err(LexerError::UnterminatedString { index: begin }))
+        (true, [c]) => Some(Ok(Lexeme { pos, token_type: TokenType::CharLiteral, value: Some(LexemeValue::Char(*c)) })),
+        _ => Some(Err(LexerError::InvalidChar { pos })),
     }
 }
 
-pub fn check_number_literal(reader: &mut CharReader) -> Option<Token>
+/// Checks for a raw string, `r"..."`, or `r#"..."#` with any number of `#` to allow quotes inside. Its text is kept
+/// as written, without escapes or interpolations.
+pub fn check_raw_string_literal<'src>(reader: &mut CharReader<'src>) -> Option<Result<Lexeme<'src>, LexerError>>
 {
-    if !reader.current().is_some_and(|c| c.is_digit(10)) { return None; }
+    if !reader.current_is(&['r']) { return None };
+
+    let mut hashes = 0;
+    while reader.peek(hashes + 1) == Some('#')
+    {
+        hashes += 1;
+    }
+
+    if reader.peek(hashes + 1) != Some('"') { return None };
 
     let begin = reader.index();
-    let mut number = String::new();
+    for _ in 0..hashes + 2
+    {
+        reader.advance();
+    }
 
-    while reader.current().is_some_and(|c| c.is_digit(10))
+    let closing = format!("\"{}", "#".repeat(hashes));
+    let text_begin = reader.byte();
+    while !reader.rest().starts_with(&closing)
     {
-        number.push(reader.advance().unwrap());
+        if reader.advance().is_none()
+        {
+            return Some(Err(LexerError::UnterminatedString { index: begin }));
+        }
     }
 
-    if reader.current_is(&['.']) && reader.peek(1).is_some_and(|c| c.is_digit(10))
+    let text = reader.since(text_begin);
+    reader.check_many(&closing);
+
+    Some(Ok(Lexeme 
     {
-        number.push(reader.advance().unwrap());
-        while reader.current().is_some_and(|c| c.is_digit(10))
+        pos: TokenPos { begin, end: reader.index() - 1 },
+        token_type: TokenType::StringLiteral,
+        value: Some(LexemeValue::String(Cow::Borrowed(text))),
+    }))
+}
+
+/// The suffixes a number can end with, with the largest value each can hold. Every integer is an `Int` at runtime, so
+/// none reach past `i64::MAX`.
+const INTEGER_SUFFIXES: &[(&str, u64)] = &[
+    ("i8", i8::MAX as u64),
+    ("i16", i16::MAX as u64),
+    ("i32", i32::MAX as u64),
+    ("i64", i64::MAX as u64),
+    ("u8", u8::MAX as u64),
+    ("u16", u16::MAX as u64),
+    ("u32", u32::MAX as u64),
+    ("u64", i64::MAX as u64),
+];
+
+/// The magnitude of `i64::MIN`, which an `Int` or `i64` literal may have so the minimum can be written. It only fits
+/// once negated, so the type checker reports it anywhere else.
+pub const MIN_INT_MAGNITUDE: u64 = i64::MIN.unsigned_abs();
+const FLOAT_SUFFIXES: &[&str] = &["f32", "f64"];
+
+/// Reads the digits of a number in the given radix, skipping `_` separators
+fn read_digits(reader: &mut CharReader, radix: u32, digits: &mut String)
+{
+    while let Some(c) = reader.current().filter(|c| c.is_digit(radix) || *c == '_')
+    {
+        if c != '_'
+        {
+            digits.push(c);
+        }
+        reader.advance();
+    }
+}
+
+/// Checks for a number: decimal with an optional fraction and exponent, or an integer in hex, octal or binary with a
+/// `0x`, `0o` or `0b` prefix. Digits can be separated by `_`, and a type suffix such as `u8` or `f32` can follow.
+pub fn check_number_literal<'src>(reader: &mut CharReader<'src>) -> Option<Result<Lexeme<'src>, LexerError>>
+{
+    if !reader.current().is_some_and(|c| c.is_digit(10)) { return None; }
+
+    let begin = reader.index();
+    let begin_byte = reader.byte();
+    let radix = match (reader.current(), reader.peek(1))
+    {
+        (Some('0'), Some('x')) => 16,
+        (Some('0'), Some('o')) => 8,
+        (Some('0'), Some('b')) => 2,
+        _ => 10,
+    };
+
+    let mut number = String::new();
+    let mut is_float = false;
+    if radix == 10
+    {
+        read_digits(reader, 10, &mut number);
+
+        if reader.current_is(&['.']) && reader.peek(1).is_some_and(|c| c.is_digit(10))
         {
             number.push(reader.advance().unwrap());
+            read_digits(reader, 10, &mut number);
+            is_float = true;
         }
 
-        Some(Token 
+        let exponent_digit = |offset| reader.peek(offset).is_some_and(|c: char| c.is_digit(10));
+        if reader.current_is(&['e', 'E']) && (exponent_digit(1) || (reader.peek(1).is_some_and(|c| c == '+' || c == '-') && exponent_digit(2)))
         {
-            pos: TokenPos { begin, end: reader.index() - 1 },
-            token_type: TokenType::FloatLiteral,
-            value: Some(TokenValue::Float(number.parse().unwrap()))
-        })   
+            number.push(reader.advance().unwrap());
+            if reader.current_is(&['+', '-'])
+            {
+                number.push(reader.advance().unwrap());
+            }
+            read_digits(reader, 10, &mut number);
+            is_float = true;
+        }
     }
-    else 
+    else
+    {
+        reader.advance();
+        reader.advance();
+        // Decimal digits are read for every radix, so a stray one is reported rather than starting a new number
+        read_digits(reader, if radix == 16 { 16 } else { 10 }, &mut number);
+    }
+
+    let mut suffix = String::new();
+    while reader.current().is_some_and(|c| c.is_alphanumeric() || c == '_')
+    {
+        suffix.push(reader.advance().unwrap());
+    }
+
+    let pos = TokenPos { begin, end: reader.index() - 1 };
+    let invalid = |reason: String| Some(Err(LexerError::InvalidNumber { reason, pos }));
+
+    if number.is_empty()
+    {
+        return invalid(format!("expected digits after `{}`", &reader.text()[begin_byte..begin_byte + 2]));
+    }
+
+    if let Some(digit) = number.chars().find(|c| radix != 10 && !c.is_digit(radix))
+    {
+        return invalid(format!("`{}` is not a digit in base {}", digit, radix));
+    }
+
+    let integer_suffix = INTEGER_SUFFIXES.iter().find(|(name, _)| *name == suffix);
+    let float_suffix = FLOAT_SUFFIXES.contains(&suffix.as_str());
+    if !suffix.is_empty() && integer_suffix.is_none() && !(float_suffix && radix == 10)
+    {
+        return invalid(format!("unknown suffix `{}`", suffix));
+    }
+
+    if is_float && integer_suffix.is_some()
     {
-        Some(Token 
+        return invalid(format!("a float cannot have the integer suffix `{}`", suffix));
+    }
+
+    let out_of_range = |type_name: &str| Some(Err(LexerError::NumberOutOfRange { type_name: type_name.to_owned(), pos }));
+    if is_float || float_suffix
+    {
+        let value: f64 = number.parse().unwrap();
+        let fits = match suffix.as_str()
+        {
+            "f32" => (value as f32).is_finite(),
+            _ => value.is_finite(),
+        };
+
+        if !fits
         {
-            pos: TokenPos { begin, end: reader.index() - 1 },
-            token_type: TokenType::IntegerLiteral,
-            value: Some(TokenValue::Int(number.parse().unwrap()))
-        })
+            return out_of_range(if suffix.is_empty() { "Float" } else { &suffix });
+        }
+
+        return Some(Ok(Lexeme { pos, token_type: TokenType::FloatLiteral, value: Some(LexemeValue::Float(value)) }));
+    }
+
+    let (type_name, max) = integer_suffix.map_or(("Int", i64::MAX as u64), |(name, max)| (*name, *max));
+    let max = if matches!(type_name, "Int" | "i64") { MIN_INT_MAGNITUDE } else { max };
+    match u64::from_str_radix(&number, radix).ok().filter(|v| *v <= max)
+    {
+        Some(value) => Some(Ok(Lexeme { pos, token_type: TokenType::IntegerLiteral, value: Some(LexemeValue::Int(value)) })),
+        // A `u64` holds no more than an `Int` does, so that is the type it is reported against
+        None if type_name == "u64" => out_of_range("Int"),
+        None => out_of_range(type_name),
     }
 }
 
-pub fn make_token(reader: &mut CharReader, length: usize, token_type: TokenType) -> Token
+pub fn make_token<'src>(reader: &mut CharReader<'src>, length: usize, token_type: TokenType) -> Lexeme<'src>
 {
     assert!(length != 0, "Length of a token cannot be 0");
     let begin = reader.index();
@@ -391,7 +904,7 @@ pub fn make_token(reader: &mut CharReader, length: usize, token_type: TokenType)
         reader.advance().expect("Expected a character");
     }
 
-    Token 
+    Lexeme 
     { 
         pos: TokenPos {
             begin,
@@ -400,4 +913,127 @@ pub fn make_token(reader: &mut CharReader, length: usize, token_type: TokenType)
         token_type, 
         value: None 
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests
+{
+    use super::{lex_text, symbol::{self, Symbol}, token::TokenType, LexerError};
+
+    /// The values of the tokens of a text that lexes without errors, leaving out the end of file
+    fn values(text: &str) -> Vec<String>
+    {
+        let lexed = lex_text(text);
+        assert!(lexed.errors.is_empty(), "{:?}", lexed.errors);
+        lexed.tokens.iter()
+            .filter(|t| t.token_type != TokenType::EOF)
+            .map(|t| t.value.map_or(format!("{:?}", t.token_type), |v| v.to_string()))
+            .collect()
+    }
+
+    fn errors(text: &str) -> Vec<String>
+    {
+        lex_text(text).errors.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn escapes()
+    {
+        assert_eq!(values(r#""a\n\t\\\"\$\u{e9}\u{1F600}\0""#), ["a\n\t\\\"$é😀\0"]);
+        assert_eq!(values(r#"'\n' '\'' '\u{41}'"#), ["\n", "'", "A"]);
+    }
+
+    #[test]
+    fn invalid_escapes()
+    {
+        assert_eq!(errors(r#""\q""#), ["Invalid escape sequence `\\q`"]);
+        assert_eq!(errors(r#""\u{110000}""#), ["Invalid escape sequence `\\u{110000}`"]);
+        assert_eq!(errors(r#""\u{}""#), ["Invalid escape sequence `\\u{}`"]);
+        assert_eq!(errors(r#""\u{1234567}""#), ["Invalid escape sequence `\\u{1234567}`"]);
+    }
+
+    #[test]
+    fn sessions()
+    {
+        let outer = Symbol::intern("outer");
+        // Each session starts empty, so their first strings get the same symbol
+        let first = symbol::session(|| Symbol::intern("first"));
+        let second = symbol::session(|| Symbol::intern("second"));
+        assert_eq!(first, second);
+        assert_eq!(&*outer.as_str(), "outer");
+        assert_eq!(symbol::session(|| values("\"lit\" name")), ["lit", "name"]);
+    }
+
+    #[test]
+    fn numbers()
+    {
+        assert_eq!(values("0xff 0o17 0b101 1_000 2.5 2.5e3 1E-2 7u8 255u8 1.5f32 3f64"), [
+            "255", "15", "5", "1000", "2.5", "2500", "0.01", "7", "255", "1.5", "3",
+        ]);
+        assert_eq!(values("9223372036854775807 9223372036854775807u64"), ["9223372036854775807", "9223372036854775807"]);
+        // The magnitude of `i64::MIN` lexes, leaving the type checker to require that it is negated
+        assert_eq!(values("9223372036854775808 9223372036854775808i64"), ["9223372036854775808", "9223372036854775808"]);
+        // Not a fraction, so the number ends before the dot
+        assert_eq!(values("1.x"), ["1", "Dot", "x"]);
+    }
+
+    #[test]
+    fn invalid_numbers()
+    {
+        assert_eq!(errors("0x"), ["Invalid number: expected digits after `0x`"]);
+        assert_eq!(errors("0b102"), ["Invalid number: `2` is not a digit in base 2"]);
+        assert_eq!(errors("0o8"), ["Invalid number: `8` is not a digit in base 8"]);
+        assert_eq!(errors("5q"), ["Invalid number: unknown suffix `q`"]);
+        assert_eq!(errors("0x1f32"), [] as [&str; 0]);
+        assert_eq!(errors("0b1f32"), ["Invalid number: unknown suffix `f32`"]);
+        assert_eq!(errors("1.5u8"), ["Invalid number: a float cannot have the integer suffix `u8`"]);
+    }
+
+    #[test]
+    fn numbers_out_of_range()
+    {
+        assert_eq!(errors("256u8"), ["Number does not fit in `u8`"]);
+        assert_eq!(errors("128i8"), ["Number does not fit in `i8`"]);
+        assert_eq!(errors("9223372036854775809"), ["Number does not fit in `Int`"]);
+        assert_eq!(errors("9223372036854775808u64"), ["Number does not fit in `Int`"]);
+        assert_eq!(errors("18446744073709551615u64"), ["Number does not fit in `Int`"]);
+        assert_eq!(errors("18446744073709551616u64"), ["Number does not fit in `Int`"]);
+        assert_eq!(errors("1e400"), ["Number does not fit in `Float`"]);
+        assert_eq!(errors("1e39f32"), ["Number does not fit in `f32`"]);
+    }
+
+    #[test]
+    fn invalid_chars()
+    {
+        for text in ["''", "'ab'", "'a"]
+        {
+            let lexed = lex_text(text);
+            assert!(matches!(lexed.errors.as_slice(), [LexerError::InvalidChar { .. }]), "{}: {:?}", text, lexed.errors);
+        }
+
+        // A literal ends at the end of its line
+        let lexed = lex_text("'a\nb");
+        assert!(matches!(lexed.errors.as_slice(), [LexerError::InvalidChar { pos }] if pos.begin == 0 && pos.end == 1), "{:?}", lexed.errors);
+
+        // The bad escape is reported once, not again as an empty literal
+        assert_eq!(errors(r"'\q'"), ["Invalid escape sequence `\\q`"]);
+    }
+
+    #[test]
+    fn block_comments()
+    {
+        let lexed = lex_text("a /* one /* two */ still one */ b");
+        assert!(lexed.errors.is_empty());
+        assert_eq!(lexed.comments.len(), 1);
+        assert_eq!(lexed.comments[0].text, "/* one /* two */ still one */");
+        assert_eq!(values("a /* one /* two */ still one */ b"), ["a", "b"]);
+    }
+
+    #[test]
+    fn unterminated_comments()
+    {
+        let lexed = lex_text("a /* one /* two */");
+        assert!(matches!(lexed.errors.as_slice(), [LexerError::UnterminatedComment { index: 2 }]), "{:?}", lexed.errors);
+        assert_eq!(errors("/*"), ["Unterminated block comment"]);
+        assert_eq!(errors("\"abc"), ["Unterminated string"]);
+    }
+}
@@ -0,0 +1,154 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::{BuildHasherDefault, Hasher};
+use std::rc::Rc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An interned string, such as the name of an identifier or the text of a string literal. Equal strings get the same
+/// symbol, so comparing symbols compares integers, and a symbol is copied where a `String` would be cloned.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+/// Hands out a symbol for each distinct string. The interner owns its strings, which are freed with it at the end of
+/// the session that filled it.
+#[derive(Default)]
+pub struct Interner
+{
+    symbols: HashMap<Rc<str>, Symbol, BuildHasherDefault<FnvHasher>>,
+    strings: Vec<Rc<str>>,
+}
+
+impl Interner
+{
+    pub fn intern(&mut self, text: &str) -> Symbol
+    {
+        if let Some(symbol) = self.symbols.get(text)
+        {
+            return *symbol;
+        }
+
+        let text: Rc<str> = text.into();
+        let symbol = Symbol(self.strings.len() as u32);
+        self.strings.push(text.clone());
+        self.symbols.insert(text, symbol);
+        symbol
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> Rc<str>
+    {
+        self.strings[symbol.0 as usize].clone()
+    }
+}
+
+/// FNV-1a, which is much quicker than the default hasher on names a few characters long. The strings come from the
+/// programs being compiled rather than from anyone able to pick collisions, so the default's guard against that is
+/// not needed.
+pub struct FnvHasher(u64);
+
+impl Default for FnvHasher
+{
+    fn default() -> Self
+    {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for FnvHasher
+{
+    fn finish(&self) -> u64
+    {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8])
+    {
+        for byte in bytes
+        {
+            self.0 = (self.0 ^ *byte as u64).wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+thread_local!
+{
+    /// The interner of the current session, so symbols can be shown and compared without passing it around
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner::default());
+}
+
+/// Runs `run` in a session of its own: it interns into a fresh interner, whose strings are freed when `run` returns.
+/// Symbols must not outlive the session they were interned in.
+pub fn session<T>(run: impl FnOnce() -> T) -> T
+{
+    /// Puts the outer session's interner back, even if `run` panics
+    struct Restore(Option<Interner>);
+
+    impl Drop for Restore
+    {
+        fn drop(&mut self)
+        {
+            if let Some(outer) = self.0.take()
+            {
+                INTERNER.with(|interner| *interner.borrow_mut() = outer);
+            }
+        }
+    }
+
+    let _restore = Restore(Some(INTERNER.with(|interner| interner.take())));
+    run()
+}
+
+impl Symbol
+{
+    pub fn intern(text: &str) -> Self
+    {
+        INTERNER.with(|interner| interner.borrow_mut().intern(text))
+    }
+
+    pub fn as_str(self) -> Rc<str>
+    {
+        INTERNER.with(|interner| interner.borrow().resolve(self))
+    }
+}
+
+impl From<&str> for Symbol
+{
+    fn from(text: &str) -> Self
+    {
+        Symbol::intern(text)
+    }
+}
+
+impl std::fmt::Display for Symbol
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.write_str(&self.as_str())
+    }
+}
+
+/// Shows the string, as a `String` would be shown, rather than the number behind it
+impl std::fmt::Debug for Symbol
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl Serialize for Symbol
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        serializer.serialize_str(&self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    {
+        let text = String::deserialize(deserializer)?;
+        Ok(Symbol::intern(&text))
+    }
+}
@@ -1,3 +1,10 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize};
+
+use super::keywords::KEYWORDS;
+use super::symbol::Symbol;
+use crate::source::SourceFile;
 
 pub const ASSIGNMENT_TOKENS: &'static [TokenType] = &[
     TokenType::Equal,
@@ -10,7 +17,7 @@ pub const ASSIGNMENT_TOKENS: &'static [TokenType] = &[
     TokenType::OrEqual,
 ];
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TokenType
 {
     // Keywords
@@ -78,6 +85,8 @@ pub enum TokenType
     ThickArrow,
 
     Dot,
+    DotDotEqual,
+    At,
     AndAnd,
     PipePipe,
     Pipe,
@@ -89,6 +98,13 @@ pub enum TokenType
     IntegerLiteral,
     FloatLiteral,
     StringLiteral,
+    CharLiteral,
+    /// The text of an interpolated string up to its first `${`, e.g. `"a ${`
+    InterpolationStart,
+    /// The text between two interpolations, e.g. `} b ${`
+    InterpolationMiddle,
+    /// The text after the last interpolation, e.g. `} c"`
+    InterpolationEnd,
 
     // Identifier
     Identifier,
@@ -97,12 +113,77 @@ pub enum TokenType
     EOF,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+impl TokenType
+{
+    /// How the token is referred to in messages: the text of keywords and symbols, or what kind of token it is
+    pub fn describe(&self) -> String
+    {
+        if let Some((keyword, _)) = KEYWORDS.iter().find(|(_, t)| *t == self)
+        {
+            return format!("`{}`", keyword);
+        }
+
+        let text = match self
+        {
+            TokenType::OpenParen => "(",
+            TokenType::CloseParen => ")",
+            TokenType::OpenBrace => "{",
+            TokenType::CloseBrace => "}",
+            TokenType::OpenBracket => "[",
+            TokenType::CloseBracket => "]",
+            TokenType::Plus => "+",
+            TokenType::Minus => "-",
+            TokenType::Multiply => "*",
+            TokenType::Divide => "/",
+            TokenType::Modulus => "%",
+            TokenType::PlusEqual => "+=",
+            TokenType::MinusEqual => "-=",
+            TokenType::MultiplyEqual => "*=",
+            TokenType::DivideEqual => "/=",
+            TokenType::ModulusEqual => "%=",
+            TokenType::Equal => "=",
+            TokenType::EqualEqual => "==",
+            TokenType::Bang => "!",
+            TokenType::BangEqual => "!=",
+            TokenType::LessEqual => "<=",
+            TokenType::GreaterEqual => ">=",
+            TokenType::GreaterThan => ">",
+            TokenType::LessThan => "<",
+            TokenType::OrEqual => "|=",
+            TokenType::AndEqual => "&=",
+            TokenType::ThinArrow => "->",
+            TokenType::ThickArrow => "=>",
+            TokenType::Dot => ".",
+            TokenType::DotDotEqual => "..=",
+            TokenType::At => "@",
+            TokenType::AndAnd => "&&",
+            TokenType::PipePipe => "||",
+            TokenType::Pipe => "|",
+            TokenType::SemiColon => ";",
+            TokenType::Colon => ":",
+            TokenType::Comma => ",",
+            TokenType::IntegerLiteral => return "an integer".into(),
+            TokenType::FloatLiteral => return "a float".into(),
+            TokenType::StringLiteral => return "a string".into(),
+            TokenType::CharLiteral => return "a character".into(),
+            TokenType::InterpolationStart => return "an interpolated string".into(),
+            TokenType::InterpolationMiddle | TokenType::InterpolationEnd => return "the end of an interpolation".into(),
+            TokenType::Identifier => return "an identifier".into(),
+            TokenType::EOF => return "the end of the file".into(),
+            keyword => return format!("{:?}", keyword),
+        };
+
+        format!("`{}`", text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum TokenValue
 {
-    String(String),
+    String(Symbol),
     Int(u64),
     Float(f64),
+    Char(char),
 }
 
 impl std::fmt::Display for TokenValue
@@ -114,11 +195,12 @@ impl std::fmt::Display for TokenValue
             TokenValue::String(s) => write!(f, "{}", s),
             TokenValue::Int(i) => write!(f, "{}", i),
             TokenValue::Float(n) => write!(f, "{}", n),
+            TokenValue::Char(c) => write!(f, "{}", c),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Token 
 {
     pub pos: TokenPos,
@@ -128,32 +210,44 @@ pub struct Token
 
 impl Token 
 {
-    pub fn get_loc(&self, text: &[char]) -> TokenTextLocation
+    pub fn get_loc(&self, source: &SourceFile) -> TokenTextLocation
     {
-        let mut line = 1;
-        let mut column = 1;
+        source.location(self.pos.begin)
+    }
+}
 
-        let line_count = text.iter().filter(|f| **f == '\n').count() + 1;
+/// A token as the lexer reads it, with the text of identifiers and strings borrowed from the source unless escapes
+/// had to be decoded, so lexing copies nothing until tokens are kept
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lexeme<'src>
+{
+    pub pos: TokenPos,
+    pub token_type: TokenType,
+    pub value: Option<LexemeValue<'src>>,
+}
 
-        if self.pos.begin >= text.len()
-        {
-            return TokenTextLocation { line: line_count, column: 0 }
-        }
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexemeValue<'src>
+{
+    String(Cow<'src, str>),
+    Int(u64),
+    Float(f64),
+    Char(char),
+}
 
-        for i in 0..=self.pos.begin
+impl From<Lexeme<'_>> for Token
+{
+    fn from(lexeme: Lexeme<'_>) -> Self
+    {
+        let value = lexeme.value.map(|value| match value
         {
-            if text[i] == '\n'
-            {
-                line += 1;
-                column = 0;
-            }
-            else 
-            {
-                column += 1;
-            }
-        }
+            LexemeValue::String(text) => TokenValue::String(Symbol::intern(&text)),
+            LexemeValue::Int(value) => TokenValue::Int(value),
+            LexemeValue::Float(value) => TokenValue::Float(value),
+            LexemeValue::Char(value) => TokenValue::Char(value),
+        });
 
-        TokenTextLocation { line, column }
+        Token { pos: lexeme.pos, token_type: lexeme.token_type, value }
     }
 }
 
@@ -166,6 +260,7 @@ impl std::fmt::Display for Token
             Some(TokenValue::Float(v)) => write!(f, "{:?}({})", self.token_type, v),
             Some(TokenValue::Int(v)) => write!(f, "{:?}({})", self.token_type, v),
             Some(TokenValue::String(v)) => write!(f, "{:?}({})", self.token_type, v),
+            Some(TokenValue::Char(v)) => write!(f, "{:?}({})", self.token_type, v),
             None => write!(f, "{:?}", self.token_type)
         }
     }
@@ -186,13 +281,22 @@ impl std::fmt::Display for TokenTextLocation
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct TokenPos
 {
     pub begin: usize,
     pub end: usize,
 }
 
+impl TokenPos
+{
+    /// The span moved by a number of characters, as text after an edit is
+    pub fn shifted(self, delta: isize) -> Self
+    {
+        Self { begin: self.begin.wrapping_add_signed(delta), end: self.end.wrapping_add_signed(delta) }
+    }
+}
+
 impl From<usize> for TokenPos
 {
     fn from(value: usize) -> Self 
@@ -203,4 +307,27 @@ impl From<usize> for TokenPos
             end: value
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CommentKind
+{
+    /// `// ...`
+    Line,
+    /// `/* ... */`, which may hold other block comments
+    Block,
+    /// `/// ...`, documenting the declaration after it
+    Doc,
+    /// `//! ...`, documenting the declaration or file it is in
+    InnerDoc,
+}
+
+/// A comment skipped by the lexer, kept so tools such as the formatter can put it back where it was
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Comment
+{
+    pub pos: TokenPos,
+    pub kind: CommentKind,
+    /// The text of the comment, starting with `//` or `/*`
+    pub text: String,
+}
@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+
+use super::char_reader::CharReader;
+use super::token::{CommentKind, Token};
+use super::LexerResult;
+use crate::source::SourceFile;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TriviaKind
+{
+    /// Spaces and tabs
+    Whitespace,
+    /// A single `\n` or `\r\n`
+    Newline,
+    Comment(CommentKind),
+    /// Text the lexer could not read as a token, such as an unknown character
+    Skipped,
+}
+
+/// Text between tokens that the parser never sees
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Trivia
+{
+    pub kind: TriviaKind,
+    pub text: String,
+}
+
+/// A token with the text around it, so the tokens of a file can be written back to exactly the text they came from
+#[derive(Debug, Clone)]
+pub struct LosslessToken
+{
+    /// The trivia after the trailing trivia of the token before, up to this token
+    pub leading: Vec<Trivia>,
+    pub token: Token,
+    /// The trivia after this token up to the end of its line, without the newline
+    pub trailing: Vec<Trivia>,
+}
+
+impl LosslessToken
+{
+    /// The text of the token alone, which is empty for the end of file
+    pub fn text<'a>(&self, source: &'a SourceFile) -> &'a str
+    {
+        match self.token.pos.begin < source.char_count()
+        {
+            true => source.slice(self.token.pos),
+            false => "",
+        }
+    }
+}
+
+impl LexerResult
+{
+    /// Gives each token the whitespace, comments and skipped text around it, slicing them out of the text the tokens
+    /// were lexed from. Lexing keeps only what the parser needs, so this is the lossless mode for tools that rewrite
+    /// source and must keep its layout.
+    pub fn with_trivia(&self, source: &SourceFile) -> Vec<LosslessToken>
+    {
+        let mut tokens: Vec<LosslessToken> = Vec::with_capacity(self.tokens.len());
+        let mut comments = self.comments.iter().peekable();
+        let mut reader = CharReader::new(source.text());
+
+        for token in &self.tokens
+        {
+            let gap_end = token.pos.begin.min(source.char_count());
+            let mut gap = vec![];
+            while reader.index() < gap_end
+            {
+                let (begin, begin_byte) = (reader.index(), reader.byte());
+                while comments.next_if(|c| c.pos.begin < begin).is_some() {}
+
+                let kind = if let Some(comment) = comments.next_if(|c| c.pos.begin == begin)
+                {
+                    skip_to(&mut reader, comment.pos.end + 1);
+                    TriviaKind::Comment(comment.kind)
+                }
+                else if newline_at(&reader)
+                {
+                    if reader.current() == Some('\r')
+                    {
+                        reader.advance();
+                    }
+
+                    reader.advance();
+                    TriviaKind::Newline
+                }
+                else if reader.current().is_some_and(char::is_whitespace)
+                {
+                    while reader.index() < gap_end && reader.current().is_some_and(char::is_whitespace) && !newline_at(&reader)
+                    {
+                        reader.advance();
+                    }
+
+                    TriviaKind::Whitespace
+                }
+                else
+                {
+                    while reader.index() < gap_end
+                        && reader.current().is_some_and(|c| !c.is_whitespace())
+                        && comments.peek().is_none_or(|c| c.pos.begin != reader.index())
+                    {
+                        reader.advance();
+                    }
+
+                    TriviaKind::Skipped
+                };
+
+                gap.push(Trivia { kind, text: reader.since(begin_byte).to_owned() });
+            }
+
+            // The trivia on the line of the token before is its trailing trivia, the rest leads into this token
+            if let Some(previous) = tokens.last_mut()
+            {
+                let line_end = gap.iter().position(|t| t.kind == TriviaKind::Newline).unwrap_or(gap.len());
+                previous.trailing = gap.drain(..line_end).collect();
+            }
+
+            tokens.push(LosslessToken { leading: gap, token: *token, trailing: vec![] });
+            skip_to(&mut reader, token.pos.end + 1);
+        }
+
+        tokens
+    }
+}
+
+/// Reads on up to a character index, or to the end of the text
+fn skip_to(reader: &mut CharReader, index: usize)
+{
+    while reader.index() < index && reader.advance().is_some() {}
+}
+
+fn newline_at(reader: &CharReader) -> bool
+{
+    match reader.current()
+    {
+        Some('\n') => true,
+        Some('\r') => reader.peek(1) == Some('\n'),
+        _ => false,
+    }
+}
@@ -1,91 +1,392 @@
-use std::{fs::{create_dir_all, File, OpenOptions}, io::{Read, Write}};
+use std::{path::{Path, PathBuf}, process::ExitCode};
 
+use cli::{Command, ParseFormat};
+use diagnostic::Diagnostic;
 use itertools::Itertools;
+use source::SourceFile;
+use workspace::{Workspace, WorkspaceError};
 
 pub mod lexing;
 pub mod parsing;
 pub mod ast;
 pub mod utils;
+pub mod interp;
+pub mod typeck;
+pub mod resolve;
+pub mod workspace;
+pub mod codegen;
+pub mod vm;
+pub mod cli;
+pub mod repl;
+pub mod diagnostic;
+pub mod format;
+pub mod source;
+pub mod cst;
+pub mod consteval;
 
-fn read_file(path: &str) -> Result<String, String> 
+/// The exit code for programs that failed to lex, parse, check or run
+const EXIT_ERROR: u8 = 1;
+/// The exit code for invalid command-line arguments
+const EXIT_USAGE: u8 = 2;
+/// The stack of the thread commands run on
+const STACK_SIZE: usize = 1024 * 1024 * 1024;
+
+fn main() -> ExitCode
 {
-    let Ok(mut file) = File::open(path) else {
-        return Err(format!("Could not open file: `{}`", path));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match cli::parse_args(&args)
+    {
+        Ok(command) => command,
+        Err(message) =>
+        {
+            eprintln!("{}\n\n{}", message, cli::USAGE);
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    // Every call the interpreter makes nests several Rust frames, so commands run on a thread whose stack has room for
+    // `interp::MAX_CALL_DEPTH` calls
+    let thread = std::thread::Builder::new().stack_size(STACK_SIZE).spawn(move || lexing::symbol::session(|| match command
+    {
+        Command::Lex { file } => lex(&file),
+        Command::Parse { file, format } => parse(&file, format),
+        Command::Check { file } => load(&file).map(|_| ()),
+        Command::Run { file, vm, args } => run(&file, vm, args),
+        Command::Disassemble { file, function } => disassemble(&file, function.as_deref()),
+        Command::Format { files, check, indent } => format_files(&files, check, indent),
+        Command::BenchParse { file, copies } => bench_parse(&file, copies),
+        Command::BenchEdit { file, copies } => bench_edit(&file, copies),
+        Command::Repl =>
+        {
+            repl::Repl::new().run();
+            Ok(())
+        },
+        Command::Help =>
+        {
+            println!("{}", cli::USAGE);
+            Ok(())
+        },
+    }));
+
+    let result = match thread.map(|thread| thread.join())
+    {
+        Ok(Ok(result)) => result,
+        Ok(Err(panic)) => std::panic::resume_unwind(panic),
+        Err(error) =>
+        {
+            eprintln!("Could not start the interpreter thread: {}", error);
+            return ExitCode::from(EXIT_ERROR);
+        },
     };
 
-    let mut contents = String::new();
-    if let Err(_) = file.read_to_string(&mut contents)
+    match result
     {
-        return Err(format!("Invalid file format"));
+        Ok(()) => ExitCode::SUCCESS,
+        Err(code) => code,
     }
+}
 
-    Ok(contents)
+fn read_file(path: &Path) -> Result<String, ExitCode>
+{
+    std::fs::read_to_string(path).map_err(|_| {
+        eprintln!("Could not open file: `{}`", path.display());
+        ExitCode::from(EXIT_ERROR)
+    })
 }
 
-fn write_to_file(filename: &str, content: &str) -> Result<(), String> {
-    // Ensure the parent directory exists
-    if let Some(parent) = std::path::Path::new(filename).parent() {
-        if let Err(e) = create_dir_all(parent) {
-            return Err(format!("Failed to create directory: {}", e));
+fn lex(path: &Path) -> Result<(), ExitCode>
+{
+    let source = SourceFile::new(read_file(path)?);
+    let mut errors = vec![];
+    for lexeme in lexing::Lexer::new(source.text())
+    {
+        match lexeme
+        {
+            Ok(lexeme) =>
+            {
+                let token = lexing::token::Token::from(lexeme);
+                println!("{:<8} {}", token.get_loc(&source).to_string(), token);
+            },
+            Err(err) => errors.push(err),
         }
     }
 
-    let Ok(mut file) = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(filename)
-    else {
-        return Err("Failed to create file".into());
+    if !errors.is_empty()
+    {
+        print_diagnostics(&errors.iter().map(Diagnostic::from).collect_vec(), &source, path);
+        return Err(ExitCode::from(EXIT_ERROR));
+    }
+
+    Ok(())
+}
+
+fn parse(path: &Path, format: ParseFormat) -> Result<(), ExitCode>
+{
+    let source = SourceFile::new(read_file(path)?);
+    let result = lexing::lex_text(source.text());
+    if !result.errors.is_empty()
+    {
+        print_diagnostics(&result.errors.iter().map(Diagnostic::from).collect_vec(), &source, path);
+        return Err(ExitCode::from(EXIT_ERROR));
+    }
+
+    let program = match parsing::parse(result.tokens.clone(), &result.comments)
+    {
+        Ok(program) => program,
+        Err(errors) =>
+        {
+            print_diagnostics(&errors.iter().map(Diagnostic::from).collect_vec(), &source, path);
+            return Err(ExitCode::from(EXIT_ERROR));
+        }
     };
 
-    let Ok(()) = file.write_all(content.as_bytes()) else {
-        return Err("Failed to write to file".into());
+    match format
+    {
+        ParseFormat::Debug => match program
+        {
+            Some(program) => println!("{:#?}", program),
+            None => println!("Empty AST"),
+        },
+        ParseFormat::Json => println!("{}", ast::json::to_json(program.as_ref())),
+        ParseFormat::Cst => print!("{}", cst::build(&result, &source, program.as_ref()).dump()),
+    }
+
+    Ok(())
+}
+
+/// Loads a file and everything it imports, then resolves names and checks types
+fn load(path: &Path) -> Result<Workspace, ExitCode>
+{
+    let mut workspace = Workspace::new(path.parent().unwrap_or(Path::new(".")));
+    if let Err(errors) = workspace.load_entry(path)
+    {
+        print_errors(&workspace, &errors);
+        return Err(ExitCode::from(EXIT_ERROR));
+    }
+
+    if let Err(errors) = workspace.check()
+    {
+        print_errors(&workspace, &errors);
+        return Err(ExitCode::from(EXIT_ERROR));
+    }
+
+    Ok(workspace)
+}
+
+fn compile(workspace: &Workspace) -> Result<codegen::chunk::Bytecode, ExitCode>
+{
+    workspace.compile().map_err(|errors| {
+        print_errors(workspace, &errors);
+        ExitCode::from(EXIT_ERROR)
+    })
+}
+
+fn run(path: &Path, use_vm: bool, args: Vec<String>) -> Result<(), ExitCode>
+{
+    let workspace = load(path)?;
+    let result = match use_vm
+    {
+        true =>
+        {
+            let mut vm = vm::Vm::new(compile(&workspace)?);
+            vm.set_args(args);
+            vm.run().map(|_| ())
+        },
+        false =>
+        {
+            let mut interpreter = interp::Interpreter::new();
+            interpreter.set_args(args);
+            interpreter.run_workspace(&workspace).map(|_| ())
+        },
     };
 
+    result.map_err(|(file, error)| {
+        let module = workspace.file(file);
+        print_diagnostics(&[Diagnostic::from(&error)], &module.source, &module.path);
+        ExitCode::from(EXIT_ERROR)
+    })
+}
+
+fn disassemble(path: &Path, function: Option<&str>) -> Result<(), ExitCode>
+{
+    let workspace = load(path)?;
+    let bytecode = compile(&workspace)?;
+
+    match function
+    {
+        None => print!("{}", codegen::disasm::disassemble(&bytecode)),
+        Some(name) => match bytecode.function(name)
+        {
+            Some(function) => print!("{}", codegen::disasm::disassemble_function(&bytecode, function)),
+            None =>
+            {
+                eprintln!("No function named `{}`", name);
+                return Err(ExitCode::from(EXIT_ERROR));
+            }
+        },
+    }
+
     Ok(())
 }
 
-fn main()
+/// Formats files in place, or with `check` only lists the files that are not formatted
+fn format_files(paths: &[PathBuf], check: bool, indent: String) -> Result<(), ExitCode>
 {
-    // let file_name = "tests/tick-tack-toe.crs";
-    let file_name = "tests/test.crs";
-    let text = read_file(file_name).unwrap();
-    // let text = "utils.Option[Int](1)";
-    let tokens = lexing::lex_text(&text);
+    let options = format::FormatOptions { indent, ..Default::default() };
+    let mut failed = false;
 
-    if tokens.errors.len() > 0 
+    for path in paths
     {
-        let mut error = "Errors: \n".to_string();
-        for e in &tokens.errors
+        let text = read_file(path)?;
+        let formatted = match format::format_text(&text, &options)
         {
-            error += &format!("- Error: {}\n", e);
+            Ok(formatted) => formatted,
+            Err(diagnostics) =>
+            {
+                print_diagnostics(&diagnostics, &SourceFile::new(text.as_str()), path);
+                failed = true;
+                continue;
+            }
+        };
+
+        if formatted == text
+        {
+            continue;
         }
 
-        println!("{}", error);
-        return;
+        if check
+        {
+            println!("{} is not formatted", path.display());
+            failed = true;
+        }
+        else if std::fs::write(path, formatted).is_err()
+        {
+            eprintln!("Could not write file: `{}`", path.display());
+            failed = true;
+        }
     }
-    
-    let ast = parsing::parse(tokens.tokens);
 
-    match ast 
+    match failed
     {
-        Ok(None) => {
-            write_to_file("./logs/log.txt", "Empty AST").unwrap();
-            println!("Compilation succeeded");
-        },
-        Ok(Some(ast)) => {
-            write_to_file("./logs/log.txt", &format!("{:#?}", ast)).unwrap();
-            println!("Compilation succeeded");
-        },
-        Err(errors) => 
-        {
-            let message = "Errors:\n".to_string() + &errors.iter()
-                .map(|e| e.format(&tokens.text, file_name))
-                .map(|e| format!(" - {}", e))
-                .join("\n");
-            
-            println!("{}", message);
+        true => Err(ExitCode::from(EXIT_ERROR)),
+        false => Ok(()),
+    }
+}
+
+/// Makes a file longer by repeating it, then times lexing and parsing it a number of times, giving the fastest run
+/// of each since that is the one least disturbed by the rest of the system
+fn bench_parse(path: &Path, copies: usize) -> Result<(), ExitCode>
+{
+    use std::time::{Duration, Instant};
+
+    const RUNS: usize = 10;
+
+    let text = vec![read_file(path)?; copies].join("\n");
+    let (mut lex_best, mut parse_best) = (Duration::MAX, Duration::MAX);
+    let mut tokens = 0;
+    for _ in 0..RUNS
+    {
+        let begin = Instant::now();
+        let lexed = lexing::lex_text(&text);
+        lex_best = lex_best.min(begin.elapsed());
+        tokens = lexed.tokens.len();
+
+        let begin = Instant::now();
+        let parsed = parsing::parse(lexed.tokens, &lexed.comments);
+        parse_best = parse_best.min(begin.elapsed());
+
+        if let Err(errors) = parsed
+        {
+            print_diagnostics(&errors.iter().map(Diagnostic::from).collect_vec(), &SourceFile::new(text), path);
+            return Err(ExitCode::from(EXIT_ERROR));
         }
     }
+
+    println!("{} lines, {} tokens, best of {} runs", SourceFile::new(text).line_count(), tokens, RUNS);
+    println!("lex:   {:>12?}", lex_best);
+    println!("parse: {:>12?}", parse_best);
+    Ok(())
+}
+
+/// Makes a file longer by repeating it, then times edits spread across it made to a `Document`, which relexes and
+/// reparses only what each changes, against lexing and parsing the edited text from scratch. Each edit is undone
+/// straight after, and the result of every edit is checked against the full parse.
+fn bench_edit(path: &Path, copies: usize) -> Result<(), ExitCode>
+{
+    use lexing::incremental::TextEdit;
+    use lexing::token::TokenType;
+    use std::time::{Duration, Instant};
+
+    let text = vec![read_file(path)?; copies].join("\n");
+    let mut document = parsing::incremental::Document::new(&text);
+    let tokens = document.lexed().tokens.clone();
+    let step = (tokens.len() / 200).max(1);
+
+    let (mut incremental, mut full) = (Duration::ZERO, Duration::ZERO);
+    let mut edits = 0;
+    for token in tokens.iter().step_by(step).filter(|t| t.token_type != TokenType::EOF)
+    {
+        let (at, inserted) = match token.token_type
+        {
+            TokenType::Identifier => (token.pos.end + 1, "x"),
+            TokenType::Fn => (token.pos.begin, "/// doc\n"),
+            _ => (token.pos.end + 1, " /* note */ "),
+        };
+
+        let length = inserted.chars().count();
+        for edit in [TextEdit::new(at..at, inserted), TextEdit::new(at..at + length, "")]
+        {
+            let begin = Instant::now();
+            document.edit(&edit);
+            incremental += begin.elapsed();
+
+            let begin = Instant::now();
+            let lexed = lexing::lex_text(document.source().text());
+            let parsed = parsing::parse(lexed.tokens.clone(), &lexed.comments);
+            full += begin.elapsed();
+            edits += 1;
+
+            let same_parse = match (document.parsed(), &parsed)
+            {
+                (Ok(a), Ok(b)) => ast::json::to_json(a.as_ref()) == ast::json::to_json(b.as_ref()),
+                (Err(a), Err(b)) => format!("{:?}", a) == format!("{:?}", b),
+                _ => false,
+            };
+
+            let errors = |errors: &[lexing::LexerError]| errors.iter().map(|e| format!("{:?}", e)).sorted().collect_vec();
+            if document.lexed().tokens != lexed.tokens
+                || document.lexed().comments != lexed.comments
+                || errors(&document.lexed().errors) != errors(&lexed.errors)
+                || !same_parse
+            {
+                eprintln!("Edit {:?} at {} does not match a full parse", edit.text, edit.range.start);
+                return Err(ExitCode::from(EXIT_ERROR));
+            }
+        }
+    }
+
+    let lines = SourceFile::new(text).line_count();
+    println!("{} lines, {} tokens, {} edits", lines, tokens.len(), edits);
+    println!("incremental: {:>12?} total, {:>10?} per edit", incremental, incremental / edits.max(1));
+    println!("full:        {:>12?} total, {:>10?} per edit", full, full / edits.max(1));
+    println!("speedup:     {:.1}x", full.as_secs_f64() / incremental.as_secs_f64().max(f64::MIN_POSITIVE));
+    Ok(())
+}
+
+fn print_errors(workspace: &Workspace, errors: &[WorkspaceError])
+{
+    let colour = diagnostic::use_colour();
+    for error in errors
+    {
+        eprintln!("{}", error.render(workspace, colour));
+    }
+}
+
+fn print_diagnostics(diagnostics: &[Diagnostic], source: &SourceFile, path: &Path)
+{
+    let colour = diagnostic::use_colour();
+    for diagnostic in diagnostics
+    {
+        eprintln!("{}", diagnostic.render(source, &path.display().to_string(), colour));
+    }
 }
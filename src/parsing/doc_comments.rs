@@ -0,0 +1,182 @@
+use either::Either;
+
+use crate::ast::*;
+use crate::lexing::token::{Comment, CommentKind, Token, TokenPos};
+
+/// Attaches doc comments to the declarations they document. A `///` comment documents the declaration right after
+/// it, and a `//!` comment the innermost declaration it is inside, or the file when it is outside all of them.
+/// Comments that document nothing are left alone, they are still in the comments of the lexer.
+pub fn attach_docs(program: &mut Program, tokens: &[Token], comments: &[Comment])
+{
+    program.docs = attach_docs_to(&mut program.declarations, tokens, comments);
+}
+
+/// Attaches doc comments to some of the declarations of a file, returning the `//!` comments among `comments` that
+/// none of them take, which document the file
+pub fn attach_docs_to(declarations: &mut [Declaration], tokens: &[Token], comments: &[Comment]) -> Vec<Comment>
+{
+    let mut attacher = DocAttacher { tokens, comments, claimed: vec![false; comments.len()] };
+    for declaration in declarations
+    {
+        attacher.declaration(declaration);
+    }
+
+    attacher.inner(TokenPos { begin: 0, end: usize::MAX })
+}
+
+struct DocAttacher<'a>
+{
+    tokens: &'a [Token],
+    comments: &'a [Comment],
+    /// Whether each comment has been attached, so a `//!` comment goes to the innermost declaration only
+    claimed: Vec<bool>,
+}
+
+impl DocAttacher<'_>
+{
+    /// Takes the `///` comments between the token before `begin` and `begin`
+    fn outer(&mut self, begin: usize) -> Vec<Comment>
+    {
+        let previous = self.tokens.partition_point(|t| t.pos.begin < begin);
+        let after = previous.checked_sub(1).map(|i| self.tokens[i].pos.end);
+        self.take(|c| c.kind == CommentKind::Doc && after.is_none_or(|a| c.pos.begin > a) && c.pos.end < begin)
+    }
+
+    /// Takes the `//!` comments inside `span` not taken by a declaration nested in it
+    fn inner(&mut self, span: TokenPos) -> Vec<Comment>
+    {
+        self.take(|c| c.kind == CommentKind::InnerDoc && c.pos.begin >= span.begin && c.pos.end <= span.end)
+    }
+
+    fn take(&mut self, matches: impl Fn(&Comment) -> bool) -> Vec<Comment>
+    {
+        let mut docs = vec![];
+        for (comment, claimed) in self.comments.iter().zip(self.claimed.iter_mut())
+        {
+            if !*claimed && matches(comment)
+            {
+                *claimed = true;
+                docs.push(comment.clone());
+            }
+        }
+
+        docs
+    }
+
+    fn declaration(&mut self, declaration: &mut Declaration)
+    {
+        let begin = declaration.span().begin;
+        match declaration
+        {
+            Declaration::Fn(_, decl) => self.fn_decl(decl, begin),
+            Declaration::Struct(_, decl) => self.struct_decl(decl, begin),
+            Declaration::Interface(_, decl) => self.interface_decl(decl, begin),
+            Declaration::Enum(_, decl) => self.enum_decl(decl, begin),
+            Declaration::Impl(decl) => for (pub_tok, member) in &mut decl.members
+            {
+                let begin = pub_tok.as_ref().map_or(member.span().begin, |t| t.pos.begin);
+                self.statement(member, begin);
+            },
+            Declaration::Mod(_, decl) => self.mod_decl(decl, begin),
+            Declaration::Type(_, _) | Declaration::Let(_, _) | Declaration::Const(_, _) | Declaration::Use(_, _) => {},
+        }
+    }
+
+    fn statement(&mut self, statement: &mut Statement, begin: usize)
+    {
+        match statement
+        {
+            Statement::FnDecl(decl) => self.fn_decl(decl, begin),
+            Statement::StructDecl(decl) => self.struct_decl(decl, begin),
+            Statement::InterfaceDecl(decl) => self.interface_decl(decl, begin),
+            Statement::EnumDecl(decl) => self.enum_decl(decl, begin),
+            Statement::While(stmt) => self.block(&mut stmt.body),
+            Statement::For(stmt) => self.block(&mut stmt.body),
+            Statement::If(stmt) => self.if_expr(stmt),
+            Statement::Block(stmt) => self.block(stmt),
+            _ => {},
+        }
+    }
+
+    fn block(&mut self, block: &mut BlockExpr)
+    {
+        for statement in &mut block.statements
+        {
+            let begin = statement.span().begin;
+            self.statement(statement, begin);
+        }
+    }
+
+    fn if_expr(&mut self, if_expr: &mut IfExpr)
+    {
+        self.block(&mut if_expr.block);
+        match &mut if_expr.else_branch
+        {
+            Some(ElseBranch { else_tok: _, body: Either::Left(if_expr) }) => self.if_expr(if_expr),
+            Some(ElseBranch { else_tok: _, body: Either::Right(block) }) => self.block(block),
+            None => {},
+        }
+    }
+
+    fn fn_decl(&mut self, decl: &mut FnDecl, begin: usize)
+    {
+        decl.docs = self.outer(begin);
+        if let Either::Left(body) = &mut decl.body
+        {
+            self.block(body);
+        }
+
+        let span = decl.span();
+        decl.docs.extend(self.inner(span));
+    }
+
+    fn struct_decl(&mut self, decl: &mut StructDecl, begin: usize)
+    {
+        decl.docs = self.outer(begin);
+        for member in &mut decl.members
+        {
+            let first = member.pub_tok.as_ref().or(member.mut_tok.as_ref()).unwrap_or(&member.id);
+            member.docs = self.outer(first.pos.begin);
+        }
+
+        decl.docs.extend(self.inner(TokenPos { begin: decl.open_brace.pos.begin, end: decl.close_brace.pos.end }));
+    }
+
+    fn interface_decl(&mut self, decl: &mut InterfaceDecl, begin: usize)
+    {
+        decl.docs = self.outer(begin);
+        for member in &mut decl.members
+        {
+            let begin = member.span().begin;
+            self.statement(member, begin);
+        }
+
+        decl.docs.extend(self.inner(TokenPos { begin: decl.open_brace.pos.begin, end: decl.close_brace.pos.end }));
+    }
+
+    fn enum_decl(&mut self, decl: &mut EnumDecl, begin: usize)
+    {
+        decl.docs = self.outer(begin);
+        for member in &mut decl.members
+        {
+            let (EnumMember::Basic { docs, id, .. } | EnumMember::Single { docs, id, .. } | EnumMember::Struct { docs, id, .. }) = member;
+            *docs = self.outer(id.pos.begin);
+        }
+
+        decl.docs.extend(self.inner(TokenPos { begin: decl.open_brace.pos.begin, end: decl.close_brace.pos.end }));
+    }
+
+    fn mod_decl(&mut self, decl: &mut ModDecl, begin: usize)
+    {
+        decl.docs = self.outer(begin);
+        if let Either::Left(body) = &mut decl.body
+        {
+            for declaration in &mut body.declarations
+            {
+                self.declaration(declaration);
+            }
+
+            decl.docs.extend(self.inner(TokenPos { begin: body.open_brace.pos.begin, end: body.close_brace.pos.end }));
+        }
+    }
+}
@@ -59,12 +59,50 @@ pub fn parse_expression(reader: &mut TokenReader) -> ParserResult<Option<Express
     {
         Ok(Some(Expression::MatchExpr(match_expr)))
     }
+    else if let Some(yield_expr) = parse_yield(reader)?
+    {
+        Ok(Some(Expression::Yield(yield_expr)))
+    }
+    else if let Some(jump) = parse_jump(reader)?
+    {
+        Ok(Some(Expression::Jump(jump)))
+    }
     else 
     {
         Ok(None)    
     }
 }
 
+pub fn parse_yield(reader: &mut TokenReader) -> ParserResult<Option<YieldExpr>>
+{
+    match reader.check(TokenType::Yield)
+    {
+        Some(yield_tok) =>
+        {
+            let expression = expect_expression(reader, parse_expression)?;
+            Ok(Some(YieldExpr { yield_tok, expression: Box::new(expression) }))
+        },
+        None => Ok(None),
+    }
+}
+
+pub fn parse_jump(reader: &mut TokenReader) -> ParserResult<Option<JumpExpr>>
+{
+    if let Some(keyword) = reader.check_many(&[TokenType::Break, TokenType::Continue])
+    {
+        Ok(Some(JumpExpr { keyword, expression: None }))
+    }
+    else if let Some(keyword) = reader.check(TokenType::Return)
+    {
+        let expression = parse_expression(reader)?.map(Box::new);
+        Ok(Some(JumpExpr { keyword, expression }))
+    }
+    else
+    {
+        Ok(None)
+    }
+}
+
 pub fn parse_match(reader: &mut TokenReader) -> ParserResult<Option<MatchExpr>>
 {
     if let Some(match_tok) = reader.check(TokenType::Match)
@@ -110,10 +148,47 @@ fn parse_match_branches(reader: &mut TokenReader) -> ParserResult<Vec<MatchBranc
 fn parse_match_branch(reader: &mut TokenReader) -> ParserResult<MatchBranch>
 {
     let pattern = expect_pattern(reader)?;
+    let guard = match reader.check(TokenType::If)
+    {
+        Some(if_tok) => Some((if_tok, Box::new(parse_guard(reader)?))),
+        None => None,
+    };
     let arrow = reader.expect(TokenType::ThickArrow)?;
     let expression = expect_expression(reader, parse_expression)?;
 
-    Ok(MatchBranch { pattern, arrow, expression: Box::new(expression) })
+    Ok(MatchBranch { pattern, guard, arrow, expression: Box::new(expression) })
+}
+
+/// Parses a match guard, which ends at the first `=>` outside brackets so that `if a == b => ...` is not read as
+/// comparing `a` with the lambda `b => ...`
+fn parse_guard(reader: &mut TokenReader) -> ParserResult<Expression>
+{
+    let tokens = reader.tokens();
+    let mut depth = 0usize;
+    let mut end = tokens.len();
+    for (i, token) in tokens.iter().enumerate().skip(reader.index())
+    {
+        match token.token_type
+        {
+            TokenType::OpenParen | TokenType::OpenBracket | TokenType::OpenBrace => depth += 1,
+            TokenType::CloseParen | TokenType::CloseBracket | TokenType::CloseBrace if depth > 0 => depth -= 1,
+            TokenType::ThickArrow if depth == 0 =>
+            {
+                end = i;
+                break;
+            },
+            _ => {},
+        }
+    }
+
+    let Some(mut guard_reader) = TokenReader::new(&tokens[..end], Some(reader.index())) else {
+        return Err(ParserError::ExpectedExpression(reader.current()));
+    };
+
+    let guard = expect_expression(&mut guard_reader, parse_expression);
+    let consumed = guard_reader.index() - reader.index();
+    reader.advance_count(consumed);
+    guard
 }
 
 pub fn parse_if(reader: &mut TokenReader) -> ParserResult<Option<IfExpr>>
@@ -202,13 +277,13 @@ fn parse_unary(reader: &mut TokenReader) -> ParserResult<Option<Expression>>
     }
     else 
     {
-        parse_call(reader)    
+        parse_cast(reader)    
     }
 }
 
 fn parse_call(reader: &mut TokenReader) -> ParserResult<Option<Expression>>
 {
-    let Some(callee) = parse_cast(reader)? else {
+    let Some(callee) = parse_primary(reader)? else {
         return Ok(None)
     };
 
@@ -300,9 +375,14 @@ fn parse_primary(reader: &mut TokenReader) -> ParserResult<Option<Expression>>
     {
         Ok(Some(con))
     }
+    else if let Some(interpolation) = parse_interpolation(reader)?
+    {
+        Ok(Some(interpolation))
+    }
     else if let Some(literal) = reader.check_many(&[
         TokenType::IntegerLiteral,
         TokenType::StringLiteral,
+        TokenType::CharLiteral,
         TokenType::FloatLiteral,
         TokenType::Identifier,
         TokenType::SelfVal,
@@ -346,6 +426,29 @@ pub fn parse_block_expression(reader: &mut TokenReader) -> ParserResult<Option<B
     }
 }
 
+fn parse_interpolation(reader: &mut TokenReader) -> ParserResult<Option<Expression>>
+{
+    let Some(start) = reader.check(TokenType::InterpolationStart) else { return Ok(None) };
+
+    let mut parts = vec![];
+    loop
+    {
+        let expression = expect_expression(reader, parse_expression)?;
+        let Some(text) = reader.check_many(&[TokenType::InterpolationMiddle, TokenType::InterpolationEnd]) else {
+            return Err(ParserError::ExpectedToken(TokenType::InterpolationEnd, reader.current()));
+        };
+        let end = text.token_type == TokenType::InterpolationEnd;
+        parts.push((expression, text));
+
+        if end
+        {
+            break;
+        }
+    }
+
+    Ok(Some(Expression::Interpolation(InterpolationExpr { start, parts })))
+}
+
 fn parse_enum_construction(reader: &mut TokenReader) -> ParserResult<Option<Expression>>
 {
     if is_type_and(reader, TypeName::is_definite).is_some()
@@ -424,24 +527,20 @@ fn parse_construction_expression(reader: &mut TokenReader) -> ParserResult<Optio
     Ok(None)
 }
 
+/// Casts apply after calls, indexing and member access, so `Status.Ok as Int` casts the member
 fn parse_cast(reader: &mut TokenReader) -> ParserResult<Option<Expression>>
 {
-    if let Some(expression) = parse_primary(reader)?
-    {
-        if let Some(as_tok) = reader.check(TokenType::As)
-        {
-            let type_name = expect_type_name(reader)?;
-            Ok(Some(Expression::Cast(CastExpr { expression: Box::new(expression), as_tok, type_name })))
-        }
-        else
-        {
-            Ok(Some(expression))
-        }
-    }
-    else 
+    let Some(mut expression) = parse_call(reader)? else {
+        return Ok(None)
+    };
+
+    while let Some(as_tok) = reader.check(TokenType::As)
     {
-        Ok(None)
+        let type_name = expect_type_name(reader)?;
+        expression = Expression::Cast(CastExpr { expression: Box::new(expression), as_tok, type_name });
     }
+
+    Ok(Some(expression))
 }
 
 fn parse_array_literal(reader: &mut TokenReader) -> ParserResult<Option<Expression>>
@@ -569,8 +668,8 @@ fn parse_lambda(reader: &mut TokenReader) -> ParserResult<Option<Expression>>
 {
     if let Some(tokens) = reader.check_sequence(&[TokenType::Identifier, TokenType::ThickArrow])
     {
-        let name = tokens[0].clone();
-        let arrow = tokens[1].clone();
+        let name = tokens[0];
+        let arrow = tokens[1];
         let Some(expression) = parse_expression(reader)? else {
             return Err(ParserError::ExpectedExpression(reader.current()));
         };
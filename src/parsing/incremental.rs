@@ -0,0 +1,701 @@
+use either::Either;
+
+use crate::ast::*;
+use crate::lexing::incremental::{Relexed, TextEdit};
+use crate::lexing::token::{Comment, Token, TokenType};
+use crate::lexing::{lex_text, LexerResult};
+use crate::source::SourceFile;
+
+use super::stmt_parsing::parse_declaration;
+use super::token_reader::TokenReader;
+use super::{doc_comments, parse, ParserError};
+
+/// A file kept lexed and parsed as it is edited, relexing and reparsing only the parts each edit can change
+#[derive(Debug)]
+pub struct Document
+{
+    source: SourceFile,
+    lexed: LexerResult,
+    parsed: Result<Option<Program>, Vec<ParserError>>,
+}
+
+impl Document
+{
+    pub fn new(text: &str) -> Self
+    {
+        let lexed = lex_text(text);
+        let parsed = parse(lexed.tokens.clone(), &lexed.comments);
+        Self { source: SourceFile::new(text), lexed, parsed }
+    }
+
+    pub fn source(&self) -> &SourceFile
+    {
+        &self.source
+    }
+
+    pub fn lexed(&self) -> &LexerResult
+    {
+        &self.lexed
+    }
+
+    pub fn parsed(&self) -> &Result<Option<Program>, Vec<ParserError>>
+    {
+        &self.parsed
+    }
+
+    /// Applies an edit, falling back to parsing the whole file when the file did not parse before or the
+    /// declarations around the edit do not parse now, so errors are reported as a full parse reports them
+    pub fn edit(&mut self, edit: &TextEdit)
+    {
+        self.source = self.source.edited(edit.range.clone(), &edit.text);
+        let relexed = self.lexed.relex(edit, &self.source);
+        let reparsed = match &mut self.parsed
+        {
+            Ok(Some(program)) => reparse(program, &self.lexed, &relexed),
+            _ => false,
+        };
+
+        if !reparsed
+        {
+            self.parsed = parse(self.lexed.tokens.clone(), &self.lexed.comments);
+        }
+    }
+}
+
+/// Reparses the top-level declarations a relex changed, keeping the others with their positions moved past the
+/// edit. A declaration is changed when the relexed text overlaps it or the comments and whitespace before it, which
+/// hold its doc comments. Parsing goes on until it reaches the first token of an untouched declaration, so an edit
+/// that opens or closes a brace takes in as many declarations as it has to. Returns false, leaving the program as it
+/// was, when what is reparsed has errors.
+pub fn reparse(program: &mut Program, lexed: &LexerResult, relexed: &Relexed) -> bool
+{
+    let old = &program.declarations;
+    let dirty = &relexed.old_text;
+    let first = old.partition_point(|d| d.span().end < dirty.start);
+    let untouched = (old.partition_point(|d| d.span().end + 1 < dirty.end) + 1).min(old.len());
+
+    let region_begin = match first
+    {
+        0 => 0,
+        _ => old[first - 1].span().end + 1,
+    };
+
+    let start = lexed.tokens.partition_point(|t| t.pos.begin < region_begin);
+    let Some(mut reader) = TokenReader::new(&lexed.tokens, Some(start)) else { return false };
+    let mut next = untouched;
+    let mut declarations = vec![];
+    loop
+    {
+        let Some(current) = reader.current() else { return false };
+        while next < old.len() && old[next].span().begin.wrapping_add_signed(relexed.delta) < current.pos.begin
+        {
+            next += 1;
+        }
+
+        if current.token_type == TokenType::EOF || old.get(next).is_some_and(|d| d.span().begin.wrapping_add_signed(relexed.delta) == current.pos.begin)
+        {
+            break;
+        }
+
+        match parse_declaration(&mut reader)
+        {
+            Ok(Some(declaration)) => declarations.push(declaration),
+            Ok(None) | Err(_) => return false,
+        }
+    }
+
+    let (old_region_end, region_end) = match old.get(next)
+    {
+        Some(declaration) => (declaration.span().begin, declaration.span().begin.wrapping_add_signed(relexed.delta)),
+        None => (usize::MAX, usize::MAX),
+    };
+
+    let comments = &lexed.comments[lexed.comments.partition_point(|c| c.pos.begin < region_begin)..lexed.comments.partition_point(|c| c.pos.begin < region_end)];
+    let file_docs = doc_comments::attach_docs_to(&mut declarations, &lexed.tokens, comments);
+
+    let mut tail = program.declarations.split_off(next);
+    tail.shift(relexed.delta);
+    program.declarations.truncate(first);
+    program.declarations.extend(declarations);
+    program.declarations.extend(tail);
+
+    let mut docs = std::mem::take(&mut program.docs);
+    let mut after = docs.split_off(docs.partition_point(|c| c.pos.begin < old_region_end));
+    after.shift(relexed.delta);
+    docs.truncate(docs.partition_point(|c| c.pos.begin < region_begin));
+    docs.extend(file_docs);
+    docs.extend(after);
+    program.docs = docs;
+
+    program.eof = *lexed.tokens.last().expect("lexing always ends with the end of file");
+    true
+}
+
+/// Moves every position in a node by a number of characters, for nodes after an edit
+trait Shift
+{
+    fn shift(&mut self, delta: isize);
+}
+
+impl Shift for Token
+{
+    fn shift(&mut self, delta: isize)
+    {
+        self.pos = self.pos.shifted(delta);
+    }
+}
+
+impl Shift for Comment
+{
+    fn shift(&mut self, delta: isize)
+    {
+        self.pos = self.pos.shifted(delta);
+    }
+}
+
+impl<T: Shift> Shift for Option<T>
+{
+    fn shift(&mut self, delta: isize)
+    {
+        if let Some(inner) = self
+        {
+            inner.shift(delta);
+        }
+    }
+}
+
+impl<T: Shift> Shift for Vec<T>
+{
+    fn shift(&mut self, delta: isize)
+    {
+        self.iter_mut().for_each(|item| item.shift(delta));
+    }
+}
+
+impl<T: Shift> Shift for Box<T>
+{
+    fn shift(&mut self, delta: isize)
+    {
+        (**self).shift(delta);
+    }
+}
+
+impl<T: Shift> Shift for &mut T
+{
+    fn shift(&mut self, delta: isize)
+    {
+        (**self).shift(delta);
+    }
+}
+
+impl<A: Shift, B: Shift> Shift for (A, B)
+{
+    fn shift(&mut self, delta: isize)
+    {
+        self.0.shift(delta);
+        self.1.shift(delta);
+    }
+}
+
+impl<A: Shift, B: Shift> Shift for Either<A, B>
+{
+    fn shift(&mut self, delta: isize)
+    {
+        match self
+        {
+            Either::Left(left) => left.shift(delta),
+            Either::Right(right) => right.shift(delta),
+        }
+    }
+}
+
+/// Shifts a struct by shifting each of its fields, naming all of them so a new field cannot be missed
+macro_rules! shift_struct
+{
+    ($($name:ident { $($field:ident),* $(,)? })*) =>
+    {
+        $(
+            impl Shift for $name
+            {
+                fn shift(&mut self, delta: isize)
+                {
+                    let $name { $($field),* } = self;
+                    $($field.shift(delta);)*
+                }
+            }
+        )*
+    };
+}
+
+shift_struct!
+{
+    GenericParams { open_bracket, params, close_bracket }
+    GenericArgs { open_bracket, args, close_bracket }
+    PatternField { mut_tok, id, colon, inner }
+    PatternLiteral { minus, token }
+    UseStmt { use_tok, ids, star, semi_colon }
+    ExpressionStmt { expression, semi_colon }
+    LetStmt { let_tok, binding, type_name, equal, expression, else_clause, semi_colon }
+    ConstDecl { const_tok, id, colon, type_name, equal, expression, semi_colon }
+    AssignStmt { value, equal, expression, semi_colon }
+    WhereSubClause { id, colon, types }
+    WhereClause { where_tok, sub_clauses }
+    FnDecl { docs, const_tok, fn_tok, id, generic_params, open_paren, params, close_paren, arrow, return_type, where_clause, body }
+    StructMember { docs, pub_tok, mut_tok, id, colon, type_name, initializer }
+    StructDecl { docs, struct_tok, id, generic_params, where_clause, open_brace, members, close_brace }
+    InterfaceDecl { docs, interface_tok, id, generic_params, where_clause, open_brace, members, close_brace }
+    EnumStructMember { mut_tok, id, colon, type_name, initializer }
+    EnumDecl { docs, enum_tok, id, generic_params, backing_type, where_clause, open_brace, members, close_brace }
+    TypeDecl { type_tok, id, generic_params, equal, type_name, semi_colon }
+    ImplStmt { impl_tok, generic_params, type_name, for_clause, where_clause, open_brace, members, close_brace }
+    ModDecl { docs, mod_tok, id, body }
+    ModBody { open_brace, declarations, close_brace }
+    BreakStmt { break_tok, semi_colon }
+    ContinueStmt { continue_tok, semi_colon }
+    ReturnStmt { return_tok, expression, semi_colon }
+    ForStmt { for_tok, pattern, in_tok, expression, body }
+    WhileStmt { while_tok, condition, body }
+    LambdaParam { name, colon, type_name }
+    LambdaExpr { params, arrow, expression }
+    CallExpr { expression, open_paren, args, close_paren }
+    IndexExpr { expression, open_bracket, indexer, close_bracket }
+    GroupingExpr { open_paren, expression, close_paren }
+    AccessExpr { expression, dot, identifier }
+    UnaryExpr { expression, operator }
+    BinaryExpr { left, operator, right }
+    BlockExpr { open_brace, statements, expression, close_brace }
+    ConstructionArg { name, colon, value }
+    ConstructionExpr { type_name, open_brace, args, close_brace }
+    EnumConstructionExpr { type_name, open_paren, expression, close_paren }
+    TypeValueExpr { type_name, dot, name }
+    MatchBranch { pattern, guard, arrow, expression }
+    MatchExpr { match_tok, expression, open_brace, branches, close_brace }
+    IfExpr { if_tok, condition, block, else_branch }
+    ElseBranch { else_tok, body }
+    ArrayLiteral { open_bracket, expressions, close_bracket }
+    CastExpr { expression, as_tok, type_name }
+    InterpolationExpr { start, parts }
+    YieldExpr { yield_tok, expression }
+    JumpExpr { keyword, expression }
+}
+
+impl Shift for TypeName
+{
+    fn shift(&mut self, delta: isize)
+    {
+        match self
+        {
+            TypeName::Identifier { name, args } =>
+            {
+                name.shift(delta);
+                args.shift(delta);
+            },
+            TypeName::Array { open_bracket, close_bracket, type_name } =>
+            {
+                open_bracket.shift(delta);
+                close_bracket.shift(delta);
+                type_name.shift(delta);
+            },
+            TypeName::Function { fn_tok, open_paren, parameter_types, close_paren, arrow, return_type } =>
+            {
+                fn_tok.shift(delta);
+                open_paren.shift(delta);
+                parameter_types.shift(delta);
+                close_paren.shift(delta);
+                arrow.shift(delta);
+                return_type.shift(delta);
+            },
+            TypeName::Access { inner, dot, name, args } =>
+            {
+                inner.shift(delta);
+                dot.shift(delta);
+                name.shift(delta);
+                args.shift(delta);
+            },
+        }
+    }
+}
+
+impl Shift for Pattern
+{
+    fn shift(&mut self, delta: isize)
+    {
+        match self
+        {
+            Pattern::Literal(literal) => literal.shift(delta),
+            Pattern::Wildcard(token) => token.shift(delta),
+            Pattern::Range { start, dots, end } =>
+            {
+                start.shift(delta);
+                dots.shift(delta);
+                end.shift(delta);
+            },
+            Pattern::Identifier { mut_tok, id } =>
+            {
+                mut_tok.shift(delta);
+                id.shift(delta);
+            },
+            Pattern::Binding { mut_tok, id, at, inner } =>
+            {
+                mut_tok.shift(delta);
+                id.shift(delta);
+                at.shift(delta);
+                inner.shift(delta);
+            },
+            Pattern::Or(patterns) => patterns.shift(delta),
+            Pattern::Grouping { open_paren, inner, close_paren } =>
+            {
+                open_paren.shift(delta);
+                inner.shift(delta);
+                close_paren.shift(delta);
+            },
+            Pattern::TypeValue { type_name, dot, id } =>
+            {
+                type_name.shift(delta);
+                dot.shift(delta);
+                id.shift(delta);
+            },
+            Pattern::EnumConstruct { type_name, open_paren, inner, close_paren } =>
+            {
+                type_name.shift(delta);
+                open_paren.shift(delta);
+                inner.shift(delta);
+                close_paren.shift(delta);
+            },
+            Pattern::StructConstruct { type_name, open_brace, patterns, close_brace } =>
+            {
+                type_name.shift(delta);
+                open_brace.shift(delta);
+                patterns.shift(delta);
+                close_brace.shift(delta);
+            },
+            Pattern::ArrayConstruct { open_bracket, patterns, close_bracket } =>
+            {
+                open_bracket.shift(delta);
+                patterns.shift(delta);
+                close_bracket.shift(delta);
+            },
+        }
+    }
+}
+
+impl Shift for LetCondition
+{
+    fn shift(&mut self, delta: isize)
+    {
+        match self
+        {
+            LetCondition::Expression(expression) => expression.shift(delta),
+            LetCondition::Pattern { let_tok, pattern, equal, expression, and, other_cond } =>
+            {
+                let_tok.shift(delta);
+                pattern.shift(delta);
+                equal.shift(delta);
+                expression.shift(delta);
+                and.shift(delta);
+                other_cond.shift(delta);
+            },
+        }
+    }
+}
+
+impl Shift for LetBinding
+{
+    fn shift(&mut self, delta: isize)
+    {
+        match self
+        {
+            LetBinding::Variable { mut_tok, id } =>
+            {
+                mut_tok.shift(delta);
+                id.shift(delta);
+            },
+            LetBinding::Pattern(pattern) => pattern.shift(delta),
+        }
+    }
+}
+
+impl Shift for FnParam
+{
+    fn shift(&mut self, delta: isize)
+    {
+        match self
+        {
+            FnParam::Normal { mut_tok, id, colon, type_name, default_value } =>
+            {
+                mut_tok.shift(delta);
+                id.shift(delta);
+                colon.shift(delta);
+                type_name.shift(delta);
+                default_value.shift(delta);
+            },
+            FnParam::SelfParam { mut_tok, self_tok } =>
+            {
+                mut_tok.shift(delta);
+                self_tok.shift(delta);
+            },
+        }
+    }
+}
+
+impl Shift for EnumMember
+{
+    fn shift(&mut self, delta: isize)
+    {
+        match self
+        {
+            EnumMember::Basic { docs, id, discriminant } =>
+            {
+                docs.shift(delta);
+                id.shift(delta);
+                discriminant.shift(delta);
+            },
+            EnumMember::Single { docs, id, open_paren, type_name, close_paren } =>
+            {
+                docs.shift(delta);
+                id.shift(delta);
+                open_paren.shift(delta);
+                type_name.shift(delta);
+                close_paren.shift(delta);
+            },
+            EnumMember::Struct { docs, id, open_brace, members, close_brace } =>
+            {
+                docs.shift(delta);
+                id.shift(delta);
+                open_brace.shift(delta);
+                members.shift(delta);
+                close_brace.shift(delta);
+            },
+        }
+    }
+}
+
+impl Shift for LambdaParams
+{
+    fn shift(&mut self, delta: isize)
+    {
+        match self
+        {
+            LambdaParams::Simple(token) => token.shift(delta),
+            LambdaParams::Complex { open_pipe, parameters, close_pipe, arrow, return_type } =>
+            {
+                open_pipe.shift(delta);
+                parameters.shift(delta);
+                close_pipe.shift(delta);
+                arrow.shift(delta);
+                return_type.shift(delta);
+            },
+        }
+    }
+}
+
+impl Shift for Declaration
+{
+    fn shift(&mut self, delta: isize)
+    {
+        match self
+        {
+            Declaration::Fn(pub_tok, decl) => (pub_tok, decl).shift(delta),
+            Declaration::Struct(pub_tok, decl) => (pub_tok, decl).shift(delta),
+            Declaration::Interface(pub_tok, decl) => (pub_tok, decl).shift(delta),
+            Declaration::Enum(pub_tok, decl) => (pub_tok, decl).shift(delta),
+            Declaration::Type(pub_tok, decl) => (pub_tok, decl).shift(delta),
+            Declaration::Let(pub_tok, decl) => (pub_tok, decl).shift(delta),
+            Declaration::Const(pub_tok, decl) => (pub_tok, decl).shift(delta),
+            Declaration::Use(pub_tok, decl) => (pub_tok, decl).shift(delta),
+            Declaration::Mod(pub_tok, decl) => (pub_tok, decl).shift(delta),
+            Declaration::Impl(decl) => decl.shift(delta),
+        }
+    }
+}
+
+impl Shift for Statement
+{
+    fn shift(&mut self, delta: isize)
+    {
+        match self
+        {
+            Statement::While(stmt) => stmt.shift(delta),
+            Statement::For(stmt) => stmt.shift(delta),
+            Statement::Return(stmt) => stmt.shift(delta),
+            Statement::Continue(stmt) => stmt.shift(delta),
+            Statement::Break(stmt) => stmt.shift(delta),
+            Statement::TypeDecl(stmt) => stmt.shift(delta),
+            Statement::EnumDecl(stmt) => stmt.shift(delta),
+            Statement::InterfaceDecl(stmt) => stmt.shift(delta),
+            Statement::StructDecl(stmt) => stmt.shift(delta),
+            Statement::FnDecl(stmt) => stmt.shift(delta),
+            Statement::Let(stmt) => stmt.shift(delta),
+            Statement::Const(stmt) => stmt.shift(delta),
+            Statement::Assign(stmt) => stmt.shift(delta),
+            Statement::If(stmt) => stmt.shift(delta),
+            Statement::Match(stmt) => stmt.shift(delta),
+            Statement::Block(stmt) => stmt.shift(delta),
+            Statement::Expression(stmt) => stmt.shift(delta),
+            Statement::Use(stmt) => stmt.shift(delta),
+        }
+    }
+}
+
+impl Shift for Expression
+{
+    fn shift(&mut self, delta: isize)
+    {
+        match self
+        {
+            Expression::Lambda(expr) => expr.shift(delta),
+            Expression::Literal(token) | Expression::Identifier(token) | Expression::SelfExpr(token) => token.shift(delta),
+            Expression::ArrayLiteral(expr) => expr.shift(delta),
+            Expression::Grouping(expr) => expr.shift(delta),
+            Expression::BlockExpr(expr) => expr.shift(delta),
+            Expression::TypeValue(expr) => expr.shift(delta),
+            Expression::Construction(expr) => expr.shift(delta),
+            Expression::EnumConstruction(expr) => expr.shift(delta),
+            Expression::Call(expr) => expr.shift(delta),
+            Expression::Access(expr) => expr.shift(delta),
+            Expression::Index(expr) => expr.shift(delta),
+            Expression::Unary(expr) => expr.shift(delta),
+            Expression::Binary(expr) => expr.shift(delta),
+            Expression::IfExpr(expr) => expr.shift(delta),
+            Expression::MatchExpr(expr) => expr.shift(delta),
+            Expression::Cast(expr) => expr.shift(delta),
+            Expression::Interpolation(expr) => expr.shift(delta),
+            Expression::Yield(expr) => expr.shift(delta),
+            Expression::Jump(expr) => expr.shift(delta),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use itertools::Itertools;
+
+    use crate::ast::json::to_json;
+    use crate::lexing::incremental::TextEdit;
+    use crate::lexing::lex_text;
+
+    use super::{parse, Document};
+
+    const TEXT: &str = "\
+/// Greets
+fn greet(name: String) -> String
+{
+    \"héllo ${name}\"
+}
+
+/* between */
+struct Point
+{
+    x: Int,
+}
+
+fn main() -> Int
+{
+    let point = Point { x: 1 };
+    println(greet(\"you\"));
+    point.x
+}
+";
+
+    /// The character index of the first `needle` in `text`, plus `offset`
+    fn at(text: &str, needle: &str, offset: usize) -> usize
+    {
+        let byte = text.find(needle).unwrap_or_else(|| panic!("{:?} is not in the text", needle));
+        text[..byte].chars().count() + offset
+    }
+
+    /// The document lexes and parses the same as its whole text lexed and parsed again
+    fn assert_matches_full_parse(document: &Document, edit: &TextEdit)
+    {
+        let lexed = lex_text(document.source().text());
+        let parsed = parse(lexed.tokens.clone(), &lexed.comments);
+        let errors = |result: &crate::lexing::LexerResult| result.errors.iter().map(|e| format!("{:?}", e)).sorted().collect_vec();
+
+        let context = format!("after replacing {:?} with {:?} in\n{}", edit.range, edit.text, document.source().text());
+        assert_eq!(document.lexed().tokens, lexed.tokens, "tokens {}", context);
+        assert_eq!(document.lexed().comments, lexed.comments, "comments {}", context);
+        assert_eq!(errors(document.lexed()), errors(&lexed), "lexer errors {}", context);
+        match (document.parsed(), &parsed)
+        {
+            (Ok(a), Ok(b)) => assert_eq!(to_json(a.as_ref()), to_json(b.as_ref()), "program {}", context),
+            (Err(a), Err(b)) => assert_eq!(format!("{:?}", a), format!("{:?}", b), "parser errors {}", context),
+            (a, b) => panic!("parsed {:?} but a full parse gives {:?} {}", a.is_ok(), b.is_ok(), context),
+        }
+    }
+
+    /// Inserts `text` at `index` and then deletes it again, checking the document against a full parse after both
+    fn insert_and_delete(index: usize, text: &str)
+    {
+        let mut document = Document::new(TEXT);
+        let length = text.chars().count();
+        for edit in [TextEdit::new(index..index, text), TextEdit::new(index..index + length, "")]
+        {
+            document.edit(&edit);
+            assert_matches_full_parse(&document, &edit);
+        }
+        assert_eq!(document.source().text(), TEXT);
+    }
+
+    /// Deletes the characters at `range` and then puts them back, checking the document against a full parse after both
+    fn delete_and_restore(range: std::ops::Range<usize>)
+    {
+        let mut document = Document::new(TEXT);
+        let removed: String = TEXT.chars().skip(range.start).take(range.len()).collect();
+        for edit in [TextEdit::new(range.clone(), ""), TextEdit::new(range.start..range.start, removed)]
+        {
+            document.edit(&edit);
+            assert_matches_full_parse(&document, &edit);
+        }
+        assert_eq!(document.source().text(), TEXT);
+    }
+
+    #[test]
+    fn quotes()
+    {
+        insert_and_delete(at(TEXT, "let point", 0), "\"");
+        insert_and_delete(at(TEXT, "greet(\"you", 6), "\"");
+        delete_and_restore(at(TEXT, "\"you", 0)..at(TEXT, "\"you", 1));
+        delete_and_restore(at(TEXT, "\"héllo", 0)..at(TEXT, "\"héllo", 1));
+    }
+
+    #[test]
+    fn block_comments()
+    {
+        insert_and_delete(at(TEXT, "struct", 0), "/*");
+        insert_and_delete(at(TEXT, "fn main", 0), "/* closed */");
+        insert_and_delete(at(TEXT, "point.x", 0), "*/");
+        delete_and_restore(at(TEXT, "/* between", 0)..at(TEXT, "/* between", 2));
+        delete_and_restore(at(TEXT, "between */", 8)..at(TEXT, "between */", 10));
+    }
+
+    #[test]
+    fn interpolations()
+    {
+        insert_and_delete(at(TEXT, "\"you", 1), "${");
+        insert_and_delete(at(TEXT, "\"you", 1), "${point.x}");
+        delete_and_restore(at(TEXT, "${name}", 0)..at(TEXT, "${name}", 2));
+        delete_and_restore(at(TEXT, "name}\"", 4)..at(TEXT, "name}\"", 5));
+    }
+
+    #[test]
+    fn braces()
+    {
+        insert_and_delete(at(TEXT, "    x: Int", 0), "{");
+        insert_and_delete(at(TEXT, "    x: Int", 0), "}");
+        insert_and_delete(at(TEXT, "    let point", 0), "{ ");
+        delete_and_restore(at(TEXT, "}\n\n/*", 0)..at(TEXT, "}\n\n/*", 1));
+        delete_and_restore(at(TEXT, "{\n    x", 0)..at(TEXT, "{\n    x", 1));
+    }
+
+    #[test]
+    fn end_of_file()
+    {
+        let end = TEXT.chars().count();
+        insert_and_delete(end, "fn extra() -> Int { 1 }\n");
+        insert_and_delete(end, "fn unfinished(");
+        insert_and_delete(end, "\"");
+        insert_and_delete(end, "/*");
+        delete_and_restore(end - 2..end);
+    }
+}
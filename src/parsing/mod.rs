@@ -3,6 +3,8 @@ pub mod type_parsing;
 pub mod expr_parsing;
 pub mod stmt_parsing;
 pub mod pattern_parsing;
+pub mod doc_comments;
+pub mod incremental;
 
 use pattern_parsing::expect_pattern;
 use stmt_parsing::parse_declaration;
@@ -11,7 +13,8 @@ pub use expr_parsing::*;
 
 use token_reader::TokenReader;
 
-use crate::lexing::token::{Token, TokenTextLocation, TokenType};
+use crate::diagnostic::Diagnostic;
+use crate::lexing::token::{Comment, Token, TokenType};
 use crate::ast::*;
 
 #[derive(Debug)]
@@ -30,33 +33,58 @@ pub enum ParserError
 
 impl ParserError
 {
-    pub fn format(&self, text: &[char], file: &str) -> String 
+    /// The token the error was found at, or `None` at the end of the input
+    pub fn get_token(&self) -> Option<&Token>
     {
-        let line_count = text.iter().filter(|f| **f == '\n').count() + 1;
-        let end_loc = TokenTextLocation { line: line_count, column: 1 };
-        
-        let formatter = |token: &Option<Token>, error: &str| { 
-            format!("[{}:{}]: {}", file, token.as_ref().map_or(end_loc, |t| t.get_loc(text)), error)
-        };
-
         match self
         {
-            ParserError::ExpectedExpression(token) => formatter(token, "Expected an expression"),
-            ParserError::ExpectedType(token) => formatter(token, "Expected a type"),
-            ParserError::ExpectedToken(token_type, token) => formatter(token, &format!("Expected token {:?} ", token_type)),
-            ParserError::ExpectedTokens(token_types, token) => formatter(token, &format!("Expected one of token {:?} ", token_types.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>())),
-            ParserError::ExpectedALambdaParameter(token) => formatter(token, "Expected a lambda parameter"),
-            ParserError::ExpectedStatement(token) => formatter(token, "Expected a statement"),
-            ParserError::ExpectedPattern(token) => formatter(token, "Expected a pattern"),
-            ParserError::ExpectedBlock(token) => formatter(token, "Expected a block expression"),
-            ParserError::ExpectedDeclaration(token) => formatter(token, "Expected a declaration"),
+            ParserError::ExpectedExpression(token)
+            | ParserError::ExpectedType(token)
+            | ParserError::ExpectedToken(_, token)
+            | ParserError::ExpectedTokens(_, token)
+            | ParserError::ExpectedALambdaParameter(token)
+            | ParserError::ExpectedStatement(token)
+            | ParserError::ExpectedPattern(token)
+            | ParserError::ExpectedBlock(token)
+            | ParserError::ExpectedDeclaration(token) => token.as_ref(),
         }
     }
 }
 
+impl From<&ParserError> for Diagnostic
+{
+    fn from(error: &ParserError) -> Self
+    {
+        let (code, expected) = match error
+        {
+            ParserError::ExpectedExpression(_) => ("E0101", "an expression".to_owned()),
+            ParserError::ExpectedType(_) => ("E0102", "a type".to_owned()),
+            ParserError::ExpectedToken(token_type, _) => ("E0103", token_type.describe()),
+            ParserError::ExpectedTokens(token_types, _) => ("E0104", match token_types.split_last()
+            {
+                Some((last, [])) => last.describe(),
+                Some((last, rest)) => format!("{} or {}", rest.iter().map(|t| t.describe()).collect::<Vec<_>>().join(", "), last.describe()),
+                None => "another token".to_owned(),
+            }),
+            ParserError::ExpectedALambdaParameter(_) => ("E0105", "a lambda parameter".to_owned()),
+            ParserError::ExpectedStatement(_) => ("E0106", "a statement".to_owned()),
+            ParserError::ExpectedPattern(_) => ("E0107", "a pattern".to_owned()),
+            ParserError::ExpectedBlock(_) => ("E0108", "a block".to_owned()),
+            ParserError::ExpectedDeclaration(_) => ("E0109", "a declaration".to_owned()),
+        };
+
+        // Errors without a token were found after the last one, so they point past the end of the text
+        let found = error.get_token().map_or(TokenType::EOF, |t| t.token_type).describe();
+        let diagnostic = Diagnostic::error(format!("Expected {} but found {}", expected, found)).with_code(code);
+        let pos = error.get_token().map_or(usize::MAX.into(), |t| t.pos);
+        diagnostic.with_primary(pos, format!("expected {}", expected))
+    }
+}
+
 pub type ParserResult<T> = Result<T, ParserError>;
 
-pub fn parse(tokens: Vec<Token>) -> Result<Option<Program>, Vec<ParserError>>
+/// Parses a file, attaching its doc comments to the declarations they document
+pub fn parse(tokens: Vec<Token>, comments: &[Comment]) -> Result<Option<Program>, Vec<ParserError>>
 {
     let Some(mut reader) = TokenReader::new(&tokens, None) else { return Ok(None) };
     let mut declarations = vec![];
@@ -70,7 +98,7 @@ pub fn parse(tokens: Vec<Token>) -> Result<Option<Program>, Vec<ParserError>>
             Ok(None) => break,
             Err(err) => {
                 errors.push(err);
-                reader.synchronize(&[TokenType::EOF, TokenType::Let, TokenType::Const, TokenType::Fn, TokenType::Struct, TokenType::Impl, TokenType::Enum, TokenType::Interface]);
+                reader.synchronize(&[TokenType::EOF, TokenType::Let, TokenType::Const, TokenType::Fn, TokenType::Struct, TokenType::Impl, TokenType::Enum, TokenType::Interface, TokenType::Mod]);
             },
         }
     }
@@ -88,7 +116,9 @@ pub fn parse(tokens: Vec<Token>) -> Result<Option<Program>, Vec<ParserError>>
         return Err(errors)
     }
 
-    Ok(Some(Program { declarations, eof }))
+    let mut program = Program { docs: vec![], declarations, eof };
+    doc_comments::attach_docs(&mut program, &tokens, comments);
+    Ok(Some(program))
 }
 
 fn expect_let_condition(reader: &mut TokenReader) -> ParserResult<LetCondition>
@@ -1,4 +1,4 @@
-use crate::{ast::{Pattern, PatternField, TypeName}, lexing::token::TokenType, parsing::peek_type};
+use crate::{ast::{Pattern, PatternField, PatternLiteral, TypeName}, lexing::token::{TokenType, TokenValue}, parsing::peek_type};
 
 use super::{token_reader::TokenReader, ParserError, ParserResult};
 
@@ -15,6 +15,28 @@ pub fn expect_pattern(reader: &mut TokenReader) -> ParserResult<Pattern>
 }
 
 pub fn parse_pattern(reader: &mut TokenReader) -> ParserResult<Option<Pattern>>
+{
+    let Some(first) = parse_single_pattern(reader)? else { return Ok(None) };
+    if !reader.current_is(&[TokenType::Pipe])
+    {
+        return Ok(Some(first));
+    }
+
+    let mut patterns = vec![first];
+    while reader.check(TokenType::Pipe).is_some()
+    {
+        match parse_single_pattern(reader)?
+        {
+            Some(pattern) => patterns.push(pattern),
+            None => return Err(ParserError::ExpectedPattern(reader.current())),
+        }
+    }
+
+    Ok(Some(Pattern::Or(patterns)))
+}
+
+/// A pattern without alternatives
+fn parse_single_pattern(reader: &mut TokenReader) -> ParserResult<Option<Pattern>>
 {
     if let Some((type_name, offset)) = peek_type(reader)
     {
@@ -53,20 +75,77 @@ pub fn parse_pattern(reader: &mut TokenReader) -> ParserResult<Option<Pattern>>
         return Ok(Some(Pattern::ArrayConstruct { open_bracket, patterns, close_bracket }));
     }
 
-    if let Some(literal) = reader.check_many(&[TokenType::IntegerLiteral, TokenType::FloatLiteral, TokenType::StringLiteral]) {
-        return Ok(Some(Pattern::Literal(literal)));
+    if let Some(open_paren) = reader.check(TokenType::OpenParen)
+    {
+        let inner = expect_pattern(reader)?;
+        let close_paren = reader.expect(TokenType::CloseParen)?;
+
+        return Ok(Some(Pattern::Grouping { open_paren, inner: Box::new(inner), close_paren }));
     }
 
-    if let Some(mut_tok) = reader.check(TokenType::Mut) {
-        let id = reader.expect(TokenType::Identifier)?;
-        return Ok(Some(Pattern::Identifier { mut_tok: Some(mut_tok), id }))
+    if let Some(start) = parse_pattern_literal(reader)?
+    {
+        if !matches!(start.token.token_type, TokenType::IntegerLiteral | TokenType::CharLiteral)
+        {
+            return Ok(Some(Pattern::Literal(start)));
+        }
+
+        if let Some(dots) = reader.check(TokenType::DotDotEqual)
+        {
+            let minus = match start.token.token_type
+            {
+                TokenType::IntegerLiteral => reader.check(TokenType::Minus),
+                _ => None,
+            };
+            let end = PatternLiteral { minus, token: reader.expect(start.token.token_type)? };
+            return Ok(Some(Pattern::Range { start, dots, end }));
+        }
+
+        return Ok(Some(Pattern::Literal(start)));
     }
 
-    if let Some(id) = reader.check(TokenType::Identifier) {
-        return Ok(Some(Pattern::Identifier { mut_tok: None, id }));
+    let mut_tok = reader.check(TokenType::Mut);
+    if mut_tok.is_none() && !reader.current_is(&[TokenType::Identifier])
+    {
+        return Ok(None);
+    }
+
+    let id = reader.expect(TokenType::Identifier)?;
+    if let Some(at) = reader.check(TokenType::At)
+    {
+        let Some(inner) = parse_single_pattern(reader)? else {
+            return Err(ParserError::ExpectedPattern(reader.current()));
+        };
+        return Ok(Some(Pattern::Binding { mut_tok, id, at, inner: Box::new(inner) }));
+    }
+
+    if mut_tok.is_none() && matches!(id.value, Some(TokenValue::String(name)) if &*name.as_str() == "_")
+    {
+        return Ok(Some(Pattern::Wildcard(id)));
+    }
+
+    Ok(Some(Pattern::Identifier { mut_tok, id }))
+}
+
+/// A literal, with the `-` of a negative number
+fn parse_pattern_literal(reader: &mut TokenReader) -> ParserResult<Option<PatternLiteral>>
+{
+    if reader.current_is(&[TokenType::Minus])
+    {
+        let minus = reader.advance();
+        let token = reader.expect_many(&[TokenType::IntegerLiteral, TokenType::FloatLiteral])?;
+        return Ok(Some(PatternLiteral { minus, token }));
     }
 
-    Ok(None)
+    let literal = reader.check_many(&[
+        TokenType::IntegerLiteral,
+        TokenType::FloatLiteral,
+        TokenType::StringLiteral,
+        TokenType::CharLiteral,
+        TokenType::True,
+        TokenType::False,
+    ]);
+    Ok(literal.map(|token| PatternLiteral { minus: None, token }))
 }
 
 fn parse_array_patterns(reader: &mut TokenReader) -> ParserResult<Vec<Pattern>>
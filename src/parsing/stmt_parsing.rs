@@ -58,6 +58,10 @@ pub fn parse_statement(reader: &mut TokenReader) -> ParserResult<Option<Statemen
     {
         Ok(Some(Statement::Let(stmt)))
     }
+    else if let Some(stmt) = parse_const(reader)?
+    {
+        Ok(Some(Statement::Const(stmt)))
+    }
     else if let Some(stmt) = parse_assignment(reader)?
     {
         Ok(Some(Statement::Assign(stmt)))
@@ -127,11 +131,21 @@ pub fn parse_declaration(reader: &mut TokenReader) -> ParserResult<Option<Declar
         return Ok(Some(Declaration::Let(pub_tok, stmt)));
     }
 
+    if let Some(stmt) = parse_const(reader)?
+    {
+        return Ok(Some(Declaration::Const(pub_tok, stmt)));
+    }
+
     if let Some(stmt) = parse_use_stmt(reader)?
     {
         return Ok(Some(Declaration::Use(pub_tok, stmt)));
     }
 
+    if let Some(stmt) = parse_mod_decl(reader)?
+    {
+        return Ok(Some(Declaration::Mod(pub_tok, stmt)));
+    }
+
     if let Some(stmt) = parse_impl_stmt(reader)?
     {
         return Ok(Some(Declaration::Impl(stmt)));
@@ -173,9 +187,15 @@ fn parse_for(reader: &mut TokenReader) -> ParserResult<Option<ForStmt>>
     Ok(Some(ForStmt { for_tok, pattern, in_tok, expression, body }))
 }
 
+// `break`, `continue` and `return` without a `;` before a `}` are left for the block's final expression
+
 fn parse_break(reader: &mut TokenReader) -> ParserResult<Option<BreakStmt>>
 {
-    if let Some(break_tok) = reader.check(TokenType::Break)
+    if reader.peek_sequence_is(0, &[TokenType::Break, TokenType::CloseBrace])
+    {
+        Ok(None)
+    }
+    else if let Some(break_tok) = reader.check(TokenType::Break)
     {
         let semi_colon = reader.expect(TokenType::SemiColon)?;
         Ok(Some(BreakStmt { break_tok, semi_colon }))
@@ -188,7 +208,11 @@ fn parse_break(reader: &mut TokenReader) -> ParserResult<Option<BreakStmt>>
 
 fn parse_continue(reader: &mut TokenReader) -> ParserResult<Option<ContinueStmt>>
 {
-    if let Some(continue_tok) = reader.check(TokenType::Continue)
+    if reader.peek_sequence_is(0, &[TokenType::Continue, TokenType::CloseBrace])
+    {
+        Ok(None)
+    }
+    else if let Some(continue_tok) = reader.check(TokenType::Continue)
     {
         let semi_colon = reader.expect(TokenType::SemiColon)?;
         Ok(Some(ContinueStmt { continue_tok, semi_colon }))
@@ -201,9 +225,16 @@ fn parse_continue(reader: &mut TokenReader) -> ParserResult<Option<ContinueStmt>
 
 fn parse_return(reader: &mut TokenReader) -> ParserResult<Option<ReturnStmt>>
 {
+    let start = reader.clone();
     if let Some(return_tok) = reader.check(TokenType::Return)
     {
         let expression = parse_expression(reader)?;
+        if reader.current_is(&[TokenType::CloseBrace])
+        {
+            *reader = start;
+            return Ok(None);
+        }
+
         let semi_colon = reader.expect(TokenType::SemiColon)?;
         Ok(Some(ReturnStmt { return_tok, expression, semi_colon }))
     }
@@ -266,6 +297,10 @@ fn parse_impl_member(reader: &mut TokenReader) -> ParserResult<Option<(Option<To
     {
         Some(Statement::TypeDecl(type_decl))
     }
+    else if let Some(const_decl) = parse_const(reader)?
+    {
+        Some(Statement::Const(const_decl))
+    }
     else 
     {
         None  
@@ -273,7 +308,7 @@ fn parse_impl_member(reader: &mut TokenReader) -> ParserResult<Option<(Option<To
 
     if pub_tok.is_some() && member.is_none()
     {
-        Err(ParserError::ExpectedTokens(vec![TokenType::Let, TokenType::Fn, TokenType::Type], reader.current()))
+        Err(ParserError::ExpectedTokens(vec![TokenType::Let, TokenType::Fn, TokenType::Type, TokenType::Const], reader.current()))
     }
     else if let Some(member) = member
     {
@@ -312,6 +347,9 @@ fn parse_enum_decl(reader: &mut TokenReader) -> ParserResult<Option<EnumDecl>>
     let Some(enum_tok) = reader.check(TokenType::Enum) else { return Ok(None); };
     let id = reader.expect(TokenType::Identifier)?;
     let generic_params = parse_generic_params(reader)?;
+    let backing_type = if let Some(colon) = reader.check(TokenType::Colon) {
+        Some((colon, expect_type_name(reader)?))
+    } else { None };
     let where_clause = parse_where_clause(reader)?;
     let open_brace = reader.expect(TokenType::OpenBrace)?;
 
@@ -325,9 +363,11 @@ fn parse_enum_decl(reader: &mut TokenReader) -> ParserResult<Option<EnumDecl>>
     let close_brace = reader.expect(TokenType::CloseBrace)?;
 
     Ok(Some(EnumDecl { 
+        docs: vec![], 
         enum_tok, 
         id, 
         generic_params, 
+        backing_type, 
         where_clause, 
         open_brace, 
         members, 
@@ -352,17 +392,20 @@ fn parse_enum_member(reader: &mut TokenReader) -> ParserResult<Option<EnumMember
 
         let close_brace = reader.expect(TokenType::CloseBrace)?;
         
-        Ok(Some(EnumMember::Struct { id, open_brace, members, close_brace }))
+        Ok(Some(EnumMember::Struct { docs: vec![], id, open_brace, members, close_brace }))
     }
     else if let Some(open_paren) = reader.check(TokenType::OpenParen)
     {
         let type_name = expect_type_name(reader)?;
         let close_paren = reader.expect(TokenType::CloseParen)?;
-        Ok(Some(EnumMember::Single { id, open_paren, type_name, close_paren }))
+        Ok(Some(EnumMember::Single { docs: vec![], id, open_paren, type_name, close_paren }))
     }
     else 
     {
-        Ok(Some(EnumMember::Basic(id)))    
+        let discriminant = if let Some(equal) = reader.check(TokenType::Equal) {
+            Some((equal, Box::new(expect_expression(reader, parse_expression)?)))
+        } else { None };
+        Ok(Some(EnumMember::Basic { docs: vec![], id, discriminant }))    
     }
 }
 
@@ -402,12 +445,13 @@ fn parse_interface_decl(reader: &mut TokenReader) -> ParserResult<Option<Interfa
     while let Some(member) = parse_fn_decl(reader)?
     {
         members.push(Statement::FnDecl(member));
-        if reader.check(TokenType::Comma).is_none() { break; }
+        let _ = reader.check(TokenType::Comma); // members end with `;`, so a comma between them is optional
     }
 
     let close_brace = reader.expect(TokenType::CloseBrace)?;
 
     Ok(Some(InterfaceDecl { 
+        docs: vec![], 
         interface_tok, 
         id, 
         generic_params, 
@@ -436,6 +480,7 @@ fn parse_struct_decl(reader: &mut TokenReader) -> ParserResult<Option<StructDecl
     let close_brace = reader.expect(TokenType::CloseBrace)?;
 
     Ok(Some(StructDecl { 
+        docs: vec![], 
         struct_tok, 
         id, 
         generic_params, 
@@ -465,6 +510,7 @@ fn parse_struct_member(reader: &mut TokenReader) -> ParserResult<Option<StructMe
     } else { None };
 
     Ok(Some(StructMember { 
+        docs: vec![], 
         pub_tok, 
         mut_tok, 
         id,
@@ -476,6 +522,12 @@ fn parse_struct_member(reader: &mut TokenReader) -> ParserResult<Option<StructMe
 
 fn parse_fn_decl(reader: &mut TokenReader) -> ParserResult<Option<FnDecl>>
 {
+    let const_tok = match reader.is_sequence(&[TokenType::Const, TokenType::Fn])
+    {
+        true => reader.advance(),
+        false => None,
+    };
+
     let Some(fn_tok) = reader.check(TokenType::Fn) else { return Ok(None) };
     let id = reader.expect(TokenType::Identifier)?;
     let generic_params = parse_generic_params(reader)?;
@@ -502,6 +554,8 @@ fn parse_fn_decl(reader: &mut TokenReader) -> ParserResult<Option<FnDecl>>
     };
 
     Ok(Some(FnDecl { 
+        docs: vec![], 
+        const_tok, 
         fn_tok, 
         id, 
         generic_params, 
@@ -641,6 +695,30 @@ fn parse_let(reader: &mut TokenReader) -> ParserResult<Option<LetStmt>>
     }
 }
 
+fn parse_const(reader: &mut TokenReader) -> ParserResult<Option<ConstDecl>>
+{
+    // `const fn` starts a function
+    if reader.is_sequence(&[TokenType::Const, TokenType::Fn]) { return Ok(None) }
+    let Some(const_tok) = reader.check(TokenType::Const) else { return Ok(None) };
+
+    let id = reader.expect(TokenType::Identifier)?;
+    let colon = reader.expect(TokenType::Colon)?;
+    let type_name = expect_type_name(reader)?;
+    let equal = reader.expect(TokenType::Equal)?;
+    let expression = expect_expression(reader, parse_expression)?;
+    let semi_colon = reader.expect(TokenType::SemiColon)?;
+
+    Ok(Some(ConstDecl {
+        const_tok,
+        id,
+        colon,
+        type_name,
+        equal,
+        expression,
+        semi_colon,
+    }))
+}
+
 fn parse_expression_stmt(reader: &mut TokenReader) -> ParserResult<Option<ExpressionStmt>>
 {
     if let Some(expression) = is_expression_and(reader, |r| r.current_is(&[TokenType::SemiColon]))
@@ -690,6 +768,30 @@ fn parse_use_stmt(reader: &mut TokenReader) -> ParserResult<Option<UseStmt>>
     }
 }
 
+fn parse_mod_decl(reader: &mut TokenReader) -> ParserResult<Option<ModDecl>>
+{
+    let Some(mod_tok) = reader.check(TokenType::Mod) else {
+        return Ok(None)
+    };
+
+    let id = reader.expect(TokenType::Identifier)?;
+    if let Some(semi_colon) = reader.check(TokenType::SemiColon)
+    {
+        return Ok(Some(ModDecl { docs: vec![], mod_tok, id, body: Either::Right(semi_colon) }));
+    }
+
+    let open_brace = reader.expect(TokenType::OpenBrace)?;
+
+    let mut declarations = vec![];
+    while let Some(declaration) = parse_declaration(reader)?
+    {
+        declarations.push(declaration);
+    }
+
+    let close_brace = reader.expect(TokenType::CloseBrace)?;
+
+    Ok(Some(ModDecl { docs: vec![], mod_tok, id, body: Either::Left(ModBody { open_brace, declarations, close_brace }) }))
+}
 
 fn parse_assignment(reader: &mut TokenReader) -> ParserResult<Option<AssignStmt>>
 {
@@ -40,7 +40,7 @@ impl<'a> TokenReader<'a>
     {
         if !self.at_end()
         {
-            Some(self.tokens[self.index].clone())
+            Some(self.tokens[self.index])
         }
         else 
         {
@@ -57,7 +57,7 @@ impl<'a> TokenReader<'a>
     {
         if !self.at_end()
         {
-            let token = self.tokens[self.index].clone();
+            let token = self.tokens[self.index];
             self.index += 1;
             Some(token)
         }
@@ -71,7 +71,7 @@ impl<'a> TokenReader<'a>
     {
         if self.index + count < self.tokens.len()
         {
-            Some(self.tokens[self.index + count].clone())
+            Some(self.tokens[self.index + count])
         }
         else 
         {
@@ -93,7 +93,7 @@ impl<'a> TokenReader<'a>
     {
         if self.index != 0
         {
-            Some(self.tokens[self.index - 1].clone())
+            Some(self.tokens[self.index - 1])
         }
         else 
         {
@@ -0,0 +1,350 @@
+use std::io::{BufRead, Write};
+
+use itertools::Itertools;
+
+use crate::{ast::*, diagnostic::{self, Diagnostic}, interp::{value::Value, Interpreter}, lexing::{self, token::{Token, TokenType}}, parsing::{expr_parsing::parse_expression, stmt_parsing::{parse_declaration, parse_statement}, token_reader::TokenReader, ParserError, ParserResult}, source::SourceFile, typeck::TypeChecker};
+
+/// The file name errors are reported in
+const FILE_NAME: &str = "<repl>";
+
+const HELP: &str = "\
+Enter declarations, statements or expressions. The value of a trailing expression is printed.
+Input continues over several lines while braces, brackets or parentheses are left open.
+
+Commands:
+    :type <expr>    Print the type of an expression without evaluating it
+    :ast <input>    Print the syntax tree of the input without running it
+    :help           Print this message
+    :quit           Leave the REPL";
+
+/// One piece of parsed input
+#[derive(Debug)]
+enum Entry
+{
+    Declaration(Declaration),
+    Statement(Statement),
+    /// An expression ending the input without a semicolon, whose value is echoed
+    Expression(Expression),
+}
+
+/// The text of every input so far, with the tokens of the newest one
+struct Source
+{
+    source: SourceFile,
+    tokens: Vec<Token>,
+    /// The index of the first token of the newest input
+    start: usize,
+}
+
+/// Keeps a checker and an interpreter alive between inputs, so later lines can use what earlier ones declared
+pub struct Repl
+{
+    interpreter: Interpreter,
+    checker: TypeChecker,
+    /// Every input so far. New input is lexed after it, so the tokens of earlier declarations still point at their text.
+    history: String,
+}
+
+impl Default for Repl
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+impl Repl
+{
+    pub fn new() -> Self
+    {
+        Self { interpreter: Interpreter::new(), checker: TypeChecker::new(), history: String::new() }
+    }
+
+    /// Reads and runs input from stdin until it is closed or `:quit` is entered
+    pub fn run(&mut self)
+    {
+        println!("Cirrus REPL, enter `:help` for help");
+
+        let mut input = String::new();
+        loop
+        {
+            print!("{}", if input.is_empty() { "> " } else { "... " });
+            let _ = std::io::stdout().flush();
+
+            let mut line = String::new();
+            match std::io::stdin().lock().read_line(&mut line)
+            {
+                Ok(0) | Err(_) =>
+                {
+                    println!();
+                    break;
+                },
+                Ok(_) => input += &line,
+            }
+
+            // A blank line submits input that is still incomplete, so its errors are shown
+            if !line.trim().is_empty() && !is_complete(&input)
+            {
+                continue;
+            }
+
+            let input = std::mem::take(&mut input);
+            if !self.handle(input.trim())
+            {
+                break;
+            }
+        }
+    }
+
+    /// Runs one complete input. Returns false when the REPL should stop.
+    pub fn handle(&mut self, input: &str) -> bool
+    {
+        if input.is_empty()
+        {
+            return true;
+        }
+
+        let Some(command) = input.strip_prefix(':') else {
+            self.execute(input);
+            return true;
+        };
+
+        let (name, argument) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+        match name
+        {
+            "quit" | "q" => return false,
+            "help" | "h" => println!("{}", HELP),
+            "type" | "t" => self.show_type(argument.trim()),
+            "ast" => self.show_ast(argument.trim()),
+            _ => eprintln!("Unknown command `:{}`, enter `:help` for the list of commands", name),
+        }
+
+        true
+    }
+
+    fn execute(&mut self, input: &str)
+    {
+        let Some(source) = self.lex(input) else { return };
+        let entries = match parse_entries(&source)
+        {
+            Ok(entries) => entries,
+            Err(error) => return print_errors(&[Diagnostic::from(&error)], &source.source),
+        };
+
+        let (declarations, rest): (Vec<Entry>, Vec<Entry>) = entries.into_iter().partition(|e| matches!(e, Entry::Declaration(_)));
+        let declarations: Vec<Declaration> = declarations.into_iter().filter_map(|e| match e
+        {
+            Entry::Declaration(declaration) => Some(declaration),
+            _ => None,
+        }).collect();
+
+        if let Some(Declaration::Use(_, use_stmt)) = declarations.iter().find(|d| matches!(d, Declaration::Use(_, _)))
+        {
+            let token = &use_stmt.use_tok;
+            let diagnostic = Diagnostic::error("`use` is not supported in the REPL").with_primary(token.pos, "cannot import here");
+            return print_errors(&[diagnostic], &source.source);
+        }
+
+        if let Some(Declaration::Mod(_, decl)) = declarations.iter().find(|d| matches!(d, Declaration::Mod(_, _)))
+        {
+            let token = &decl.mod_tok;
+            let diagnostic = Diagnostic::error("`mod` is not supported in the REPL").with_primary(token.pos, "cannot declare a module here");
+            return print_errors(&[diagnostic], &source.source);
+        }
+
+        // Declarations are hoisted above the statements they arrive with, as they are at the top of a file
+        let eof = source.tokens.last().cloned().expect("lexing always produces an end of file token");
+        let program = Program { docs: vec![], declarations, eof };
+        self.checker.check_program(&program);
+        for entry in &rest
+        {
+            match entry
+            {
+                Entry::Statement(statement) => self.checker.check_statement(statement),
+                Entry::Expression(expression) => { self.checker.check_expression(expression); },
+                Entry::Declaration(_) => unreachable!("declarations were separated out"),
+            }
+        }
+
+        let errors = self.checker.take_errors();
+        if !errors.is_empty()
+        {
+            return print_errors(&errors.iter().map(Diagnostic::from).collect_vec(), &source.source);
+        }
+
+        let result = self.interpreter.load_program(&program).and_then(|()| {
+            let mut echo = None;
+            for entry in &rest
+            {
+                match entry
+                {
+                    Entry::Statement(statement) => self.interpreter.execute_global(statement)?,
+                    Entry::Expression(expression) => echo = Some(self.interpreter.eval_global(expression)?),
+                    Entry::Declaration(_) => unreachable!("declarations were separated out"),
+                }
+            }
+
+            Ok(echo)
+        });
+
+        match result
+        {
+            Ok(None | Some(Value::Void)) => {},
+            Ok(Some(value)) => println!("{}", value),
+            Err(error) => print_errors(&[Diagnostic::from(&error)], &source.source),
+        }
+    }
+
+    fn show_type(&mut self, input: &str)
+    {
+        let Some(source) = self.lex(input) else { return };
+        let expression = match parse_single_expression(&source)
+        {
+            Ok(expression) => expression,
+            Err(error) => return print_errors(&[Diagnostic::from(&error)], &source.source),
+        };
+
+        let type_name = self.checker.check_expression(&expression);
+        let errors = self.checker.take_errors();
+        match errors.is_empty()
+        {
+            true => println!("{}", type_name),
+            false => print_errors(&errors.iter().map(Diagnostic::from).collect_vec(), &source.source),
+        }
+    }
+
+    fn show_ast(&mut self, input: &str)
+    {
+        let Some(source) = self.lex(input) else { return };
+        match parse_entries(&source)
+        {
+            Ok(entries) => for entry in entries
+            {
+                match entry
+                {
+                    Entry::Declaration(declaration) => println!("{:#?}", declaration),
+                    Entry::Statement(statement) => println!("{:#?}", statement),
+                    Entry::Expression(expression) => println!("{:#?}", expression),
+                }
+            },
+            Err(error) => print_errors(&[Diagnostic::from(&error)], &source.source),
+        }
+    }
+
+    /// Lexes new input after everything entered before it
+    fn lex(&mut self, input: &str) -> Option<Source>
+    {
+        let previous = self.history.len();
+        if !self.history.is_empty()
+        {
+            self.history.push('\n');
+        }
+
+        let offset = self.history.chars().count();
+        self.history += input;
+
+        let source = SourceFile::new(self.history.as_str());
+        let result = lexing::lex_text(&self.history);
+        if !result.errors.is_empty()
+        {
+            // Only keep input that lexes, so the history can always be lexed again
+            self.history.truncate(previous);
+            print_errors(&result.errors.iter().map(Diagnostic::from).collect_vec(), &source);
+            return None;
+        }
+
+        let start = result.tokens.iter().position(|t| t.pos.begin >= offset).unwrap_or(result.tokens.len() - 1);
+        Some(Source { source, tokens: result.tokens, start })
+    }
+}
+
+/// Whether the input can be run: every brace, bracket and parenthesis is closed, and it does not stop partway
+/// through a declaration or statement, as `struct Point` does before the brace on the next line
+fn is_complete(input: &str) -> bool
+{
+    let result = lexing::lex_text(input);
+    let depth = result.tokens.iter().fold(0i32, |depth, token| match token.token_type
+    {
+        TokenType::OpenBrace | TokenType::OpenBracket | TokenType::OpenParen => depth + 1,
+        TokenType::CloseBrace | TokenType::CloseBracket | TokenType::CloseParen => depth - 1,
+        _ => depth,
+    });
+
+    if depth > 0
+    {
+        return false;
+    }
+
+    if input.trim_start().starts_with(':')
+    {
+        return true;
+    }
+
+    let source = Source { source: SourceFile::new(input), tokens: result.tokens, start: 0 };
+    match parse_entries(&source)
+    {
+        Err(error) => error.get_token().is_some_and(|t| t.token_type != TokenType::EOF),
+        Ok(_) => true,
+    }
+}
+
+/// Parses the newest input into declarations and statements, possibly ending with an expression
+fn parse_entries(source: &Source) -> ParserResult<Vec<Entry>>
+{
+    let mut reader = TokenReader::new(&source.tokens, Some(source.start)).expect("lexing always produces an end of file token");
+    let mut entries = vec![];
+
+    while reader.current_type() != Some(TokenType::EOF)
+    {
+        let mut lookahead = reader.clone();
+        if let Ok(Some(expression)) = parse_expression(&mut lookahead)
+        {
+            if lookahead.current_type() == Some(TokenType::EOF)
+            {
+                entries.push(Entry::Expression(expression));
+                break;
+            }
+        }
+
+        let entry = match reader.current_type()
+        {
+            Some(TokenType::Pub | TokenType::Fn | TokenType::Struct | TokenType::Enum | TokenType::Interface | TokenType::Type | TokenType::Impl | TokenType::Use | TokenType::Mod) =>
+            {
+                parse_declaration(&mut reader)?.map(Entry::Declaration)
+            },
+            Some(TokenType::Const) if reader.is_sequence(&[TokenType::Const, TokenType::Fn]) => parse_declaration(&mut reader)?.map(Entry::Declaration),
+            _ => parse_statement(&mut reader)?.map(Entry::Statement),
+        };
+
+        match entry
+        {
+            Some(entry) => entries.push(entry),
+            None => return Err(ParserError::ExpectedStatement(reader.current())),
+        }
+    }
+
+    Ok(entries)
+}
+
+fn parse_single_expression(source: &Source) -> ParserResult<Expression>
+{
+    let mut reader = TokenReader::new(&source.tokens, Some(source.start)).expect("lexing always produces an end of file token");
+    let Some(expression) = parse_expression(&mut reader)? else {
+        return Err(ParserError::ExpectedExpression(reader.current()));
+    };
+
+    match reader.current_type()
+    {
+        Some(TokenType::EOF) => Ok(expression),
+        _ => Err(ParserError::ExpectedToken(TokenType::EOF, reader.current())),
+    }
+}
+
+fn print_errors(diagnostics: &[Diagnostic], source: &SourceFile)
+{
+    let colour = diagnostic::use_colour();
+    for diagnostic in diagnostics
+    {
+        eprintln!("{}", diagnostic.render(source, FILE_NAME, colour));
+    }
+}
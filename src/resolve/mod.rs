@@ -0,0 +1,433 @@
+pub mod symbols;
+pub mod walk;
+
+use symbols::{ScopeId, ScopeKind, SymbolId, SymbolKind, SymbolTable};
+
+use crate::{diagnostic::Diagnostic, ast::*, interp::{builtins::{BUILTIN_NAMES, BUILTIN_TYPES}, token_str}, lexing::token::Token};
+
+#[derive(Debug, Clone)]
+pub enum ResolveError
+{
+    UndefinedName(Token),
+    UndefinedType(Token),
+    DuplicateDeclaration
+    {
+        token: Token,
+        previous: Token,
+    },
+    UsedBeforeDefinition
+    {
+        token: Token,
+        definition: Token,
+    },
+    InconsistentBinding(Token),
+}
+
+impl ResolveError
+{
+    pub fn get_token(&self) -> &Token
+    {
+        match self
+        {
+            ResolveError::UndefinedName(token) => token,
+            ResolveError::UndefinedType(token) => token,
+            ResolveError::DuplicateDeclaration { token, .. } => token,
+            ResolveError::UsedBeforeDefinition { token, .. } => token,
+            ResolveError::InconsistentBinding(token) => token,
+        }
+    }
+}
+
+impl std::fmt::Display for ResolveError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            ResolveError::UndefinedName(token) => write!(f, "Undefined name `{}`", token_str(token)),
+            ResolveError::UndefinedType(token) => write!(f, "Undefined type `{}`", token_str(token)),
+            ResolveError::DuplicateDeclaration { token, previous: _ } => write!(f, "`{}` is declared more than once", token_str(token)),
+            ResolveError::UsedBeforeDefinition { token, definition: _ } => write!(f, "`{}` is used before it is defined", token_str(token)),
+            ResolveError::InconsistentBinding(token) => write!(f, "`{}` is not bound in every alternative", token_str(token)),
+        }
+    }
+}
+
+impl From<&ResolveError> for Diagnostic
+{
+    fn from(error: &ResolveError) -> Self
+    {
+        let diagnostic = Diagnostic::error(error.to_string());
+        match error
+        {
+            ResolveError::UndefinedName(token) => diagnostic.with_code("E0301").with_primary(token.pos, "not found in this scope"),
+            ResolveError::UndefinedType(token) => diagnostic.with_code("E0302").with_primary(token.pos, "not found in this scope"),
+            ResolveError::DuplicateDeclaration { token, previous } => diagnostic
+                .with_code("E0303")
+                .with_primary(token.pos, "declared again here")
+                .with_label(previous.pos, "first declared here"),
+            ResolveError::UsedBeforeDefinition { token, definition } => diagnostic
+                .with_code("E0304")
+                .with_primary(token.pos, "used here")
+                .with_label(definition.pos, "defined here"),
+            ResolveError::InconsistentBinding(token) => diagnostic
+                .with_code("E0305")
+                .with_primary(token.pos, "bound here")
+                .with_help("every alternative of a `|` pattern must bind the same names"),
+        }
+    }
+}
+
+pub struct Resolver
+{
+    table: SymbolTable,
+    scope: ScopeId,
+    /// The member scopes of the types whose methods are being resolved, so that fields can be referred to by name
+    self_members: Vec<Option<ScopeId>>,
+    errors: Vec<ResolveError>,
+}
+
+/// Resolves every name in `program`, returning the resulting symbol table
+pub fn resolve_program(program: &Program) -> Result<SymbolTable, Vec<ResolveError>>
+{
+    let mut resolver = Resolver::new();
+    resolver.resolve_program(program);
+    resolver.finish()
+}
+
+impl Resolver
+{
+    pub fn new() -> Self
+    {
+        let mut table = SymbolTable::new();
+        let prelude = table.add_scope(ScopeKind::Prelude, None);
+        for name in BUILTIN_TYPES.iter().map(|(name, _)| *name).chain(BUILTIN_NAMES.iter().copied())
+        {
+            table.add_symbol(prelude, name, SymbolKind::Builtin, None);
+        }
+
+        let module = table.add_scope(ScopeKind::Module, Some(prelude));
+        Self { table, scope: module, self_members: vec![], errors: vec![] }
+    }
+
+    pub fn table(&self) -> &SymbolTable
+    {
+        &self.table
+    }
+
+    pub fn finish(self) -> Result<SymbolTable, Vec<ResolveError>>
+    {
+        match self.errors.is_empty()
+        {
+            true => Ok(self.table),
+            false => Err(self.errors),
+        }
+    }
+
+    fn error(&mut self, error: ResolveError)
+    {
+        self.errors.push(error);
+    }
+
+    fn push_scope(&mut self, kind: ScopeKind) -> ScopeId
+    {
+        self.scope = self.table.add_scope(kind, Some(self.scope));
+        self.scope
+    }
+
+    fn pop_scope(&mut self)
+    {
+        self.scope = self.table.scope(self.scope).parent.expect("Cannot pop the prelude scope");
+    }
+
+    /// Declares a symbol in `scope`, reporting a duplicate if the name is already declared there
+    fn declare(&mut self, scope: ScopeId, id: &Token, kind: SymbolKind) -> SymbolId
+    {
+        self.declare_named(scope, &token_str(id), id, kind)
+    }
+
+    fn declare_named(&mut self, scope: ScopeId, name: &str, token: &Token, kind: SymbolKind) -> SymbolId
+    {
+        let (symbol, previous) = self.table.add_symbol(scope, name, kind, Some(*token));
+        if let Some(previous) = previous.and_then(|p| self.table.symbol(p).token)
+        {
+            self.error(ResolveError::DuplicateDeclaration { token: *token, previous });
+        }
+
+        self.table.add_reference(token, symbol);
+        symbol
+    }
+
+    /// Binds a variable in the current scope. Unlike items, variables may shadow earlier ones.
+    fn define_var(&mut self, id: &Token, kind: SymbolKind)
+    {
+        let (symbol, _) = self.table.add_symbol(self.scope, &token_str(id), kind, Some(*id));
+        self.table.add_reference(id, symbol);
+    }
+
+    /// Binds a name brought in by `use`, before the program that contains it is resolved
+    pub fn declare_import(&mut self, name: &str, token: &Token, kind: SymbolKind)
+    {
+        self.declare_named(self.scope, name, token, kind);
+    }
+
+    pub fn resolve_program(&mut self, program: &Program)
+    {
+        for declaration in &program.declarations
+        {
+            match declaration
+            {
+                Declaration::Fn(_, decl) => { self.declare(self.scope, &decl.id, SymbolKind::Function); },
+                Declaration::Struct(_, decl) => self.declare_struct(decl),
+                Declaration::Interface(_, decl) => self.declare_interface(decl),
+                Declaration::Enum(_, decl) => self.declare_enum(decl),
+                Declaration::Type(_, decl) => { self.declare(self.scope, &decl.id, SymbolKind::TypeAlias); },
+                Declaration::Let(_, let_stmt) => self.declare_pending(let_stmt),
+                Declaration::Const(_, decl) => { self.declare(self.scope, &decl.id, SymbolKind::Const); },
+                Declaration::Use(_, _) | Declaration::Mod(_, _) | Declaration::Impl(_) => {},
+            }
+        }
+
+        for declaration in &program.declarations
+        {
+            if let Declaration::Impl(impl_stmt) = declaration
+            {
+                self.declare_impl(impl_stmt);
+            }
+        }
+
+        // Globals are initialized before anything else runs, so function bodies may use any of them
+        for declaration in &program.declarations
+        {
+            match declaration
+            {
+                Declaration::Let(_, let_stmt) => self.resolve_let(let_stmt),
+                Declaration::Const(_, decl) => self.resolve_const_value(decl),
+                _ => {},
+            }
+        }
+
+        for declaration in &program.declarations
+        {
+            match declaration
+            {
+                Declaration::Fn(_, decl) => self.resolve_fn(decl),
+                Declaration::Struct(_, decl) => self.resolve_struct(decl),
+                Declaration::Interface(_, decl) => self.resolve_interface(decl),
+                Declaration::Enum(_, decl) => self.resolve_enum(decl),
+                Declaration::Type(_, decl) => self.resolve_type_decl(decl),
+                Declaration::Impl(impl_stmt) => self.resolve_impl(impl_stmt),
+                Declaration::Let(_, _) | Declaration::Const(_, _) | Declaration::Use(_, _) | Declaration::Mod(_, _) => {},
+            }
+        }
+    }
+
+    /// Declares the items among `statements` in the current scope, so they can be used before the point they are written.
+    /// Names bound by `let` and `const` are recorded as pending until their statement is reached.
+    pub fn declare_items(&mut self, statements: &[Statement])
+    {
+        for statement in statements
+        {
+            match statement
+            {
+                Statement::FnDecl(decl) => { self.declare(self.scope, &decl.id, SymbolKind::Function); },
+                Statement::StructDecl(decl) => self.declare_struct(decl),
+                Statement::EnumDecl(decl) => self.declare_enum(decl),
+                Statement::InterfaceDecl(decl) => self.declare_interface(decl),
+                Statement::TypeDecl(decl) => { self.declare(self.scope, &decl.id, SymbolKind::TypeAlias); },
+                Statement::Let(let_stmt) => self.declare_pending(let_stmt),
+                Statement::Const(decl) =>
+                {
+                    let pending = &mut self.table.scope_mut(self.scope).pending;
+                    pending.entry(token_str(&decl.id)).or_insert(decl.id);
+                },
+                _ => {},
+            }
+        }
+    }
+
+    fn declare_struct(&mut self, decl: &StructDecl)
+    {
+        let symbol = self.declare(self.scope, &decl.id, SymbolKind::Struct);
+        let members = self.table.add_scope(ScopeKind::Struct, Some(self.scope));
+        self.table.set_members(symbol, members);
+        for member in &decl.members
+        {
+            self.declare(members, &member.id, SymbolKind::Field);
+        }
+    }
+
+    fn declare_enum(&mut self, decl: &EnumDecl)
+    {
+        let symbol = self.declare(self.scope, &decl.id, SymbolKind::Enum);
+        let members = self.table.add_scope(ScopeKind::Enum, Some(self.scope));
+        self.table.set_members(symbol, members);
+        for member in &decl.members
+        {
+            let id = match member
+            {
+                EnumMember::Basic { id, .. } => id,
+                EnumMember::Single { id, .. } => id,
+                EnumMember::Struct { id, .. } => id,
+            };
+
+            self.declare(members, id, SymbolKind::Variant);
+        }
+    }
+
+    fn declare_interface(&mut self, decl: &InterfaceDecl)
+    {
+        let symbol = self.declare(self.scope, &decl.id, SymbolKind::Interface);
+        let members = self.table.add_scope(ScopeKind::Interface, Some(self.scope));
+        self.table.set_members(symbol, members);
+        for member in &decl.members
+        {
+            if let Statement::FnDecl(method) = member
+            {
+                self.declare(members, &method.id, SymbolKind::Method);
+            }
+        }
+    }
+
+    fn declare_pending(&mut self, let_stmt: &LetStmt)
+    {
+        for id in let_binding_tokens(&let_stmt.binding)
+        {
+            let pending = &mut self.table.scope_mut(self.scope).pending;
+            pending.entry(token_str(id)).or_insert_with(|| *id);
+        }
+    }
+
+    /// Adds the methods and statics of an impl block to the members of the type it implements
+    fn declare_impl(&mut self, impl_stmt: &ImplStmt)
+    {
+        let Some(members) = self.impl_members(impl_stmt) else { return };
+        for (_, member) in &impl_stmt.members
+        {
+            match member
+            {
+                Statement::FnDecl(decl) => { self.declare(members, &decl.id, SymbolKind::Method); },
+                Statement::Let(let_stmt) =>
+                {
+                    for id in let_binding_tokens(&let_stmt.binding)
+                    {
+                        self.declare(members, id, SymbolKind::Static);
+                    }
+                },
+                Statement::Const(decl) => { self.declare(members, &decl.id, SymbolKind::Const); },
+                _ => {},
+            }
+        }
+    }
+
+    /// The member scope of the type an impl block is for
+    fn impl_members(&self, impl_stmt: &ImplStmt) -> Option<ScopeId>
+    {
+        let target = match &impl_stmt.for_clause
+        {
+            Some((_, target)) => target,
+            None => &impl_stmt.type_name,
+        };
+
+        let TypeName::Identifier { name, args: _ } = target else { return None };
+        let symbol = self.table.lookup(self.scope, &token_str(name))?;
+        self.table.symbol(symbol).members
+    }
+
+    /// Resolves a name used as a value, reporting it if nothing by that name is visible
+    fn resolve_name(&mut self, token: &Token) -> Option<SymbolId>
+    {
+        self.lookup_name(token, false)
+    }
+
+    fn lookup_name(&mut self, token: &Token, is_type: bool) -> Option<SymbolId>
+    {
+        let name = token_str(token);
+        let field = || self.self_members.last().copied().flatten()
+            .and_then(|members| self.table.lookup_local(members, &name))
+            .filter(|s| self.table.symbol(*s).kind == SymbolKind::Field);
+
+        if let Some(symbol) = self.table.lookup(self.scope, &name).or_else(|| if is_type { None } else { field() })
+        {
+            self.table.add_reference(token, symbol);
+            return Some(symbol);
+        }
+
+        let error = match self.table.lookup_pending(self.scope, &name)
+        {
+            Some(definition) => ResolveError::UsedBeforeDefinition { token: *token, definition: *definition },
+            None if is_type => ResolveError::UndefinedType(*token),
+            None => ResolveError::UndefinedName(*token),
+        };
+
+        self.error(error);
+        None
+    }
+
+    /// Records a reference to a member of `owner`, if `owner` is a type whose members are known
+    fn resolve_member(&mut self, owner: Option<SymbolId>, name: &Token) -> Option<SymbolId>
+    {
+        let members = self.table.symbol(owner?).members?;
+        let symbol = self.table.lookup_local(members, &token_str(name))?;
+        self.table.add_reference(name, symbol);
+        Some(symbol)
+    }
+
+    fn declare_generics(&mut self, params: &Option<GenericParams>)
+    {
+        for param in params.iter().flat_map(|p| &p.params)
+        {
+            self.declare(self.scope, param, SymbolKind::GenericParam);
+        }
+    }
+
+    fn resolve_where_clause(&mut self, where_clause: &Option<WhereClause>)
+    {
+        for sub_clause in where_clause.iter().flat_map(|w| &w.sub_clauses)
+        {
+            self.lookup_name(&sub_clause.id, true);
+            for type_name in &sub_clause.types
+            {
+                self.resolve_type_name(type_name);
+            }
+        }
+    }
+}
+
+impl Default for Resolver
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+/// The identifiers a pattern binds
+pub fn pattern_binding_tokens(pattern: &Pattern) -> Vec<&Token>
+{
+    match pattern
+    {
+        Pattern::Literal(_) | Pattern::Wildcard(_) | Pattern::Range { .. } | Pattern::TypeValue { .. } => vec![],
+        Pattern::Identifier { mut_tok: _, id } => vec![id],
+        Pattern::Binding { mut_tok: _, id, at: _, inner } => std::iter::once(id).chain(pattern_binding_tokens(inner)).collect(),
+        // Every alternative binds the same names
+        Pattern::Or(patterns) => patterns.first().map_or(vec![], pattern_binding_tokens),
+        Pattern::Grouping { inner, .. } => pattern_binding_tokens(inner),
+        Pattern::EnumConstruct { inner, .. } => pattern_binding_tokens(inner),
+        Pattern::StructConstruct { patterns, .. } => patterns.iter().flat_map(|f| match &f.inner
+        {
+            Some(inner) => pattern_binding_tokens(inner),
+            None => vec![&f.id],
+        }).collect(),
+        Pattern::ArrayConstruct { patterns, .. } => patterns.iter().flat_map(pattern_binding_tokens).collect(),
+    }
+}
+
+pub fn let_binding_tokens(binding: &LetBinding) -> Vec<&Token>
+{
+    match binding
+    {
+        LetBinding::Variable { mut_tok: _, id } => vec![id],
+        LetBinding::Pattern(pattern) => pattern_binding_tokens(pattern),
+    }
+}
@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+
+use crate::lexing::token::Token;
+
+pub type SymbolId = usize;
+pub type ScopeId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind
+{
+    /// Functions, types and values provided by the runtime rather than declared in the program
+    Builtin,
+    /// A module bound by `use`
+    Module,
+    /// An item brought in from another module by `use`
+    Import,
+    Function,
+    Struct,
+    Enum,
+    Interface,
+    TypeAlias,
+    GenericParam,
+    Field,
+    Variant,
+    Method,
+    Static,
+    Const,
+    Parameter
+    {
+        mutable: bool,
+    },
+    Variable
+    {
+        mutable: bool,
+    },
+}
+
+impl SymbolKind
+{
+    /// Whether this symbol can appear where a type name is expected
+    pub fn is_type(&self) -> bool
+    {
+        matches!(self, SymbolKind::Builtin | SymbolKind::Struct | SymbolKind::Enum | SymbolKind::Interface | SymbolKind::TypeAlias | SymbolKind::GenericParam)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol
+{
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The identifier that declared this symbol, or `None` for builtins
+    pub token: Option<Token>,
+    pub scope: ScopeId,
+    /// The scope holding the fields, variants or methods of a type
+    pub members: Option<ScopeId>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeKind
+{
+    Prelude,
+    Module,
+    Struct,
+    Enum,
+    Interface,
+    Impl,
+    Function,
+    Lambda,
+    Block,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scope
+{
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    pub symbols: HashMap<String, SymbolId>,
+    /// Names bound by a `let` later in this scope, which are not yet visible
+    pub pending: HashMap<String, Token>,
+}
+
+/// Every scope and symbol of a program, along with the symbol each identifier refers to
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable
+{
+    scopes: Vec<Scope>,
+    symbols: Vec<Symbol>,
+    /// Keyed by the starting offset of the referencing token
+    references: HashMap<usize, SymbolId>,
+}
+
+impl SymbolTable
+{
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    pub fn add_scope(&mut self, kind: ScopeKind, parent: Option<ScopeId>) -> ScopeId
+    {
+        self.scopes.push(Scope { kind, parent, symbols: HashMap::new(), pending: HashMap::new() });
+        self.scopes.len() - 1
+    }
+
+    /// Adds a symbol to `scope`. Returns the symbol it replaced in that scope, if any.
+    pub fn add_symbol(&mut self, scope: ScopeId, name: &str, kind: SymbolKind, token: Option<Token>) -> (SymbolId, Option<SymbolId>)
+    {
+        let id = self.symbols.len();
+        self.symbols.push(Symbol { name: name.to_owned(), kind, token, scope, members: None });
+        self.scopes[scope].pending.remove(name);
+        (id, self.scopes[scope].symbols.insert(name.to_owned(), id))
+    }
+
+    pub fn set_members(&mut self, symbol: SymbolId, members: ScopeId)
+    {
+        self.symbols[symbol].members = Some(members);
+    }
+
+    pub fn add_reference(&mut self, token: &Token, symbol: SymbolId)
+    {
+        self.references.insert(token.pos.begin, symbol);
+    }
+
+    pub fn symbol(&self, id: SymbolId) -> &Symbol
+    {
+        &self.symbols[id]
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope
+    {
+        &self.scopes[id]
+    }
+
+    pub fn scope_mut(&mut self, id: ScopeId) -> &mut Scope
+    {
+        &mut self.scopes[id]
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = (SymbolId, &Symbol)>
+    {
+        self.symbols.iter().enumerate()
+    }
+
+    /// The symbol an identifier token was resolved to
+    pub fn resolved(&self, token: &Token) -> Option<&Symbol>
+    {
+        self.references.get(&token.pos.begin).map(|id| &self.symbols[*id])
+    }
+
+    /// Every token position that refers to `symbol`, in no particular order
+    pub fn references_to(&self, symbol: SymbolId) -> impl Iterator<Item = usize> + '_
+    {
+        self.references.iter().filter(move |(_, s)| **s == symbol).map(|(pos, _)| *pos)
+    }
+
+    /// Looks a name up in `scope` only
+    pub fn lookup_local(&self, scope: ScopeId, name: &str) -> Option<SymbolId>
+    {
+        self.scopes[scope].symbols.get(name).copied()
+    }
+
+    /// Looks a name up in `scope` and then its parents
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<SymbolId>
+    {
+        let mut current = Some(scope);
+        while let Some(id) = current
+        {
+            if let Some(symbol) = self.lookup_local(id, name)
+            {
+                return Some(symbol);
+            }
+
+            current = self.scopes[id].parent;
+        }
+
+        None
+    }
+
+    /// Finds a `let` in `scope` or its parents that binds `name` but has not been reached yet
+    pub fn lookup_pending(&self, scope: ScopeId, name: &str) -> Option<&Token>
+    {
+        let mut current = Some(scope);
+        while let Some(id) = current
+        {
+            if let Some(token) = self.scopes[id].pending.get(name)
+            {
+                return Some(token);
+            }
+
+            current = self.scopes[id].parent;
+        }
+
+        None
+    }
+}
@@ -0,0 +1,561 @@
+use either::Either;
+
+use crate::{ast::*, interp::token_str, lexing::token::TokenType};
+
+use super::{pattern_binding_tokens, symbols::{ScopeKind, SymbolId, SymbolKind}, ResolveError, Resolver};
+
+impl Resolver
+{
+    pub fn resolve_fn(&mut self, decl: &FnDecl)
+    {
+        self.push_scope(ScopeKind::Function);
+        self.declare_generics(&decl.generic_params);
+        self.resolve_where_clause(&decl.where_clause);
+
+        for param in &decl.params
+        {
+            if let FnParam::Normal { mut_tok, id, colon: _, type_name, default_value } = param
+            {
+                self.resolve_type_name(type_name);
+                if let Some((_, expression)) = default_value
+                {
+                    self.resolve_expression(expression);
+                }
+
+                self.declare(self.scope, id, SymbolKind::Parameter { mutable: mut_tok.is_some() });
+            }
+        }
+
+        self.resolve_type_name(&decl.return_type);
+        if let Either::Left(body) = &decl.body
+        {
+            self.resolve_block(body);
+        }
+
+        self.pop_scope();
+    }
+
+    pub fn resolve_struct(&mut self, decl: &StructDecl)
+    {
+        self.push_scope(ScopeKind::Block);
+        self.declare_generics(&decl.generic_params);
+        self.resolve_where_clause(&decl.where_clause);
+
+        for member in &decl.members
+        {
+            self.resolve_type_name(&member.type_name);
+            if let Some((_, initializer)) = &member.initializer
+            {
+                self.resolve_expression(initializer);
+            }
+        }
+
+        self.pop_scope();
+    }
+
+    pub fn resolve_enum(&mut self, decl: &EnumDecl)
+    {
+        self.push_scope(ScopeKind::Block);
+        self.declare_generics(&decl.generic_params);
+        if let Some((_, type_name)) = &decl.backing_type
+        {
+            self.resolve_type_name(type_name);
+        }
+        self.resolve_where_clause(&decl.where_clause);
+
+        for member in &decl.members
+        {
+            match member
+            {
+                EnumMember::Basic { docs: _, id: _, discriminant } =>
+                {
+                    if let Some((_, expression)) = discriminant
+                    {
+                        self.resolve_expression(expression);
+                    }
+                },
+                EnumMember::Single { docs: _, id: _, open_paren: _, type_name, close_paren: _ } => { self.resolve_type_name(type_name); },
+                EnumMember::Struct { docs: _, id: _, open_brace: _, members, close_brace: _ } =>
+                {
+                    for member in members
+                    {
+                        self.resolve_type_name(&member.type_name);
+                        if let Some((_, initializer)) = &member.initializer
+                        {
+                            self.resolve_expression(initializer);
+                        }
+                    }
+                },
+            }
+        }
+
+        self.pop_scope();
+    }
+
+    pub fn resolve_interface(&mut self, decl: &InterfaceDecl)
+    {
+        self.push_scope(ScopeKind::Block);
+        self.declare_generics(&decl.generic_params);
+        self.resolve_where_clause(&decl.where_clause);
+
+        for member in &decl.members
+        {
+            self.resolve_statement(member);
+        }
+
+        self.pop_scope();
+    }
+
+    pub fn resolve_type_decl(&mut self, decl: &TypeDecl)
+    {
+        self.push_scope(ScopeKind::Block);
+        self.declare_generics(&decl.generic_params);
+        self.resolve_type_name(&decl.type_name);
+        self.pop_scope();
+    }
+
+    pub fn resolve_impl(&mut self, impl_stmt: &ImplStmt)
+    {
+        let members = self.impl_members(impl_stmt);
+
+        self.push_scope(ScopeKind::Impl);
+        self.declare_generics(&impl_stmt.generic_params);
+        self.resolve_type_name(&impl_stmt.type_name);
+        if let Some((_, target)) = &impl_stmt.for_clause
+        {
+            self.resolve_type_name(target);
+        }
+
+        self.resolve_where_clause(&impl_stmt.where_clause);
+
+        self.self_members.push(members);
+        for (_, member) in &impl_stmt.members
+        {
+            match member
+            {
+                // Statics and constants were already declared as members of the type
+                Statement::Let(let_stmt) => self.resolve_let_value(let_stmt),
+                Statement::Const(decl) => self.resolve_const_value(decl),
+                other => self.resolve_statement(other),
+            }
+        }
+
+        self.self_members.pop();
+        self.pop_scope();
+    }
+
+    pub fn resolve_block(&mut self, block: &BlockExpr)
+    {
+        self.push_scope(ScopeKind::Block);
+        self.declare_items(&block.statements);
+
+        for statement in &block.statements
+        {
+            self.resolve_statement(statement);
+        }
+
+        if let Some(expression) = &block.expression
+        {
+            self.resolve_expression(expression);
+        }
+
+        self.pop_scope();
+    }
+
+    pub fn resolve_statement(&mut self, statement: &Statement)
+    {
+        match statement
+        {
+            Statement::While(while_stmt) =>
+            {
+                self.push_scope(ScopeKind::Block);
+                self.resolve_let_condition(&while_stmt.condition);
+                self.resolve_block(&while_stmt.body);
+                self.pop_scope();
+            },
+            Statement::For(for_stmt) =>
+            {
+                self.resolve_expression(&for_stmt.expression);
+                self.push_scope(ScopeKind::Block);
+                self.resolve_pattern(&for_stmt.pattern);
+                self.resolve_block(&for_stmt.body);
+                self.pop_scope();
+            },
+            Statement::Return(return_stmt) =>
+            {
+                if let Some(expression) = &return_stmt.expression
+                {
+                    self.resolve_expression(expression);
+                }
+            },
+            Statement::Continue(_) | Statement::Break(_) | Statement::Use(_) => {},
+            Statement::TypeDecl(decl) => self.resolve_type_decl(decl),
+            Statement::EnumDecl(decl) => self.resolve_enum(decl),
+            Statement::InterfaceDecl(decl) => self.resolve_interface(decl),
+            Statement::StructDecl(decl) => self.resolve_struct(decl),
+            Statement::FnDecl(decl) => self.resolve_fn(decl),
+            Statement::Let(let_stmt) => self.resolve_let(let_stmt),
+            Statement::Const(decl) =>
+            {
+                self.resolve_const_value(decl);
+                self.define_var(&decl.id, SymbolKind::Const);
+            },
+            Statement::Assign(assign) =>
+            {
+                self.resolve_expression(&assign.value);
+                self.resolve_expression(&assign.expression);
+            },
+            Statement::If(if_expr) => self.resolve_if(if_expr),
+            Statement::Match(match_expr) => self.resolve_match(match_expr),
+            Statement::Block(block) => self.resolve_block(block),
+            Statement::Expression(expression_stmt) => { self.resolve_expression(&expression_stmt.expression); },
+        }
+    }
+
+    pub fn resolve_let(&mut self, let_stmt: &LetStmt)
+    {
+        self.resolve_let_value(let_stmt);
+        match &let_stmt.binding
+        {
+            LetBinding::Variable { mut_tok, id } => self.define_var(id, SymbolKind::Variable { mutable: mut_tok.is_some() }),
+            LetBinding::Pattern(pattern) => self.resolve_pattern(pattern),
+        }
+    }
+
+    /// Resolves everything in a `let` except the names it binds
+    fn resolve_let_value(&mut self, let_stmt: &LetStmt)
+    {
+        if let Some((_, type_name)) = &let_stmt.type_name
+        {
+            self.resolve_type_name(type_name);
+        }
+
+        self.resolve_expression(&let_stmt.expression);
+        if let Some((_, block)) = &let_stmt.else_clause
+        {
+            self.resolve_block(block);
+        }
+    }
+
+    /// Resolves everything in a `const` except the name it binds
+    pub fn resolve_const_value(&mut self, decl: &ConstDecl)
+    {
+        self.resolve_type_name(&decl.type_name);
+        self.resolve_expression(&decl.expression);
+    }
+
+    fn resolve_let_condition(&mut self, condition: &LetCondition)
+    {
+        match condition
+        {
+            LetCondition::Expression(expression) => { self.resolve_expression(expression); },
+            LetCondition::Pattern { let_tok: _, pattern, equal: _, expression, and: _, other_cond } =>
+            {
+                self.resolve_expression(expression);
+                self.resolve_pattern(pattern);
+                if let Some(other_cond) = other_cond
+                {
+                    self.resolve_let_condition(other_cond);
+                }
+            },
+        }
+    }
+
+    fn resolve_if(&mut self, if_expr: &IfExpr)
+    {
+        self.push_scope(ScopeKind::Block);
+        self.resolve_let_condition(&if_expr.condition);
+        self.resolve_block(&if_expr.block);
+        self.pop_scope();
+
+        if let Some(else_branch) = &if_expr.else_branch
+        {
+            match &else_branch.body
+            {
+                Either::Left(if_expr) => self.resolve_if(if_expr),
+                Either::Right(block) => self.resolve_block(block),
+            }
+        }
+    }
+
+    fn resolve_match(&mut self, match_expr: &MatchExpr)
+    {
+        self.resolve_expression(&match_expr.expression);
+        for branch in &match_expr.branches
+        {
+            self.push_scope(ScopeKind::Block);
+            self.resolve_pattern(&branch.pattern);
+            if let Some((_, guard)) = &branch.guard
+            {
+                self.resolve_expression(guard);
+            }
+            self.resolve_expression(&branch.expression);
+            self.pop_scope();
+        }
+    }
+
+    /// Resolves the names in an expression. Returns the symbol the expression itself names, if any.
+    pub fn resolve_expression(&mut self, expression: &Expression) -> Option<SymbolId>
+    {
+        match expression
+        {
+            Expression::Literal(token) | Expression::Identifier(token) if token.token_type == TokenType::Identifier => self.resolve_name(token),
+            Expression::Literal(_) | Expression::Identifier(_) | Expression::SelfExpr(_) => None,
+            Expression::Lambda(lambda) =>
+            {
+                self.push_scope(ScopeKind::Lambda);
+                match &lambda.params
+                {
+                    LambdaParams::Simple(id) => { self.declare(self.scope, id, SymbolKind::Parameter { mutable: false }); },
+                    LambdaParams::Complex { open_pipe: _, parameters, close_pipe: _, arrow: _, return_type } =>
+                    {
+                        for param in parameters
+                        {
+                            if let Some(type_name) = &param.type_name
+                            {
+                                self.resolve_type_name(type_name);
+                            }
+
+                            self.declare(self.scope, &param.name, SymbolKind::Parameter { mutable: false });
+                        }
+
+                        if let Some(return_type) = return_type
+                        {
+                            self.resolve_type_name(return_type);
+                        }
+                    },
+                };
+
+                self.resolve_expression(&lambda.expression);
+                self.pop_scope();
+                None
+            },
+            Expression::ArrayLiteral(array) =>
+            {
+                for element in &array.expressions
+                {
+                    self.resolve_expression(element);
+                }
+
+                None
+            },
+            Expression::Grouping(grouping) => self.resolve_expression(&grouping.expression),
+            Expression::BlockExpr(block) =>
+            {
+                self.resolve_block(block);
+                None
+            },
+            Expression::TypeValue(type_value) =>
+            {
+                let owner = self.resolve_type_name(&type_value.type_name);
+                self.resolve_member(owner, &type_value.name)
+            },
+            Expression::Construction(construction) =>
+            {
+                let target = self.resolve_type_name(&construction.type_name);
+                for arg in &construction.args
+                {
+                    self.resolve_member(target, &arg.name);
+                    self.resolve_expression(&arg.value);
+                }
+
+                None
+            },
+            Expression::EnumConstruction(construction) =>
+            {
+                self.resolve_type_name(&construction.type_name);
+                self.resolve_expression(&construction.expression);
+                None
+            },
+            Expression::Call(call) =>
+            {
+                self.resolve_expression(&call.expression);
+                for arg in &call.args
+                {
+                    self.resolve_expression(arg);
+                }
+
+                None
+            },
+            Expression::Access(access) =>
+            {
+                // Members of values depend on their type, so only members of named types are resolved here
+                let owner = self.resolve_expression(&access.expression);
+                let is_type = owner.is_some_and(|o| self.table.symbol(o).kind.is_type());
+                match is_type
+                {
+                    true => self.resolve_member(owner, &access.identifier),
+                    false => None,
+                }
+            },
+            Expression::Index(index) =>
+            {
+                self.resolve_expression(&index.expression);
+                self.resolve_expression(&index.indexer);
+                None
+            },
+            Expression::Unary(unary) =>
+            {
+                self.resolve_expression(&unary.expression);
+                None
+            },
+            Expression::Binary(binary) =>
+            {
+                self.resolve_expression(&binary.left);
+                self.resolve_expression(&binary.right);
+                None
+            },
+            Expression::IfExpr(if_expr) =>
+            {
+                self.resolve_if(if_expr);
+                None
+            },
+            Expression::MatchExpr(match_expr) =>
+            {
+                self.resolve_match(match_expr);
+                None
+            },
+            Expression::Cast(cast) =>
+            {
+                self.resolve_expression(&cast.expression);
+                self.resolve_type_name(&cast.type_name);
+                None
+            },
+            Expression::Interpolation(interpolation) =>
+            {
+                for (expression, _) in &interpolation.parts
+                {
+                    self.resolve_expression(expression);
+                }
+                None
+            },
+            Expression::Yield(yield_expr) =>
+            {
+                self.resolve_expression(&yield_expr.expression);
+                None
+            },
+            Expression::Jump(jump) =>
+            {
+                if let Some(expression) = &jump.expression
+                {
+                    self.resolve_expression(expression);
+                }
+                None
+            },
+        }
+    }
+
+    /// Resolves the names in a type name. Returns the symbol of the type it names, if known.
+    pub fn resolve_type_name(&mut self, type_name: &TypeName) -> Option<SymbolId>
+    {
+        match type_name
+        {
+            TypeName::Identifier { name, args } =>
+            {
+                self.resolve_generic_args(args);
+                match name.token_type
+                {
+                    TokenType::SelfType => None,
+                    _ => self.lookup_name(name, true),
+                }
+            },
+            TypeName::Array { open_bracket: _, close_bracket: _, type_name } =>
+            {
+                self.resolve_type_name(type_name);
+                None
+            },
+            TypeName::Function { fn_tok: _, open_paren: _, parameter_types, close_paren: _, arrow: _, return_type } =>
+            {
+                for param in parameter_types
+                {
+                    self.resolve_type_name(param);
+                }
+
+                self.resolve_type_name(return_type);
+                None
+            },
+            TypeName::Access { inner, dot: _, name, args } =>
+            {
+                self.resolve_generic_args(args);
+                let owner = self.resolve_type_name(inner);
+                self.resolve_member(owner, name)
+            },
+        }
+    }
+
+    fn resolve_generic_args(&mut self, args: &Option<GenericArgs>)
+    {
+        for arg in args.iter().flat_map(|a| &a.args)
+        {
+            self.resolve_type_name(arg);
+        }
+    }
+
+    /// Resolves the types a pattern refers to and binds the names it introduces in the current scope
+    pub fn resolve_pattern(&mut self, pattern: &Pattern)
+    {
+        match pattern
+        {
+            Pattern::Literal(_) | Pattern::Wildcard(_) | Pattern::Range { .. } => {},
+            Pattern::Identifier { mut_tok, id } => self.define_var(id, SymbolKind::Variable { mutable: mut_tok.is_some() }),
+            Pattern::Binding { mut_tok, id, at: _, inner } =>
+            {
+                self.define_var(id, SymbolKind::Variable { mutable: mut_tok.is_some() });
+                self.resolve_pattern(inner);
+            },
+            Pattern::Grouping { open_paren: _, inner, close_paren: _ } => self.resolve_pattern(inner),
+            Pattern::Or(patterns) =>
+            {
+                let Some((first, rest)) = patterns.split_first() else { return };
+                self.resolve_pattern(first);
+
+                // The first alternative's bindings are the ones the branch sees, the rest only need to agree with them
+                let first_names = pattern_binding_tokens(first);
+                for pattern in rest
+                {
+                    self.push_scope(ScopeKind::Block);
+                    self.resolve_pattern(pattern);
+                    self.pop_scope();
+
+                    let names = pattern_binding_tokens(pattern);
+                    let missing = first_names.iter().filter(|a| !names.iter().any(|b| token_str(a) == token_str(b)));
+                    let extra = names.iter().filter(|b| !first_names.iter().any(|a| token_str(a) == token_str(b)));
+                    for token in missing.chain(extra)
+                    {
+                        self.error(ResolveError::InconsistentBinding(**token));
+                    }
+                }
+            },
+            Pattern::TypeValue { type_name, dot: _, id } =>
+            {
+                let owner = self.resolve_type_name(type_name);
+                self.resolve_member(owner, id);
+            },
+            Pattern::EnumConstruct { type_name, open_paren: _, inner, close_paren: _ } =>
+            {
+                self.resolve_type_name(type_name);
+                self.resolve_pattern(inner);
+            },
+            Pattern::StructConstruct { type_name, open_brace: _, patterns, close_brace: _ } =>
+            {
+                let target = self.resolve_type_name(type_name);
+                for field in patterns
+                {
+                    self.resolve_member(target, &field.id);
+                    match &field.inner
+                    {
+                        Some(inner) => self.resolve_pattern(inner),
+                        None => self.define_var(&field.id, SymbolKind::Variable { mutable: field.mut_tok.is_some() }),
+                    }
+                }
+            },
+            Pattern::ArrayConstruct { open_bracket: _, patterns, close_bracket: _ } =>
+            {
+                for pattern in patterns
+                {
+                    self.resolve_pattern(pattern);
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use std::ops::Range;
+
+use crate::lexing::token::{TokenPos, TokenTextLocation};
+
+/// A character taking more than one byte in UTF-8
+#[derive(Debug, Clone, Copy)]
+struct WideChar
+{
+    /// The index of the character, counted in characters as `TokenPos` does
+    index: usize,
+    byte: usize,
+    len_utf8: usize,
+    /// The bytes past one that this and every wide character before it take, in UTF-8 and in UTF-16 code units
+    extra_bytes: usize,
+    extra_utf16: usize,
+}
+
+/// A position as editor protocols such as LSP count them: a 0-based line and a 0-based column in UTF-16 code units
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Utf16Position
+{
+    pub line: usize,
+    pub character: usize,
+}
+
+/// The text of a file with where each of its lines starts, so the character indexes of tokens can be turned into
+/// lines, columns and byte offsets without scanning the text each time
+#[derive(Debug, Clone)]
+pub struct SourceFile
+{
+    text: String,
+    char_count: usize,
+    /// The character index of the first character of each line
+    line_starts: Vec<usize>,
+    /// Every character taking more than one byte, in order
+    wide_chars: Vec<WideChar>,
+}
+
+impl SourceFile
+{
+    pub fn new(text: impl Into<String>) -> Self
+    {
+        let text = text.into();
+        let mut line_starts = vec![0];
+        let mut wide_chars = vec![];
+        let (mut extra_bytes, mut extra_utf16) = (0, 0);
+        let mut char_count = 0;
+
+        for (index, (byte, c)) in text.char_indices().enumerate()
+        {
+            if c.len_utf8() > 1
+            {
+                extra_bytes += c.len_utf8() - 1;
+                extra_utf16 += c.len_utf16() - 1;
+                wide_chars.push(WideChar { index, byte, len_utf8: c.len_utf8(), extra_bytes, extra_utf16 });
+            }
+
+            if c == '\n'
+            {
+                line_starts.push(index + 1);
+            }
+
+            char_count += 1;
+        }
+
+        Self { text, char_count, line_starts, wide_chars }
+    }
+
+    pub fn text(&self) -> &str
+    {
+        &self.text
+    }
+
+    /// The number of characters in the text, which is where the end of file token is
+    pub fn char_count(&self) -> usize
+    {
+        self.char_count
+    }
+
+    pub fn line_count(&self) -> usize
+    {
+        self.line_starts.len()
+    }
+
+    /// The 1-based line and column of a character index, with the column counted in characters. Indexes past the
+    /// end of the text, as the end of file token has, are just after its last character.
+    pub fn location(&self, index: usize) -> TokenTextLocation
+    {
+        let index = index.min(self.char_count);
+        let line = self.line_starts.partition_point(|start| *start <= index);
+        TokenTextLocation { line, column: index - self.line_starts[line - 1] + 1 }
+    }
+
+    /// The character indexes a 1-based line covers, without its `\n` or `\r\n`
+    pub fn line_range(&self, line: usize) -> Range<usize>
+    {
+        let begin = self.line_starts[line - 1];
+        let end = match self.line_starts.get(line)
+        {
+            Some(next) if *next >= 2 && *next - 2 >= begin && self.text.as_bytes()[self.byte_offset(*next - 2)] == b'\r' => next - 2,
+            Some(next) => next - 1,
+            None => self.char_count,
+        };
+
+        begin..end
+    }
+
+    /// The text of a 1-based line, without its `\n` or `\r\n`
+    pub fn line_text(&self, line: usize) -> &str
+    {
+        let range = self.line_range(line);
+        &self.text[self.byte_offset(range.start)..self.byte_offset(range.end)]
+    }
+
+    /// The offset in bytes of the character at an index, or of the end of the text for indexes past it
+    pub fn byte_offset(&self, index: usize) -> usize
+    {
+        let index = index.min(self.char_count);
+        let before = self.wide_chars.partition_point(|w| w.index < index);
+        index + before.checked_sub(1).map_or(0, |i| self.wide_chars[i].extra_bytes)
+    }
+
+    /// The index of the character a byte offset falls in
+    pub fn char_index(&self, byte: usize) -> usize
+    {
+        let byte = byte.min(self.text.len());
+        let before = self.wide_chars.partition_point(|w| w.byte < byte);
+        match before.checked_sub(1).map(|i| self.wide_chars[i])
+        {
+            // A byte inside a wide character belongs to it
+            Some(wide) if byte < wide.byte + wide.len_utf8 => wide.index,
+            Some(wide) => byte - wide.extra_bytes,
+            None => byte,
+        }
+    }
+
+    /// The bytes a span covers in the text, so it can be sliced out of the original string
+    pub fn byte_range(&self, pos: TokenPos) -> Range<usize>
+    {
+        self.byte_offset(pos.begin)..self.byte_offset(pos.end + 1)
+    }
+
+    /// The text a span covers
+    pub fn slice(&self, pos: TokenPos) -> &str
+    {
+        &self.text[self.byte_range(pos)]
+    }
+
+    /// The number of line breaks between two character indexes
+    pub fn lines_between(&self, begin: usize, end: usize) -> usize
+    {
+        self.location(end).line.saturating_sub(self.location(begin).line)
+    }
+
+    /// The file with the characters in `range` replaced by `text`
+    pub fn edited(&self, range: Range<usize>, text: &str) -> SourceFile
+    {
+        let begin = self.byte_offset(range.start);
+        let end = self.byte_offset(range.end).max(begin);
+        SourceFile::new([&self.text[..begin], text, &self.text[end..]].concat())
+    }
+
+    fn utf16_before(&self, index: usize) -> usize
+    {
+        let before = self.wide_chars.partition_point(|w| w.index < index);
+        index + before.checked_sub(1).map_or(0, |i| self.wide_chars[i].extra_utf16)
+    }
+
+    /// The position of a character index in UTF-16 code units, as editors count columns
+    pub fn utf16_position(&self, index: usize) -> Utf16Position
+    {
+        let index = index.min(self.char_count);
+        let location = self.location(index);
+        let line_start = self.line_starts[location.line - 1];
+        Utf16Position { line: location.line - 1, character: self.utf16_before(index) - self.utf16_before(line_start) }
+    }
+
+    /// The character index of a position given in UTF-16 code units. Columns past the end of their line are at its
+    /// end, and a column inside a character that takes two code units is at that character.
+    pub fn utf16_index(&self, position: Utf16Position) -> usize
+    {
+        let Some(&line_start) = self.line_starts.get(position.line) else { return self.char_count };
+        let line = self.line_range(position.line + 1);
+        let target = self.utf16_before(line_start) + position.character;
+
+        // The last character on the line starting at or before the target column
+        let (mut low, mut high) = (line.start, line.end);
+        while low < high
+        {
+            let middle = (low + high).div_ceil(2);
+            match self.utf16_before(middle) <= target
+            {
+                true => low = middle,
+                false => high = middle - 1,
+            }
+        }
+
+        low
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::{SourceFile, Utf16Position};
+
+    /// `é` takes two bytes and one UTF-16 unit, `😀` four bytes and two units, and the first line ends in `\r\n`
+    const TEXT: &str = "aé😀b\r\nxy\n";
+
+    #[test]
+    fn lines_without_line_endings()
+    {
+        let source = SourceFile::new(TEXT);
+        assert_eq!(source.line_count(), 3);
+        assert_eq!(source.line_range(1), 0..4);
+        assert_eq!(source.line_text(1), "aé😀b");
+        assert_eq!(source.line_text(2), "xy");
+        assert_eq!(source.line_text(3), "");
+    }
+
+    #[test]
+    fn byte_offsets_and_char_indexes()
+    {
+        let source = SourceFile::new(TEXT);
+        assert_eq!(source.byte_offset(2), 3);
+        assert_eq!(source.byte_offset(3), 7);
+        assert_eq!(source.byte_offset(100), TEXT.len());
+
+        assert_eq!(source.char_index(1), 1);
+        assert_eq!(source.char_index(2), 1);
+        assert_eq!(source.char_index(5), 2);
+        assert_eq!(source.char_index(7), 3);
+        assert_eq!(source.char_index(10), 6);
+        assert_eq!(source.char_index(100), 9);
+    }
+
+    #[test]
+    fn utf16_positions()
+    {
+        let source = SourceFile::new(TEXT);
+        assert_eq!(source.utf16_position(2), Utf16Position { line: 0, character: 2 });
+        assert_eq!(source.utf16_position(3), Utf16Position { line: 0, character: 4 });
+        assert_eq!(source.utf16_position(6), Utf16Position { line: 1, character: 0 });
+        assert_eq!(source.utf16_position(100), Utf16Position { line: 2, character: 0 });
+    }
+
+    #[test]
+    fn utf16_indexes()
+    {
+        let source = SourceFile::new(TEXT);
+        assert_eq!(source.utf16_index(Utf16Position { line: 0, character: 2 }), 2);
+        // Inside the two units of the emoji
+        assert_eq!(source.utf16_index(Utf16Position { line: 0, character: 3 }), 2);
+        assert_eq!(source.utf16_index(Utf16Position { line: 0, character: 4 }), 3);
+        // Past the end of a line, which stops before its `\r\n`
+        assert_eq!(source.utf16_index(Utf16Position { line: 0, character: 100 }), 4);
+        assert_eq!(source.utf16_index(Utf16Position { line: 1, character: 10 }), 8);
+        assert_eq!(source.utf16_index(Utf16Position { line: 7, character: 0 }), 9);
+    }
+}
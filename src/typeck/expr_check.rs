@@ -0,0 +1,790 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use either::Either;
+use itertools::Itertools;
+
+use crate::{ast::*, interp::token_str, lexing::token::{Token, TokenType, TokenValue}};
+
+use super::{types::{FnSig, ParamSig, Type, UntypedLambda}, TypeChecker, TypeError, TypeInfoKind, VariantKind};
+
+impl TypeChecker
+{
+    pub fn check_expression(&mut self, expression: &Expression) -> Type
+    {
+        match expression
+        {
+            Expression::Lambda(lambda) => self.check_lambda(lambda),
+            Expression::Literal(token) => self.check_literal(token),
+            Expression::ArrayLiteral(array) =>
+            {
+                let mut element = Type::Unknown;
+                for expression in &array.expressions
+                {
+                    let actual = self.check_expression(expression);
+                    self.expect_type(&element, &actual, expression.get_token());
+                    element = element.join(actual);
+                }
+
+                Type::Array(Box::new(element))
+            },
+            Expression::Identifier(token) => self.check_literal(token),
+            Expression::Grouping(grouping) => self.check_expression(&grouping.expression),
+            Expression::SelfExpr(token) => self.check_literal(token),
+            Expression::BlockExpr(block) =>
+            {
+                self.push_scope();
+                let block_type = self.check_block(block);
+                self.pop_scope();
+                block_type
+            },
+            Expression::TypeValue(type_value) =>
+            {
+                let owner = self.resolve_type_name(&type_value.type_name);
+                let owner = match owner
+                {
+                    Type::Named { name, args: _ } => Type::TypeRef(name),
+                    other => other,
+                };
+
+                self.member_type(&owner, &type_value.name)
+            },
+            Expression::Construction(construction) => self.check_construction(construction),
+            Expression::EnumConstruction(construction) =>
+            {
+                let from = self.check_expression(&construction.expression);
+                let to = self.resolve_type_name(&construction.type_name);
+                self.check_cast(from, to, &construction.open_paren)
+            },
+            Expression::Call(call) =>
+            {
+                let callee = self.check_expression(&call.expression);
+                self.check_call(&callee, &call.args, &call.open_paren)
+            },
+            Expression::Access(access) =>
+            {
+                let owner = self.check_expression(&access.expression);
+                self.member_type(&owner, &access.identifier)
+            },
+            Expression::Index(index) =>
+            {
+                let owner = self.check_expression(&index.expression);
+                let indexer = self.check_expression(&index.indexer);
+                self.expect_type(&Type::Int, &indexer, index.indexer.get_token());
+
+                match owner
+                {
+                    Type::Array(element) => *element,
+                    Type::String => Type::String,
+                    other if other.is_unknown() => Type::Unknown,
+                    other =>
+                    {
+                        self.error(TypeError::NotIndexable { token: index.open_bracket, found: other });
+                        Type::Unknown
+                    },
+                }
+            },
+            Expression::Unary(unary) =>
+            {
+                let operand = match (unary.operator.token_type, unary.expression.as_ref())
+                {
+                    // The magnitude of `i64::MIN` fits once it is negated
+                    (TokenType::Minus, Expression::Literal(token)) if token.token_type == TokenType::IntegerLiteral => Type::Int,
+                    _ => self.check_expression(&unary.expression),
+                };
+                match (unary.operator.token_type, &operand)
+                {
+                    (_, t) if t.is_unknown() => operand,
+                    (TokenType::Minus, Type::Int | Type::Float) => operand,
+                    (TokenType::Bang, Type::Bool) => operand,
+                    _ =>
+                    {
+                        self.error(TypeError::InvalidOperands { operator: unary.operator, left: operand, right: None });
+                        Type::Unknown
+                    },
+                }
+            },
+            Expression::Binary(binary) =>
+            {
+                let left = self.check_expression(&binary.left);
+                let right = self.check_expression(&binary.right);
+                self.binary_result(&binary.operator, left, right)
+            },
+            Expression::IfExpr(if_expr) => self.check_if(if_expr),
+            Expression::MatchExpr(match_expr) => self.check_match(match_expr),
+            Expression::Cast(cast) =>
+            {
+                let from = self.check_expression(&cast.expression);
+                let to = self.resolve_type_name(&cast.type_name);
+                self.check_cast(from, to, &cast.as_tok)
+            },
+            Expression::Interpolation(interpolation) =>
+            {
+                // Any value can be cast to a `String`, so any value can be interpolated
+                for (expression, _) in &interpolation.parts
+                {
+                    self.check_expression(expression);
+                }
+                Type::String
+            },
+            Expression::Yield(yield_expr) =>
+            {
+                let actual = self.check_expression(&yield_expr.expression);
+                match self.yield_types.last().cloned()
+                {
+                    Some(Some(expected)) => self.expect_type(&expected, &actual, yield_expr.expression.get_token()),
+                    _ => self.error(TypeError::YieldOutsideGenerator(yield_expr.yield_tok)),
+                }
+
+                Type::Void
+            },
+            Expression::Jump(jump) =>
+            {
+                match jump.keyword.token_type
+                {
+                    TokenType::Return => self.check_return(&jump.keyword, jump.expression.as_deref()),
+                    _ => self.check_jump(&jump.keyword),
+                }
+
+                Type::Never
+            },
+        }
+    }
+
+    pub fn check_block(&mut self, block: &BlockExpr) -> Type
+    {
+        self.declare_items(block.statements.iter().filter(|s| is_item(s)), &[]);
+
+        let Some((last, statements)) = block.statements.split_last() else {
+            return match &block.expression
+            {
+                Some(expression) => self.check_expression(expression),
+                None => Type::Void,
+            };
+        };
+
+        for statement in statements
+        {
+            self.check_statement(statement);
+        }
+
+        if let Some(expression) = &block.expression
+        {
+            self.check_statement(last);
+            return self.check_expression(expression);
+        }
+
+        // A trailing `if`, `match` or block is parsed as a statement but gives the block its value
+        match last
+        {
+            Statement::If(if_expr) => self.check_if(if_expr),
+            Statement::Match(match_expr) => self.check_match(match_expr),
+            Statement::Block(block) =>
+            {
+                self.push_scope();
+                let block_type = self.check_block(block);
+                self.pop_scope();
+                block_type
+            },
+            Statement::Return(_) | Statement::Break(_) | Statement::Continue(_) =>
+            {
+                self.check_statement(last);
+                Type::Never
+            },
+            _ =>
+            {
+                self.check_statement(last);
+                Type::Void
+            },
+        }
+    }
+
+    pub fn check_if(&mut self, if_expr: &IfExpr) -> Type
+    {
+        self.push_scope();
+        self.check_let_condition(&if_expr.condition);
+        let then_type = self.check_block(&if_expr.block);
+        self.pop_scope();
+
+        let else_type = match &if_expr.else_branch
+        {
+            Some(ElseBranch { else_tok: _, body: Either::Left(if_expr) }) => self.check_if(if_expr),
+            Some(ElseBranch { else_tok: _, body: Either::Right(block) }) =>
+            {
+                self.push_scope();
+                let block_type = self.check_block(block);
+                self.pop_scope();
+                block_type
+            },
+            None => return Type::Void,
+        };
+
+        if !then_type.is_compatible(&else_type) && !else_type.is_compatible(&then_type)
+        {
+            let token = if_expr.else_branch.as_ref().map_or(&if_expr.if_tok, |e| &e.else_tok);
+            self.error(TypeError::Mismatch { expected: then_type, found: else_type, token: *token });
+            return Type::Unknown;
+        }
+
+        then_type.join(else_type)
+    }
+
+    pub fn check_match(&mut self, match_expr: &MatchExpr) -> Type
+    {
+        let scrutinee = self.check_expression(&match_expr.expression);
+        let mut result = Type::Never;
+        for branch in &match_expr.branches
+        {
+            self.push_scope();
+            self.check_pattern(&branch.pattern, &scrutinee);
+            if let Some((_, guard)) = &branch.guard
+            {
+                let actual = self.check_expression(guard);
+                self.expect_type(&Type::Bool, &actual, guard.get_token());
+            }
+            let branch_type = self.check_expression(&branch.expression);
+            self.pop_scope();
+
+            if !result.is_compatible(&branch_type) && !branch_type.is_compatible(&result)
+            {
+                self.error(TypeError::Mismatch { expected: result.clone(), found: branch_type, token: branch.arrow });
+                continue;
+            }
+
+            result = result.join(branch_type);
+        }
+
+        result
+    }
+
+    /// Checks a condition, defining the bindings of any `let` patterns in the current scope
+    pub fn check_let_condition(&mut self, condition: &LetCondition)
+    {
+        match condition
+        {
+            LetCondition::Expression(expression) =>
+            {
+                let actual = self.check_expression(expression);
+                self.expect_type(&Type::Bool, &actual, expression.get_token());
+            },
+            LetCondition::Pattern { let_tok: _, pattern, equal: _, expression, and: _, other_cond } =>
+            {
+                let value = self.check_expression(expression);
+                self.check_pattern(pattern, &value);
+                if let Some(other) = other_cond
+                {
+                    self.check_let_condition(other);
+                }
+            },
+        }
+    }
+
+    fn check_literal(&mut self, token: &Token) -> Type
+    {
+        match token.token_type
+        {
+            TokenType::IntegerLiteral =>
+            {
+                if matches!(token.value, Some(TokenValue::Int(value)) if value > i64::MAX as u64)
+                {
+                    self.error(TypeError::IntegerOutOfRange(*token));
+                }
+                Type::Int
+            },
+            TokenType::FloatLiteral => Type::Float,
+            TokenType::StringLiteral => Type::String,
+            TokenType::CharLiteral => Type::Char,
+            TokenType::True | TokenType::False => Type::Bool,
+            TokenType::SelfVal => match self.lookup_var("self")
+            {
+                Some(var) => var.type_name.clone(),
+                None =>
+                {
+                    self.error(TypeError::UndefinedName(*token));
+                    Type::Unknown
+                },
+            },
+            TokenType::SelfType => match &self.self_type
+            {
+                Some(Type::Named { name, args: _ }) => Type::TypeRef(name.clone()),
+                _ =>
+                {
+                    self.error(TypeError::UndefinedType(*token));
+                    Type::Unknown
+                },
+            },
+            _ => self.lookup_name(token),
+        }
+    }
+
+    /// Looks up a name as a variable, an implicit field of `self`, or a type
+    fn lookup_name(&mut self, token: &Token) -> Type
+    {
+        let name = token_str(token);
+        if let Some(var) = self.lookup_var(&name)
+        {
+            return var.type_name.clone();
+        }
+
+        if let Some(field) = self.self_field(&name)
+        {
+            return field.type_name;
+        }
+
+        match self.type_names.get(&name)
+        {
+            Some(key) => self.type_ref(key),
+            None =>
+            {
+                self.error(TypeError::UndefinedName(*token));
+                Type::Unknown
+            }
+        }
+    }
+
+    /// Finds a field of the struct `self` refers to, so that methods can name fields directly
+    pub fn self_field(&self, name: &str) -> Option<super::FieldInfo>
+    {
+        let Some(Type::Named { name: type_name, args }) = self.lookup_var("self").map(|v| v.type_name.clone()) else {
+            return None;
+        };
+
+        let info = self.types.get(&type_name)?;
+        let TypeInfoKind::Struct(fields) = &info.kind else { return None };
+        let bindings = info.bindings(&args);
+        fields.iter().find(|f| f.name == name).map(|f| super::FieldInfo {
+            type_name: f.type_name.substitute_partial(&bindings),
+            ..f.clone()
+        })
+    }
+
+    pub fn member_type(&mut self, owner: &Type, name_tok: &Token) -> Type
+    {
+        let name = token_str(name_tok);
+        let found = match owner
+        {
+            t if t.is_unknown() => return Type::Unknown,
+            Type::Named { name: type_name, args } =>
+            {
+                let info = self.types.get(type_name).cloned();
+                info.and_then(|info| {
+                    let bindings = info.bindings(args);
+                    let field = match &info.kind
+                    {
+                        TypeInfoKind::Struct(fields) => fields.iter().find(|f| f.name == name).map(|f| f.type_name.clone()),
+                        _ => None,
+                    };
+
+                    field
+                        .or_else(|| info.methods.get(&name).filter(|m| m.has_self).map(|m| Type::Function(m.sig.clone())))
+                        .map(|t| t.substitute_partial(&bindings))
+                })
+            },
+            Type::TypeRef(type_name) =>
+            {
+                let info = self.types.get(type_name).cloned();
+                info.and_then(|info| {
+                    if let Some(variant) = info.variant(&name)
+                    {
+                        let self_type = info.self_type();
+                        return match &variant.kind
+                        {
+                            VariantKind::Basic => Some(self_type.substitute(&HashMap::new())),
+                            VariantKind::Single(payload) => Some(Type::Function(Rc::new(FnSig {
+                                generics: info.generics.clone(),
+                                params: vec![ParamSig { name: String::new(), type_name: payload.clone(), has_default: false }],
+                                ret: self_type,
+                                variadic: false,
+                                lambda: None,
+                            }))),
+                            VariantKind::Struct(_) => Some(Type::Unknown),
+                        };
+                    }
+
+                    if let Some(method) = info.methods.get(&name)
+                    {
+                        let mut sig = (*method.sig).clone();
+                        sig.generics.extend(info.generics.iter().cloned());
+                        if method.has_self
+                        {
+                            sig.params.insert(0, ParamSig { name: "self".into(), type_name: info.self_type(), has_default: false });
+                        }
+
+                        return Some(Type::Function(Rc::new(sig)));
+                    }
+
+                    info.statics.get(&name).cloned()
+                })
+            },
+            Type::Module(module) =>
+            {
+                let found = self.modules.get(module).and_then(|info| match info.values.get(&name)
+                {
+                    Some(value) => Some(value.clone()),
+                    None => info.types.get(&name).map(|key| self.type_ref(key)),
+                });
+
+                if found.is_some()
+                {
+                    self.expect_exported(module, name_tok);
+                }
+
+                found
+            },
+            Type::Array(element) => match name.as_str()
+            {
+                "length" => Some(Type::Function(Rc::new(FnSig::new(vec![], Type::Int)))),
+                "push" => Some(Type::Function(Rc::new(FnSig::new(vec![*element.clone()], Type::Void)))),
+                "pop" => Some(Type::Function(Rc::new(FnSig::new(vec![], *element.clone())))),
+                _ => None,
+            },
+            Type::String => match name.as_str()
+            {
+                "length" => Some(Type::Function(Rc::new(FnSig::new(vec![], Type::Int)))),
+                _ => None,
+            },
+            Type::Int => match name.as_str()
+            {
+                "is_even" | "is_odd" => Some(Type::Function(Rc::new(FnSig::new(vec![], Type::Bool)))),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        match found
+        {
+            Some(t) => t,
+            None =>
+            {
+                self.error(TypeError::UndefinedMember { token: *name_tok, owner: owner.clone() });
+                Type::Unknown
+            }
+        }
+    }
+
+    pub fn check_call(&mut self, callee: &Type, args: &[Expression], token: &Token) -> Type
+    {
+        let arg_types: Vec<Type> = args.iter().map(|a| self.check_expression(a)).collect();
+
+        let sig = match callee
+        {
+            Type::Function(sig) => sig.clone(),
+            t if t.is_unknown() => return Type::Unknown,
+            other =>
+            {
+                self.error(TypeError::NotCallable { token: *token, found: other.clone() });
+                return Type::Unknown;
+            }
+        };
+
+        if sig.variadic
+        {
+            return sig.ret.clone();
+        }
+
+        if arg_types.len() < sig.required_count() || arg_types.len() > sig.params.len()
+        {
+            self.error(TypeError::ArgumentCount { expected: sig.params.len(), found: arg_types.len(), token: *token });
+            return sig.ret.substitute(&HashMap::new());
+        }
+
+        let mut bindings = HashMap::new();
+        for ((param, actual), arg) in sig.params.iter().zip(&arg_types).zip(args)
+        {
+            if !param.type_name.unify(actual, &mut bindings)
+            {
+                self.error(TypeError::Mismatch { expected: param.type_name.substitute_partial(&bindings), found: actual.clone(), token: *arg.get_token() });
+            }
+        }
+
+        if let Some(lambda) = &sig.lambda
+        {
+            return self.call_untyped_lambda(lambda, &arg_types);
+        }
+
+        // Only the function's own generic parameters are inferred, any others belong to an enclosing item
+        let bindings = sig.generics.iter().map(|g| (g.clone(), bindings.get(g).cloned().unwrap_or(Type::Unknown))).collect();
+        sig.ret.substitute_partial(&bindings)
+    }
+
+    fn check_lambda(&mut self, lambda: &LambdaExpr) -> Type
+    {
+        let untyped = match &lambda.params
+        {
+            LambdaParams::Simple(_) => true,
+            LambdaParams::Complex { parameters, .. } => parameters.iter().any(|p| p.type_name.is_none()),
+        };
+
+        // The body is checked here with the untyped parameters unknown, and again at each call once they are known
+        let defined_in = untyped.then(|| (self.scopes.clone(), self.self_type.clone(), self.generics.clone()));
+        let (params, ret) = self.check_lambda_body(lambda, None);
+
+        let mut sig = FnSig::new(params, ret);
+        sig.lambda = defined_in.map(|(scopes, self_type, generics)| Rc::new(UntypedLambda {
+            expr: lambda.clone(),
+            scopes,
+            self_type,
+            generics,
+            results: RefCell::new(HashMap::new()),
+        }));
+        Type::Function(Rc::new(sig))
+    }
+
+    /// Checks the body of a lambda, giving the parameters without a type those of `args` when they are known. Returns
+    /// the types of the parameters and of the result.
+    fn check_lambda_body(&mut self, lambda: &LambdaExpr, args: Option<&[Type]>) -> (Vec<Type>, Type)
+    {
+        let arg = |i: usize| args.and_then(|args| args.get(i)).cloned().unwrap_or(Type::Unknown);
+
+        self.push_scope();
+        let (params, declared_ret) = match &lambda.params
+        {
+            LambdaParams::Simple(name) =>
+            {
+                self.define_var(&token_str(name), arg(0), false);
+                (vec![Type::Unknown], None)
+            },
+            LambdaParams::Complex { open_pipe: _, parameters, close_pipe: _, arrow: _, return_type } =>
+            {
+                let mut params = vec![];
+                for (i, param) in parameters.iter().enumerate()
+                {
+                    let param_type = param.type_name.as_ref().map_or(Type::Unknown, |t| self.resolve_type_name(t));
+                    let local_type = if param.type_name.is_some() { param_type.clone() } else { arg(i) };
+                    self.define_var(&token_str(&param.name), local_type, false);
+                    params.push(param_type);
+                }
+
+                (params, return_type.as_ref().map(|t| self.resolve_type_name(t)))
+            },
+        };
+
+        self.return_types.push(declared_ret.clone().unwrap_or(Type::Unknown));
+        self.yield_types.push(None);
+        self.loop_depths.push(0);
+        let body = self.check_expression(&lambda.expression);
+        self.loop_depths.pop();
+        self.yield_types.pop();
+        self.return_types.pop();
+        self.pop_scope();
+
+        let ret = match declared_ret
+        {
+            Some(declared) =>
+            {
+                self.expect_type(&declared, &body, lambda.expression.get_token());
+                declared
+            },
+            None => body,
+        };
+
+        (params, ret)
+    }
+
+    /// Checks the body of a lambda with untyped parameters again, where they have the types of `args`, in the scope it
+    /// was written in. Gives the type of its result.
+    fn call_untyped_lambda(&mut self, lambda: &UntypedLambda, args: &[Type]) -> Type
+    {
+        let key = args.iter().join(", ");
+        if let Some(result) = lambda.results.borrow().get(&key)
+        {
+            return result.clone();
+        }
+
+        // Recorded first, so a lambda that is passed to itself and calls it stops here
+        lambda.results.borrow_mut().insert(key.clone(), Type::Unknown);
+
+        let scopes = std::mem::replace(&mut self.scopes, lambda.scopes.clone());
+        let self_type = std::mem::replace(&mut self.self_type, lambda.self_type.clone());
+        let generics = std::mem::replace(&mut self.generics, lambda.generics.clone());
+        let reported = self.errors.len();
+
+        let (_, result) = self.check_lambda_body(&lambda.expr, Some(args));
+
+        // Errors that do not depend on the arguments were already reported where the lambda was written
+        let new_errors: Vec<TypeError> = self.errors.drain(reported..).collect();
+        for error in new_errors
+        {
+            let repeated = self.errors.iter().any(|e| e.get_token().pos == error.get_token().pos && e.to_string() == error.to_string());
+            if !repeated
+            {
+                self.error(error);
+            }
+        }
+
+        self.scopes = scopes;
+        self.self_type = self_type;
+        self.generics = generics;
+
+        lambda.results.borrow_mut().insert(key, result.clone());
+        result
+    }
+
+    fn check_construction(&mut self, construction: &ConstructionExpr) -> Type
+    {
+        let (result, fields) = match self.construction_target(&construction.type_name)
+        {
+            Some(target) => target,
+            None =>
+            {
+                for arg in &construction.args
+                {
+                    self.check_expression(&arg.value);
+                }
+
+                return Type::Unknown;
+            }
+        };
+
+        for arg in &construction.args
+        {
+            let actual = self.check_expression(&arg.value);
+            match fields.iter().find(|f| f.name == token_str(&arg.name))
+            {
+                Some(field) => self.expect_type(&field.type_name, &actual, arg.value.get_token()),
+                None => self.error(TypeError::UndefinedMember { token: arg.name, owner: result.clone() }),
+            }
+        }
+
+        for field in fields.iter().filter(|f| !f.has_default)
+        {
+            if !construction.args.iter().any(|a| token_str(&a.name) == field.name)
+            {
+                self.error(TypeError::MissingField { token: construction.open_brace, field: field.name.clone(), owner: result.to_string() });
+            }
+        }
+
+        result
+    }
+
+    /// Resolves the type named in a construction or struct pattern into the type it produces and the fields it has.
+    /// Accepts both structs (`Foo { .. }`) and struct variants of enums (`Shape.Rect { .. }`).
+    pub fn construction_target(&mut self, type_name: &TypeName) -> Option<(Type, Vec<super::FieldInfo>)>
+    {
+        // `Shape.Rect` names a variant, while `shapes.Rect` names a type in a module
+        if let TypeName::Access { inner, dot: _, name, args: _ } = type_name
+        {
+            if self.module_path(inner).is_none()
+            {
+                let Type::Named { name: enum_name, args } = self.resolve_type_name(inner) else { return None };
+                let info = self.types.get(&enum_name)?.clone();
+                let bindings = info.bindings(&args);
+                return match info.variant(&token_str(name)).map(|v| &v.kind)
+                {
+                    Some(VariantKind::Struct(fields)) => Some((
+                        Type::Named { name: enum_name, args },
+                        fields.iter().map(|f| super::FieldInfo { type_name: f.type_name.substitute_partial(&bindings), ..f.clone() }).collect()
+                    )),
+                    _ =>
+                    {
+                        self.error(TypeError::UndefinedMember { token: *name, owner: Type::TypeRef(enum_name) });
+                        None
+                    }
+                };
+            }
+        }
+
+        let resolved = self.resolve_type_name(type_name);
+        let Type::Named { name, args } = &resolved else {
+            if !resolved.is_unknown()
+            {
+                self.error(TypeError::Mismatch { expected: Type::named("struct"), found: resolved.clone(), token: *type_name.get_token() });
+            }
+
+            return None;
+        };
+
+        let info = self.types.get(name)?.clone();
+        let TypeInfoKind::Struct(fields) = &info.kind else {
+            self.error(TypeError::Mismatch { expected: Type::named("struct"), found: resolved.clone(), token: *type_name.get_token() });
+            return None;
+        };
+
+        let bindings = info.bindings(args);
+        Some((resolved.clone(), fields.iter().map(|f| super::FieldInfo { type_name: f.type_name.substitute_partial(&bindings), ..f.clone() }).collect()))
+    }
+
+    pub fn binary_result(&mut self, operator: &Token, left: Type, right: Type) -> Type
+    {
+        if left.is_unknown() || right.is_unknown()
+        {
+            return match operator.token_type
+            {
+                TokenType::EqualEqual | TokenType::BangEqual | TokenType::LessThan | TokenType::LessEqual |
+                TokenType::GreaterThan | TokenType::GreaterEqual | TokenType::AndAnd | TokenType::PipePipe => Type::Bool,
+                _ => Type::Unknown,
+            };
+        }
+
+        let result = match operator.token_type
+        {
+            TokenType::Plus | TokenType::Minus | TokenType::Multiply | TokenType::Divide | TokenType::Modulus => match (&left, &right)
+            {
+                (Type::Int, Type::Int) => Some(Type::Int),
+                (a, b) if a.is_numeric() && b.is_numeric() => Some(Type::Float),
+                (Type::String, Type::String) if operator.token_type == TokenType::Plus => Some(Type::String),
+                _ => None,
+            },
+            TokenType::LessThan | TokenType::LessEqual | TokenType::GreaterThan | TokenType::GreaterEqual => match (&left, &right)
+            {
+                (a, b) if a.is_numeric() && b.is_numeric() => Some(Type::Bool),
+                (Type::String, Type::String) => Some(Type::Bool),
+                (Type::Char, Type::Char) => Some(Type::Bool),
+                _ => None,
+            },
+            TokenType::EqualEqual | TokenType::BangEqual =>
+            {
+                let numeric = left.is_numeric() && right.is_numeric();
+                (numeric || left.is_compatible(&right) || right.is_compatible(&left)).then_some(Type::Bool)
+            },
+            TokenType::AndAnd | TokenType::PipePipe => (left == Type::Bool && right == Type::Bool).then_some(Type::Bool),
+            _ => None,
+        };
+
+        match result
+        {
+            Some(result) => result,
+            None =>
+            {
+                self.error(TypeError::InvalidOperands { operator: *operator, left, right: Some(right) });
+                Type::Unknown
+            }
+        }
+    }
+
+    fn check_cast(&mut self, from: Type, to: Type, token: &Token) -> Type
+    {
+        let valid = match (&from, &to)
+        {
+            (a, b) if a.is_unknown() || b.is_unknown() => true,
+            (_, Type::String) => true,
+            (a, b) if a.is_numeric() && b.is_numeric() => true,
+            (Type::Bool, Type::Int) => true,
+            (Type::String, Type::Int | Type::Float) => true,
+            (Type::Char, Type::Int) | (Type::Int, Type::Char) => true,
+            (Type::Named { name, .. }, Type::Int) | (Type::Int, Type::Named { name, .. }) if self.is_fieldless_enum(name) => true,
+            (a, b) => b.is_compatible(a),
+        };
+
+        if !valid
+        {
+            self.error(TypeError::InvalidCast { from, to: to.clone(), token: *token });
+        }
+
+        to
+    }
+
+    /// Whether `name` is an enum whose members carry no data, which converts to and from its discriminant with `as`
+    fn is_fieldless_enum(&self, name: &str) -> bool
+    {
+        match self.types.get(name).map(|info| &info.kind)
+        {
+            Some(TypeInfoKind::Enum(variants)) => variants.iter().all(|v| matches!(v.kind, VariantKind::Basic)),
+            _ => false,
+        }
+    }
+}
+
+fn is_item(statement: &Statement) -> bool
+{
+    matches!(statement, Statement::StructDecl(_) | Statement::EnumDecl(_) | Statement::InterfaceDecl(_) | Statement::TypeDecl(_) | Statement::FnDecl(_))
+}
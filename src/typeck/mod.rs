@@ -0,0 +1,1086 @@
+pub mod types;
+pub mod expr_check;
+pub mod stmt_check;
+pub mod pattern_check;
+
+use std::{collections::{HashMap, HashSet}, rc::Rc};
+
+use either::Either;
+use types::{FnSig, ParamSig, Type};
+
+use crate::{diagnostic::Diagnostic, ast::*, interp::token_str, lexing::token::{Token, TokenType}};
+
+#[derive(Debug, Clone)]
+pub enum TypeError
+{
+    Mismatch
+    {
+        expected: Type,
+        found: Type,
+        token: Token,
+    },
+    UndefinedName(Token),
+    UndefinedType(Token),
+    UndefinedMember
+    {
+        token: Token,
+        owner: Type,
+    },
+    ArgumentCount
+    {
+        expected: usize,
+        found: usize,
+        token: Token,
+    },
+    InvalidCast
+    {
+        from: Type,
+        to: Type,
+        token: Token,
+    },
+    InvalidOperands
+    {
+        operator: Token,
+        left: Type,
+        right: Option<Type>,
+    },
+    NotCallable
+    {
+        token: Token,
+        found: Type,
+    },
+    NotIndexable
+    {
+        token: Token,
+        found: Type,
+    },
+    NotIterable
+    {
+        token: Token,
+        found: Type,
+    },
+    MissingField
+    {
+        token: Token,
+        field: String,
+        owner: String,
+    },
+    AssignToImmutable(Token),
+    InvalidAssignment(Token),
+    YieldOutsideGenerator(Token),
+    /// A `yield` in the target of an assignment
+    MisplacedYield(Token),
+    GeneratorReturnType
+    {
+        token: Token,
+        found: Type,
+    },
+    /// The type after the colon of an enum, which must be an integer type
+    InvalidBackingType
+    {
+        token: Token,
+        found: Type,
+    },
+    /// A range pattern whose start is greater than its end
+    EmptyRangePattern
+    {
+        start: PatternLiteral,
+        end: PatternLiteral,
+    },
+    /// A member of another module that it does not mark `pub`
+    PrivateMember
+    {
+        token: Token,
+        module: String,
+    },
+    /// An integer literal of the magnitude of `i64::MIN` that is not negated
+    IntegerOutOfRange(Token),
+    /// A `break` that is not in the body of a loop of its own function or lambda
+    BreakOutsideLoop(Token),
+    ContinueOutsideLoop(Token),
+}
+
+impl TypeError
+{
+    pub fn get_token(&self) -> &Token
+    {
+        match self
+        {
+            TypeError::Mismatch { token, .. } => token,
+            TypeError::UndefinedName(token) => token,
+            TypeError::UndefinedType(token) => token,
+            TypeError::UndefinedMember { token, .. } => token,
+            TypeError::ArgumentCount { token, .. } => token,
+            TypeError::InvalidCast { token, .. } => token,
+            TypeError::InvalidOperands { operator, .. } => operator,
+            TypeError::NotCallable { token, .. } => token,
+            TypeError::NotIndexable { token, .. } => token,
+            TypeError::NotIterable { token, .. } => token,
+            TypeError::MissingField { token, .. } => token,
+            TypeError::AssignToImmutable(token) => token,
+            TypeError::InvalidAssignment(token) => token,
+            TypeError::YieldOutsideGenerator(token) => token,
+            TypeError::MisplacedYield(token) => token,
+            TypeError::GeneratorReturnType { token, .. } => token,
+            TypeError::InvalidBackingType { token, .. } => token,
+            TypeError::EmptyRangePattern { start, .. } => &start.token,
+            TypeError::PrivateMember { token, .. } => token,
+            TypeError::IntegerOutOfRange(token) => token,
+            TypeError::BreakOutsideLoop(token) => token,
+            TypeError::ContinueOutsideLoop(token) => token,
+        }
+    }
+}
+
+impl std::fmt::Display for TypeError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            TypeError::Mismatch { expected, found, token: _ } => write!(f, "Mismatched types: expected `{}` but found `{}`", expected, found),
+            TypeError::UndefinedName(token) => write!(f, "Undefined name `{}`", token_str(token)),
+            TypeError::UndefinedType(token) => write!(f, "Undefined type `{}`", token_str(token)),
+            TypeError::UndefinedMember { token, owner } => write!(f, "`{}` has no member `{}`", owner, token_str(token)),
+            TypeError::ArgumentCount { expected, found, token: _ } => write!(f, "Expected {} argument(s) but found {}", expected, found),
+            TypeError::InvalidCast { from, to, token: _ } => write!(f, "Cannot cast `{}` as `{}`", from, to),
+            TypeError::InvalidOperands { operator, left, right: Some(right) } => write!(f, "Cannot apply {} to `{}` and `{}`", operator.token_type.describe(), left, right),
+            TypeError::InvalidOperands { operator, left, right: None } => write!(f, "Cannot apply {} to `{}`", operator.token_type.describe(), left),
+            TypeError::NotCallable { token: _, found } => write!(f, "`{}` is not callable", found),
+            TypeError::NotIndexable { token: _, found } => write!(f, "`{}` cannot be indexed", found),
+            TypeError::NotIterable { token: _, found } => write!(f, "`{}` is not iterable", found),
+            TypeError::MissingField { token: _, field, owner } => write!(f, "Missing field `{}` when constructing `{}`", field, owner),
+            TypeError::AssignToImmutable(token) => write!(f, "Cannot assign to immutable `{}`", token_str(token)),
+            TypeError::InvalidAssignment(_) => write!(f, "Invalid assignment target"),
+            TypeError::YieldOutsideGenerator(_) => write!(f, "`yield` can only be used in the body of a `fn`"),
+            TypeError::MisplacedYield(_) => write!(f, "`yield` cannot be used in the target of an assignment"),
+            TypeError::GeneratorReturnType { token: _, found } => write!(f, "A function that yields must return `Iter[T]`, found `{}`", found),
+            TypeError::InvalidBackingType { token: _, found } => write!(f, "An enum must be backed by an integer type, found `{}`", found),
+            TypeError::EmptyRangePattern { start, end } =>
+            {
+                let quoted = |literal: &PatternLiteral| match (literal.token.token_type, literal.minus)
+                {
+                    (TokenType::CharLiteral, _) => format!("'{}'", token_str(&literal.token)),
+                    (_, Some(_)) => format!("-{}", token_str(&literal.token)),
+                    (_, None) => token_str(&literal.token),
+                };
+
+                write!(f, "The range pattern `{}..={}` cannot match anything", quoted(start), quoted(end))
+            },
+            TypeError::PrivateMember { token, module } => write!(f, "`{}` is private to module `{}`", token_str(token), module),
+            TypeError::IntegerOutOfRange(_) => write!(f, "Number does not fit in `Int`"),
+            TypeError::BreakOutsideLoop(_) => write!(f, "`break` outside of a loop"),
+            TypeError::ContinueOutsideLoop(_) => write!(f, "`continue` outside of a loop"),
+        }
+    }
+}
+
+impl From<&TypeError> for Diagnostic
+{
+    fn from(error: &TypeError) -> Self
+    {
+        let (code, label) = match error
+        {
+            TypeError::Mismatch { expected, found, token: _ } => ("E0401", format!("expected `{}`, found `{}`", expected, found)),
+            TypeError::UndefinedName(_) => ("E0402", "not found in this scope".to_owned()),
+            TypeError::UndefinedType(_) => ("E0403", "not found in this scope".to_owned()),
+            TypeError::UndefinedMember { token: _, owner } => ("E0404", format!("not a member of `{}`", owner)),
+            TypeError::ArgumentCount { expected, found: _, token: _ } => ("E0405", format!("expected {} argument(s)", expected)),
+            TypeError::InvalidCast { from: _, to, token: _ } => ("E0406", format!("cannot become `{}`", to)),
+            TypeError::InvalidOperands { .. } => ("E0407", "no implementation for these operands".to_owned()),
+            TypeError::NotCallable { token: _, found } => ("E0408", format!("has type `{}`", found)),
+            TypeError::NotIndexable { token: _, found } => ("E0409", format!("has type `{}`", found)),
+            TypeError::NotIterable { token: _, found } => ("E0410", format!("has type `{}`", found)),
+            TypeError::MissingField { token: _, field, owner: _ } => ("E0411", format!("missing `{}`", field)),
+            TypeError::AssignToImmutable(_) => ("E0412", "cannot be assigned to".to_owned()),
+            TypeError::InvalidAssignment(_) => ("E0413", "cannot be assigned to".to_owned()),
+            TypeError::YieldOutsideGenerator(_) => ("E0414", "cannot `yield` here".to_owned()),
+            TypeError::MisplacedYield(_) => ("E0415", "cannot `yield` here".to_owned()),
+            TypeError::GeneratorReturnType { .. } => ("E0416", "expected `Iter[T]`".to_owned()),
+            TypeError::InvalidBackingType { .. } => ("E0417", "not an integer type".to_owned()),
+            TypeError::EmptyRangePattern { .. } => ("E0418", "greater than the end of the range".to_owned()),
+            TypeError::PrivateMember { .. } => ("E0419", "private".to_owned()),
+            TypeError::IntegerOutOfRange(_) => ("E0420", "out of range for `Int`".to_owned()),
+            TypeError::BreakOutsideLoop(_) => ("E0421", "cannot `break` here".to_owned()),
+            TypeError::ContinueOutsideLoop(_) => ("E0422", "cannot `continue` here".to_owned()),
+        };
+
+        let pos = match error
+        {
+            TypeError::EmptyRangePattern { start, .. } => start.span(),
+            _ => error.get_token().pos,
+        };
+
+        let diagnostic = Diagnostic::error(error.to_string()).with_code(code).with_primary(pos, label);
+        match error
+        {
+            TypeError::AssignToImmutable(token) => diagnostic.with_help(format!("declare it with `let mut {}` to allow assignment", token_str(token))),
+            TypeError::PrivateMember { token: _, module } => diagnostic.with_help(format!("mark it `pub` in `{}` to use it from other modules", module)),
+            TypeError::IntegerOutOfRange(token) => diagnostic.with_help(format!("only `-{}`, the minimum of `Int`, can be written with this number", token_str(token))),
+            _ => diagnostic,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldInfo
+{
+    pub name: String,
+    pub type_name: Type,
+    pub mutable: bool,
+    pub has_default: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum VariantKind
+{
+    Basic,
+    Single(Type),
+    Struct(Vec<FieldInfo>),
+}
+
+#[derive(Debug, Clone)]
+pub struct VariantInfo
+{
+    pub name: String,
+    pub kind: VariantKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum TypeInfoKind
+{
+    Struct(Vec<FieldInfo>),
+    Enum(Vec<VariantInfo>),
+    Interface,
+    Alias(Type),
+    Native,
+}
+
+#[derive(Debug, Clone)]
+pub struct MethodInfo
+{
+    pub sig: Rc<FnSig>,
+    pub has_self: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeInfo
+{
+    pub name: String,
+    pub generics: Vec<String>,
+    pub kind: TypeInfoKind,
+    pub methods: HashMap<String, MethodInfo>,
+    pub statics: HashMap<String, Type>,
+    pub interfaces: Vec<String>,
+}
+
+impl TypeInfo
+{
+    pub fn bindings(&self, args: &[Type]) -> HashMap<String, Type>
+    {
+        self.generics.iter().cloned().zip(args.iter().cloned()).collect()
+    }
+
+    pub fn self_type(&self) -> Type
+    {
+        Type::Named { name: self.name.clone(), args: self.generics.iter().map(|g| Type::Generic(g.clone())).collect() }
+    }
+
+    pub fn variant(&self, name: &str) -> Option<&VariantInfo>
+    {
+        match &self.kind
+        {
+            TypeInfoKind::Enum(variants) => variants.iter().find(|v| v.name == name),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VarInfo
+{
+    pub type_name: Type,
+    pub mutable: bool,
+}
+
+/// What a module offers to the modules that import it
+#[derive(Debug, Clone)]
+pub struct ModuleInfo
+{
+    pub name: String,
+    /// The names declared or imported at the top level of the module, with their types
+    pub values: HashMap<String, Type>,
+    /// The types the module can name, as the keys they have in [`TypeChecker::types`]
+    pub types: HashMap<String, String>,
+    /// The names other modules may use
+    pub exports: HashSet<String>,
+}
+
+pub struct TypeChecker
+{
+    /// Every type known to the module, keyed by the name it was declared with, qualified by the module declaring it
+    types: HashMap<String, TypeInfo>,
+    /// The key in `types` of each type name in scope
+    type_names: HashMap<String, String>,
+    /// The builtins, then the top level of the module, then any blocks being checked
+    scopes: Vec<HashMap<String, VarInfo>>,
+    generics: Vec<Vec<String>>,
+    self_type: Option<Type>,
+    return_types: Vec<Type>,
+    /// The type each enclosing function yields, or `None` for functions and lambdas that cannot yield
+    yield_types: Vec<Option<Type>>,
+    /// How many loop bodies enclose the code being checked within each enclosing function or lambda, after a first
+    /// count for code outside of any, such as the lines of the REPL
+    loop_depths: Vec<usize>,
+    /// The module being checked, which qualifies the names of the types it declares. Empty for the entry file.
+    module: String,
+    exports: HashSet<String>,
+    /// Every module whose types are known, keyed by its dotted path
+    modules: HashMap<String, ModuleInfo>,
+    errors: Vec<TypeError>,
+}
+
+impl Default for TypeChecker
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+/// Type checks every declaration in `program`
+pub fn check_program(program: &Program) -> Result<(), Vec<TypeError>>
+{
+    let mut checker = TypeChecker::new();
+    checker.check_program(program);
+    checker.finish()
+}
+
+impl TypeChecker
+{
+    pub fn new() -> Self
+    {
+        let mut checker = Self
+        {
+            types: HashMap::new(),
+            type_names: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            generics: vec![],
+            self_type: None,
+            return_types: vec![],
+            yield_types: vec![],
+            loop_depths: vec![0],
+            module: String::new(),
+            exports: HashSet::new(),
+            modules: HashMap::new(),
+            errors: vec![],
+        };
+
+        checker.define_builtins();
+        checker.push_scope();
+        checker
+    }
+
+    /// A checker for the module at the dotted path `module`, which lets other modules use the names in `exports`
+    pub fn for_module(module: &str, exports: HashSet<String>) -> Self
+    {
+        Self { module: module.to_owned(), exports, ..Self::new() }
+    }
+
+    fn define_builtins(&mut self)
+    {
+        let variadic = |ret: Type| Type::Function(Rc::new(FnSig { generics: vec![], params: vec![], ret, variadic: true, lambda: None }));
+        self.define_var("print", variadic(Type::Void), false);
+        self.define_var("println", variadic(Type::Void), false);
+        self.define_var("range", Type::Function(Rc::new(FnSig::new(vec![Type::Int, Type::Int], Type::Array(Box::new(Type::Int))))), false);
+
+        let mut console = TypeInfo {
+            name: "Console".into(),
+            generics: vec![],
+            kind: TypeInfoKind::Native,
+            methods: HashMap::new(),
+            statics: HashMap::new(),
+            interfaces: vec![],
+        };
+
+        for name in ["ReadLine", "ReadKey"]
+        {
+            console.methods.insert(name.into(), MethodInfo { sig: Rc::new(FnSig::new(vec![], Type::String)), has_self: false });
+        }
+
+        self.type_names.insert(console.name.clone(), console.name.clone());
+        self.types.insert(console.name.clone(), console);
+    }
+
+    /// Brings a name from an already checked module into scope. `item` is the name in that module,
+    /// or `None` to bind the module itself. Every type and module the other module knows is made available too,
+    /// so that the signatures it exports still resolve.
+    pub fn import(&mut self, name: &str, from: &TypeChecker, item: Option<&str>)
+    {
+        for (key, info) in &from.types
+        {
+            self.types.entry(key.clone()).or_insert_with(|| info.clone());
+        }
+
+        for (path, module) in &from.modules
+        {
+            self.modules.entry(path.clone()).or_insert_with(|| module.clone());
+        }
+
+        let module = from.module_info();
+        match item
+        {
+            Some(item) =>
+            {
+                if let Some(key) = module.types.get(item)
+                {
+                    self.type_names.insert(name.to_owned(), key.clone());
+                }
+
+                if let Some(value) = module.values.get(item)
+                {
+                    self.define_var(name, value.clone(), false);
+                }
+            },
+            None => self.define_var(name, Type::Module(from.module.clone()), false),
+        }
+
+        self.modules.insert(from.module.clone(), module);
+    }
+
+    /// The names this module offers to the modules that import it
+    pub fn module_info(&self) -> ModuleInfo
+    {
+        ModuleInfo
+        {
+            name: self.module.clone(),
+            values: self.scopes[1].iter().map(|(name, var)| (name.clone(), var.type_name.clone())).collect(),
+            types: self.type_names.iter()
+                .filter(|(_, key)| !matches!(self.types[*key].kind, TypeInfoKind::Native))
+                .map(|(name, key)| (name.clone(), key.clone()))
+                .collect(),
+            exports: self.exports.clone(),
+        }
+    }
+
+    /// The key in `types` of a type called `name` declared in this module
+    fn qualified(&self, name: &str) -> String
+    {
+        match self.module.is_empty()
+        {
+            true => name.to_owned(),
+            false => format!("{}.{}", self.module, name),
+        }
+    }
+
+    /// The module a path such as `shapes` or `shapes.solid` names, if it names one
+    fn module_path(&self, type_name: &TypeName) -> Option<String>
+    {
+        let found = match type_name
+        {
+            TypeName::Identifier { name, args: None } => self.lookup_var(&token_str(name))?.type_name.clone(),
+            TypeName::Access { inner, dot: _, name, args: None } => self.modules.get(&self.module_path(inner)?)?.values.get(&token_str(name))?.clone(),
+            _ => return None,
+        };
+
+        match found
+        {
+            Type::Module(module) => Some(module),
+            _ => None,
+        }
+    }
+
+    /// Reports the modules along a path to a module that the module around them does not export
+    fn check_module_path(&mut self, type_name: &TypeName)
+    {
+        let TypeName::Access { inner, dot: _, name, args: _ } = type_name else { return };
+        self.check_module_path(inner);
+        if let Some(outer) = self.module_path(inner)
+        {
+            self.expect_exported(&outer, name);
+        }
+    }
+
+    /// Reports a member of another module that the module does not mark `pub`
+    fn expect_exported(&mut self, module: &str, name: &Token)
+    {
+        let Some(info) = self.modules.get(module) else { return };
+        if !info.exports.contains(&token_str(name))
+        {
+            let module = info.name.clone();
+            self.error(TypeError::PrivateMember { token: *name, module });
+        }
+    }
+
+    /// The type a type name refers to, given its key in `types`
+    fn named_type(&self, key: String, args: Vec<Type>) -> Type
+    {
+        match self.types.get(&key).map(|t| &t.kind)
+        {
+            Some(TypeInfoKind::Alias(target)) => target.clone(),
+            _ => Type::Named { name: key, args },
+        }
+    }
+
+    /// The type of an expression that names the type with the key `key`, following aliases
+    fn type_ref(&self, key: &str) -> Type
+    {
+        match self.types.get(key).map(|t| &t.kind)
+        {
+            Some(TypeInfoKind::Alias(Type::Named { name, args: _ })) => Type::TypeRef(name.clone()),
+            _ => Type::TypeRef(key.to_owned()),
+        }
+    }
+
+    pub fn errors(&self) -> &[TypeError]
+    {
+        &self.errors
+    }
+
+    /// Removes the errors reported so far, so a checker can be reused for input that arrives piece by piece
+    pub fn take_errors(&mut self) -> Vec<TypeError>
+    {
+        std::mem::take(&mut self.errors)
+    }
+
+    pub fn finish(self) -> Result<(), Vec<TypeError>>
+    {
+        if self.errors.is_empty() { Ok(()) } else { Err(self.errors) }
+    }
+
+    fn error(&mut self, error: TypeError)
+    {
+        self.errors.push(error);
+    }
+
+    /// Reports a mismatch unless `actual` can be used where `expected` is required
+    fn expect_type(&mut self, expected: &Type, actual: &Type, token: &Token)
+    {
+        if !expected.is_compatible(actual)
+        {
+            self.error(TypeError::Mismatch { expected: expected.clone(), found: actual.clone(), token: *token });
+        }
+    }
+
+    fn push_scope(&mut self)
+    {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn pop_scope(&mut self)
+    {
+        self.scopes.pop();
+    }
+
+    fn define_var(&mut self, name: &str, type_name: Type, mutable: bool)
+    {
+        self.scopes.last_mut().unwrap().insert(name.to_owned(), VarInfo { type_name, mutable });
+    }
+
+    fn lookup_var(&self, name: &str) -> Option<&VarInfo>
+    {
+        self.scopes.iter().rev().find_map(|s| s.get(name))
+    }
+
+    pub fn check_program(&mut self, program: &Program)
+    {
+        let declarations: Vec<Statement> = program.declarations.iter().filter_map(|d| match d
+        {
+            Declaration::Fn(_, decl) => Some(Statement::FnDecl(decl.clone())),
+            Declaration::Struct(_, decl) => Some(Statement::StructDecl(decl.clone())),
+            Declaration::Interface(_, decl) => Some(Statement::InterfaceDecl(decl.clone())),
+            Declaration::Enum(_, decl) => Some(Statement::EnumDecl(decl.clone())),
+            Declaration::Type(_, decl) => Some(Statement::TypeDecl(decl.clone())),
+            _ => None,
+        }).collect();
+
+        let impls: Vec<&ImplStmt> = program.declarations.iter().filter_map(|d| match d
+        {
+            Declaration::Impl(impl_stmt) => Some(impl_stmt),
+            _ => None,
+        }).collect();
+
+        self.declare_items(declarations.iter(), &impls);
+
+        // Constants are evaluated before anything runs, so every declaration may use them
+        for declaration in &program.declarations
+        {
+            if let Declaration::Const(_, decl) = declaration
+            {
+                let declared = self.resolve_type_name(&decl.type_name);
+                self.define_var(&token_str(&decl.id), declared, false);
+            }
+        }
+
+        for declaration in &program.declarations
+        {
+            match declaration
+            {
+                Declaration::Let(_, let_stmt) => self.check_let(let_stmt),
+                Declaration::Const(_, decl) => self.check_const(decl),
+                Declaration::Fn(_, decl) => self.check_fn_body(decl),
+                Declaration::Struct(_, decl) => self.check_struct_initializers(decl),
+                Declaration::Enum(_, decl) => self.check_discriminants(decl),
+                Declaration::Impl(impl_stmt) => self.check_impl(impl_stmt),
+                _ => {},
+            }
+        }
+    }
+
+    /// Registers the types, functions and impls of a group of items, so they can refer to one another regardless of order.
+    fn declare_items<'a>(&mut self, statements: impl Iterator<Item = &'a Statement> + Clone, impls: &[&ImplStmt])
+    {
+        // First every type name, so signatures can refer to types declared later
+        for statement in statements.clone()
+        {
+            let (id, generic_params, kind) = match statement
+            {
+                Statement::StructDecl(decl) => (&decl.id, &decl.generic_params, TypeInfoKind::Struct(vec![])),
+                Statement::EnumDecl(decl) => (&decl.id, &decl.generic_params, TypeInfoKind::Enum(vec![])),
+                Statement::InterfaceDecl(decl) => (&decl.id, &decl.generic_params, TypeInfoKind::Interface),
+                Statement::TypeDecl(decl) => (&decl.id, &decl.generic_params, TypeInfoKind::Alias(Type::Unknown)),
+                _ => continue,
+            };
+
+            let info = TypeInfo {
+                name: self.qualified(&token_str(id)),
+                generics: generic_names(generic_params),
+                kind,
+                methods: HashMap::new(),
+                statics: HashMap::new(),
+                interfaces: vec![],
+            };
+
+            self.type_names.insert(token_str(id), info.name.clone());
+            self.types.insert(info.name.clone(), info);
+        }
+
+        for statement in statements.clone()
+        {
+            match statement
+            {
+                Statement::StructDecl(decl) =>
+                {
+                    let name = self.qualified(&token_str(&decl.id));
+                    self.generics.push(generic_names(&decl.generic_params));
+                    let fields = decl.members.iter().map(|m| FieldInfo {
+                        name: token_str(&m.id),
+                        type_name: self.resolve_type_name(&m.type_name),
+                        mutable: m.mut_tok.is_some(),
+                        has_default: m.initializer.is_some(),
+                    }).collect();
+                    self.generics.pop();
+
+                    self.types.get_mut(&name).unwrap().kind = TypeInfoKind::Struct(fields);
+                },
+                Statement::EnumDecl(decl) =>
+                {
+                    let name = self.qualified(&token_str(&decl.id));
+                    self.generics.push(generic_names(&decl.generic_params));
+                    let variants = decl.members.iter().map(|m| match m
+                    {
+                        EnumMember::Basic { id, .. } => VariantInfo { name: token_str(id), kind: VariantKind::Basic },
+                        EnumMember::Single { id, type_name, .. } => VariantInfo { name: token_str(id), kind: VariantKind::Single(self.resolve_type_name(type_name)) },
+                        EnumMember::Struct { id, members, .. } => VariantInfo {
+                            name: token_str(id),
+                            kind: VariantKind::Struct(members.iter().map(|m| FieldInfo {
+                                name: token_str(&m.id),
+                                type_name: self.resolve_type_name(&m.type_name),
+                                mutable: m.mut_tok.is_some(),
+                                has_default: m.initializer.is_some(),
+                            }).collect()),
+                        },
+                    }).collect();
+                    self.generics.pop();
+
+                    self.types.get_mut(&name).unwrap().kind = TypeInfoKind::Enum(variants);
+                },
+                Statement::InterfaceDecl(decl) =>
+                {
+                    let name = self.qualified(&token_str(&decl.id));
+                    self.generics.push(generic_names(&decl.generic_params));
+                    self.self_type = Some(Type::named(&name));
+                    for member in &decl.members
+                    {
+                        let Statement::FnDecl(fn_decl) = member else { continue };
+                        let method = self.method_info(fn_decl);
+                        self.types.get_mut(&name).unwrap().methods.insert(token_str(&fn_decl.id), method);
+                    }
+                    self.self_type = None;
+                    self.generics.pop();
+                },
+                Statement::TypeDecl(decl) =>
+                {
+                    self.generics.push(generic_names(&decl.generic_params));
+                    let target = self.resolve_type_name(&decl.type_name);
+                    self.generics.pop();
+                    let name = self.qualified(&token_str(&decl.id));
+                    self.types.get_mut(&name).unwrap().kind = TypeInfoKind::Alias(target);
+                },
+                Statement::FnDecl(decl) =>
+                {
+                    let sig = self.fn_sig(decl);
+                    self.define_var(&token_str(&decl.id), Type::Function(Rc::new(sig)), false);
+                },
+                _ => {},
+            }
+        }
+
+        for impl_stmt in impls
+        {
+            self.declare_impl(impl_stmt);
+        }
+    }
+
+    fn declare_impl(&mut self, impl_stmt: &ImplStmt)
+    {
+        let (target, interface) = match &impl_stmt.for_clause
+        {
+            Some((_, target)) => (target, Some(&impl_stmt.type_name)),
+            None => (&impl_stmt.type_name, None),
+        };
+
+        let Some(name) = self.impl_target_name(target) else { return };
+
+        self.generics.push(generic_names(&impl_stmt.generic_params));
+        let self_type = self.types[&name].self_type();
+        self.self_type = Some(self_type);
+
+        let mut methods = vec![];
+        for (_, member) in &impl_stmt.members
+        {
+            if let Statement::FnDecl(decl) = member
+            {
+                methods.push((token_str(&decl.id), self.method_info(decl)));
+            }
+        }
+
+        let mut statics = vec![];
+        for (_, member) in &impl_stmt.members
+        {
+            match member
+            {
+                Statement::Let(LetStmt { binding: LetBinding::Variable { mut_tok: _, id }, type_name: Some((_, type_name)), .. })
+                | Statement::Const(ConstDecl { id, type_name, .. }) =>
+                {
+                    let type_name = self.resolve_type_name(type_name);
+                    statics.push((token_str(id), type_name));
+                },
+                _ => {},
+            }
+        }
+
+        self.self_type = None;
+        self.generics.pop();
+
+        let mut interface_name = None;
+        let mut inherited = vec![];
+        if let Some(interface) = interface
+        {
+            if let Some(interface) = self.impl_target_name(interface)
+            {
+                inherited = self.types[&interface].methods.iter().map(|(n, m)| (n.clone(), m.clone())).collect();
+                interface_name = Some(interface);
+            }
+        }
+
+        let info = self.types.get_mut(&name).unwrap();
+        for (method_name, method) in inherited
+        {
+            info.methods.entry(method_name).or_insert(method);
+        }
+
+        info.methods.extend(methods);
+        info.statics.extend(statics);
+        info.interfaces.extend(interface_name);
+    }
+
+    fn impl_target_name(&mut self, type_name: &TypeName) -> Option<String>
+    {
+        match type_name
+        {
+            TypeName::Identifier { name, args: _ } if self.type_names.contains_key(&token_str(name)) => Some(self.type_names[&token_str(name)].clone()),
+            other =>
+            {
+                self.error(TypeError::UndefinedType(*other.get_token()));
+                None
+            }
+        }
+    }
+
+    fn method_info(&mut self, decl: &FnDecl) -> MethodInfo
+    {
+        let has_self = decl.params.iter().any(|p| matches!(p, FnParam::SelfParam { .. }));
+        MethodInfo { sig: Rc::new(self.fn_sig(decl)), has_self }
+    }
+
+    fn fn_sig(&mut self, decl: &FnDecl) -> FnSig
+    {
+        let generics = generic_names(&decl.generic_params);
+        self.generics.push(generics.clone());
+
+        let params = decl.params.iter().filter_map(|p| match p
+        {
+            FnParam::Normal { mut_tok: _, id, colon: _, type_name, default_value } => Some(ParamSig {
+                name: token_str(id),
+                type_name: self.resolve_type_name(type_name),
+                has_default: default_value.is_some(),
+            }),
+            FnParam::SelfParam { .. } => None,
+        }).collect();
+
+        let ret = self.resolve_type_name(&decl.return_type);
+        self.generics.pop();
+
+        FnSig { generics, params, ret, variadic: false, lambda: None }
+    }
+
+    /// Converts a syntactic type into a [`Type`], reporting names that do not refer to a type
+    pub fn resolve_type_name(&mut self, type_name: &TypeName) -> Type
+    {
+        match type_name
+        {
+            TypeName::Identifier { name, args } =>
+            {
+                let text = token_str(name);
+                let args: Vec<Type> = args.as_ref().map_or(vec![], |a| a.args.iter().map(|t| self.resolve_type_name(t)).collect());
+
+                if name.token_type == TokenType::SelfType
+                {
+                    return self.self_type.clone().unwrap_or_else(|| {
+                        self.error(TypeError::UndefinedType(*name));
+                        Type::Unknown
+                    });
+                }
+
+                if self.generics.iter().any(|g| g.contains(&text))
+                {
+                    return Type::Generic(text);
+                }
+
+                if let Some(builtin) = builtin_type(&text)
+                {
+                    return match builtin
+                    {
+                        // `Iter[T]` is the only builtin type that takes arguments
+                        Type::Named { name, args: _ } => Type::Named { name, args },
+                        other => other,
+                    };
+                }
+
+                match self.type_names.get(&text).cloned()
+                {
+                    Some(key) => self.named_type(key, args),
+                    None =>
+                    {
+                        self.error(TypeError::UndefinedType(*name));
+                        Type::Unknown
+                    }
+                }
+            },
+            TypeName::Array { open_bracket: _, close_bracket: _, type_name } => Type::Array(Box::new(self.resolve_type_name(type_name))),
+            TypeName::Function { fn_tok: _, open_paren: _, parameter_types, close_paren: _, arrow: _, return_type } =>
+            {
+                let params = parameter_types.iter().map(|t| self.resolve_type_name(t)).collect();
+                let ret = self.resolve_type_name(return_type);
+                Type::Function(Rc::new(FnSig::new(params, ret)))
+            },
+            TypeName::Access { inner, dot: _, name, args } =>
+            {
+                let Some(module) = self.module_path(inner) else {
+                    self.error(TypeError::UndefinedType(*type_name.get_token()));
+                    return Type::Unknown;
+                };
+
+                self.check_module_path(inner);
+                let args: Vec<Type> = args.as_ref().map_or(vec![], |a| a.args.iter().map(|t| self.resolve_type_name(t)).collect());
+                match self.modules.get(&module).and_then(|m| m.types.get(&token_str(name))).cloned()
+                {
+                    Some(key) =>
+                    {
+                        self.expect_exported(&module, name);
+                        self.named_type(key, args)
+                    },
+                    None =>
+                    {
+                        self.error(TypeError::UndefinedType(*name));
+                        Type::Unknown
+                    },
+                }
+            },
+        }
+    }
+
+    fn check_fn_body(&mut self, decl: &FnDecl)
+    {
+        let Either::Left(body) = &decl.body else { return };
+
+        self.generics.push(generic_names(&decl.generic_params));
+        let sig = self.fn_sig(decl);
+        self.push_scope();
+
+        for param in &decl.params
+        {
+            match param
+            {
+                FnParam::Normal { mut_tok, id, colon: _, type_name: _, default_value } =>
+                {
+                    let param_type = sig.params.iter().find(|p| p.name == token_str(id)).map_or(Type::Unknown, |p| p.type_name.clone());
+                    if let Some((equal, default_value)) = default_value
+                    {
+                        let value_type = self.check_expression(default_value);
+                        self.expect_type(&param_type, &value_type, equal);
+                    }
+
+                    self.define_var(&token_str(id), param_type, mut_tok.is_some());
+                },
+                FnParam::SelfParam { mut_tok, self_tok: _ } =>
+                {
+                    let self_type = self.self_type.clone().unwrap_or(Type::Unknown);
+                    self.define_var("self", self_type, mut_tok.is_some());
+                },
+            }
+        }
+
+        let yields = decl.yields();
+        let yield_type = match yields.is_empty()
+        {
+            true => None,
+            false => Some(self.yielded_type(&sig.ret, &decl.return_type)),
+        };
+
+        for site in yields.iter().filter(|site| !site.resumable)
+        {
+            self.error(TypeError::MisplacedYield(site.yield_tok));
+        }
+
+        // A generator's `return` only ends it, the values it produces are the ones it yields
+        self.return_types.push(if yield_type.is_some() { Type::Void } else { sig.ret.clone() });
+        self.yield_types.push(yield_type.clone());
+        self.loop_depths.push(0);
+        let body_type = self.check_block(body);
+        self.loop_depths.pop();
+        self.yield_types.pop();
+        self.return_types.pop();
+
+        if yield_type.is_none() && sig.ret != Type::Void
+        {
+            let token = body.expression.as_ref().map_or(&body.close_brace, |e| e.get_token());
+            self.expect_type(&sig.ret, &body_type, token);
+        }
+
+        self.pop_scope();
+        self.generics.pop();
+    }
+
+    /// The type of the values a generator yields, taken from the `Iter[T]` it is declared to return
+    fn yielded_type(&mut self, ret: &Type, return_type: &TypeName) -> Type
+    {
+        match ret
+        {
+            Type::Named { name, args } if name == "Iter" => args.first().cloned().unwrap_or(Type::Unknown),
+            other if other.is_unknown() => Type::Unknown,
+            other =>
+            {
+                self.error(TypeError::GeneratorReturnType { token: *return_type.get_token(), found: other.clone() });
+                Type::Unknown
+            },
+        }
+    }
+
+    fn check_struct_initializers(&mut self, decl: &StructDecl)
+    {
+        self.generics.push(generic_names(&decl.generic_params));
+        for member in &decl.members
+        {
+            if let Some((equal, initializer)) = &member.initializer
+            {
+                let expected = self.resolve_type_name(&member.type_name);
+                let actual = self.check_expression(initializer);
+                self.expect_type(&expected, &actual, equal);
+            }
+        }
+        self.generics.pop();
+    }
+
+    /// Checks that an enum is backed by an integer type and that its discriminants are `Int`s
+    fn check_discriminants(&mut self, decl: &EnumDecl)
+    {
+        if let Some((_, type_name)) = &decl.backing_type
+        {
+            let backing = self.resolve_type_name(type_name);
+            if !backing.is_compatible(&Type::Int)
+            {
+                self.error(TypeError::InvalidBackingType { token: *type_name.get_token(), found: backing });
+            }
+        }
+
+        for member in &decl.members
+        {
+            if let EnumMember::Basic { discriminant: Some((equal, expression)), .. } = member
+            {
+                let actual = self.check_expression(expression);
+                self.expect_type(&Type::Int, &actual, equal);
+            }
+        }
+    }
+
+    fn check_impl(&mut self, impl_stmt: &ImplStmt)
+    {
+        let target = match &impl_stmt.for_clause
+        {
+            Some((_, target)) => target,
+            None => &impl_stmt.type_name,
+        };
+
+        let TypeName::Identifier { name, args: _ } = target else { return };
+        let Some(info) = self.type_names.get(&token_str(name)).and_then(|key| self.types.get(key)) else { return };
+
+        self.generics.push(generic_names(&impl_stmt.generic_params));
+        self.self_type = Some(info.self_type());
+
+        for (_, member) in &impl_stmt.members
+        {
+            match member
+            {
+                Statement::FnDecl(decl) => self.check_fn_body(decl),
+                Statement::Let(let_stmt) =>
+                {
+                    self.push_scope();
+                    self.check_let(let_stmt);
+                    self.pop_scope();
+                },
+                Statement::Const(decl) =>
+                {
+                    self.push_scope();
+                    self.check_const(decl);
+                    self.pop_scope();
+                },
+                _ => {},
+            }
+        }
+
+        self.self_type = None;
+        self.generics.pop();
+    }
+}
+
+fn generic_names(params: &Option<GenericParams>) -> Vec<String>
+{
+    params.as_ref().map_or(vec![], |p| p.params.iter().map(token_str).collect())
+}
+
+pub fn builtin_type(name: &str) -> Option<Type>
+{
+    match crate::interp::builtins::builtin_type_kind(name)?
+    {
+        "Int" => Some(Type::Int),
+        "Float" => Some(Type::Float),
+        "Bool" => Some(Type::Bool),
+        "String" => Some(Type::String),
+        "Char" => Some(Type::Char),
+        "Void" => Some(Type::Void),
+        "Iter" => Some(Type::named("Iter")),
+        _ => None,
+    }
+}
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use crate::{ast::*, interp::token_str, lexing::token::TokenValue, resolve::pattern_binding_tokens};
+
+use super::{types::Type, TypeChecker, TypeError, VarInfo, VariantKind};
+
+impl TypeChecker
+{
+    /// Checks that `pattern` can match a value of type `expected`, defining its bindings in the current scope
+    pub fn check_pattern(&mut self, pattern: &Pattern, expected: &Type)
+    {
+        match pattern
+        {
+            Pattern::Literal(literal) =>
+            {
+                let actual = self.check_expression(&literal_expression(literal));
+                self.expect_type(expected, &actual, &literal.token);
+            },
+            Pattern::Wildcard(_) => {},
+            Pattern::Range { start, dots: _, end } =>
+            {
+                let actual = self.check_expression(&literal_expression(start));
+                self.expect_type(expected, &actual, &start.token);
+                // The end is the same kind of literal, so this only reports it being out of range
+                self.check_expression(&literal_expression(end));
+
+                let empty = match (range_bound(start), range_bound(end))
+                {
+                    (Some(a), Some(b)) => a > b,
+                    _ => false,
+                };
+
+                if empty
+                {
+                    self.error(TypeError::EmptyRangePattern { start: start.clone(), end: end.clone() });
+                }
+            },
+            Pattern::Grouping { open_paren: _, inner, close_paren: _ } => self.check_pattern(inner, expected),
+            Pattern::Identifier { mut_tok, id } => self.define_var(&token_str(id), expected.clone(), mut_tok.is_some()),
+            Pattern::Binding { mut_tok, id, at: _, inner } =>
+            {
+                self.define_var(&token_str(id), expected.clone(), mut_tok.is_some());
+                self.check_pattern(inner, expected);
+            },
+            Pattern::Or(patterns) =>
+            {
+                // Each alternative is checked on its own, then the first one's bindings are kept for the branch
+                let mut first: Option<HashMap<String, VarInfo>> = None;
+                for pattern in patterns
+                {
+                    self.push_scope();
+                    self.check_pattern(pattern, expected);
+                    let bindings = self.scopes.pop().unwrap();
+
+                    match &first
+                    {
+                        Some(first) => for id in pattern_binding_tokens(pattern)
+                        {
+                            let (Some(a), Some(b)) = (first.get(&token_str(id)), bindings.get(&token_str(id))) else { continue };
+                            if !a.type_name.is_compatible(&b.type_name)
+                            {
+                                self.error(TypeError::Mismatch { expected: a.type_name.clone(), found: b.type_name.clone(), token: *id });
+                            }
+                        },
+                        None => first = Some(bindings),
+                    }
+                }
+
+                for (name, var) in first.unwrap_or_default()
+                {
+                    self.define_var(&name, var.type_name, var.mutable);
+                }
+            },
+            Pattern::TypeValue { type_name, dot: _, id } =>
+            {
+                let owner = self.resolve_type_name(type_name);
+                let Type::Named { name, args: _ } = &owner else { return };
+                let actual = self.member_type(&Type::TypeRef(name.clone()), id);
+                self.expect_type(expected, &actual, id);
+            },
+            Pattern::EnumConstruct { type_name, open_paren: _, inner, close_paren: _ } =>
+            {
+                let TypeName::Access { inner: enum_type, dot: _, name, args: _ } = type_name else {
+                    self.error(TypeError::UndefinedType(*type_name.get_token()));
+                    return;
+                };
+
+                let owner = self.resolve_type_name(enum_type);
+                let Type::Named { name: enum_name, args: _ } = &owner else { return };
+                self.expect_type(expected, &owner, name);
+
+                let Some(info) = self.types.get(enum_name).cloned() else { return };
+                let payload = match info.variant(&token_str(name)).map(|v| &v.kind)
+                {
+                    Some(VariantKind::Single(payload)) =>
+                    {
+                        let args = match expected
+                        {
+                            Type::Named { name: n, args } if n == enum_name => args.clone(),
+                            _ => vec![],
+                        };
+
+                        payload.substitute_partial(&info.bindings(&args))
+                    },
+                    _ =>
+                    {
+                        self.error(TypeError::UndefinedMember { token: *name, owner: Type::TypeRef(enum_name.clone()) });
+                        Type::Unknown
+                    },
+                };
+
+                self.check_pattern(inner, &payload);
+            },
+            Pattern::StructConstruct { type_name, open_brace: _, patterns, close_brace: _ } =>
+            {
+                let Some((owner, fields)) = self.construction_target(type_name) else {
+                    for field in patterns
+                    {
+                        self.check_pattern_field(field, &Type::Unknown);
+                    }
+
+                    return;
+                };
+
+                self.expect_type(expected, &owner, type_name.get_token());
+                for field in patterns
+                {
+                    let field_type = match fields.iter().find(|f| f.name == token_str(&field.id))
+                    {
+                        Some(info) => info.type_name.clone(),
+                        None =>
+                        {
+                            self.error(TypeError::UndefinedMember { token: field.id, owner: owner.clone() });
+                            Type::Unknown
+                        },
+                    };
+
+                    self.check_pattern_field(field, &field_type);
+                }
+            },
+            Pattern::ArrayConstruct { open_bracket, patterns, close_bracket: _ } =>
+            {
+                let element = match expected
+                {
+                    Type::Array(element) => *element.clone(),
+                    other if other.is_unknown() => Type::Unknown,
+                    other =>
+                    {
+                        self.error(TypeError::Mismatch { expected: other.clone(), found: Type::Array(Box::new(Type::Unknown)), token: *open_bracket });
+                        Type::Unknown
+                    },
+                };
+
+                for pattern in patterns
+                {
+                    self.check_pattern(pattern, &element);
+                }
+            },
+        }
+    }
+
+    fn check_pattern_field(&mut self, field: &PatternField, field_type: &Type)
+    {
+        match &field.inner
+        {
+            Some(inner) => self.check_pattern(inner, field_type),
+            None => self.define_var(&token_str(&field.id), field_type.clone(), field.mut_tok.is_some()),
+        }
+    }
+}
+
+/// The expression a literal pattern compares against, so a negated one is checked as a negation
+fn literal_expression(literal: &PatternLiteral) -> Expression
+{
+    let value = Expression::Literal(literal.token);
+    match literal.minus
+    {
+        Some(operator) => Expression::Unary(UnaryExpr { operator, expression: Box::new(value) }),
+        None => value,
+    }
+}
+
+/// The value of an end of a range pattern, with characters as their code points
+fn range_bound(literal: &PatternLiteral) -> Option<i128>
+{
+    let value = match literal.token.value
+    {
+        Some(TokenValue::Int(i)) => i as i128,
+        Some(TokenValue::Char(c)) => c as i128,
+        _ => return None,
+    };
+
+    Some(if literal.minus.is_some() { -value } else { value })
+}
@@ -0,0 +1,242 @@
+use crate::{ast::*, interp::token_str, lexing::token::{Token, TokenType}};
+
+use super::{types::Type, TypeChecker, TypeError, TypeInfoKind};
+
+impl TypeChecker
+{
+    pub fn check_statement(&mut self, statement: &Statement)
+    {
+        match statement
+        {
+            Statement::While(while_stmt) =>
+            {
+                self.push_scope();
+                self.check_let_condition(&while_stmt.condition);
+                self.check_loop_body(&while_stmt.body);
+                self.pop_scope();
+            },
+            Statement::For(for_stmt) =>
+            {
+                let iterable = self.check_expression(&for_stmt.expression);
+                let element = match iterable
+                {
+                    Type::Array(element) => *element,
+                    Type::String => Type::String,
+                    Type::Named { name, args } if name == "Iter" => args.into_iter().next().unwrap_or(Type::Unknown),
+                    other if other.is_unknown() => Type::Unknown,
+                    other =>
+                    {
+                        self.error(TypeError::NotIterable { token: for_stmt.in_tok, found: other });
+                        Type::Unknown
+                    },
+                };
+
+                self.push_scope();
+                self.check_pattern(&for_stmt.pattern, &element);
+                self.check_loop_body(&for_stmt.body);
+                self.pop_scope();
+            },
+            Statement::Return(return_stmt) => self.check_return(&return_stmt.return_tok, return_stmt.expression.as_ref()),
+            Statement::Continue(continue_stmt) => self.check_jump(&continue_stmt.continue_tok),
+            Statement::Break(break_stmt) => self.check_jump(&break_stmt.break_tok),
+            Statement::Use(_) => {},
+            // Items were declared when the enclosing block was entered
+            Statement::TypeDecl(_) | Statement::InterfaceDecl(_) => {},
+            Statement::EnumDecl(decl) => self.check_discriminants(decl),
+            Statement::StructDecl(decl) =>
+            {
+                self.check_struct_initializers(decl);
+            },
+            Statement::FnDecl(decl) =>
+            {
+                let self_type = self.self_type.take();
+                self.check_fn_body(decl);
+                self.self_type = self_type;
+            },
+            Statement::Let(let_stmt) => self.check_let(let_stmt),
+            Statement::Const(decl) => self.check_const(decl),
+            Statement::Assign(assign) => self.check_assign(assign),
+            Statement::If(if_expr) => { self.check_if(if_expr); },
+            Statement::Match(match_expr) => { self.check_match(match_expr); },
+            Statement::Block(block) =>
+            {
+                self.push_scope();
+                self.check_block(block);
+                self.pop_scope();
+            },
+            Statement::Expression(expression_stmt) => { self.check_expression(&expression_stmt.expression); },
+        }
+    }
+
+    pub fn check_let(&mut self, let_stmt: &LetStmt)
+    {
+        let actual = self.check_expression(&let_stmt.expression);
+        let declared = let_stmt.type_name.as_ref().map(|(colon, type_name)| (colon, self.resolve_type_name(type_name)));
+
+        let binding_type = match declared
+        {
+            Some((_, declared)) =>
+            {
+                self.expect_type(&declared, &actual, let_stmt.expression.get_token());
+                declared
+            },
+            None => actual,
+        };
+
+        if let Some((_, block)) = &let_stmt.else_clause
+        {
+            self.push_scope();
+            self.check_block(block);
+            self.pop_scope();
+        }
+
+        match &let_stmt.binding
+        {
+            LetBinding::Variable { mut_tok, id } => self.define_var(&token_str(id), binding_type, mut_tok.is_some()),
+            LetBinding::Pattern(pattern) => self.check_pattern(pattern, &binding_type),
+        }
+    }
+
+    /// Checks a constant against its declared type. Whether its value can be computed while compiling is left to the
+    /// const evaluator.
+    pub fn check_const(&mut self, decl: &ConstDecl)
+    {
+        let actual = self.check_expression(&decl.expression);
+        let declared = self.resolve_type_name(&decl.type_name);
+        self.expect_type(&declared, &actual, decl.expression.get_token());
+        self.define_var(&token_str(&decl.id), declared, false);
+    }
+
+    /// Checks the body of a `while` or `for` loop, inside which `break` and `continue` may be used
+    fn check_loop_body(&mut self, body: &BlockExpr)
+    {
+        *self.loop_depths.last_mut().unwrap() += 1;
+        self.check_block(body);
+        *self.loop_depths.last_mut().unwrap() -= 1;
+    }
+
+    /// Checks that a `break` or `continue`, whether it is a statement or an expression, is inside a loop
+    pub fn check_jump(&mut self, keyword: &Token)
+    {
+        if self.loop_depths.last() != Some(&0)
+        {
+            return;
+        }
+
+        match keyword.token_type
+        {
+            TokenType::Break => self.error(TypeError::BreakOutsideLoop(*keyword)),
+            _ => self.error(TypeError::ContinueOutsideLoop(*keyword)),
+        }
+    }
+
+    /// Checks a `return`, whether it is a statement or stands where an expression is expected
+    pub fn check_return(&mut self, return_tok: &Token, expression: Option<&Expression>)
+    {
+        let actual = match expression
+        {
+            Some(expression) => self.check_expression(expression),
+            None => Type::Void,
+        };
+
+        let expected = self.return_types.last().cloned().unwrap_or(Type::Unknown);
+        let token = expression.map_or(return_tok, |e| e.get_token());
+        self.expect_type(&expected, &actual, token);
+    }
+
+    fn check_assign(&mut self, assign: &AssignStmt)
+    {
+        let target = self.assign_target_type(&assign.value);
+        let value = self.check_expression(&assign.expression);
+
+        if assign.equal.token_type == TokenType::Equal
+        {
+            self.expect_type(&target, &value, assign.expression.get_token());
+            return;
+        }
+
+        let operator = Token {
+            token_type: compound_operator(assign.equal.token_type),
+            ..assign.equal
+        };
+
+        let result = self.binary_result(&operator, target.clone(), value);
+        self.expect_type(&target, &result, &assign.equal);
+    }
+
+    /// The type of the place being assigned to, reporting targets that cannot be assigned
+    fn assign_target_type(&mut self, target: &Expression) -> Type
+    {
+        match target
+        {
+            Expression::Literal(token) | Expression::Identifier(token) if token.token_type == TokenType::Identifier =>
+            {
+                let name = token_str(token);
+                if let Some(var) = self.lookup_var(&name).cloned()
+                {
+                    if !var.mutable
+                    {
+                        self.error(TypeError::AssignToImmutable(*token));
+                    }
+
+                    return var.type_name;
+                }
+
+                if let Some(field) = self.self_field(&name)
+                {
+                    if !field.mutable
+                    {
+                        self.error(TypeError::AssignToImmutable(*token));
+                    }
+
+                    return field.type_name;
+                }
+
+                self.error(TypeError::UndefinedName(*token));
+                Type::Unknown
+            },
+            Expression::Access(access) =>
+            {
+                let owner = self.check_expression(&access.expression);
+                if let Type::Named { name, args: _ } = &owner
+                {
+                    let field = match self.types.get(name).map(|t| &t.kind)
+                    {
+                        Some(TypeInfoKind::Struct(fields)) => fields.iter().find(|f| f.name == token_str(&access.identifier)).cloned(),
+                        _ => None,
+                    };
+
+                    if field.is_some_and(|f| !f.mutable)
+                    {
+                        self.error(TypeError::AssignToImmutable(access.identifier));
+                    }
+                }
+
+                self.member_type(&owner, &access.identifier)
+            },
+            Expression::Index(_) => self.check_expression(target),
+            Expression::Grouping(grouping) => self.assign_target_type(&grouping.expression),
+            other =>
+            {
+                self.check_expression(other);
+                self.error(TypeError::InvalidAssignment(*other.get_token()));
+                Type::Unknown
+            },
+        }
+    }
+}
+
+fn compound_operator(token_type: TokenType) -> TokenType
+{
+    match token_type
+    {
+        TokenType::PlusEqual => TokenType::Plus,
+        TokenType::MinusEqual => TokenType::Minus,
+        TokenType::MultiplyEqual => TokenType::Multiply,
+        TokenType::DivideEqual => TokenType::Divide,
+        TokenType::ModulusEqual => TokenType::Modulus,
+        TokenType::AndEqual => TokenType::AndAnd,
+        TokenType::OrEqual => TokenType::PipePipe,
+        other => other,
+    }
+}
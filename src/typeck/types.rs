@@ -0,0 +1,224 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use itertools::Itertools;
+
+use crate::ast::LambdaExpr;
+
+use super::VarInfo;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type
+{
+    Int,
+    Float,
+    Bool,
+    String,
+    Char,
+    Void,
+    /// The type of expressions that never produce a value, such as `return`
+    Never,
+    /// Produced after an error has been reported, or where nothing can be inferred. Compatible with everything.
+    Unknown,
+    Array(Box<Type>),
+    Function(Rc<FnSig>),
+    Named
+    {
+        name: String,
+        args: Vec<Type>,
+    },
+    Generic(String),
+    /// The type of an expression that names a type, such as `Player` in `Player.X`
+    TypeRef(String),
+    /// The type of a name bound to a module by `use` or `mod`, whose `pub` members are reached with `.`
+    Module(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamSig
+{
+    pub name: String,
+    pub type_name: Type,
+    pub has_default: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FnSig
+{
+    pub generics: Vec<String>,
+    pub params: Vec<ParamSig>,
+    pub ret: Type,
+    /// Builtins such as `println` accept any number of arguments of any type
+    pub variadic: bool,
+    /// The lambda this is the type of, when some of its parameters are not given a type
+    pub lambda: Option<Rc<UntypedLambda>>,
+}
+
+/// A lambda with parameters that have no type. Its body is checked again with the types of the arguments of each call,
+/// which gives the type of its result.
+#[derive(Debug)]
+pub struct UntypedLambda
+{
+    pub expr: LambdaExpr,
+    /// The variables, `Self` and generic parameters in scope where the lambda was written
+    pub scopes: Vec<HashMap<String, VarInfo>>,
+    pub self_type: Option<Type>,
+    pub generics: Vec<Vec<String>>,
+    /// The result for each list of argument types it has been called with, shown as a string
+    pub results: RefCell<HashMap<String, Type>>,
+}
+
+/// Each lambda is its own type, like each function is
+impl PartialEq for UntypedLambda
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl FnSig
+{
+    pub fn new(params: Vec<Type>, ret: Type) -> Self
+    {
+        Self
+        {
+            generics: vec![],
+            params: params.into_iter().map(|t| ParamSig { name: String::new(), type_name: t, has_default: false }).collect(),
+            ret,
+            variadic: false,
+            lambda: None,
+        }
+    }
+
+    pub fn required_count(&self) -> usize
+    {
+        self.params.iter().filter(|p| !p.has_default).count()
+    }
+}
+
+impl Type
+{
+    pub fn named(name: &str) -> Self
+    {
+        Type::Named { name: name.to_owned(), args: vec![] }
+    }
+
+    pub fn is_numeric(&self) -> bool
+    {
+        matches!(self, Type::Int | Type::Float)
+    }
+
+    /// Whether this type gives no information, in which case no further errors should be reported about it
+    pub fn is_unknown(&self) -> bool
+    {
+        matches!(self, Type::Unknown | Type::Never | Type::Generic(_))
+    }
+
+    /// Replaces generic parameters with the types they were bound to, and any unbound ones with [`Type::Unknown`]
+    pub fn substitute(&self, bindings: &HashMap<String, Type>) -> Type
+    {
+        self.replace_generics(&|name| Some(bindings.get(name).cloned().unwrap_or(Type::Unknown)))
+    }
+
+    /// Replaces the generic parameters that have a binding, leaving the others in place
+    pub fn substitute_partial(&self, bindings: &HashMap<String, Type>) -> Type
+    {
+        self.replace_generics(&|name| bindings.get(name).cloned())
+    }
+
+    fn replace_generics(&self, replace: &dyn Fn(&str) -> Option<Type>) -> Type
+    {
+        match self
+        {
+            Type::Generic(name) => replace(name).unwrap_or_else(|| self.clone()),
+            Type::Array(inner) => Type::Array(Box::new(inner.replace_generics(replace))),
+            Type::Named { name, args } => Type::Named { name: name.clone(), args: args.iter().map(|a| a.replace_generics(replace)).collect() },
+            Type::Function(sig) => Type::Function(Rc::new(FnSig {
+                generics: sig.generics.iter().filter(|g| replace(g).is_none()).cloned().collect(),
+                params: sig.params.iter().map(|p| ParamSig { name: p.name.clone(), type_name: p.type_name.replace_generics(replace), has_default: p.has_default }).collect(),
+                ret: sig.ret.replace_generics(replace),
+                variadic: sig.variadic,
+                lambda: sig.lambda.clone(),
+            })),
+            other => other.clone(),
+        }
+    }
+
+    /// Matches `self` (an expected type that may contain generic parameters) against `actual`,
+    /// recording the types the generic parameters are bound to. Returns `false` if the types conflict.
+    pub fn unify(&self, actual: &Type, bindings: &mut HashMap<String, Type>) -> bool
+    {
+        match (self, actual)
+        {
+            (Type::Generic(name), _) =>
+            {
+                match bindings.get(name).cloned()
+                {
+                    Some(bound) if !bound.is_unknown() => bound.unify(actual, bindings),
+                    _ =>
+                    {
+                        if !actual.is_unknown()
+                        {
+                            bindings.insert(name.clone(), actual.clone());
+                        }
+
+                        true
+                    }
+                }
+            },
+            (_, Type::Unknown | Type::Never | Type::Generic(_)) | (Type::Unknown, _) => true,
+            (Type::Array(a), Type::Array(b)) => a.unify(b, bindings),
+            (Type::Named { name: a, args: a_args }, Type::Named { name: b, args: b_args }) =>
+            {
+                a == b && (a_args.is_empty() || b_args.is_empty() || (a_args.len() == b_args.len() && a_args.iter().zip(b_args).all(|(x, y)| x.unify(y, bindings))))
+            },
+            (Type::Function(a), Type::Function(b)) =>
+            {
+                a.params.len() == b.params.len()
+                    && a.params.iter().zip(&b.params).all(|(x, y)| x.type_name.unify(&y.type_name, bindings))
+                    && a.ret.unify(&b.ret, bindings)
+            },
+            (a, b) => a == b,
+        }
+    }
+
+    pub fn is_compatible(&self, actual: &Type) -> bool
+    {
+        self.unify(actual, &mut HashMap::new())
+    }
+
+    /// Picks the more informative of two types that are already known to be compatible
+    pub fn join(self, other: Type) -> Type
+    {
+        match (&self, &other)
+        {
+            (Type::Never, _) | (Type::Unknown, _) => other,
+            _ => self,
+        }
+    }
+}
+
+impl std::fmt::Display for Type
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            Type::Int => write!(f, "Int"),
+            Type::Float => write!(f, "Float"),
+            Type::Bool => write!(f, "Bool"),
+            Type::String => write!(f, "String"),
+            Type::Char => write!(f, "Char"),
+            Type::Void => write!(f, "Void"),
+            Type::Never => write!(f, "!"),
+            Type::Unknown => write!(f, "_"),
+            Type::Array(inner) => write!(f, "[]{}", inner),
+            Type::Function(sig) => write!(f, "fn({}) -> {}", sig.params.iter().map(|p| p.type_name.to_string()).join(", "), sig.ret),
+            Type::Named { name, args } if args.is_empty() => write!(f, "{}", name),
+            Type::Named { name, args } => write!(f, "{}[{}]", name, args.iter().join(", ")),
+            Type::Generic(name) => write!(f, "{}", name),
+            Type::TypeRef(name) => write!(f, "type {}", name),
+            Type::Module(name) => write!(f, "module {}", name),
+        }
+    }
+}
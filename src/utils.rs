@@ -3,6 +3,8 @@ pub struct PrettyPrinter
 {
     indent_level: usize,
     indent_str: String,
+    /// Written after the indentation of every indented line, such as `- ` to draw a tree
+    marker: String,
     result: String,
 }
 
@@ -14,10 +16,21 @@ impl PrettyPrinter
         {
             indent_level: 0,
             indent_str: indent_str.to_string(),
+            marker: "- ".to_string(),
             result: String::new(),
         }
     }
 
+    /// A printer for source code, whose indented lines start with nothing but the indentation
+    pub fn plain(indent_str: &str) -> Self
+    {
+        PrettyPrinter
+        {
+            marker: String::new(),
+            ..Self::new(indent_str)
+        }
+    }
+
     pub fn indent(&mut self) 
     {
         self.indent_level += 1;
@@ -32,17 +45,68 @@ impl PrettyPrinter
 
     pub fn append_line(&mut self, line: &str) 
     {
-        for _ in 0..self.indent_level 
+        self.write(line);
+        self.new_line();
+    }
+
+    /// Adds text to the end of the current line, indenting it first if the line is empty
+    pub fn write(&mut self, text: &str)
+    {
+        if self.at_line_start()
+        {
+            for _ in 0..self.indent_level 
+            {
+                self.result.push_str(&self.indent_str);
+            }
+            if self.indent_level > 0
+            {
+                self.result.push_str(&self.marker);
+            }
+        }
+
+        self.result.push_str(text);
+    }
+
+    /// Ends the current line, dropping any spaces left at its end
+    pub fn new_line(&mut self)
+    {
+        let trimmed = self.result.trim_end_matches([' ', '\t']).len();
+        self.result.truncate(trimmed);
+        self.result.push('\n');
+    }
+
+    /// Ends the current line if it has anything on it
+    pub fn end_line(&mut self)
+    {
+        if !self.at_line_start()
         {
-            self.result.push_str(&self.indent_str);
+            self.new_line();
         }
-        if self.indent_level > 0
+    }
+
+    /// Leaves one empty line before whatever is written next, unless nothing has been written yet
+    pub fn blank_line(&mut self)
+    {
+        self.end_line();
+        if !self.result.is_empty() && !self.result.ends_with("\n\n")
         {
-            self.result.push_str("- ");
+            self.new_line();
+        }
+    }
+
+    pub fn at_line_start(&self) -> bool
+    {
+        self.result.is_empty() || self.result.ends_with('\n')
+    }
+
+    /// The column, counted in characters, that the next text written will start at
+    pub fn column(&self) -> usize
+    {
+        match self.at_line_start()
+        {
+            true => self.indent_level * self.indent_str.chars().count(),
+            false => self.result.chars().rev().take_while(|c| *c != '\n').count(),
         }
-        
-        self.result.push_str(line);
-        self.result.push('\n');
     }
 
     pub fn to_string(&self) -> String {
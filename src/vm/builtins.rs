@@ -0,0 +1,130 @@
+use std::{io::{BufRead, Write}, rc::Rc};
+
+use itertools::Itertools;
+
+use crate::{codegen::chunk::TypeShape, interp::RuntimeError, lexing::token::Token};
+
+use super::{value::{NativeFn, NativeFnPtr, TypeObject, Value}, Vm, VmResult};
+
+fn native(name: &'static str, arity: Option<usize>, func: NativeFnPtr) -> Value
+{
+    Value::Native(Rc::new(NativeFn { name, arity, func }))
+}
+
+fn bound_native(receiver: &Value, name: &'static str, arity: Option<usize>, func: NativeFnPtr) -> Value
+{
+    Value::BoundNative(Box::new(receiver.clone()), Rc::new(NativeFn { name, arity, func }))
+}
+
+/// The value of one of the names in `BUILTIN_NAMES`
+pub fn builtin_global(name: &str) -> Option<Value>
+{
+    match name
+    {
+        "print" => Some(native("print", None, builtin_print)),
+        "println" => Some(native("println", None, builtin_println)),
+        "range" => Some(native("range", Some(2), builtin_range)),
+        "Console" =>
+        {
+            let console = TypeObject::new("Console", TypeShape::Native);
+            console.statics.borrow_mut().insert("ReadLine".into(), native("ReadLine", Some(0), builtin_read_line));
+            console.statics.borrow_mut().insert("ReadKey".into(), native("ReadKey", Some(0), builtin_read_key));
+            Some(Value::Type(Rc::new(console)))
+        },
+        _ => None,
+    }
+}
+
+/// Methods that builtin values expose, such as `array.length()`
+pub fn get_builtin_method(value: &Value, name: &str) -> Option<Value>
+{
+    match (value, name)
+    {
+        (Value::Array(_), "length") => Some(bound_native(value, "length", Some(0), builtin_length)),
+        (Value::Array(_), "push") => Some(bound_native(value, "push", Some(1), builtin_push)),
+        (Value::Array(_), "pop") => Some(bound_native(value, "pop", Some(0), builtin_pop)),
+        (Value::String(_), "length") => Some(bound_native(value, "length", Some(0), builtin_length)),
+        (Value::Int(_), "is_even") => Some(bound_native(value, "is_even", Some(0), builtin_is_even)),
+        (Value::Int(_), "is_odd") => Some(bound_native(value, "is_odd", Some(0), builtin_is_odd)),
+        _ => None,
+    }
+}
+
+fn builtin_print(_: &mut Vm, args: Vec<Value>, _: &Token) -> VmResult<Value>
+{
+    print!("{}", args.iter().map(|a| a.to_string()).join(" "));
+    let _ = std::io::stdout().flush();
+    Ok(Value::Void)
+}
+
+fn builtin_println(_: &mut Vm, args: Vec<Value>, _: &Token) -> VmResult<Value>
+{
+    println!("{}", args.iter().map(|a| a.to_string()).join(" "));
+    Ok(Value::Void)
+}
+
+fn builtin_range(_: &mut Vm, args: Vec<Value>, token: &Token) -> VmResult<Value>
+{
+    match (&args[0], &args[1])
+    {
+        (Value::Int(begin), Value::Int(end)) => Ok(Value::new_array((*begin..*end).map(Value::Int).collect())),
+        (a, b) => Err(RuntimeError::TypeMismatch(Some(*token), format!("`range` expects `Int` bounds, found `{}` and `{}`", a.type_name(), b.type_name()))),
+    }
+}
+
+fn read_line(token: &Token) -> VmResult<String>
+{
+    let mut line = String::new();
+    match std::io::stdin().lock().read_line(&mut line)
+    {
+        Ok(_) => Ok(line.trim_end_matches(['\n', '\r']).to_owned()),
+        Err(e) => Err(RuntimeError::Native(Some(*token), format!("Failed to read from stdin: {}", e))),
+    }
+}
+
+fn builtin_read_line(_: &mut Vm, _: Vec<Value>, token: &Token) -> VmResult<Value>
+{
+    read_line(token).map(Value::String)
+}
+
+fn builtin_read_key(_: &mut Vm, _: Vec<Value>, token: &Token) -> VmResult<Value>
+{
+    let line = read_line(token)?;
+    Ok(Value::String(line.chars().next().map(|c| c.to_string()).unwrap_or_default()))
+}
+
+fn builtin_length(_: &mut Vm, args: Vec<Value>, _: &Token) -> VmResult<Value>
+{
+    match &args[0]
+    {
+        Value::Array(array) => Ok(Value::Int(array.borrow().len() as i64)),
+        Value::String(s) => Ok(Value::Int(s.chars().count() as i64)),
+        _ => unreachable!("only bound to arrays and strings"),
+    }
+}
+
+fn builtin_push(_: &mut Vm, args: Vec<Value>, _: &Token) -> VmResult<Value>
+{
+    let Value::Array(array) = &args[0] else { unreachable!("only bound to arrays") };
+    array.borrow_mut().push(args[1].clone());
+    Ok(Value::Void)
+}
+
+fn builtin_pop(_: &mut Vm, args: Vec<Value>, token: &Token) -> VmResult<Value>
+{
+    let Value::Array(array) = &args[0] else { unreachable!("only bound to arrays") };
+    let popped = array.borrow_mut().pop();
+    popped.ok_or(RuntimeError::IndexOutOfBounds { token: Some(*token), index: 0, length: 0 })
+}
+
+fn builtin_is_even(_: &mut Vm, args: Vec<Value>, _: &Token) -> VmResult<Value>
+{
+    let Value::Int(i) = args[0] else { unreachable!("only bound to integers") };
+    Ok(Value::Bool(i % 2 == 0))
+}
+
+fn builtin_is_odd(_: &mut Vm, args: Vec<Value>, _: &Token) -> VmResult<Value>
+{
+    let Value::Int(i) = args[0] else { unreachable!("only bound to integers") };
+    Ok(Value::Bool(i % 2 != 0))
+}
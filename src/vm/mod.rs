@@ -0,0 +1,629 @@
+pub mod value;
+pub mod builtins;
+pub mod ops;
+
+use std::{cell::RefCell, rc::Rc};
+
+use value::{Closure, EnumPayload, EnumValue, Generator, GeneratorState, StructValue, TypeObject, Value};
+
+use crate::{codegen::chunk::{Bytecode, Capture, Constant, FailKind, FunctionProto, Instr}, interp::{builtins::BUILTIN_NAMES, RuntimeError, MAX_CALL_DEPTH}, lexing::token::{Token, TokenType}, workspace::FileId};
+
+pub type VmResult<T> = Result<T, RuntimeError>;
+
+/// The deepest the call stack may grow before the program is stopped, the same as in the interpreter
+const MAX_FRAMES: usize = MAX_CALL_DEPTH;
+
+#[derive(Debug)]
+struct Frame
+{
+    closure: Rc<Closure>,
+    ip: usize,
+    /// The stack index of the frame's first local slot
+    base: usize,
+    /// The number of arguments the caller passed, counting the receiver of a method
+    argc: usize,
+}
+
+/// Runs compiled bytecode with a value stack and a stack of call frames
+pub struct Vm
+{
+    bytecode: Bytecode,
+    globals: Vec<Value>,
+    types: Vec<Rc<TypeObject>>,
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    /// The command-line arguments passed on to `fn main`
+    args: Vec<String>,
+    /// The instruction pointer and stack values of the generator frame a `yield` just left, picked up by the
+    /// `resume` that was running it
+    suspended: Option<(usize, Vec<Value>)>,
+}
+
+impl Vm
+{
+    pub fn new(bytecode: Bytecode) -> Self
+    {
+        let types: Vec<Rc<TypeObject>> = bytecode.types.iter().map(|t| Rc::new(TypeObject::new(&t.name, t.shape.clone()))).collect();
+        for (type_obj, proto) in types.iter().zip(&bytecode.types)
+        {
+            let mut methods = type_obj.methods.borrow_mut();
+            for (name, index) in &proto.methods
+            {
+                methods.insert(name.clone(), Rc::new(Closure { proto: bytecode.functions[*index as usize].clone(), upvalues: vec![] }));
+            }
+        }
+
+        let mut globals = vec![Value::Void; bytecode.globals.len()];
+        for (slot, name) in BUILTIN_NAMES.iter().enumerate()
+        {
+            globals[slot] = builtins::builtin_global(name).unwrap_or(Value::Void);
+        }
+
+        Self { bytecode, globals, types, stack: vec![], frames: vec![], args: vec![], suspended: None }
+    }
+
+    /// Sets the arguments `fn main` receives if it declares a parameter for them
+    pub fn set_args(&mut self, args: Vec<String>)
+    {
+        self.args = args;
+    }
+
+    pub fn bytecode(&self) -> &Bytecode
+    {
+        &self.bytecode
+    }
+
+    /// Initializes every file's globals and then calls the entry file's `fn main`.
+    /// Errors are returned along with the file they occurred in.
+    pub fn run(&mut self) -> Result<Value, (FileId, RuntimeError)>
+    {
+        for init in self.bytecode.inits.clone()
+        {
+            let proto = self.bytecode.functions[init as usize].clone();
+            self.call_top(Value::Closure(Rc::new(Closure { proto, upvalues: vec![] })), vec![])?;
+        }
+
+        let entry = self.bytecode.inits.last().map(|i| self.bytecode.functions[*i as usize].module).unwrap_or(0);
+        match self.bytecode.main.map(|slot| self.globals[slot as usize].clone())
+        {
+            Some(Value::Closure(main)) =>
+            {
+                // `fn main` may declare a parameter for the command-line arguments
+                let args = match main.proto.arity
+                {
+                    0 => vec![],
+                    _ => vec![Value::new_array(self.args.iter().cloned().map(Value::String).collect())],
+                };
+
+                self.call_top(Value::Closure(main), args)
+            },
+            _ => Err((entry, RuntimeError::NoMainFunction)),
+        }
+    }
+
+    /// Calls a function from outside of any frame
+    fn call_top(&mut self, callee: Value, args: Vec<Value>) -> Result<Value, (FileId, RuntimeError)>
+    {
+        let depth = self.frames.len();
+        let argc = args.len();
+        self.stack.push(callee);
+        self.stack.extend(args);
+
+        let result = match self.call_value(argc)
+        {
+            Ok(()) if self.frames.len() == depth => Ok(self.stack.pop().unwrap_or(Value::Void)),
+            Ok(()) => self.execute(depth),
+            Err(error) => Err(error),
+        };
+
+        result.map_err(|error| {
+            let module = self.frames.last().map(|f| f.closure.proto.module).unwrap_or(0);
+            self.frames.truncate(depth);
+            self.stack.clear();
+            (module, error)
+        })
+    }
+
+    fn frame(&self) -> &Frame
+    {
+        self.frames.last().expect("a function is running")
+    }
+
+    /// The token of the instruction being executed, or an end of file token for calls made from outside the program
+    fn token(&self) -> Token
+    {
+        match self.frames.last()
+        {
+            Some(frame) => frame.closure.proto.chunk.tokens[frame.ip - 1],
+            None => Token { token_type: TokenType::EOF, pos: 0.into(), value: None },
+        }
+    }
+
+    fn pop(&mut self) -> Value
+    {
+        self.stack.pop().expect("the stack is not empty")
+    }
+
+    fn peek(&self) -> &Value
+    {
+        self.stack.last().expect("the stack is not empty")
+    }
+
+    fn local(&self, slot: u16) -> &Value
+    {
+        &self.stack[self.frame().base + slot as usize]
+    }
+
+    fn local_mut(&mut self, slot: u16) -> &mut Value
+    {
+        let base = self.frame().base;
+        &mut self.stack[base + slot as usize]
+    }
+
+    /// Runs until the frame at `depth` returns, producing its result
+    fn execute(&mut self, depth: usize) -> VmResult<Value>
+    {
+        loop
+        {
+            let frame = self.frames.last_mut().expect("a function is running");
+            let proto = frame.closure.proto.clone();
+            let instr = proto.chunk.code[frame.ip];
+            frame.ip += 1;
+
+            match instr
+            {
+                Instr::Constant(index) => self.stack.push(match &proto.chunk.constants[index as usize]
+                {
+                    Constant::Int(i) => Value::Int(*i),
+                    Constant::Float(f) => Value::Float(*f),
+                    Constant::String(s) => Value::String(s.clone()),
+                    Constant::Char(c) => Value::Char(*c),
+                }),
+                Instr::Void => self.stack.push(Value::Void),
+                Instr::True => self.stack.push(Value::Bool(true)),
+                Instr::False => self.stack.push(Value::Bool(false)),
+                Instr::Pop => { self.pop(); },
+                Instr::Dup => self.stack.push(self.peek().clone()),
+
+                Instr::GetLocal(slot) => self.stack.push(self.local(slot).clone()),
+                Instr::MarkStack(slot) =>
+                {
+                    let height = self.stack.len() - self.frame().base;
+                    *self.local_mut(slot) = Value::Int(height as i64);
+                },
+                Instr::ResetStack(slot) =>
+                {
+                    let Value::Int(height) = *self.local(slot) else { unreachable!("the slot holds a stack height") };
+                    let base = self.frame().base;
+                    self.stack.truncate(base + height as usize);
+                },
+                Instr::SetLocal(slot) | Instr::DefineLocal(slot) =>
+                {
+                    let value = self.pop();
+                    *self.local_mut(slot) = value;
+                },
+                Instr::GetCell(slot) =>
+                {
+                    let value = match self.local(slot)
+                    {
+                        Value::Cell(cell) => cell.borrow().clone(),
+                        other => other.clone(),
+                    };
+
+                    self.stack.push(value);
+                },
+                Instr::SetCell(slot) =>
+                {
+                    let value = self.pop();
+                    match self.local_mut(slot)
+                    {
+                        Value::Cell(cell) => *cell.borrow_mut() = value,
+                        other => *other = Value::new_cell(value),
+                    }
+                },
+                Instr::DefineCell(slot) =>
+                {
+                    // A fresh cell, so closures from earlier loop iterations keep their own variable
+                    let value = self.pop();
+                    *self.local_mut(slot) = Value::new_cell(value);
+                },
+                Instr::GetUpvalue(index) =>
+                {
+                    let value = self.frame().closure.upvalues[index as usize].borrow().clone();
+                    self.stack.push(value);
+                },
+                Instr::SetUpvalue(index) =>
+                {
+                    let value = self.pop();
+                    *self.frame().closure.upvalues[index as usize].borrow_mut() = value;
+                },
+                Instr::GetGlobal(slot) => self.stack.push(self.globals[slot as usize].clone()),
+                Instr::SetGlobal(slot) => self.globals[slot as usize] = self.pop(),
+                Instr::Closure(index) =>
+                {
+                    let function = self.bytecode.functions[index as usize].clone();
+                    let upvalues = function.captures.iter().map(|capture| match *capture
+                    {
+                        Capture::Local(slot) => match self.local_mut(slot)
+                        {
+                            Value::Cell(cell) => cell.clone(),
+                            other =>
+                            {
+                                let cell = Value::new_cell(std::mem::replace(other, Value::Void));
+                                *other = cell;
+                                let Value::Cell(cell) = other else { unreachable!() };
+                                cell.clone()
+                            },
+                        },
+                        Capture::Upvalue(index) => self.frame().closure.upvalues[index as usize].clone(),
+                    }).collect();
+
+                    self.stack.push(Value::Closure(Rc::new(Closure { proto: function, upvalues })));
+                },
+                Instr::Type(index) => self.stack.push(Value::Type(self.types[index as usize].clone())),
+
+                Instr::GetMember(name) =>
+                {
+                    let owner = self.pop();
+                    let value = self.get_member(owner, proto.chunk.string(name), &self.token())?;
+                    self.stack.push(value);
+                },
+                Instr::SetField(name) =>
+                {
+                    let value = self.pop();
+                    let owner = self.pop();
+                    self.set_field(&owner, proto.chunk.string(name), value, &self.token())?;
+                },
+                Instr::SetStatic(name) =>
+                {
+                    let value = self.pop();
+                    if let Value::Type(type_obj) = self.pop()
+                    {
+                        type_obj.statics.borrow_mut().insert(proto.chunk.string(name).to_owned(), value);
+                    }
+                },
+                Instr::GetIndex =>
+                {
+                    let indexer = self.pop();
+                    let owner = self.pop();
+                    let value = self.index_value(&owner, &indexer, &self.token())?;
+                    self.stack.push(value);
+                },
+                Instr::SetIndex =>
+                {
+                    let value = self.pop();
+                    let indexer = self.pop();
+                    let owner = self.pop();
+                    self.set_index(&owner, &indexer, value, &self.token())?;
+                },
+                Instr::Array(count) =>
+                {
+                    let values = self.stack.split_off(self.stack.len() - count as usize);
+                    self.stack.push(Value::new_array(values));
+                },
+                Instr::Construct(index, count) =>
+                {
+                    let fields = self.stack.split_off(self.stack.len() - count as usize);
+                    let type_obj = self.types[index as usize].clone();
+                    self.stack.push(Value::Struct(Rc::new(StructValue { type_obj, fields: fields.into() })));
+                },
+                Instr::ConstructVariant(index, variant, count) =>
+                {
+                    let fields = self.stack.split_off(self.stack.len() - count as usize);
+                    let type_obj = self.types[index as usize].clone();
+                    self.stack.push(Value::Enum(Rc::new(EnumValue { type_obj, variant, payload: EnumPayload::Struct(fields.into()) })));
+                },
+
+                Instr::Add | Instr::Subtract | Instr::Multiply | Instr::Divide | Instr::Modulus
+                | Instr::Equal | Instr::NotEqual | Instr::Less | Instr::LessEqual | Instr::Greater | Instr::GreaterEqual
+                | Instr::And | Instr::Or =>
+                {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let value = self.binary_op(instr, left, right, &self.token())?;
+                    self.stack.push(value);
+                },
+                Instr::Negate | Instr::Not =>
+                {
+                    let value = self.pop();
+                    let value = self.unary_op(instr, value, &self.token())?;
+                    self.stack.push(value);
+                },
+                Instr::ExpectBool =>
+                {
+                    self.expect_bool(self.peek(), &self.token())?;
+                },
+                Instr::Cast(target) =>
+                {
+                    let value = self.pop();
+                    let value = self.cast_value(value, target, &self.token())?;
+                    self.stack.push(value);
+                },
+
+                Instr::Jump(target) => self.frames.last_mut().unwrap().ip = target as usize,
+                Instr::JumpIfFalse(target) =>
+                {
+                    let value = self.pop();
+                    if !self.expect_bool(&value, &self.token())?
+                    {
+                        self.frames.last_mut().unwrap().ip = target as usize;
+                    }
+                },
+                Instr::JumpIfArgPassed(slot, target) =>
+                {
+                    let frame = self.frames.last_mut().unwrap();
+                    if frame.argc > slot as usize
+                    {
+                        frame.ip = target as usize;
+                    }
+                },
+                Instr::Call(argc) => self.call_value(argc as usize)?,
+                Instr::Return =>
+                {
+                    let result = self.pop();
+                    let frame = self.frames.pop().expect("a function is running");
+                    self.stack.truncate(frame.base - 1);
+                    if self.frames.len() == depth
+                    {
+                        return Ok(result);
+                    }
+
+                    self.stack.push(result);
+                },
+                Instr::Yield =>
+                {
+                    // Generator frames are only ever run by `resume`, which is waiting at `depth`
+                    let value = self.pop();
+                    let frame = self.frames.pop().expect("a function is running");
+                    let stack = self.stack.split_off(frame.base);
+                    self.pop();
+                    self.suspended = Some((frame.ip, stack));
+                    return Ok(value);
+                },
+
+                Instr::IsInstance =>
+                {
+                    let pattern = self.pop();
+                    let value = self.pop();
+                    self.stack.push(Value::Bool(self.is_instance(&value, &pattern)));
+                },
+                Instr::Payload =>
+                {
+                    let payload = match self.pop()
+                    {
+                        Value::Enum(e) => match &e.payload
+                        {
+                            EnumPayload::Single(value) => Some(value.clone()),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+
+                    match payload
+                    {
+                        Some(value) => self.stack.push(value),
+                        None => return Err(RuntimeError::PatternNotMatched(Some(self.token()))),
+                    }
+                },
+                Instr::IsArray(length) =>
+                {
+                    let matches = matches!(self.pop(), Value::Array(array) if array.borrow().len() == length as usize);
+                    self.stack.push(Value::Bool(matches));
+                },
+                Instr::InRange(start, end) =>
+                {
+                    let constants = &proto.chunk.constants;
+                    let matches = match (self.pop(), &constants[start as usize], &constants[end as usize])
+                    {
+                        (Value::Int(v), Constant::Int(a), Constant::Int(b)) => *a <= v && v <= *b,
+                        (Value::Char(v), Constant::Char(a), Constant::Char(b)) => *a <= v && v <= *b,
+                        _ => false,
+                    };
+
+                    self.stack.push(Value::Bool(matches));
+                },
+                Instr::IntoSequence =>
+                {
+                    // Arrays are copied so that changing one inside the loop does not change what the loop visits
+                    let sequence = match self.pop()
+                    {
+                        Value::Array(array) => Value::new_array(array.borrow().clone()),
+                        Value::String(s) => Value::new_array(s.chars().map(|c| Value::String(c.to_string())).collect()),
+                        generator @ Value::Generator(_) => generator,
+                        other => return Err(RuntimeError::TypeMismatch(Some(self.token()), format!("`{}` is not iterable", other.type_name()))),
+                    };
+
+                    self.stack.push(sequence);
+                },
+                Instr::ForNext(sequence, index, exit) =>
+                {
+                    let next = match (self.local(sequence), self.local(index))
+                    {
+                        (Value::Array(array), Value::Int(i)) =>
+                        {
+                            let (item, i) = (array.borrow().get(*i as usize).cloned(), *i);
+                            if item.is_some()
+                            {
+                                *self.local_mut(index) = Value::Int(i + 1);
+                            }
+
+                            item
+                        },
+                        (Value::Generator(generator), _) => self.resume(&generator.clone())?,
+                        _ => unreachable!("`for` loops keep their sequence and index in hidden slots"),
+                    };
+
+                    match next
+                    {
+                        Some(item) => self.stack.push(item),
+                        None => self.frames.last_mut().unwrap().ip = exit as usize,
+                    }
+                },
+                Instr::Fail(FailKind::PatternNotMatched) => return Err(RuntimeError::PatternNotMatched(Some(self.token()))),
+                Instr::Fail(FailKind::LetElseDidNotDiverge) => return Err(RuntimeError::LetElseDidNotDiverge(self.token())),
+            }
+        }
+    }
+
+    /// Calls the value below the top `argc` values of the stack. Functions compiled to bytecode get a new frame,
+    /// while everything else runs to completion and leaves its result in place of the callee.
+    fn call_value(&mut self, argc: usize) -> VmResult<()>
+    {
+        let callee_index = self.stack.len() - argc - 1;
+        let arity_error = |vm: &Self, expected: usize, found: usize| RuntimeError::ArityMismatch { token: Some(vm.token()), expected, found };
+
+        match self.stack[callee_index].clone()
+        {
+            Value::Closure(closure) => self.call_closure(closure, argc),
+            Value::BoundMethod(receiver, closure) =>
+            {
+                self.stack.insert(callee_index + 1, *receiver);
+                self.call_closure(closure, argc + 1)
+            },
+            Value::Native(native) =>
+            {
+                if native.arity.is_some_and(|a| a != argc)
+                {
+                    return Err(arity_error(self, native.arity.unwrap(), argc));
+                }
+
+                let args = self.stack.split_off(callee_index + 1);
+                self.pop();
+                let result = (native.func)(self, args, &self.token())?;
+                self.stack.push(result);
+                Ok(())
+            },
+            Value::BoundNative(receiver, native) =>
+            {
+                if native.arity.is_some_and(|a| a != argc)
+                {
+                    return Err(arity_error(self, native.arity.unwrap(), argc));
+                }
+
+                let mut args = self.stack.split_off(callee_index + 1);
+                args.insert(0, *receiver);
+                self.pop();
+                let result = (native.func)(self, args, &self.token())?;
+                self.stack.push(result);
+                Ok(())
+            },
+            Value::VariantConstructor(type_obj, variant) =>
+            {
+                if argc != 1
+                {
+                    return Err(arity_error(self, 1, argc));
+                }
+
+                let payload = EnumPayload::Single(self.pop());
+                self.pop();
+                self.stack.push(Value::Enum(Rc::new(EnumValue { type_obj, variant, payload })));
+                Ok(())
+            },
+            other => Err(RuntimeError::NotCallable(Some(self.token()), other.type_name())),
+        }
+    }
+
+    fn call_closure(&mut self, closure: Rc<Closure>, argc: usize) -> VmResult<()>
+    {
+        let proto: Rc<FunctionProto> = closure.proto.clone();
+
+        // Methods called through their type (`Type.method(value, ...)`) take the receiver as the first argument
+        let found = match proto.has_self
+        {
+            true => argc.saturating_sub(1),
+            false => argc,
+        };
+
+        if found < proto.required || found > proto.arity
+        {
+            return Err(RuntimeError::ArityMismatch { token: Some(self.token()), expected: proto.arity, found });
+        }
+
+        if proto.has_self && argc == 0
+        {
+            return Err(RuntimeError::ArityMismatch { token: Some(self.token()), expected: proto.arity + 1, found: 0 });
+        }
+
+        if self.frames.len() >= MAX_FRAMES
+        {
+            return Err(RuntimeError::StackOverflow(Some(self.token())));
+        }
+
+        let base = self.stack.len() - argc;
+        self.stack.resize(base + proto.locals, Value::Void);
+        for &slot in &proto.boxed_params
+        {
+            let value = std::mem::replace(&mut self.stack[base + slot as usize], Value::Void);
+            self.stack[base + slot as usize] = Value::new_cell(value);
+        }
+
+        if proto.generator
+        {
+            // The new frame is set aside until the generator is first resumed
+            let stack = self.stack.split_off(base);
+            self.pop();
+            let generator = Generator { closure, state: GeneratorState::Suspended { ip: 0, argc, stack } };
+            self.stack.push(Value::Generator(Rc::new(RefCell::new(generator))));
+            return Ok(());
+        }
+
+        self.frames.push(Frame { closure, ip: 0, base, argc });
+        Ok(())
+    }
+
+    /// Runs a generator until its next `yield`, producing the yielded value, or `None` once its body has finished
+    fn resume(&mut self, generator: &Rc<RefCell<Generator>>) -> VmResult<Option<Value>>
+    {
+        if self.frames.len() >= MAX_FRAMES
+        {
+            return Err(RuntimeError::StackOverflow(Some(self.token())));
+        }
+
+        let closure = generator.borrow().closure.clone();
+        let state = std::mem::replace(&mut generator.borrow_mut().state, GeneratorState::Running);
+        let (ip, argc, stack) = match state
+        {
+            GeneratorState::Suspended { ip, argc, stack } => (ip, argc, stack),
+            GeneratorState::Running => return Err(RuntimeError::GeneratorRunning(Some(self.token()))),
+            GeneratorState::Finished =>
+            {
+                generator.borrow_mut().state = GeneratorState::Finished;
+                return Ok(None);
+            },
+        };
+
+        // The frame goes back on the stack above a stand-in for the callee, as if it had just been called
+        let depth = self.frames.len();
+        self.stack.push(Value::Void);
+        let base = self.stack.len();
+        self.stack.extend(stack);
+        if ip > 0
+        {
+            // The value of the `yield` it stopped at
+            self.stack.push(Value::Void);
+        }
+
+        self.frames.push(Frame { closure, ip, base, argc });
+        let result = self.execute(depth);
+
+        let mut generator = generator.borrow_mut();
+        match (result, self.suspended.take())
+        {
+            (Ok(value), Some((ip, stack))) =>
+            {
+                generator.state = GeneratorState::Suspended { ip, argc, stack };
+                Ok(Some(value))
+            },
+            (Ok(_), None) =>
+            {
+                generator.state = GeneratorState::Finished;
+                Ok(None)
+            },
+            (Err(error), _) =>
+            {
+                generator.state = GeneratorState::Finished;
+                Err(error)
+            },
+        }
+    }
+}
@@ -0,0 +1,330 @@
+use std::rc::Rc;
+
+use crate::{codegen::chunk::{CastTarget, Instr, TypeShape, VariantShape}, interp::RuntimeError, lexing::token::{Token, TokenType}};
+
+use super::{builtins::get_builtin_method, value::{EnumPayload, EnumValue, Value}, Vm, VmResult};
+
+/// The operator a binary instruction was compiled from, for error messages
+fn operator(instr: Instr) -> TokenType
+{
+    match instr
+    {
+        Instr::Add => TokenType::Plus,
+        Instr::Subtract => TokenType::Minus,
+        Instr::Multiply => TokenType::Multiply,
+        Instr::Divide => TokenType::Divide,
+        Instr::Modulus => TokenType::Modulus,
+        Instr::Equal => TokenType::EqualEqual,
+        Instr::NotEqual => TokenType::BangEqual,
+        Instr::Less => TokenType::LessThan,
+        Instr::LessEqual => TokenType::LessEqual,
+        Instr::Greater => TokenType::GreaterThan,
+        Instr::GreaterEqual => TokenType::GreaterEqual,
+        Instr::And => TokenType::AndAnd,
+        Instr::Or => TokenType::PipePipe,
+        other => unreachable!("{:?} is not a binary operator", other),
+    }
+}
+
+impl Vm
+{
+    pub fn binary_op(&self, instr: Instr, left: Value, right: Value, token: &Token) -> VmResult<Value>
+    {
+        let op = operator(instr);
+        let mismatch = |left: &Value, right: &Value| {
+            RuntimeError::TypeMismatch(
+                Some(*token),
+                format!("cannot apply {} to `{}` and `{}`", op.describe(), left.type_name(), right.type_name())
+            )
+        };
+
+        match instr
+        {
+            Instr::Equal => return Ok(Value::Bool(left == right)),
+            Instr::NotEqual => return Ok(Value::Bool(left != right)),
+            _ => {}
+        }
+
+        match (&left, &right)
+        {
+            (Value::Int(a), Value::Int(b)) => match instr
+            {
+                Instr::Add => Ok(Value::Int(a.wrapping_add(*b))),
+                Instr::Subtract => Ok(Value::Int(a.wrapping_sub(*b))),
+                Instr::Multiply => Ok(Value::Int(a.wrapping_mul(*b))),
+                Instr::Divide | Instr::Modulus if *b == 0 => Err(RuntimeError::DivisionByZero(Some(*token))),
+                Instr::Divide => Ok(Value::Int(a.wrapping_div(*b))),
+                Instr::Modulus => Ok(Value::Int(a.wrapping_rem(*b))),
+                Instr::Less => Ok(Value::Bool(a < b)),
+                Instr::LessEqual => Ok(Value::Bool(a <= b)),
+                Instr::Greater => Ok(Value::Bool(a > b)),
+                Instr::GreaterEqual => Ok(Value::Bool(a >= b)),
+                _ => Err(mismatch(&left, &right)),
+            },
+            (Value::Int(_), Value::Float(_)) | (Value::Float(_), Value::Int(_)) | (Value::Float(_), Value::Float(_)) =>
+            {
+                let a = as_float(&left);
+                let b = as_float(&right);
+                match instr
+                {
+                    Instr::Add => Ok(Value::Float(a + b)),
+                    Instr::Subtract => Ok(Value::Float(a - b)),
+                    Instr::Multiply => Ok(Value::Float(a * b)),
+                    Instr::Divide => Ok(Value::Float(a / b)),
+                    Instr::Modulus => Ok(Value::Float(a % b)),
+                    Instr::Less => Ok(Value::Bool(a < b)),
+                    Instr::LessEqual => Ok(Value::Bool(a <= b)),
+                    Instr::Greater => Ok(Value::Bool(a > b)),
+                    Instr::GreaterEqual => Ok(Value::Bool(a >= b)),
+                    _ => Err(mismatch(&left, &right)),
+                }
+            },
+            (Value::String(a), Value::String(b)) => match instr
+            {
+                Instr::Add => Ok(Value::String(format!("{}{}", a, b))),
+                Instr::Less => Ok(Value::Bool(a < b)),
+                Instr::LessEqual => Ok(Value::Bool(a <= b)),
+                Instr::Greater => Ok(Value::Bool(a > b)),
+                Instr::GreaterEqual => Ok(Value::Bool(a >= b)),
+                _ => Err(mismatch(&left, &right)),
+            },
+            (Value::Char(a), Value::Char(b)) => match instr
+            {
+                Instr::Less => Ok(Value::Bool(a < b)),
+                Instr::LessEqual => Ok(Value::Bool(a <= b)),
+                Instr::Greater => Ok(Value::Bool(a > b)),
+                Instr::GreaterEqual => Ok(Value::Bool(a >= b)),
+                _ => Err(mismatch(&left, &right)),
+            },
+            (Value::Bool(a), Value::Bool(b)) => match instr
+            {
+                Instr::And => Ok(Value::Bool(*a && *b)),
+                Instr::Or => Ok(Value::Bool(*a || *b)),
+                _ => Err(mismatch(&left, &right)),
+            },
+            _ => Err(mismatch(&left, &right)),
+        }
+    }
+
+    pub fn unary_op(&self, instr: Instr, value: Value, token: &Token) -> VmResult<Value>
+    {
+        match (instr, &value)
+        {
+            (Instr::Negate, Value::Int(i)) => Ok(Value::Int(i.wrapping_neg())),
+            (Instr::Negate, Value::Float(f)) => Ok(Value::Float(-f)),
+            (Instr::Not, Value::Bool(b)) => Ok(Value::Bool(!b)),
+            _ => Err(RuntimeError::TypeMismatch(
+                Some(*token),
+                format!("cannot apply {} to `{}`", token.token_type.describe(), value.type_name())
+            )),
+        }
+    }
+
+    pub fn expect_bool(&self, value: &Value, token: &Token) -> VmResult<bool>
+    {
+        match value
+        {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(RuntimeError::TypeMismatch(Some(*token), format!("expected `Bool` but found `{}`", value.type_name()))),
+        }
+    }
+
+    pub fn cast_value(&self, value: Value, target: CastTarget, token: &Token) -> VmResult<Value>
+    {
+        let error = |value: &Value| {
+            let target = match target
+            {
+                CastTarget::Type(index) => self.types[index as usize].name.clone(),
+                other => format!("{:?}", other),
+            };
+
+            RuntimeError::TypeMismatch(Some(*token), format!("cannot cast `{}` to `{}`", value.type_name(), target))
+        };
+
+        match (target, &value)
+        {
+            (CastTarget::Int, Value::Int(_)) => Ok(value),
+            (CastTarget::Int, Value::Float(f)) => Ok(Value::Int(*f as i64)),
+            (CastTarget::Int, Value::Bool(b)) => Ok(Value::Int(*b as i64)),
+            (CastTarget::Int, Value::Char(c)) => Ok(Value::Int(*c as i64)),
+            (CastTarget::Int, Value::String(s)) => s.trim().parse().map(Value::Int).map_err(|_| error(&value)),
+            (CastTarget::Int, Value::Enum(e)) => e.type_obj.discriminant(e.variant).map(Value::Int).ok_or_else(|| error(&value)),
+            (CastTarget::Float, Value::Int(i)) => Ok(Value::Float(*i as f64)),
+            (CastTarget::Float, Value::Float(_)) => Ok(value),
+            (CastTarget::Float, Value::String(s)) => s.trim().parse().map(Value::Float).map_err(|_| error(&value)),
+            (CastTarget::Bool, Value::Bool(_)) => Ok(value),
+            (CastTarget::Char, Value::Char(_)) => Ok(value),
+            (CastTarget::Char, Value::Int(i)) => u32::try_from(*i).ok().and_then(char::from_u32).map(Value::Char).ok_or_else(|| error(&value)),
+            (CastTarget::String, _) => Ok(Value::String(value.to_string())),
+            (CastTarget::Array, Value::Array(_)) => Ok(value),
+            (CastTarget::Iter, Value::Generator(_)) => Ok(value),
+            (CastTarget::Function, Value::Closure(_) | Value::Native(_) | Value::BoundMethod(_, _)) => Ok(value),
+            // User defined types only accept values that already have that type, and enums also the discriminant of a member
+            (CastTarget::Type(index), Value::Struct(s)) if Rc::ptr_eq(&self.types[index as usize], &s.type_obj) => Ok(value),
+            (CastTarget::Type(index), Value::Enum(e)) if Rc::ptr_eq(&self.types[index as usize], &e.type_obj) => Ok(value),
+            (CastTarget::Type(index), Value::Int(i)) if matches!(self.types[index as usize].shape, TypeShape::Enum(_)) =>
+            {
+                let type_obj = &self.types[index as usize];
+                match type_obj.from_discriminant(*i)
+                {
+                    Some(variant) => Ok(Value::Enum(Rc::new(EnumValue { type_obj: type_obj.clone(), variant, payload: EnumPayload::None }))),
+                    None => Err(RuntimeError::InvalidDiscriminant { token: Some(*token), type_name: type_obj.name.clone(), value: *i }),
+                }
+            },
+            _ => Err(error(&value)),
+        }
+    }
+
+    pub fn index_value(&self, value: &Value, indexer: &Value, token: &Token) -> VmResult<Value>
+    {
+        match (value, indexer)
+        {
+            (Value::Array(array), Value::Int(index)) =>
+            {
+                let array = array.borrow();
+                let i = check_index(*index, array.len(), token)?;
+                Ok(array[i].clone())
+            },
+            (Value::String(s), Value::Int(index)) =>
+            {
+                let chars: Vec<char> = s.chars().collect();
+                let i = check_index(*index, chars.len(), token)?;
+                Ok(Value::String(chars[i].to_string()))
+            },
+            _ => Err(RuntimeError::TypeMismatch(
+                Some(*token),
+                format!("cannot index `{}` with `{}`", value.type_name(), indexer.type_name())
+            )),
+        }
+    }
+
+    pub fn set_index(&self, owner: &Value, indexer: &Value, value: Value, token: &Token) -> VmResult<()>
+    {
+        match (owner, indexer)
+        {
+            (Value::Array(array), Value::Int(i)) =>
+            {
+                let length = array.borrow().len();
+                let i = check_index(*i, length, token)?;
+                array.borrow_mut()[i] = value;
+                Ok(())
+            },
+            _ => Err(RuntimeError::TypeMismatch(
+                Some(*token),
+                format!("cannot assign to an index of `{}`", owner.type_name())
+            )),
+        }
+    }
+
+    pub fn get_member(&self, value: Value, name: &str, token: &Token) -> VmResult<Value>
+    {
+        match &value
+        {
+            Value::Struct(s) =>
+            {
+                if let Some(index) = s.type_obj.fields().iter().position(|f| f == name)
+                {
+                    return Ok(s.fields.borrow()[index].clone());
+                }
+
+                if let Some(method) = s.type_obj.get_method(name)
+                {
+                    return Ok(Value::BoundMethod(Box::new(value.clone()), method));
+                }
+            },
+            Value::Enum(e) =>
+            {
+                if let Some(field) = e.get_field(name)
+                {
+                    return Ok(field);
+                }
+
+                if let Some(method) = e.type_obj.get_method(name)
+                {
+                    return Ok(Value::BoundMethod(Box::new(value.clone()), method));
+                }
+            },
+            Value::Type(type_obj) =>
+            {
+                if let Some((variant, shape)) = type_obj.variant(name)
+                {
+                    return Ok(match shape
+                    {
+                        VariantShape::Basic(_) => Value::Enum(Rc::new(EnumValue {
+                            type_obj: type_obj.clone(),
+                            variant,
+                            payload: EnumPayload::None,
+                        })),
+                        _ => Value::VariantConstructor(type_obj.clone(), variant),
+                    });
+                }
+
+                if let Some(method) = type_obj.get_method(name)
+                {
+                    return Ok(Value::Closure(method));
+                }
+
+                if let Some(value) = type_obj.statics.borrow().get(name)
+                {
+                    return Ok(value.clone());
+                }
+            },
+            _ =>
+            {
+                if let Some(method) = get_builtin_method(&value, name)
+                {
+                    return Ok(method);
+                }
+            },
+        }
+
+        Err(RuntimeError::UndefinedMember(*token, value.type_name()))
+    }
+
+    pub fn set_field(&self, owner: &Value, name: &str, value: Value, token: &Token) -> VmResult<()>
+    {
+        if let Value::Struct(s) = owner
+        {
+            if let Some(index) = s.type_obj.fields().iter().position(|f| f == name)
+            {
+                s.fields.borrow_mut()[index] = value;
+                return Ok(());
+            }
+        }
+
+        Err(RuntimeError::UndefinedMember(*token, owner.type_name()))
+    }
+
+    /// Whether `value` is an instance of the type or enum variant `pattern` evaluated to
+    pub fn is_instance(&self, value: &Value, pattern: &Value) -> bool
+    {
+        match (pattern, value)
+        {
+            (Value::Type(t), Value::Struct(s)) => matches!(t.shape, TypeShape::Struct(_)) && Rc::ptr_eq(t, &s.type_obj),
+            (Value::VariantConstructor(t, variant), Value::Enum(e)) => Rc::ptr_eq(t, &e.type_obj) && e.variant == *variant,
+            _ => false,
+        }
+    }
+}
+
+fn as_float(value: &Value) -> f64
+{
+    match value
+    {
+        Value::Int(i) => *i as f64,
+        Value::Float(f) => *f,
+        _ => unreachable!(),
+    }
+}
+
+fn check_index(index: i64, length: usize, token: &Token) -> VmResult<usize>
+{
+    if index < 0 || index as usize >= length
+    {
+        Err(RuntimeError::IndexOutOfBounds { token: Some(*token), index, length })
+    }
+    else
+    {
+        Ok(index as usize)
+    }
+}
@@ -0,0 +1,309 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use itertools::Itertools;
+
+use crate::{codegen::chunk::{FunctionProto, TypeShape, VariantShape}, interp::RuntimeError, lexing::token::Token};
+
+use super::Vm;
+
+pub type NativeFnPtr = fn(&mut Vm, Vec<Value>, &Token) -> Result<Value, RuntimeError>;
+
+#[derive(Debug, Clone)]
+pub enum Value
+{
+    Void,
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Char(char),
+    Array(Rc<RefCell<Vec<Value>>>),
+    Struct(Rc<StructValue>),
+    Enum(Rc<EnumValue>),
+    Closure(Rc<Closure>),
+    BoundMethod(Box<Value>, Rc<Closure>),
+    Native(Rc<NativeFn>),
+    BoundNative(Box<Value>, Rc<NativeFn>),
+    Type(Rc<TypeObject>),
+    VariantConstructor(Rc<TypeObject>, u16),
+    /// A local that closures capture. Cells only ever live in local slots and upvalues.
+    Cell(Rc<RefCell<Value>>),
+    Generator(Rc<RefCell<Generator>>),
+}
+
+impl Value
+{
+    pub fn type_name(&self) -> String
+    {
+        match self
+        {
+            Value::Void => "Void".into(),
+            Value::Int(_) => "Int".into(),
+            Value::Float(_) => "Float".into(),
+            Value::Bool(_) => "Bool".into(),
+            Value::String(_) => "String".into(),
+            Value::Char(_) => "Char".into(),
+            Value::Array(_) => "Array".into(),
+            Value::Struct(s) => s.type_obj.name.clone(),
+            Value::Enum(e) => e.type_obj.name.clone(),
+            Value::Closure(_) | Value::BoundMethod(_, _) | Value::Native(_) | Value::BoundNative(_, _) | Value::VariantConstructor(_, _) => "Function".into(),
+            Value::Type(_) => "Type".into(),
+            Value::Cell(cell) => cell.borrow().type_name(),
+            Value::Generator(_) => "Iter".into(),
+        }
+    }
+
+    pub fn new_array(values: Vec<Value>) -> Self
+    {
+        Value::Array(Rc::new(RefCell::new(values)))
+    }
+
+    pub fn new_cell(value: Value) -> Self
+    {
+        Value::Cell(Rc::new(RefCell::new(value)))
+    }
+}
+
+impl PartialEq for Value
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        match (self, other)
+        {
+            (Value::Void, Value::Void) => true,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Int(a), Value::Float(b)) | (Value::Float(b), Value::Int(a)) => (*a as f64) == *b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Char(a), Value::Char(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => Rc::ptr_eq(a, b) || *a.borrow() == *b.borrow(),
+            (Value::Struct(a), Value::Struct(b)) => Rc::ptr_eq(a, b) || (Rc::ptr_eq(&a.type_obj, &b.type_obj) && *a.fields.borrow() == *b.fields.borrow()),
+            (Value::Enum(a), Value::Enum(b)) => Rc::ptr_eq(&a.type_obj, &b.type_obj) && a.variant == b.variant && a.payload == b.payload,
+            (Value::Closure(a), Value::Closure(b)) => Rc::ptr_eq(a, b),
+            (Value::Native(a), Value::Native(b)) => Rc::ptr_eq(a, b),
+            (Value::Type(a), Value::Type(b)) => Rc::ptr_eq(a, b),
+            (Value::Generator(a), Value::Generator(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Value
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        let fields = |names: &[String], values: &[Value]| names.iter().zip(values).map(|(n, v)| format!("{}: {}", n, v)).join(", ");
+
+        match self
+        {
+            Value::Void => write!(f, "void"),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Float(n) => write!(f, "{:?}", n),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Char(c) => write!(f, "{}", c),
+            Value::Array(values) => write!(f, "[{}]", values.borrow().iter().map(|v| v.to_string()).join(", ")),
+            Value::Struct(s) =>
+            {
+                let values = s.fields.borrow();
+                if values.is_empty()
+                {
+                    write!(f, "{} {{}}", s.type_obj.name)
+                }
+                else
+                {
+                    write!(f, "{} {{ {} }}", s.type_obj.name, fields(s.type_obj.fields(), &values))
+                }
+            },
+            Value::Enum(e) =>
+            {
+                let variant = e.type_obj.variant_name(e.variant);
+                match &e.payload
+                {
+                    EnumPayload::None => write!(f, "{}.{}", e.type_obj.name, variant),
+                    EnumPayload::Single(v) => write!(f, "{}.{}({})", e.type_obj.name, variant, v),
+                    EnumPayload::Struct(values) => write!(f, "{}.{} {{ {} }}", e.type_obj.name, variant, fields(e.type_obj.variant_fields(e.variant), &values.borrow())),
+                }
+            },
+            Value::Closure(closure) if closure.proto.name == "<lambda>" => write!(f, "<lambda>"),
+            Value::Closure(closure) => write!(f, "<fn {}>", closure.short_name()),
+            Value::BoundMethod(_, closure) => write!(f, "<method {}>", closure.short_name()),
+            Value::Native(native) => write!(f, "<native fn {}>", native.name),
+            Value::BoundNative(_, native) => write!(f, "<native method {}>", native.name),
+            Value::Type(t) => write!(f, "<type {}>", t.name),
+            Value::VariantConstructor(t, variant) => write!(f, "<constructor {}.{}>", t.name, t.variant_name(*variant)),
+            Value::Cell(cell) => write!(f, "{}", cell.borrow()),
+            Value::Generator(generator) => write!(f, "<iter {}>", generator.borrow().closure.short_name()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct StructValue
+{
+    pub type_obj: Rc<TypeObject>,
+    /// Field values, in declaration order
+    pub fields: RefCell<Vec<Value>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EnumPayload
+{
+    None,
+    Single(Value),
+    Struct(RefCell<Vec<Value>>),
+}
+
+#[derive(Debug)]
+pub struct EnumValue
+{
+    pub type_obj: Rc<TypeObject>,
+    pub variant: u16,
+    pub payload: EnumPayload,
+}
+
+impl EnumValue
+{
+    pub fn get_field(&self, name: &str) -> Option<Value>
+    {
+        let EnumPayload::Struct(values) = &self.payload else { return None };
+        let index = self.type_obj.variant_fields(self.variant).iter().position(|f| f == name)?;
+        values.borrow().get(index).cloned()
+    }
+}
+
+#[derive(Debug)]
+pub struct Closure
+{
+    pub proto: Rc<FunctionProto>,
+    pub upvalues: Vec<Rc<RefCell<Value>>>,
+}
+
+impl Closure
+{
+    /// The function's name without the type it is a method of
+    pub fn short_name(&self) -> &str
+    {
+        self.proto.name.rsplit('.').next().unwrap_or(&self.proto.name)
+    }
+}
+
+/// A call to a function that yields, which runs a piece at a time, each time it is resumed
+#[derive(Debug)]
+pub struct Generator
+{
+    pub closure: Rc<Closure>,
+    pub state: GeneratorState,
+}
+
+#[derive(Debug)]
+pub enum GeneratorState
+{
+    /// Waiting to be resumed at `ip`, with the values its frame had on the stack
+    Suspended
+    {
+        ip: usize,
+        argc: usize,
+        stack: Vec<Value>,
+    },
+    Running,
+    Finished,
+}
+
+pub struct NativeFn
+{
+    pub name: &'static str,
+    pub arity: Option<usize>,
+    pub func: NativeFnPtr,
+}
+
+impl std::fmt::Debug for NativeFn
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "NativeFn({})", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct TypeObject
+{
+    pub name: String,
+    pub shape: TypeShape,
+    pub methods: RefCell<HashMap<String, Rc<Closure>>>,
+    pub statics: RefCell<HashMap<String, Value>>,
+}
+
+impl TypeObject
+{
+    pub fn new(name: &str, shape: TypeShape) -> Self
+    {
+        Self { name: name.to_owned(), shape, methods: RefCell::new(HashMap::new()), statics: RefCell::new(HashMap::new()) }
+    }
+
+    pub fn get_method(&self, name: &str) -> Option<Rc<Closure>>
+    {
+        self.methods.borrow().get(name).cloned()
+    }
+
+    /// The field names of a struct
+    pub fn fields(&self) -> &[String]
+    {
+        match &self.shape
+        {
+            TypeShape::Struct(fields) => fields,
+            _ => &[],
+        }
+    }
+
+    pub fn variant(&self, name: &str) -> Option<(u16, &VariantShape)>
+    {
+        let TypeShape::Enum(variants) = &self.shape else { return None };
+        variants.iter().position(|(n, _)| n == name).map(|i| (i as u16, &variants[i].1))
+    }
+
+    /// The discriminant of an enum's basic variant
+    pub fn discriminant(&self, variant: u16) -> Option<i64>
+    {
+        match &self.shape
+        {
+            TypeShape::Enum(variants) => match variants[variant as usize].1
+            {
+                VariantShape::Basic(discriminant) => Some(discriminant),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The basic variant of an enum whose discriminant is `value`
+    pub fn from_discriminant(&self, value: i64) -> Option<u16>
+    {
+        let TypeShape::Enum(variants) = &self.shape else { return None };
+        variants.iter().position(|(_, shape)| matches!(shape, VariantShape::Basic(d) if *d == value)).map(|i| i as u16)
+    }
+
+    pub fn variant_name(&self, variant: u16) -> &str
+    {
+        match &self.shape
+        {
+            TypeShape::Enum(variants) => &variants[variant as usize].0,
+            _ => "",
+        }
+    }
+
+    /// The field names of an enum's struct variant
+    pub fn variant_fields(&self, variant: u16) -> &[String]
+    {
+        match &self.shape
+        {
+            TypeShape::Enum(variants) => match &variants[variant as usize].1
+            {
+                VariantShape::Struct(fields) => fields,
+                _ => &[],
+            },
+            _ => &[],
+        }
+    }
+}
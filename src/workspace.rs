@@ -0,0 +1,610 @@
+use std::{collections::{HashMap, HashSet}, fs, path::{Path, PathBuf}};
+
+use either::Either;
+use itertools::Itertools;
+
+use crate::{ast::*, consteval::{ConstError, ConstEvaluator}, diagnostic::Diagnostic, codegen::{self, chunk::Bytecode, CompileError}, interp::token_str, lexing::{self, token::Token, LexerError}, parsing::{self, ParserError}, resolve::{symbols::SymbolKind, ResolveError, Resolver}, source::SourceFile, typeck::{TypeChecker, TypeError}};
+
+pub type FileId = usize;
+
+pub const SOURCE_EXTENSION: &str = "crs";
+
+#[derive(Debug)]
+pub enum WorkspaceError
+{
+    FileNotFound(PathBuf),
+    Lex
+    {
+        file: FileId,
+        error: LexerError,
+    },
+    Parse
+    {
+        file: FileId,
+        error: ParserError,
+    },
+    ModuleNotFound
+    {
+        file: FileId,
+        token: Token,
+        path: String,
+    },
+    ModFileNotFound
+    {
+        file: FileId,
+        token: Token,
+        path: PathBuf,
+    },
+    ImportCycle
+    {
+        file: FileId,
+        token: Token,
+        cycle: Vec<String>,
+    },
+    PrivateItem
+    {
+        file: FileId,
+        token: Token,
+        module: String,
+    },
+    UndefinedItem
+    {
+        file: FileId,
+        token: Token,
+        module: String,
+    },
+    Resolve
+    {
+        file: FileId,
+        error: ResolveError,
+    },
+    Type
+    {
+        file: FileId,
+        error: TypeError,
+    },
+    Const
+    {
+        file: FileId,
+        error: ConstError,
+    },
+    Compile
+    {
+        file: FileId,
+        error: CompileError,
+    },
+}
+
+impl WorkspaceError
+{
+    /// The file the error is in, or `None` when it has no source to point at, and the diagnostic describing it
+    pub fn diagnostic(&self) -> (Option<FileId>, Diagnostic)
+    {
+        match self
+        {
+            WorkspaceError::FileNotFound(path) => (None, Diagnostic::error(format!("Could not open file: `{}`", path.display())).with_code("E0201")),
+            WorkspaceError::Lex { file, error } => (Some(*file), error.into()),
+            WorkspaceError::Parse { file, error } => (Some(*file), error.into()),
+            WorkspaceError::ModuleNotFound { file, token, path } => (Some(*file), Diagnostic::error(format!("Could not find module `{}`", path))
+                .with_code("E0202")
+                .with_primary(token.pos, "no such module")
+                .with_help(format!("modules are found relative to the entry file, as `{}.{}`", path.replace('.', "/"), SOURCE_EXTENSION))),
+            WorkspaceError::ModFileNotFound { file, token, path } => (Some(*file), Diagnostic::error(format!("Could not find `{}` for module `{}`", path.display(), token_str(token)))
+                .with_code("E0206")
+                .with_primary(token.pos, "no such file")
+                .with_help(format!("`mod {0};` loads `{0}.{1}` from the directory of the file declaring it", token_str(token), SOURCE_EXTENSION))),
+            WorkspaceError::ImportCycle { file, token, cycle } => (Some(*file), Diagnostic::error("Import cycle")
+                .with_code("E0203")
+                .with_primary(token.pos, "this import closes the cycle")
+                .with_note(format!("the cycle is {}", cycle.iter().join(" -> ")))),
+            WorkspaceError::PrivateItem { file, token, module } => (Some(*file), Diagnostic::error(format!("`{}` is private to module `{}`", token_str(token), module))
+                .with_code("E0204")
+                .with_primary(token.pos, "private item")
+                .with_help(format!("mark it `pub` in `{}` to use it from other modules", module))),
+            WorkspaceError::UndefinedItem { file, token, module } => (Some(*file), Diagnostic::error(format!("Module `{}` has no item `{}`", module, token_str(token)))
+                .with_code("E0205")
+                .with_primary(token.pos, "not found in this module")),
+            WorkspaceError::Resolve { file, error } => (Some(*file), error.into()),
+            WorkspaceError::Type { file, error } => (Some(*file), error.into()),
+            WorkspaceError::Const { file, error } => (Some(*file), error.into()),
+            WorkspaceError::Compile { file, error } => (Some(*file), error.into()),
+        }
+    }
+
+    /// Renders the error's diagnostic against the source of the file it is in
+    pub fn render(&self, workspace: &Workspace, colour: bool) -> String
+    {
+        match self.diagnostic()
+        {
+            (Some(file), diagnostic) =>
+            {
+                let source = workspace.file(file);
+                diagnostic.render(&source.source, &source.path.display().to_string(), colour)
+            },
+            (None, diagnostic) => match self
+            {
+                WorkspaceError::FileNotFound(path) => diagnostic.render(&SourceFile::new(""), &path.display().to_string(), colour),
+                _ => unreachable!("only missing files have no source"),
+            },
+        }
+    }
+}
+
+/// A name that a `use` binds in the file containing it
+#[derive(Debug, Clone)]
+pub struct Import
+{
+    pub name: String,
+    pub token: Token,
+    pub source: FileId,
+    /// The item imported from `source`, or `None` when the whole module is bound to `name`
+    pub item: Option<String>,
+    pub public: bool,
+}
+
+/// A file of the program, or a module declared with `mod` in one, with what it imports from and exports to other modules
+#[derive(Debug)]
+pub struct Module
+{
+    /// The file the module is in, which an inline `mod` shares with the module around it
+    pub path: PathBuf,
+    /// The dotted path other files import this one by
+    pub module_path: Vec<String>,
+    pub source: SourceFile,
+    /// `None` if the file failed to lex or parse
+    pub program: Option<Program>,
+    pub imports: Vec<Import>,
+    /// The names other files may import: `pub` declarations and `pub use` imports
+    pub exports: HashSet<String>,
+}
+
+impl Module
+{
+    pub fn module_name(&self) -> String
+    {
+        self.module_path.join(".")
+    }
+}
+
+/// Every source file of a program, loaded by following `use` and `mod` declarations from an entry file
+pub struct Workspace
+{
+    root: PathBuf,
+    files: Vec<Module>,
+    modules: HashMap<Vec<String>, FileId>,
+    /// The module each loaded file holds, so a file reached by both `use` and `mod` is only loaded once
+    paths: HashMap<PathBuf, FileId>,
+    /// Files ordered so that each comes after every file it imports
+    order: Vec<FileId>,
+    errors: Vec<WorkspaceError>,
+}
+
+impl Workspace
+{
+    /// Creates a workspace that resolves module paths relative to `root`
+    pub fn new(root: impl Into<PathBuf>) -> Self
+    {
+        Self { root: root.into(), files: vec![], modules: HashMap::new(), paths: HashMap::new(), order: vec![], errors: vec![] }
+    }
+
+    pub fn root(&self) -> &Path
+    {
+        &self.root
+    }
+
+    pub fn file(&self, id: FileId) -> &Module
+    {
+        &self.files[id]
+    }
+
+    pub fn files(&self) -> &[Module]
+    {
+        &self.files
+    }
+
+    pub fn order(&self) -> &[FileId]
+    {
+        &self.order
+    }
+
+    /// The file passed to `load_entry`
+    pub fn entry(&self) -> FileId
+    {
+        0
+    }
+
+    /// Loads the entry file and every module it imports, directly or not
+    pub fn load_entry(&mut self, path: &Path) -> Result<FileId, Vec<WorkspaceError>>
+    {
+        let Ok(text) = fs::read_to_string(path) else {
+            return Err(vec![WorkspaceError::FileNotFound(path.to_owned())]);
+        };
+
+        let module_path = path.file_stem().map(|s| s.to_string_lossy().into_owned()).into_iter().collect();
+        let entry = self.add_file(path.to_owned(), module_path, &text, &mut vec![]);
+        match self.errors.is_empty()
+        {
+            true => Ok(entry),
+            false => Err(std::mem::take(&mut self.errors)),
+        }
+    }
+
+    fn module_file(&self, module_path: &[String]) -> PathBuf
+    {
+        let mut path = self.root.clone();
+        path.extend(module_path);
+        path.set_extension(SOURCE_EXTENSION);
+        path
+    }
+
+    /// Whether `module_path` names a file, or may name a module declared with `mod` in one
+    fn module_exists(&self, module_path: &[String]) -> bool
+    {
+        self.modules.contains_key(module_path)
+            || self.module_file(module_path).is_file()
+            || module_path.len() > 1 && self.module_exists(&module_path[..module_path.len() - 1])
+    }
+
+    /// Loads the module named by `path`, the identifiers of a `use`, unless it was already loaded. A module declared
+    /// with `mod` is found by loading the module around it.
+    fn load_module(&mut self, path: &[Token], from: FileId, token: &Token, stack: &mut Vec<FileId>) -> Option<FileId>
+    {
+        let module_path = path.iter().map(token_str).collect::<Vec<_>>();
+        let file = self.module_file(&module_path);
+        if let Some(id) = self.modules.get(&module_path).or_else(|| self.paths.get(&file)).copied()
+        {
+            return self.loaded(id, from, token, stack);
+        }
+
+        let (outer, name) = path.split_at(path.len() - 1);
+        if !file.is_file() && !outer.is_empty() && self.module_exists(&module_path[..outer.len()])
+        {
+            let outer = self.load_module(outer, from, token, stack)?;
+            if let Some(id) = self.modules.get(&module_path).copied()
+            {
+                let outer = &self.files[outer];
+                if !outer.exports.contains(&token_str(&name[0]))
+                {
+                    self.errors.push(WorkspaceError::PrivateItem { file: from, token: name[0], module: outer.module_name() });
+                    return None;
+                }
+
+                return Some(id);
+            }
+        }
+
+        let Ok(text) = fs::read_to_string(&file) else {
+            self.errors.push(WorkspaceError::ModuleNotFound { file: from, token: *token, path: module_path.join(".") });
+            return None;
+        };
+
+        Some(self.add_file(file, module_path, &text, stack))
+    }
+
+    /// Loads the file a `mod name;` declaration refers to, unless it was already loaded
+    fn load_mod_file(&mut self, path: PathBuf, module_path: Vec<String>, from: FileId, token: &Token, stack: &mut Vec<FileId>) -> Option<FileId>
+    {
+        if let Some(id) = self.paths.get(&path).copied()
+        {
+            return self.loaded(id, from, token, stack);
+        }
+
+        let Ok(text) = fs::read_to_string(&path) else {
+            self.errors.push(WorkspaceError::ModFileNotFound { file: from, token: *token, path });
+            return None;
+        };
+
+        Some(self.add_file(path, module_path, &text, stack))
+    }
+
+    /// A module that was already loaded, unless `from` is inside it and so closes a cycle
+    fn loaded(&mut self, id: FileId, from: FileId, token: &Token, stack: &[FileId]) -> Option<FileId>
+    {
+        if let Some(start) = stack.iter().position(|f| *f == id)
+        {
+            let cycle = stack[start..].iter().chain([&id]).map(|f| self.files[*f].module_name()).collect();
+            self.errors.push(WorkspaceError::ImportCycle { file: from, token: *token, cycle });
+            return None;
+        }
+
+        Some(id)
+    }
+
+    fn add_file(&mut self, path: PathBuf, module_path: Vec<String>, text: &str, stack: &mut Vec<FileId>) -> FileId
+    {
+        let id = self.files.len();
+        let tokens = lexing::lex_text(text);
+        let program = match tokens.errors.is_empty()
+        {
+            true => match parsing::parse(tokens.tokens, &tokens.comments)
+            {
+                Ok(program) => program,
+                Err(errors) =>
+                {
+                    self.errors.extend(errors.into_iter().map(|error| WorkspaceError::Parse { file: id, error }));
+                    None
+                },
+            },
+            false =>
+            {
+                self.errors.extend(tokens.errors.into_iter().map(|error| WorkspaceError::Lex { file: id, error }));
+                None
+            },
+        };
+
+        self.paths.insert(path.clone(), id);
+        self.add_module(path, module_path, SourceFile::new(text), program, stack)
+    }
+
+    /// Adds a module, after the modules it declares with `mod` and imports with `use`
+    fn add_module(&mut self, path: PathBuf, module_path: Vec<String>, source: SourceFile, program: Option<Program>, stack: &mut Vec<FileId>) -> FileId
+    {
+        let id = self.files.len();
+        self.modules.insert(module_path.clone(), id);
+        self.files.push(Module { path, module_path, source, program, imports: vec![], exports: HashSet::new() });
+
+        let declarations = self.files[id].program.iter().flat_map(|p| &p.declarations);
+        let mods = declarations.clone()
+            .filter_map(|d| match d
+            {
+                Declaration::Mod(pub_tok, decl) => Some((pub_tok.is_some(), decl.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let uses = declarations
+            .filter_map(|d| match d
+            {
+                Declaration::Use(pub_tok, use_stmt) => Some((pub_tok.is_some(), use_stmt.clone())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        stack.push(id);
+        for (public, decl) in &mods
+        {
+            self.add_mod(id, decl, *public, stack);
+        }
+
+        for (public, use_stmt) in &uses
+        {
+            self.add_use(id, use_stmt, *public, stack);
+        }
+        stack.pop();
+
+        let file = &mut self.files[id];
+        let mut exports: HashSet<String> = file.program.iter()
+            .flat_map(declared_names)
+            .filter_map(|(name, public)| public.then_some(name))
+            .collect();
+        exports.extend(file.imports.iter().filter(|i| i.public).map(|i| i.name.clone()));
+        file.exports = exports;
+
+        self.order.push(id);
+        id
+    }
+
+    /// Adds the module a `mod` declares, inline or from the file next to `file`, and binds it in `file`
+    fn add_mod(&mut self, file: FileId, decl: &ModDecl, public: bool, stack: &mut Vec<FileId>)
+    {
+        let name = token_str(&decl.id);
+        let outer = &self.files[file];
+        let module_path = outer.module_path.iter().cloned().chain([name.clone()]).collect();
+        let source = match &decl.body
+        {
+            Either::Left(body) =>
+            {
+                let program = Program { docs: vec![], declarations: body.declarations.clone(), eof: body.close_brace };
+                Some(self.add_module(outer.path.clone(), module_path, outer.source.clone(), Some(program), stack))
+            },
+            Either::Right(_) =>
+            {
+                let path = outer.path.with_file_name(format!("{}.{}", name, SOURCE_EXTENSION));
+                self.load_mod_file(path, module_path, file, &decl.id, stack)
+            },
+        };
+
+        if let Some(source) = source
+        {
+            self.files[file].imports.push(Import { name, token: decl.id, source, item: None, public });
+        }
+    }
+
+    /// Loads the module a `use` refers to and records the names it binds in `file`
+    fn add_use(&mut self, file: FileId, use_stmt: &UseStmt, public: bool, stack: &mut Vec<FileId>)
+    {
+        let ids = use_stmt.ids.iter().map(token_str).collect::<Vec<_>>();
+        let Some(last) = use_stmt.ids.last() else { return };
+
+        // `use a.b.*;` imports everything `a.b` exports
+        if let Some(star) = &use_stmt.star
+        {
+            let Some(source) = self.load_module(&use_stmt.ids, file, last, stack) else { return };
+            let imports = self.files[source].exports.iter().sorted().map(|name| Import {
+                name: name.clone(),
+                token: *star,
+                source,
+                item: Some(name.clone()),
+                public,
+            }).collect::<Vec<_>>();
+
+            self.files[file].imports.extend(imports);
+            return;
+        }
+
+        // `use a.b.c;` imports the module `a.b.c` if there is one, and otherwise the item `c` of the module `a.b`
+        let module_path = match self.module_file(&ids).is_file() || ids.len() == 1
+        {
+            true => &use_stmt.ids[..],
+            false => &use_stmt.ids[..ids.len() - 1],
+        };
+
+        let Some(source) = self.load_module(module_path, file, last, stack) else { return };
+        let name = token_str(last);
+        let item = match module_path.len() == ids.len()
+        {
+            true => None,
+            false => Some(name.clone()),
+        };
+
+        if let Some(item) = &item
+        {
+            let source_file = &self.files[source];
+            if source_file.program.is_some() && !source_file.exports.contains(item)
+            {
+                let declared = source_file.program.iter().flat_map(declared_names).any(|(n, _)| n == *item);
+                let module = source_file.module_name();
+                self.errors.push(match declared
+                {
+                    true => WorkspaceError::PrivateItem { file, token: *last, module },
+                    false => WorkspaceError::UndefinedItem { file, token: *last, module },
+                });
+                return;
+            }
+        }
+
+        // `use a.b;` where `a` declares `mod b` imports the module rather than an item
+        let (source, item) = match self.modules.get(&ids)
+        {
+            Some(&module) if item.is_some() => (module, None),
+            _ => (source, item),
+        };
+
+        self.files[file].imports.push(Import { name, token: *last, source, item, public });
+    }
+
+    /// Resolves names, checks types and evaluates constants in every file, dependencies first
+    pub fn check(&self) -> Result<(), Vec<WorkspaceError>>
+    {
+        let mut errors = vec![];
+        let mut checkers: HashMap<FileId, TypeChecker> = HashMap::new();
+        let mut evaluators: HashMap<FileId, ConstEvaluator> = HashMap::new();
+
+        for &id in &self.order
+        {
+            let file = &self.files[id];
+            let Some(program) = &file.program else { continue };
+
+            let mut resolver = Resolver::new();
+            for import in &file.imports
+            {
+                let kind = match import.item
+                {
+                    Some(_) => SymbolKind::Import,
+                    None => SymbolKind::Module,
+                };
+
+                resolver.declare_import(&import.name, &import.token, kind);
+            }
+
+            resolver.resolve_program(program);
+            if let Err(resolve_errors) = resolver.finish()
+            {
+                errors.extend(resolve_errors.into_iter().map(|error| WorkspaceError::Resolve { file: id, error }));
+                continue;
+            }
+
+            // The entry file's types keep their plain names, so its errors read as they would on their own
+            let module = match id == self.entry()
+            {
+                true => String::new(),
+                false => file.module_name(),
+            };
+
+            let mut checker = TypeChecker::for_module(&module, file.exports.clone());
+            for import in &file.imports
+            {
+                if let Some(source) = checkers.get(&import.source)
+                {
+                    checker.import(&import.name, source, import.item.as_deref());
+                }
+            }
+
+            checker.check_program(program);
+            if !checker.errors().is_empty()
+            {
+                errors.extend(checker.errors().iter().cloned().map(|error| WorkspaceError::Type { file: id, error }));
+            }
+            else
+            {
+                let evaluator = self.eval_file_consts(id, &evaluators);
+                errors.extend(evaluator.errors().iter().cloned().map(|error| WorkspaceError::Const { file: id, error }));
+                evaluators.insert(id, evaluator);
+            }
+
+            checkers.insert(id, checker);
+        }
+
+        match errors.is_empty()
+        {
+            true => Ok(()),
+            false => Err(errors),
+        }
+    }
+
+    /// Evaluates the constants of every file, dependencies first. Expects the workspace to have passed `check`.
+    pub fn eval_consts(&self) -> Result<HashMap<FileId, ConstEvaluator>, Vec<WorkspaceError>>
+    {
+        let mut evaluators = HashMap::new();
+        for &id in &self.order
+        {
+            let evaluator = self.eval_file_consts(id, &evaluators);
+            if !evaluator.errors().is_empty()
+            {
+                return Err(evaluator.errors().iter().cloned().map(|error| WorkspaceError::Const { file: id, error }).collect());
+            }
+
+            evaluators.insert(id, evaluator);
+        }
+
+        Ok(evaluators)
+    }
+
+    /// Evaluates the constants of one file, given those of the files it imports
+    fn eval_file_consts(&self, id: FileId, evaluated: &HashMap<FileId, ConstEvaluator>) -> ConstEvaluator
+    {
+        let file = &self.files[id];
+        let mut evaluator = ConstEvaluator::new();
+        for import in &file.imports
+        {
+            if let Some(source) = evaluated.get(&import.source)
+            {
+                evaluator.import(&import.name, source, import.item.as_deref());
+            }
+        }
+
+        if let Some(program) = &file.program
+        {
+            evaluator.eval_program(program);
+        }
+
+        evaluator
+    }
+
+    /// Lowers every file to bytecode. Expects the workspace to have passed `check`.
+    pub fn compile(&self) -> Result<Bytecode, Vec<WorkspaceError>>
+    {
+        codegen::compile_workspace(self).map_err(|errors| {
+            errors.into_iter().map(|(file, error)| WorkspaceError::Compile { file, error }).collect()
+        })
+    }
+}
+
+/// The names a program declares at the top level, and whether each is `pub`
+pub fn declared_names(program: &Program) -> Vec<(String, bool)>
+{
+    program.declarations.iter().flat_map(|d| match d
+    {
+        Declaration::Fn(pub_tok, decl) => vec![(token_str(&decl.id), pub_tok.is_some())],
+        Declaration::Struct(pub_tok, decl) => vec![(token_str(&decl.id), pub_tok.is_some())],
+        Declaration::Interface(pub_tok, decl) => vec![(token_str(&decl.id), pub_tok.is_some())],
+        Declaration::Enum(pub_tok, decl) => vec![(token_str(&decl.id), pub_tok.is_some())],
+        Declaration::Type(pub_tok, decl) => vec![(token_str(&decl.id), pub_tok.is_some())],
+        Declaration::Let(pub_tok, let_stmt) => crate::interp::pattern_eval::let_binding_names(&let_stmt.binding)
+            .into_iter()
+            .map(|n| (n, pub_tok.is_some()))
+            .collect(),
+        Declaration::Const(pub_tok, decl) => vec![(token_str(&decl.id), pub_tok.is_some())],
+        Declaration::Mod(pub_tok, decl) => vec![(token_str(&decl.id), pub_tok.is_some())],
+        Declaration::Use(_, _) | Declaration::Impl(_) => vec![],
+    }).collect()
+}